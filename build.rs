@@ -0,0 +1,22 @@
+use std::process::Command;
+
+
+fn main() {
+    // Make the git revision available for run manifests (see `manifest.rs`).
+    // Building outside of a git checkout is fine, the revision is then just
+    // unknown.
+    let revision = Command::new("git")
+        .args(["describe", "--always", "--dirty", "--abbrev=12"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+
+    if let Some(revision) = revision {
+        println!("cargo:rustc-env=BEAVER_GIT_REVISION={}", revision.trim());
+    }
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=.git/index");
+}
@@ -1,61 +1,418 @@
-use crate::{outcome::OutcomeSink, tape::{CellId, Tape}, tm::{Move, NextState, Tm}};
+use std::{
+    borrow::BorrowMut,
+    cmp::{Ordering, max, min},
+    fmt, mem,
+    ops::Range,
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
+};
 
+#[cfg(feature = "tape-stats")]
+use crate::tape::TapeStats;
+use crate::{
+    best::BestSoFar,
+    budget::Budget,
+    continuation::Continuation,
+    ctl,
+    machine::Step,
+    macro_machine::{self, DEFAULT_BLOCK_SIZE, MacroMachine},
+    outcome::{Configuration, OutcomeSink},
+    profile::{self, Phase},
+    tape::{CellId, CellValue, RleTape, Tape, TapeLike, hash_runs},
+    tm::{Move, NextState, Tm},
+};
+
+
+/// The deciders `Analyzer::analyze` applies, in that order.
+pub const DECIDERS: &[&str] = &[
+    "immediate-halt",
+    "simple-elope",
+    "halt-exists",
+    "halt-reachable",
+    "run-away",
+    "cycle",
+    "translated-cycler",
+    "backward-reasoning",
+];
+
+/// Has to be increased whenever the outcome of any TM changes, e.g. by
+/// changing a decider or the simulation. Checkpoints of other versions can
+/// then not be resumed, as that would mix results of different semantics.
+pub const DECIDERS_VERSION: u32 = 5;
 
 /// Holds data used by different analysis operations. This is just a cache so
 /// that we don't have to allocate memory again for each TM.
-pub struct Analyzer<const N: usize> {
-    max_steps: u32,
+///
+/// The scratch memory (see `Scratch`) is either owned by the analyzer (the
+/// default) or borrowed via `&mut Scratch`. Create analyzers with the latter
+/// via `AnalyzerBuilder` to control allocations and memory reuse explicitly.
+pub struct Analyzer<const N: usize, S = Scratch> {
+    max_steps: u64,
+    halt_convention: HaltConvention,
+    run_budget: Option<Budget>,
+    cycle_budget: Option<Budget>,
+    ctl_budget: Option<Budget>,
+    prune: Option<Arc<BestSoFar>>,
+    lin_recurrence: bool,
+    optional_deciders: OptionalDeciders,
+    accelerated: bool,
+    scratch: S,
+}
+
+/// Deciders that are not applied by default, as they are slow or decide
+/// only few TMs. Like all deciders after the simulation, they are only
+/// applied to TMs that reach the maximum number of steps. Parsed from and
+/// displayed as a comma separated list, or `none`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptionalDeciders {
+    /// The closed tape language decider (see `ctl`).
+    pub ctl: bool,
+}
+
+impl FromStr for OptionalDeciders {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut out = Self::default();
+        if s == "none" {
+            return Ok(out);
+        }
+        for name in s.split(',') {
+            match name.trim() {
+                "ctl" => out.ctl = true,
+                other => return Err(format!("unknown decider '{}'", other)),
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for OptionalDeciders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ctl {
+            false => f.write_str("none"),
+            true => f.write_str("ctl"),
+        }
+    }
+}
 
+/// Whether the final transition (the one into the halt state) is counted. The
+/// literature is not consistent about this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltConvention {
+    /// The halting transition counts as step and the symbol it writes counts
+    /// towards the score. This is the convention used by Radó and most
+    /// published results (e.g. S(2) = 6, Σ(2) = 4).
+    Include,
+
+    /// The halting transition is completely ignored: it is not counted as a
+    /// step and its symbol is not written to the tape. It still has to be
+    /// executed within the maximum number of steps, though.
+    Exclude,
+}
+
+impl FromStr for HaltConvention {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(Self::Include),
+            "exclude" => Ok(Self::Exclude),
+            _ => Err("invalid value for halt convention"),
+        }
+    }
+}
+
+impl fmt::Display for HaltConvention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Include => f.write_str("include"),
+            Self::Exclude => f.write_str("exclude"),
+        }
+    }
+}
+
+/// Memory used by the analyzer that would otherwise have to be allocated for
+/// each TM. The contents are meaningless between two analyses; only the
+/// allocated capacity matters.
+pub struct Scratch {
     /// Stack containing state-ids used by `check_halt_reachable`.
     dfs_stack: Vec<u8>,
 
     /// The actual TM tape used by `run_tm`.
     tape: Tape,
+
+    /// The configuration `run_tm` compares to, to detect cycles.
+    saved: SavedConfig,
+
+    /// The records on the left and right end of the tape used by `run_tm` to
+    /// detect translated cyclers.
+    records: [Record; 2],
+
+    /// The tape at the start of the Lin recurrence check (see
+    /// `lin_recurrence`).
+    snapshot: Vec<bool>,
+
+    /// Simulates long runs in `run_tm` (see `MACRO_THRESHOLD`).
+    macro_machine: MacroMachine,
+
+    /// The tape used by `run_tm` after the macro machine, and the one the
+    /// macro machine writes to when it takes over again.
+    rle_tape: RleTape,
+    spare_rle_tape: RleTape,
+}
+
+impl Default for Scratch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scratch {
+    /// Creates new scratch memory without allocating much upfront.
+    pub fn new() -> Self {
+        Self {
+            dfs_stack: Vec::new(),
+            tape: Tape::new(),
+            saved: SavedConfig::new(),
+            records: Default::default(),
+            snapshot: Vec::new(),
+            macro_machine: MacroMachine::new(DEFAULT_BLOCK_SIZE),
+            rle_tape: RleTape::new(),
+            spare_rle_tape: RleTape::new(),
+        }
+    }
+
+    /// Creates new scratch memory with a tape that can store at least `cells`
+    /// many cells (half of them left, the other half right of the start cell)
+    /// before it has to grow.
+    pub fn with_tape_capacity(cells: usize) -> Self {
+        Self {
+            dfs_stack: Vec::new(),
+            tape: Tape::with_capacity(cells),
+            saved: SavedConfig::new(),
+            records: Default::default(),
+            snapshot: Vec::new(),
+            macro_machine: MacroMachine::new(DEFAULT_BLOCK_SIZE),
+            rle_tape: RleTape::new(),
+            spare_rle_tape: RleTape::new(),
+        }
+    }
+
+    /// Sets whether the tape is deallocated before each TM (see
+    /// `Tape::set_cold`).
+    #[cfg(feature = "tape-stats")]
+    pub fn set_cold_tape(&mut self, cold: bool) {
+        self.tape.set_cold(cold);
+    }
+
+    /// Returns the counters of the tape (see `TapeStats`).
+    #[cfg(feature = "tape-stats")]
+    pub fn tape_stats(&self) -> TapeStats {
+        self.tape.stats()
+    }
+}
+
+/// Builder for `Analyzer`. Created via `Analyzer::builder`.
+pub struct AnalyzerBuilder<S> {
+    max_steps: u64,
+    halt_convention: HaltConvention,
+    run_budget: Option<Budget>,
+    cycle_budget: Option<Budget>,
+    ctl_budget: Option<Budget>,
+    prune: Option<Arc<BestSoFar>>,
+    lin_recurrence: bool,
+    optional_deciders: OptionalDeciders,
+    accelerated: bool,
+    scratch: S,
+}
+
+impl<S: BorrowMut<Scratch>> AnalyzerBuilder<S> {
+    /// Sets the number of steps after which TMs are stopped. Default: 200.
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets whether the halting transition is counted. Default:
+    /// `HaltConvention::Include`.
+    pub fn halt_convention(mut self, halt_convention: HaltConvention) -> Self {
+        self.halt_convention = halt_convention;
+        self
+    }
+
+    /// Sets the budget for running a single TM (in addition to the maximum
+    /// number of steps). A step budget limits the number of steps executed by
+    /// one call to `run_tm` or `continue_tm`. TMs exceeding the budget are
+    /// reported as aborted. Default: `None`.
+    pub fn run_budget(mut self, run_budget: Option<Budget>) -> Self {
+        self.run_budget = run_budget;
+        self
+    }
+
+    /// Sets the budget of the cycle detection for a single TM: after that
+    /// many steps or that much time of one call to `run_tm` or
+    /// `continue_tm`, the TM is run without checking for cycles. Default:
+    /// `None`.
+    pub fn cycle_budget(mut self, cycle_budget: Option<Budget>) -> Self {
+        self.cycle_budget = cycle_budget;
+        self
+    }
+
+    /// Sets the budget of the CTL decider (see `OptionalDeciders`) for a
+    /// single TM, where a step is computing the successors of one window
+    /// (see `ctl`). TMs exceeding it are not decided by it. Default: `None`.
+    pub fn ctl_budget(mut self, ctl_budget: Option<Budget>) -> Self {
+        self.ctl_budget = ctl_budget;
+        self
+    }
+
+    /// Sets the shared high score against which running TMs are pruned: a TM
+    /// is stopped (and reported as pruned) as soon as it could not reach that
+    /// score anymore, even if it wrote a new 1 in every remaining step. TMs
+    /// that could still tie the high score are not pruned. Default: `None`.
+    pub fn prune(mut self, best: Option<Arc<BestSoFar>>) -> Self {
+        self.prune = best;
+        self
+    }
+
+    /// Sets whether TMs that reach the maximum number of steps are checked
+    /// for a Lin recurrence (see `lin_recurrence`) before they are reported
+    /// as aborted. The check runs them for up to `max_steps` more steps, so it
+    /// catches cycles with a long preperiod the other checks miss. Default:
+    /// `false`.
+    pub fn lin_recurrence(mut self, lin_recurrence: bool) -> Self {
+        self.lin_recurrence = lin_recurrence;
+        self
+    }
+
+    /// Sets which optional deciders are applied to TMs that reach the
+    /// maximum number of steps. Default: none.
+    pub fn optional_deciders(mut self, optional_deciders: OptionalDeciders) -> Self {
+        self.optional_deciders = optional_deciders;
+        self
+    }
+
+    /// Sets whether the macro machine that continues long runs detects and
+    /// applies rules (see `macro_machine::Rule`), which makes TMs with a
+    /// regular long-term behavior much faster to run. It does not change any
+    /// outcome. Default: `false`.
+    pub fn accelerated(mut self, accelerated: bool) -> Self {
+        self.accelerated = accelerated;
+        self
+    }
+
+    /// Sets the scratch memory to use. Can be an owned `Scratch` or a
+    /// `&mut Scratch`, in which case the memory can be reused after the
+    /// analyzer is dropped.
+    pub fn scratch<T: BorrowMut<Scratch>>(self, scratch: T) -> AnalyzerBuilder<T> {
+        AnalyzerBuilder {
+            max_steps: self.max_steps,
+            halt_convention: self.halt_convention,
+            run_budget: self.run_budget,
+            cycle_budget: self.cycle_budget,
+            ctl_budget: self.ctl_budget,
+            prune: self.prune,
+            lin_recurrence: self.lin_recurrence,
+            optional_deciders: self.optional_deciders,
+            accelerated: self.accelerated,
+            scratch,
+        }
+    }
+
+    pub fn build<const N: usize>(self) -> Analyzer<N, S> {
+        Analyzer {
+            max_steps: self.max_steps,
+            halt_convention: self.halt_convention,
+            run_budget: self.run_budget,
+            cycle_budget: self.cycle_budget,
+            ctl_budget: self.ctl_budget,
+            prune: self.prune,
+            lin_recurrence: self.lin_recurrence,
+            optional_deciders: self.optional_deciders,
+            accelerated: self.accelerated,
+            scratch: self.scratch,
+        }
+    }
 }
 
 impl<const N: usize> Analyzer<N>
 where
     [bool; N]: Default,
 {
-    /// Creates a new analyzer instance. You can use this instance to analyze
-    /// different TMs. You can reuse this as often as you like. All values
-    /// stored inside of this either don't change or are cleared for each new
-    /// TM.
-    pub fn new(max_steps: u32) -> Self {
-        Self {
-            max_steps,
-            dfs_stack: Vec::new(),
-            tape: Tape::new(),
+    /// Returns a builder to configure a new analyzer instance. You can use
+    /// this instance to analyze different TMs. You can reuse this as often as
+    /// you like. All values stored inside of this either don't change or are
+    /// cleared for each new TM.
+    pub fn builder() -> AnalyzerBuilder<Scratch> {
+        AnalyzerBuilder {
+            max_steps: 200,
+            halt_convention: HaltConvention::Include,
+            run_budget: None,
+            cycle_budget: None,
+            ctl_budget: None,
+            prune: None,
+            lin_recurrence: false,
+            optional_deciders: OptionalDeciders::default(),
+            accelerated: false,
+            scratch: Scratch::new(),
         }
     }
+}
 
+impl<const N: usize, S: BorrowMut<Scratch>> Analyzer<N, S>
+where
+    [bool; N]: Default,
+{
     /// Main entry point: analyze the given TM.
-    pub fn analyze(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink) {
+    pub fn analyze(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink<N>) {
+        // Before even running the TM (dynamic analysis), we analyze it
+        // statically to categorize certain TMs early.
+        if self.check_static(tm, sink) {
+            return;
+        }
+
+        profile::enter(Phase::Simulation);
+        self.run_tm(tm, sink)
+    }
+
+    /// Performs all static checks of `analyze`. Returns `true` if one of them
+    /// decided the TM, which was then reported to `sink`. Otherwise the TM
+    /// has to be run.
+    pub fn check_static(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink<N>) -> bool {
         macro_rules! try_check {
             ($e:expr) => {
                 if $e {
-                    return;
+                    return true;
                 }
             };
         }
 
-        // Before even running the TM (dynamic analysis), we analyze it
-        // statically to categorize certain TMs early.
-        try_check!(Self::check_immediate_halt(tm, sink));
+        profile::enter(Phase::ImmediateHalt);
+        try_check!(self.check_immediate_halt(tm, sink));
+        profile::enter(Phase::SimpleElope);
         try_check!(Self::check_simple_elope(tm, sink));
+        profile::enter(Phase::HaltExists);
         try_check!(Self::check_halt_exists(tm, sink));
+        profile::enter(Phase::HaltReachable);
         try_check!(self.check_halt_reachable(tm, sink));
 
-        self.run_tm(tm, sink)
+        false
     }
 
     /// Static analysis (very fast): checks if the start 0 action is
     /// transitioning to the halt state. In that case the
     #[inline(always)]
-    pub fn check_immediate_halt(tm: Tm<N>, sink: &mut impl OutcomeSink) -> bool {
+    pub fn check_immediate_halt(&self, tm: Tm<N>, sink: &mut impl OutcomeSink<N>) -> bool {
         if tm.start_action().will_halt() {
-            let wrote_one = tm.start_action().write_value().0;
-            sink.report_immediate_halt(wrote_one);
+            match self.halt_convention {
+                HaltConvention::Include => {
+                    let wrote_one = tm.start_action().write_value().0;
+                    sink.report_immediate_halt(tm, 1, wrote_one as u32);
+                }
+                HaltConvention::Exclude => sink.report_immediate_halt(tm, 0, 0),
+            }
             return true;
         }
 
@@ -65,9 +422,9 @@ where
     /// Static analysis (very fast): checks if the first action has the start
     /// state as the next state. In those cases, the TM will just run away in
     /// one direction immediately.
-    pub fn check_simple_elope(tm: Tm<N>, sink: &mut impl OutcomeSink) -> bool {
+    pub fn check_simple_elope(tm: Tm<N>, sink: &mut impl OutcomeSink<N>) -> bool {
         if tm.start_action().next_state() == NextState::State(0) {
-            sink.report_simple_elope();
+            sink.report_simple_elope(tm);
             return true
         }
 
@@ -76,7 +433,7 @@ where
 
     /// Static analysis (fast): checks if the TM has a transition to the halt
     /// state at all.
-    pub fn check_halt_exists(tm: Tm<N>, sink: &mut impl OutcomeSink) -> bool {
+    pub fn check_halt_exists(tm: Tm<N>, sink: &mut impl OutcomeSink<N>) -> bool {
         /// This is a helper to create a bitmask by repeating a 5 bit pattern
         /// N * 2 times.
         const fn make_repeating_mask<const N: usize>(pattern: u8) -> u64 {
@@ -156,7 +513,7 @@ where
         let overflow = added & !state_mask;
 
         if overflow == 0 {
-            sink.report_no_halt_transition();
+            sink.report_no_halt_transition(tm);
             return true;
         }
 
@@ -172,16 +529,17 @@ where
     /// can ignore all `on_1` transitions, meaning that this check will more
     /// likely detect when a TM cannot halt.
     #[inline(never)]
-    pub fn check_halt_reachable(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink) -> bool {
-        self.dfs_stack.clear();
-        self.dfs_stack.push(0);
+    pub fn check_halt_reachable(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink<N>) -> bool {
+        let dfs_stack = &mut self.scratch.borrow_mut().dfs_stack;
+        dfs_stack.clear();
+        dfs_stack.push(0);
         let mut visited: [bool; N] = array(false);
 
         // Stays `true` until we encounter an action that actually writes a 1.
         let mut only_0s = true;
 
         let mut reached_halt = false;
-        'outer: while let Some(state_id) = self.dfs_stack.pop() {
+        'outer: while let Some(state_id) = dfs_stack.pop() {
             let state_visited = &mut visited[state_id as usize];
             if *state_visited {
                 continue;
@@ -196,8 +554,8 @@ where
                 // We have to reset the search here, because we ignored `on_1`
                 // transitions so far. But since we can encounter 1s now, we have
                 // to reconsider them again.
-                self.dfs_stack.clear();
-                self.dfs_stack.push(0);
+                dfs_stack.clear();
+                dfs_stack.push(0);
                 visited = array(false);
             }
 
@@ -209,7 +567,7 @@ where
                             break 'outer;
                         }
                         NextState::State(v) => {
-                            self.dfs_stack.push(v);
+                            dfs_stack.push(v);
                         }
                     }
                 };
@@ -224,7 +582,7 @@ where
         }
 
         if !reached_halt {
-            sink.report_halt_state_not_reachable();
+            sink.report_halt_state_not_reachable(tm);
             return true;
         }
 
@@ -233,10 +591,201 @@ where
 
     /// Actually run the TM.
     #[inline(never)]
-    pub fn run_tm(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink) {
-        self.tape.clear();
-        let mut head = CellId(0);
-        let mut current_state: u8 = 0;
+    pub fn run_tm(&mut self, tm: Tm<N>, sink: &mut impl OutcomeSink<N>) {
+        self.run_tm_with_hook(tm, sink, &mut ());
+    }
+
+    /// Like `run_tm`, but calls `hook` for every step (see `StepHook`).
+    #[inline(always)]
+    pub fn run_tm_with_hook(
+        &mut self,
+        tm: Tm<N>,
+        sink: &mut impl OutcomeSink<N>,
+        hook: &mut impl StepHook<N>,
+    ) {
+        self.scratch.borrow_mut().tape.clear();
+        self.run_from(tm, 0, CellId(0), 0, sink, hook);
+    }
+
+    /// Continues running a TM from the configuration stored in the given
+    /// continuation record, e.g. one written by a previous run with a smaller
+    /// step limit. The static checks are not performed again, as the TM
+    /// already passed them to get to this point.
+    pub fn continue_tm(
+        &mut self,
+        tm: Tm<N>,
+        continuation: &Continuation,
+        sink: &mut impl OutcomeSink<N>,
+    ) {
+        let tape = &mut self.scratch.borrow_mut().tape;
+        continuation.restore_tape(tape);
+        if continuation.steps >= self.max_steps {
+            let config = Configuration {
+                state: continuation.state,
+                head: continuation.head,
+                steps: continuation.steps,
+                tape,
+            };
+            sink.report_aborted_after_max_steps(tm, config);
+            return;
+        }
+
+        let (state, head, steps) = (continuation.state, continuation.head, continuation.steps);
+        self.run_from(tm, state, head, steps, sink, &mut ());
+    }
+
+    /// Runs the TM from the given configuration. The tape has to be prepared
+    /// by the caller already.
+    #[inline(always)]
+    fn run_from(
+        &mut self,
+        tm: Tm<N>,
+        current_state: u8,
+        head: CellId,
+        steps: u64,
+        sink: &mut impl OutcomeSink<N>,
+        hook: &mut impl StepHook<N>,
+    ) {
+        let max_steps = match self.run_budget.and_then(|b| b.steps()) {
+            Some(budget) => min(self.max_steps, steps.saturating_add(budget)),
+            None => self.max_steps,
+        };
+        let deadline = self.run_budget.and_then(|b| b.time()).map(|t| Instant::now() + t);
+        let cycle_until = match self.cycle_budget.and_then(|b| b.steps()) {
+            Some(budget) => steps.saturating_add(budget),
+            None => u64::MAX,
+        };
+        let cycle_deadline = self.cycle_budget.and_then(|b| b.time()).map(|t| Instant::now() + t);
+        // Long runs alternate between the macro machine, which skips all
+        // deciders, and windows of `MACRO_THRESHOLD` steps simulated
+        // normally, in which the deciders start from scratch. Each time, the
+        // macro machine runs until the step count grew by `MACRO_GROWTH`, and
+        // the last window ends at `max_steps`, so that the TM halts or gets
+        // aborted in one of them.
+        let skippable = hook.skippable();
+        let pause_after = |steps: u64| {
+            let end = steps.saturating_add(MACRO_THRESHOLD);
+            (skippable && end.saturating_add(MACRO_THRESHOLD) < max_steps).then_some(end)
+        };
+        let pause_at = pause_after(steps);
+        let Scratch { tape, rle_tape, spare_rle_tape, saved, records, snapshot, macro_machine, .. }
+            = self.scratch.borrow_mut();
+        let mut run = Run {
+            sink,
+            hook,
+            saved,
+            records,
+            snapshot,
+            step_limit: self.max_steps,
+            max_steps,
+            deadline,
+            cycle_until,
+            cycle_deadline,
+            pause_at,
+            halt_convention: self.halt_convention,
+            prune_below: self.prune.as_ref().and_then(|b| b.score()).unwrap_or(0),
+            lin_recurrence: self.lin_recurrence,
+            ctl: self.optional_deciders.ctl,
+            ctl_budget: self.ctl_budget,
+        };
+        let mut config = match run.simulate(tm, (current_state, head, steps), tape) {
+            Some(config) => config,
+            None => return,
+        };
+
+        // As the tape might be huge after the macro machine, it is stored
+        // run-length encoded from here on. Rules work best with the block
+        // size that compresses the tape most.
+        let block_size = match self.accelerated {
+            true => macro_machine::best_block_size(config.1, tape),
+            false => DEFAULT_BLOCK_SIZE,
+        };
+        macro_machine.set_block_size(block_size);
+        macro_machine.set_rules(self.accelerated);
+        let mut on_rle_tape = false;
+        loop {
+            let until = min(
+                config.2.saturating_mul(MACRO_GROWTH),
+                max_steps - MACRO_THRESHOLD,
+            );
+            let before = config.2;
+            config = match on_rle_tape {
+                false => macro_machine.run(tm, config, until, deadline, tape, rle_tape),
+                true => {
+                    let config =
+                        macro_machine.run(tm, config, until, deadline, rle_tape, spare_rle_tape);
+                    mem::swap(rle_tape, spare_rle_tape);
+                    config
+                }
+            };
+            on_rle_tape = true;
+
+            // If the macro machine stopped early, e.g. because the TM got
+            // stuck inside a block, the rest is simulated normally.
+            run.pause_at = match config.2 - before >= MACRO_THRESHOLD {
+                true => pause_after(config.2),
+                false => None,
+            };
+            config = match run.simulate(tm, config, rle_tape) {
+                Some(config) => config,
+                None => return,
+            };
+        }
+    }
+}
+
+/// One run of a TM in `Analyzer::run_from`: everything the simulation needs
+/// besides the tape, so that it can continue on a different type of tape.
+struct Run<'a, S, H> {
+    sink: &'a mut S,
+    hook: &'a mut H,
+    saved: &'a mut SavedConfig,
+    records: &'a mut [Record; 2],
+    snapshot: &'a mut Vec<bool>,
+
+    /// `Analyzer::max_steps`. `max_steps` is lower if the run budget is
+    /// exhausted first.
+    step_limit: u64,
+    max_steps: u64,
+    deadline: Option<Instant>,
+
+    /// The cycle detection is only done up to this step. Lowered to the
+    /// current step once `cycle_deadline` has passed.
+    cycle_until: u64,
+    cycle_deadline: Option<Instant>,
+
+    /// If set, `simulate` stops after this many steps.
+    pause_at: Option<u64>,
+
+    halt_convention: HaltConvention,
+    prune_below: u32,
+    lin_recurrence: bool,
+    ctl: bool,
+    ctl_budget: Option<Budget>,
+}
+
+impl<S, H> Run<'_, S, H> {
+    /// Simulates the TM on `tape`, starting with the given state, head and
+    /// steps, until it is decided or aborted, and reports the outcome. If it
+    /// reaches `pause_at` before, returns state, head and steps at that point
+    /// instead.
+    #[inline(always)]
+    fn simulate<const N: usize, T: TapeLike>(
+        &mut self,
+        tm: Tm<N>,
+        (mut current_state, mut head, mut steps): (u8, CellId, u64),
+        tape: &mut T,
+    ) -> Option<(u8, CellId, u64)>
+    where
+        S: OutcomeSink<N>,
+        H: StepHook<N>,
+        [bool; N]: Default,
+    {
+        // For pruning: the TM can write at most one new 1 per step, so it can
+        // never end up with more 1s than the size of the written range plus
+        // the number of remaining steps. The high score is only read once per
+        // TM, so it might be slightly outdated, but that's fine.
+        let prune_from = self.step_limit.saturating_sub(self.prune_below as u64);
 
         // The following variables are part of a simple run-away analysis.
         // Whenever we reach a cell outside of the "written range" (the range
@@ -259,54 +808,440 @@ where
         let mut running_away = false;
         let mut visited_during_run_away: [bool; N] = array(false);
 
-        let mut steps = 0;
-        loop {
+        // Cycle detection à la Brent: the configuration is saved whenever the
+        // number of steps since `start` is a power of two. If the TM later is in exactly
+        // that configuration again, it repeats the steps in between forever.
+        // Every cycle is found as soon as the saved configuration is part of
+        // it and the cycle is not longer than the steps since saving. The
+        // tapes are only compared if state, head, written range and the hash
+        // of the tape match.
+        let start = steps;
+        self.saved.save(current_state, head, tape);
+
+        // Detection of translated cyclers, which repeat the same behavior
+        // shifted along the tape. Whenever the head visits a new cell at the
+        // right end of the tape (a record), nothing right of it was written
+        // yet. If at two records R1 and R2 the state is the same and the cells
+        // left of the head that were visited between R1 and R2 have the same
+        // contents, the TM repeats the steps from R1 to R2, shifted by the
+        // distance of the two records, forever. The left end is the same,
+        // mirrored. Like above, steps are counted from `start` and a new
+        // record is only saved once their number doubled since the last one.
+        // `lowest` and `highest` are the cells visited since the right and
+        // left record were saved.
+        self.records.iter_mut().for_each(|record| record.valid = false);
+        let (mut lowest, mut highest) = (head.0, head.0);
+
+        let last_read = loop {
+            if self.pause_at.is_some_and(|pause_at| steps >= pause_at) {
+                return Some((current_state, head, steps));
+            }
+
             steps += 1;
 
-            if !self.tape.written_range().contains(&head) {
+            if !tape.written_range().contains(&head) {
                 running_away = true;
                 let visited_state = &mut visited_during_run_away[current_state as usize];
                 if *visited_state {
-                    sink.report_run_away();
-                    return;
+                    self.sink.report_run_away(tm);
+                    return None;
                 } else {
                     *visited_state = true;
                 }
+
+                let right = head >= tape.written_range().end;
+                let record = &mut self.records[right as usize];
+                let depth = if right { record.head - lowest } else { highest - record.head };
+                if record.matches(current_state, head, depth, tape) {
+                    self.sink.report_translated_cycler(tm);
+                    return None;
+                }
+                if !record.valid || (steps - start) / 2 >= record.steps {
+                    record.save(current_state, head, steps - start, right, tape);
+                    if right {
+                        lowest = head.0;
+                    } else {
+                        highest = head.0;
+                    }
+                }
             } else if running_away {
                 // Reset everything related to this check.
                 running_away = false;
                 visited_during_run_away = array(false);
             }
 
-            let value = self.tape.get(head);
+            let value = tape.get(head);
             let action = tm.state(current_state).action_for(value);
-            self.tape.write(head, action.write_value());
+            tape.write(head, action.write_value());
+            self.hook.on_step(&Step { state: current_state, head, read: value, action }, tape);
 
             current_state = match action.next_state() {
-                NextState::HaltState => break,
+                NextState::HaltState => break value,
                 NextState::State(v) => v,
             };
             match action.movement() {
                 Move::Left => head.0 -= 1,
                 Move::Right => head.0 += 1,
             }
+            lowest = min(lowest, head.0);
+            highest = max(highest, head.0);
+
+            if steps <= self.cycle_until {
+                if self.saved.matches(current_state, head, tape) {
+                    self.sink.report_cycle(tm);
+                    return None;
+                }
+                if (steps - start) & (steps - start - 1) == 0 {
+                    self.saved.save(current_state, head, tape);
+                }
+            }
+
+            // Checking the clock is comparatively expensive, so we only do it
+            // every few thousand steps.
+            let check_clock = steps & (TIME_CHECK_INTERVAL - 1) == 0;
+            let out_of_time = check_clock
+                && self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let cycle_out_of_time = check_clock
+                && self.cycle_deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if cycle_out_of_time {
+                self.cycle_until = min(self.cycle_until, steps);
+            }
+
+            if steps >= prune_from {
+                let remaining = self.step_limit - steps;
+                let r = tape.written_range();
+                if (r.end.0 - r.start.0) as u64 + remaining < self.prune_below as u64 {
+                    self.sink.report_pruned(tm, steps);
+                    return None;
+                }
+            }
+
+            if steps >= self.max_steps || out_of_time {
+                // Not if only the run budget is exhausted: the TM might be
+                // continued later.
+                if steps >= self.step_limit {
+                    if halt_unreachable_backwards(tm) {
+                        self.sink.report_backward_reasoning(tm);
+                        return None;
+                    }
+                    if self.ctl && ctl::decide(tm, self.ctl_budget) {
+                        self.sink.report_ctl(tm);
+                        return None;
+                    }
+                    let limit = self.step_limit;
+                    if self.lin_recurrence
+                        && lin_recurrence(tm, current_state, head, limit, tape, self.snapshot)
+                    {
+                        self.sink.report_lin_recurrence(tm);
+                        return None;
+                    }
+                }
+
+                let config = Configuration {
+                    state: current_state,
+                    head,
+                    steps,
+                    tape,
+                };
+                self.sink.report_aborted_after_max_steps(tm, config);
+                return None;
+            }
+        };
+
+        // If the halting transition should not be counted, we undo it.
+        if self.halt_convention == HaltConvention::Exclude {
+            steps -= 1;
+            tape.write(head, last_read);
+        }
+
+        let ones = tape.ones() as u32;
+        self.sink.report_halted(tm, steps, ones, tape);
+        None
+    }
+}
+
+/// Observes each step of a TM run by the analyzer, e.g. to collect
+/// statistics. The analyzer is generic over the hook, so the no-op hook `()`
+/// used by `analyze` costs nothing.
+pub trait StepHook<const N: usize> {
+    /// Called after the TM executed `step` (but before the head moved),
+    /// with the tape already containing the written value. This includes
+    /// the halting transition, even with `HaltConvention::Exclude`.
+    fn on_step(&mut self, step: &Step<N>, tape: &impl TapeLike);
+
+    /// Whether the analyzer may skip steps without calling `on_step`, by
+    /// simulating long runs with a macro machine.
+    fn skippable(&self) -> bool {
+        false
+    }
+}
+
+impl<const N: usize> StepHook<N> for () {
+    #[inline(always)]
+    fn on_step(&mut self, _: &Step<N>, _: &impl TapeLike) {}
+
+    fn skippable(&self) -> bool {
+        true
+    }
+}
+
+/// A configuration of a running TM for the cycle detection in
+/// `Analyzer::run_from`.
+struct SavedConfig {
+    state: u8,
+    head: CellId,
+    written_range: Range<CellId>,
+    tape_hash: u64,
+
+    /// The runs of the tape (see `TapeLike::runs`), as value and length.
+    runs: Vec<(bool, i64)>,
+}
+
+impl SavedConfig {
+    fn new() -> Self {
+        Self {
+            state: 0,
+            head: CellId(0),
+            written_range: CellId(0)..CellId(0),
+            tape_hash: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    fn save(&mut self, state: u8, head: CellId, tape: &impl TapeLike) {
+        self.state = state;
+        self.head = head;
+        self.written_range = tape.written_range();
+        self.runs.clear();
+        self.runs.extend(tape.runs().map(|(value, cells)| (value.0, cells.end.0 - cells.start.0)));
+        self.tape_hash = hash_runs(self.runs.iter().copied());
+    }
+
+    /// Whether the given configuration is the same as this one. Cheap unless
+    /// state and head match. The hash only filters out most different tapes
+    /// quickly, the tape contents are always compared in the end.
+    #[inline(always)]
+    fn matches(&self, state: u8, head: CellId, tape: &impl TapeLike) -> bool {
+        state == self.state
+            && head == self.head
+            && tape.written_range() == self.written_range
+            && tape.content_hash() == self.tape_hash
+            && tape.runs()
+                .map(|(value, cells)| (value.0, cells.end.0 - cells.start.0))
+                .eq(self.runs.iter().copied())
+    }
+}
+
+/// A configuration in which the head is on a cell it never visited before,
+/// for the translated cycler detection in `Analyzer::run_from`.
+#[derive(Default)]
+struct Record {
+    /// Whether this record was saved during the current run.
+    valid: bool,
+    state: u8,
+    head: i64,
+    steps: u64,
+
+    /// `-1` if the record is at the right end of the tape, `1` otherwise.
+    behind: i64,
+
+    /// The cells behind the head, i.e. in the direction of `behind`, nearest
+    /// first.
+    cells: Vec<bool>,
+}
+
+impl Record {
+    fn save(&mut self, state: u8, head: CellId, steps: u64, right: bool, tape: &impl TapeLike) {
+        let range = tape.written_range();
+        let (behind, len) = if right {
+            (-1, head.0 - range.start.0)
+        } else {
+            (1, range.end.0 - head.0 - 1)
+        };
+        self.valid = true;
+        self.state = state;
+        self.head = head.0;
+        self.steps = steps;
+        self.behind = behind;
+        self.cells.clear();
+        self.cells.extend((1..=len).map(|k| tape.get(CellId(head.0 + behind * k)).0));
+    }
+
+    /// Whether the TM in state `state` at the record `head` repeats the steps
+    /// since this record. `depth` is the number of cells behind this record
+    /// that were visited since.
+    fn matches(&self, state: u8, head: CellId, depth: i64, tape: &impl TapeLike) -> bool {
+        self.valid
+            && state == self.state
+            && (1..=depth).all(|k| {
+                let old = self.cells.get(k as usize - 1).copied().unwrap_or(false);
+                old == tape.get(CellId(head.0 + self.behind * k)).0
+            })
+    }
+}
+
+/// The maximum number of steps `halt_unreachable_backwards` goes back from a
+/// halting transition.
+const BACKWARD_DEPTH: usize = 64;
+
+/// The maximum number of configurations `halt_unreachable_backwards` looks
+/// at for one halting transition.
+const BACKWARD_MAX_CONFIGS: u32 = 10_000;
 
-            if steps == self.max_steps {
-                sink.report_aborted_after_max_steps();
-                return;
+/// Backward reasoning: starting at each halting transition, the steps that
+/// could have led there are executed backwards. A configuration is only
+/// partially known: a cell we know nothing about can have any value. A
+/// predecessor of a configuration has to be in a state with a transition
+/// into the current state that wrote a value to the cell the head came from
+/// which does not contradict what we know about that cell. If after a few
+/// steps no configuration has a predecessor anymore, the halting transition
+/// can never be reached, except from the start configuration itself.
+///
+/// Returns `true` if this is the case for all halting transitions, i.e. if
+/// the TM never halts. Gives up (returns `false`) if a configuration could
+/// be the start configuration or after `BACKWARD_DEPTH` steps or
+/// `BACKWARD_MAX_CONFIGS` configurations.
+fn halt_unreachable_backwards<const N: usize>(tm: Tm<N>) -> bool {
+    /// The known cells around the halting transition, which is in the
+    /// middle. `None` is unknown.
+    type Cells = [Option<bool>; 2 * BACKWARD_DEPTH + 1];
+
+    /// Returns `false` if the state `state` with the head on `pos` might be
+    /// reachable.
+    fn unreachable<const N: usize>(
+        tm: Tm<N>,
+        state: u8,
+        pos: usize,
+        cells: &mut Cells,
+        depth: usize,
+        budget: &mut u32,
+    ) -> bool {
+        // The empty tape is all 0 and the start state is always the first.
+        let start_possible = state == 0 && cells.iter().all(|&c| c != Some(true));
+        if start_possible || depth == BACKWARD_DEPTH || *budget == 0 {
+            return false;
+        }
+        *budget -= 1;
+
+        for prev in 0..N as u8 {
+            for read in [false, true] {
+                let action = tm.state(prev).action_for(CellValue(read));
+                if action.next_state() != NextState::State(state) {
+                    continue;
+                }
+
+                // The head moved to `pos` from the other side.
+                let from = match action.movement() {
+                    Move::Left => pos + 1,
+                    Move::Right => pos - 1,
+                };
+                let old = cells[from];
+                if old.is_some_and(|v| v != action.write_value().0) {
+                    continue;
+                }
+
+                cells[from] = Some(read);
+                let dead = unreachable(tm, prev, from, cells, depth + 1, budget);
+                cells[from] = old;
+                if !dead {
+                    return false;
+                }
             }
         }
 
+        true
+    }
+
+    (0..N as u8).all(|state| {
+        [false, true].iter().all(|&read| {
+            let action = tm.state(state).action_for(CellValue(read));
+            if action.next_state() != NextState::HaltState {
+                return true;
+            }
+
+            let mut cells: Cells = [None; 2 * BACKWARD_DEPTH + 1];
+            cells[BACKWARD_DEPTH] = Some(read);
+            let mut budget = BACKWARD_MAX_CONFIGS;
+            unreachable(tm, state, BACKWARD_DEPTH, &mut cells, 0, &mut budget)
+        })
+    })
+}
+
+/// Lin's recurrence check, as used in the historical searches for BB(3) and
+/// BB(4): the configuration at the start is kept as reference and the TM is
+/// run for at most `steps` more steps. If it reaches the same state with the
+/// cells visited in between shifted by the distance the head moved (or not
+/// shifted at all), it repeats these steps forever. For a shift to the right,
+/// all cells right of the visited ones have to be blank in the reference, as
+/// the next repetition reads them; the left is mirrored.
+///
+/// Returns whether a recurrence was found. The tape is restored afterwards.
+fn lin_recurrence<const N: usize>(
+    tm: Tm<N>,
+    state: u8,
+    head: CellId,
+    steps: u64,
+    tape: &mut impl TapeLike,
+    snapshot: &mut Vec<bool>,
+) -> bool {
+    let range = tape.written_range();
+    snapshot.clear();
+    snapshot.extend(tape.iter_written().map(|(_, value)| value.0));
+    let old = |cell: i64| {
+        range.contains(&CellId(cell)) && snapshot[(cell - range.start.0) as usize]
+    };
 
-        let r = self.tape.written_range();
-        let ones = (r.start.0..r.end.0)
-            .filter(|&id| self.tape.get(CellId(id)).0)
-            .count() as u32;
+    let (mut current_state, mut pos) = (state, head.0);
+    let (mut lowest, mut highest) = (pos, pos);
+    let mut found = false;
+    for _ in 0..steps {
+        let action = tm.state(current_state).action_for(tape.get(CellId(pos)));
+        tape.write(CellId(pos), action.write_value());
+        lowest = min(lowest, pos);
+        highest = max(highest, pos);
+        current_state = match action.next_state() {
+            NextState::HaltState => break,
+            NextState::State(v) => v,
+        };
+        match action.movement() {
+            Move::Left => pos -= 1,
+            Move::Right => pos += 1,
+        }
 
-        sink.report_halted(steps, ones);
+        let shift = pos - head.0;
+        let blank_ahead = match shift.cmp(&0) {
+            Ordering::Equal => true,
+            Ordering::Greater => highest >= range.end.0 - 1,
+            Ordering::Less => lowest <= range.start.0,
+        };
+        if current_state == state
+            && blank_ahead
+            && (lowest..=highest).all(|cell| tape.get(CellId(cell + shift)).0 == old(cell))
+        {
+            found = true;
+            break;
+        }
     }
+
+    tape.clear();
+    for (cell, &value) in (range.start.0..).zip(snapshot.iter()) {
+        tape.write(CellId(cell), CellValue(value));
+    }
+    found
 }
 
+/// Number of steps between two checks whether the time budget is exceeded.
+/// Has to be a power of two.
+const TIME_CHECK_INTERVAL: u64 = 1 << 12;
+
+/// After this many steps, `run_tm` continues with the macro machine. Also the
+/// number of steps simulated normally each time the macro machine stops.
+const MACRO_THRESHOLD: u64 = 1 << 16;
+
+/// How much longer the run of a TM gets each time `run_tm` uses the macro
+/// machine.
+const MACRO_GROWTH: u64 = 4;
+
 fn array<T: Copy + Default, const N: usize>(v: T) -> [T; N]
 where
     [T; N]: Default,
@@ -315,3 +1250,217 @@ where
     out.iter_mut().for_each(|x| *x = v);
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gen::{Optimized, TmGenerator},
+        outcome::{AnalysisResult, FnSink},
+    };
+    use super::*;
+
+    fn analyze<const N: usize>(tm: &str, max_steps: u64) -> Option<AnalysisResult>
+    where
+        [bool; N]: Default,
+    {
+        let tm = <Tm<N>>::from_standard(tm).unwrap();
+        let mut analyzer = Analyzer::<N>::builder().max_steps(max_steps).build();
+        let mut result = None;
+        analyzer.analyze(tm, &mut FnSink(|_, r| result = Some(r)));
+        result
+    }
+
+    fn analyze_with_lin<const N: usize>(tm: &str, max_steps: u64) -> Option<AnalysisResult>
+    where
+        [bool; N]: Default,
+    {
+        let tm = <Tm<N>>::from_standard(tm).unwrap();
+        let mut analyzer = Analyzer::<N>::builder()
+            .max_steps(max_steps)
+            .lin_recurrence(true)
+            .build();
+        let mut result = None;
+        analyzer.analyze(tm, &mut FnSink(|_, r| result = Some(r)));
+        result
+    }
+
+    fn tape(cells: &[u8]) -> Tape {
+        let mut tape = Tape::new();
+        for (i, &value) in cells.iter().enumerate() {
+            tape.write(CellId(i as i64), CellValue(value == 1));
+        }
+        tape
+    }
+
+    #[test]
+    fn translated_cyclers() {
+        // Moves left by one cell every three steps, leaving 1s behind. The
+        // second one is the same, mirrored.
+        let left = analyze::<3>("1LB1LH_0RB1LA_1LA1LA", 1000);
+        let right = analyze::<3>("1RB1RH_0LB1RA_1RA1RA", 1000);
+        assert_eq!(left, Some(AnalysisResult::TranslatedCycler));
+        assert_eq!(right, Some(AnalysisResult::TranslatedCycler));
+    }
+
+    #[test]
+    fn lin_recurrence_decides_cyclers_reaching_the_limit() {
+        // A translated cycler that reaches the limit before the records can
+        // catch it, with and without the Lin recurrence check.
+        let tm = "0LB1LH_1RA1LA";
+        assert_eq!(analyze_with_lin::<2>(tm, 8), Some(AnalysisResult::LinRecurrence));
+        assert!(matches!(
+            analyze::<2>(tm, 8),
+            Some(AnalysisResult::AbortedAfterMaxSteps { .. }),
+        ));
+
+        // The BB(2) champion halts after 6 steps, so it must never be decided
+        // as recurrent when it reaches the limit before.
+        let champion = "1RB1LB_1LA1RZ";
+        for max_steps in 1..6 {
+            assert!(matches!(
+                analyze_with_lin::<2>(champion, max_steps),
+                Some(AnalysisResult::AbortedAfterMaxSteps { .. }),
+            ));
+        }
+        assert_eq!(
+            analyze_with_lin::<2>(champion, 100),
+            Some(AnalysisResult::Halted { num_steps: 6, num_ones: 4 }),
+        );
+    }
+
+    #[test]
+    fn cycle_budget() {
+        // Alternates between two configurations from the second step on.
+        let tm = <Tm<2>>::from_standard("0RB1LH_1LA1LA").unwrap();
+        let analyze = |budget| {
+            let mut analyzer = Analyzer::<2>::builder().cycle_budget(budget).build();
+            let mut result = None;
+            analyzer.analyze(tm, &mut FnSink(|_, r| result = Some(r)));
+            result
+        };
+
+        assert_eq!(analyze(None), Some(AnalysisResult::CycleDetected));
+        assert_eq!(analyze(Some(Budget::Steps(100))), Some(AnalysisResult::CycleDetected));
+
+        // Without the cycle detection, it reaches the step limit and is only
+        // decided there.
+        assert_eq!(analyze(Some(Budget::Steps(1))), Some(AnalysisResult::BackwardReasoning));
+    }
+
+    #[test]
+    fn prune() {
+        let tm = <Tm<2>>::from_standard("1RB1LB_1LA1RZ").unwrap();
+        let analyze = |max_steps, high_score| {
+            let mut analyzer = Analyzer::<2>::builder()
+                .max_steps(max_steps)
+                .prune(Some(Arc::new(BestSoFar::with_baseline(high_score))))
+                .build();
+            let mut result = None;
+            analyzer.analyze(tm, &mut FnSink(|_, r| result = Some(r)));
+            result
+        };
+
+        // A TM that could still tie the high score is not pruned.
+        let halted = AnalysisResult::Halted { num_steps: 6, num_ones: 4 };
+        assert_eq!(analyze(100, 4), Some(halted));
+        assert_eq!(analyze(6, 4), Some(halted));
+
+        // Even writing a 1 in each of the 6 steps doesn't reach 7 ones.
+        assert!(matches!(analyze(6, 7), Some(AnalysisResult::Pruned { .. })));
+        assert_eq!(analyze(100, 7), Some(halted));
+    }
+
+    #[test]
+    fn continue_beyond_macro_threshold() {
+        // The macro machine takes over after `MACRO_THRESHOLD` steps, but a
+        // continuation from later on still runs the deciders first. Starting
+        // from the empty tape, only the step count differs from a new run.
+        let continue_from = |tm: &str, steps| {
+            let tm = <Tm<2>>::from_standard(tm).unwrap();
+            let tape = Tape::new();
+            let config = Configuration { state: 0, head: CellId(0), steps, tape: &tape };
+            let continuation = Continuation::new(tm, config);
+            let mut analyzer = Analyzer::<2>::builder().max_steps(2 * steps + 1000).build();
+            let mut result = None;
+            analyzer.continue_tm(tm, &continuation, &mut FnSink(|_, r| result = Some(r)));
+            result
+        };
+
+        for steps in [5, MACRO_THRESHOLD + 1, 100_000, 10_000_000] {
+            let translated = continue_from("0LB1LH_1RA1LA", steps);
+            assert_eq!(translated, Some(AnalysisResult::TranslatedCycler), "from {}", steps);
+            let cycler = continue_from("0RB1LH_1LA1LA", steps);
+            assert_eq!(cycler, Some(AnalysisResult::CycleDetected), "from {}", steps);
+        }
+    }
+
+    #[test]
+    fn backward_reasoning_decides() {
+        // The halting transition C0 can only be reached via B0, which moves
+        // left. So B read a 0 right of the halting cell, which was 0 before
+        // as well. But B is only entered by A0 and B1 moving right, which
+        // would have written a 1 into that cell.
+        let tm = "1RB1LA_1LC1RB_1LH1LA";
+        assert!(halt_unreachable_backwards(<Tm<3>>::from_standard(tm).unwrap()));
+        assert_eq!(analyze::<3>(tm, 1000), Some(AnalysisResult::BackwardReasoning));
+    }
+
+    #[test]
+    fn backward_reasoning_never_decides_halting_tms() {
+        // The champions of BB(2), BB(3) and BB(4).
+        assert!(!halt_unreachable_backwards(<Tm<2>>::from_standard("1RB1LB_1LA1RZ").unwrap()));
+        let tm = <Tm<3>>::from_standard("1RB1RZ_1LB0RC_1LC1LA").unwrap();
+        assert!(!halt_unreachable_backwards(tm));
+        let tm = <Tm<4>>::from_standard("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").unwrap();
+        assert!(!halt_unreachable_backwards(tm));
+
+        // All halting TMs with 3 states.
+
+        Optimized::<3>::default().for_all(|tm| {
+            let mut analyzer = Analyzer::<3>::builder().max_steps(1000).build();
+            analyzer.analyze(tm, &mut FnSink(|tm, result| {
+                if let AnalysisResult::Halted { .. } = result {
+                    assert!(!halt_unreachable_backwards(tm), "{}", tm.to_standard_notation());
+                }
+            }));
+        });
+    }
+
+    #[test]
+    fn backward_reasoning_gives_up_at_depth_limit() {
+        // B can only be reached from itself, but going backwards from the
+        // halting transition B1, B0 can be applied forever without
+        // contradiction: the search gives up after `BACKWARD_DEPTH` steps.
+        // (A static check decides this TM, as A runs away to the right.)
+        let tm = "1RA1RA_0LB1LH";
+        assert!(!halt_unreachable_backwards(<Tm<2>>::from_standard(tm).unwrap()));
+        assert_eq!(analyze::<2>(tm, 1000), Some(AnalysisResult::SimpleElope));
+    }
+
+    #[test]
+    fn record_compares_visited_cells_behind() {
+        // A record at the right end, with the cells 1, 1, 0, 1 behind it.
+        let mut record = Record::default();
+        record.save(2, CellId(4), 10, true, &tape(&[1, 0, 1, 1]));
+
+        // Behind the new record are the cells 1, 1, 1, 0, 1, 0.
+        let later = tape(&[0, 1, 0, 1, 1, 1]);
+        assert!(record.matches(2, CellId(6), 2, &later));
+        assert!(!record.matches(1, CellId(6), 2, &later));
+        assert!(!record.matches(2, CellId(6), 3, &later));
+
+        // The cells beyond the saved ones were 0.
+        assert!(record.matches(2, CellId(6), 6, &tape(&[0, 0, 1, 0, 1, 1])));
+        assert!(!record.matches(2, CellId(6), 6, &tape(&[1, 0, 1, 0, 1, 1])));
+    }
+
+    #[test]
+    fn record_at_left_end() {
+        // A record at the left end, with the cells 1, 0 behind it.
+        let mut record = Record::default();
+        record.save(0, CellId(-1), 10, false, &tape(&[1, 0]));
+
+        assert!(record.matches(0, CellId(-1), 2, &tape(&[1, 0, 1])));
+        assert!(!record.matches(0, CellId(-1), 2, &tape(&[1, 1])));
+    }
+}
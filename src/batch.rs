@@ -0,0 +1,350 @@
+//! Simulating many TMs at once, for the TMs of a sweep that pass the static
+//! checks (see `SweepBuilder::simd`).
+//!
+//! Most of these TMs halt after a few steps, but the analyzer spends a lot of
+//! time per step on its deciders. Here, `LANES` TMs are run in lockstep by a
+//! single thread instead: each lane has a tiny tape of two `u64` shift
+//! registers around the head, and each step updates all lanes with the same
+//! branch-free code, which the compiler turns into SIMD instructions. The
+//! actions are looked up directly in `Tm::encoded`. As soon as a lane halts,
+//! exceeds the step budget or would leave its tape, the next TM is loaded
+//! into it. Only the TMs that halted are reported from here, all others are
+//! analyzed as usual, from scratch. So the outcomes are exactly the same.
+//!
+//! This does not pay off yet: the TMs that halt in a lane are cheap for the
+//! analyzer as well, and the others are simulated twice. Sweeps with it are
+//! slower than without (e.g. by about 25% for N = 4 with 300 steps). Keeping
+//! those TMs alive in the lanes until they are decided would need the
+//! deciders of the analyzer in here as well.
+
+use std::{cmp::min, ops::Range};
+
+use crate::{
+    analyze::HaltConvention,
+    outcome::OutcomeSink,
+    tape::{CellId, CellValue, Tape, TapeLike},
+    tm::Tm,
+};
+
+
+/// The number of TMs simulated in lockstep.
+pub const LANES: usize = 16;
+
+/// The head of a lane may move at most this many cells from the start.
+/// Together with the written cells, it then always fits into the 64 cells
+/// stored on either side of the head.
+const RADIUS: i64 = 31;
+
+/// The largest tape of a `Halted` TM: cells `-MAX_CELLS / 2..MAX_CELLS / 2`.
+pub const MAX_CELLS: usize = 256;
+
+/// A TM that halted in a batch simulation (here or on the GPU), with
+/// everything needed to report it like the analyzer would.
+#[derive(Debug, Clone)]
+pub struct Halted {
+    /// The number of steps, according to the halt convention.
+    pub steps: u64,
+
+    /// The cells that were written, as `Tape::written_range`.
+    written: Range<CellId>,
+
+    /// The values of the cells in `written`, cell `c` is bit `c +
+    /// MAX_CELLS / 2`.
+    cells: [u64; MAX_CELLS / 64],
+}
+
+impl Halted {
+    /// Takes the values of all cells in `written` from `get`.
+    pub(crate) fn new(steps: u64, written: Range<CellId>, get: impl Fn(CellId) -> bool) -> Self {
+        let mut cells = [0; MAX_CELLS / 64];
+        for id in written.start.0..written.end.0 {
+            let bit = (id + MAX_CELLS as i64 / 2) as usize;
+            cells[bit / 64] |= (get(CellId(id)) as u64) << (bit % 64);
+        }
+
+        Self { steps, written, cells }
+    }
+
+    /// Reports the TM as halted to `sink`, with its final tape written to
+    /// `tape`.
+    pub fn report<const N: usize>(
+        &self,
+        tm: Tm<N>,
+        tape: &mut Tape,
+        sink: &mut impl OutcomeSink<N>,
+    ) {
+        tape.clear();
+        for id in self.written.start.0..self.written.end.0 {
+            let bit = (id + MAX_CELLS as i64 / 2) as usize;
+            tape.write(CellId(id), CellValue((self.cells[bit / 64] >> (bit % 64)) & 1 != 0));
+        }
+        let ones = tape.ones() as u32;
+        sink.report_halted(tm, self.steps, ones, tape);
+    }
+}
+
+/// Simulates all `tms` for at most `max_steps` (or `step_budget`, if that is
+/// lower) and calls `on_halted` with the index in `tms` of each TM that
+/// halted, in no particular order.
+pub fn simulate<const N: usize>(
+    tms: &[Tm<N>],
+    max_steps: u64,
+    step_budget: u32,
+    halt_convention: HaltConvention,
+    mut on_halted: impl FnMut(usize, Halted),
+) {
+    let max_steps = min(max_steps, step_budget as u64);
+    let exclude = halt_convention == HaltConvention::Exclude;
+    let mut lanes = Lanes::default();
+    let mut next = 0;
+
+    // All lanes are empty in the beginning, so they are all loaded.
+    let mut finished = ALL_LANES;
+    loop {
+        // Only the lanes that finished are visited, one bit after another, to
+        // avoid mispredicted branches.
+        while finished != 0 {
+            let lane = finished.trailing_zeros() as usize;
+            finished &= finished - 1;
+            if let Some(i) = lanes.index[lane] {
+                if let Some(halted) = lanes.result(lane, exclude) {
+                    on_halted(i, halted);
+                }
+            }
+            match tms.get(next) {
+                Some(&tm) => {
+                    lanes.load(lane, next, tm);
+                    next += 1;
+                }
+                None => lanes.unload(lane),
+            }
+        }
+        if lanes.active == 0 {
+            break;
+        }
+
+        finished = lanes.run::<N>(max_steps, exclude);
+    }
+}
+
+/// The bit mask of all lanes.
+const ALL_LANES: u32 = ((1u64 << LANES) - 1) as u32;
+
+/// The configurations of all lanes, one array per field so that all lanes
+/// can be updated at once. Everything used in `step` has 64 bits, so that
+/// all of it is processed with the same number of lanes per instruction.
+#[derive(Default)]
+struct Lanes {
+    /// The index in the batch of the TM in each lane, `None` if empty.
+    index: [Option<usize>; LANES],
+
+    /// The lanes with a TM, one bit per lane.
+    active: u32,
+
+    /// The TMs, see `Tm::encoded`.
+    tm: [u64; LANES],
+    state: [u64; LANES],
+
+    /// The cells left of the head, the closest one in the lowest bit.
+    left: [u64; LANES],
+
+    /// The cell under the head in the lowest bit and the cells right of it.
+    right: [u64; LANES],
+
+    /// The position of the head and the range of written cells, relative to
+    /// the start.
+    pos: [i64; LANES],
+    lo: [i64; LANES],
+    hi: [i64; LANES],
+
+    /// The states visited since the head left the written cells, one bit
+    /// per state. If a state is visited twice, the TM runs away forever (see
+    /// `Analyzer`), so it is left to the analyzer to report that.
+    visited: [u64; LANES],
+
+    steps: [u64; LANES],
+
+    /// All ones if the TM halted in the last step, see `mask`.
+    halted: [u64; LANES],
+}
+
+impl Lanes {
+    fn load<const N: usize>(&mut self, lane: usize, index: usize, tm: Tm<N>) {
+        self.index[lane] = Some(index);
+        self.active |= 1 << lane;
+        self.tm[lane] = tm.encoded;
+        self.state[lane] = 0;
+        self.left[lane] = 0;
+        self.right[lane] = 0;
+        self.pos[lane] = 0;
+        self.lo[lane] = 0;
+        self.hi[lane] = 0;
+        self.visited[lane] = 0;
+        self.steps[lane] = 0;
+        self.halted[lane] = 0;
+    }
+
+    fn unload(&mut self, lane: usize) {
+        self.index[lane] = None;
+        self.active &= !(1 << lane);
+    }
+
+    /// Steps all lanes until at least one TM finished and returns the lanes
+    /// with a TM that finished, see `step`.
+    ///
+    /// Vectorizing `step` needs shifts by a different amount per lane, which
+    /// x86_64 only has with AVX2. So if the CPU supports it, a version
+    /// compiled for AVX2 is used, even if the binary is not.
+    fn run<const N: usize>(&mut self, max_steps: u64, exclude: bool) -> u32 {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // Safety: the CPU supports AVX2, as just checked.
+            return unsafe { self.run_avx2::<N>(max_steps, exclude) };
+        }
+
+        self.run_generic::<N>(max_steps, exclude)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn run_avx2<const N: usize>(&mut self, max_steps: u64, exclude: bool) -> u32 {
+        self.run_generic::<N>(max_steps, exclude)
+    }
+
+    #[inline(always)]
+    fn run_generic<const N: usize>(&mut self, max_steps: u64, exclude: bool) -> u32 {
+        loop {
+            let finished = self.step::<N>(max_steps, exclude);
+            if finished != 0 {
+                return finished;
+            }
+        }
+    }
+
+    /// Does one step in all lanes and returns the lanes with a TM that
+    /// finished, one bit per lane. Lanes that are finished or empty are
+    /// stepped as well, as that is cheaper than masking them, but their
+    /// results are ignored. There are no branches, so that the compiler can
+    /// vectorize this: conditions are masks and values are chosen with
+    /// `select`.
+    #[inline(always)]
+    fn step<const N: usize>(&mut self, max_steps: u64, exclude: bool) -> u32 {
+        let exclude = mask(exclude);
+        let mut finished = 0;
+        for lane in 0..LANES {
+            let (left, right, pos) = (self.left[lane], self.right[lane], self.pos[lane]);
+            let state = self.state[lane];
+            let read = right & 1;
+            let action = (self.tm[lane] >> ((state * 2 + read) * 5)) & 0b11111;
+            let next = action >> 2;
+            let halts = mask(next == N as u64);
+
+            let outside = mask(pos < self.lo[lane]) | mask(pos > self.hi[lane]);
+            let state_bit = 1 << state;
+            let runs_away = outside & mask(self.visited[lane] & state_bit != 0);
+            self.visited[lane] = (self.visited[lane] | state_bit) & outside;
+
+            // The action stores the inverted value to write. If the halting
+            // transition is not counted, it doesn't write.
+            let write = select(halts & exclude, read, !action & 1);
+            let right = (right & !1) | write;
+            self.lo[lane] = self.lo[lane].min(pos);
+            self.hi[lane] = self.hi[lane].max(pos);
+            self.steps[lane] += 1;
+
+            // Halting TMs stay where they are, so that the tape can be read
+            // from the registers as is.
+            let moves_right = mask(action & 0b10 != 0);
+            let moved_left = select(moves_right, (left << 1) | (right & 1), left >> 1);
+            let moved_right = select(moves_right, right >> 1, (right << 1) | (left & 1));
+            let pos = pos + (select(moves_right, 1, u64::MAX) & !halts) as i64;
+            self.left[lane] = select(halts, left, moved_left);
+            self.right[lane] = select(halts, right, moved_right);
+            self.pos[lane] = pos;
+            self.state[lane] = select(halts, state, next);
+            self.halted[lane] = halts;
+
+            let done = halts
+                | runs_away
+                | mask(self.steps[lane] >= max_steps)
+                | mask(pos.abs() > RADIUS);
+            finished |= ((done & 1) as u32) << lane;
+        }
+
+        finished & self.active
+    }
+
+    /// The result of the finished TM in `lane`.
+    fn result(&self, lane: usize, exclude: bool) -> Option<Halted> {
+        if self.halted[lane] == 0 {
+            return None;
+        }
+
+        let (left, right, pos) = (self.left[lane], self.right[lane], self.pos[lane]);
+        let steps = self.steps[lane] - exclude as u64;
+        let written = CellId(self.lo[lane])..CellId(self.hi[lane] + 1);
+        Some(Halted::new(steps, written, |id| {
+            if id.0 >= pos {
+                (right >> (id.0 - pos)) & 1 != 0
+            } else {
+                (left >> (pos - 1 - id.0)) & 1 != 0
+            }
+        }))
+    }
+}
+
+/// All ones if `b` is true, all zeros otherwise.
+#[inline(always)]
+fn mask(b: bool) -> u64 {
+    0u64.wrapping_sub(b as u64)
+}
+
+/// The bits of `a` where `mask` is set and the ones of `b` elsewhere.
+#[inline(always)]
+fn select(mask: u64, a: u64, b: u64) -> u64 {
+    (a & mask) | (b & !mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analyze::Analyzer,
+        gen::{All, TmGenerator},
+        outcome::{AnalysisResult, FnSink},
+    };
+    use super::*;
+
+    /// All TMs with 2 states and the first ones with 3 states that halted in
+    /// the batch simulation have the same outcome as with the analyzer.
+    #[test]
+    fn same_as_analyzer() {
+        fn check<const N: usize>(halt_convention: HaltConvention)
+        where
+            [bool; N]: Default,
+        {
+            let generator = <All<N>>::default();
+            let mut tms = Vec::new();
+            generator.for_range(0..generator.num_tms().min(100_000), |tm| tms.push(tm));
+
+            let mut analyzer = Analyzer::builder()
+                .max_steps(100)
+                .halt_convention(halt_convention)
+                .build();
+            let mut tape = Tape::new();
+            let mut num_halted = 0;
+            simulate(&tms, 100, 50, halt_convention, |i, halted| {
+                let mut ours = None;
+                halted.report(tms[i], &mut tape, &mut FnSink(|_, r| ours = Some(r)));
+                let mut expected = None;
+                analyzer.run_tm(tms[i], &mut FnSink(|_, r| expected = Some(r)));
+                assert_eq!(ours, expected, "{:?}", tms[i]);
+                assert!(matches!(ours, Some(AnalysisResult::Halted { .. })));
+                num_halted += 1;
+            });
+            assert!(num_halted > 0);
+        }
+
+        check::<2>(HaltConvention::Include);
+        check::<3>(HaltConvention::Exclude);
+    }
+}
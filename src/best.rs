@@ -0,0 +1,165 @@
+//! The best TM found so far during a sweep, shared by all worker threads.
+//!
+//! The `Summary` of each worker only knows about the TMs of that worker and
+//! the summaries are merged at the very end. This registry is updated by all
+//! workers immediately, so the current high score is known during the run.
+
+use std::sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}};
+
+use crate::{
+    outcome::{Configuration, OutcomeSink},
+    tape::TapeLike,
+    tm::Tm,
+};
+
+
+/// A TM that halted after `steps` steps with `score` many 1s on the tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Best {
+    pub score: u32,
+    pub id: u64,
+    pub steps: u64,
+}
+
+/// A function that is called with each new best TM, see
+/// `BestSoFar::on_new_best`.
+pub type NewBestCallback = Arc<dyn Fn(Best) + Send + Sync>;
+
+/// The halting TM with the most 1s that any worker has found so far. If
+/// multiple TMs reach the high score, the one reported first is kept, so the
+/// ID is not necessarily the final champion (see `TieBreak`).
+///
+/// Checking whether a score is a new high score is a single atomic load.
+/// Workers only take the lock when they actually found a better TM, which
+/// happens only a handful of times per run.
+#[derive(Default)]
+pub struct BestSoFar {
+    /// `score + 1` of `best` or 0 if there is none yet.
+    threshold: AtomicU64,
+    best: Mutex<Option<Best>>,
+    on_new_best: Option<NewBestCallback>,
+}
+
+impl BestSoFar {
+    /// Creates a registry that already knows a high score of `score`, e.g.
+    /// from a previous run, without knowing the TM. Only TMs with a higher
+    /// score are recorded.
+    pub fn with_baseline(score: u32) -> Self {
+        Self {
+            threshold: AtomicU64::new(score as u64 + 1),
+            best: Mutex::new(None),
+            on_new_best: None,
+        }
+    }
+
+    /// Sets a function that is called whenever a new best TM is recorded.
+    /// It is called from the thread that offered the TM while holding the
+    /// lock, so calls never overlap and come in increasing order of score.
+    pub fn on_new_best(mut self, f: Option<NewBestCallback>) -> Self {
+        self.on_new_best = f;
+        self
+    }
+
+    /// Records the TM if it is better than the best one so far. Returns
+    /// whether it was recorded.
+    pub fn offer(&self, score: u32, id: u64, steps: u64) -> bool {
+        if self.threshold.load(Ordering::Acquire) > score as u64 {
+            return false;
+        }
+
+        let mut best = self.best.lock().expect("poisened lock");
+        if best.is_some_and(|b| b.score >= score) {
+            return false;
+        }
+        let new = Best { score, id, steps };
+        *best = Some(new);
+        self.threshold.store(score as u64 + 1, Ordering::Release);
+        if let Some(f) = &self.on_new_best {
+            f(new);
+        }
+        true
+    }
+
+    /// Returns the score of the best TM so far. Unlike `get`, this never
+    /// takes the lock.
+    pub fn score(&self) -> Option<u32> {
+        self.threshold.load(Ordering::Acquire).checked_sub(1).map(|s| s as u32)
+    }
+
+    /// Returns the best TM so far. This is `None` if no TM beat the baseline
+    /// (see `with_baseline`).
+    pub fn get(&self) -> Option<Best> {
+        if self.threshold.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+        *self.best.lock().expect("poisened lock")
+    }
+}
+
+impl<const N: usize> OutcomeSink<N> for &BestSoFar {
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, _: &impl TapeLike) {
+        self.offer(num_ones, tm.encoded, num_steps);
+    }
+
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32) {
+        self.offer(num_ones, tm.encoded, num_steps);
+    }
+
+    fn report_no_halt_transition(&mut self, _: Tm<N>) {}
+    fn report_simple_elope(&mut self, _: Tm<N>) {}
+    fn report_halt_state_not_reachable(&mut self, _: Tm<N>) {}
+    fn report_run_away(&mut self, _: Tm<N>) {}
+    fn report_cycle(&mut self, _: Tm<N>) {}
+    fn report_translated_cycler(&mut self, _: Tm<N>) {}
+    fn report_backward_reasoning(&mut self, _: Tm<N>) {}
+    fn report_ctl(&mut self, _: Tm<N>) {}
+    fn report_lin_recurrence(&mut self, _: Tm<N>) {}
+    fn report_aborted_after_max_steps(&mut self, _: Tm<N>, _: Configuration<'_, impl TapeLike>) {}
+    fn report_pruned(&mut self, _: Tm<N>, _: u64) {}
+    fn report_known(&mut self, _: Tm<N>) {}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{Arc, Mutex}, thread};
+    use super::{Best, BestSoFar};
+
+    #[test]
+    fn concurrent_offers() {
+        let best = BestSoFar::default();
+        assert_eq!(best.get(), None);
+
+        thread::scope(|s| {
+            for t in 0..4u64 {
+                let best = &best;
+                s.spawn(move || {
+                    for score in 0..1000 {
+                        best.offer(score, t * 1000 + score as u64, 1);
+                    }
+                });
+            }
+        });
+
+        let b = best.get().unwrap();
+        assert_eq!(b.score, 999);
+        assert_eq!(b.id % 1000, 999);
+        assert!(!best.offer(999, 0, 1));
+        assert!(best.offer(1000, 7, 3));
+        assert_eq!(best.get(), Some(Best { score: 1000, id: 7, steps: 3 }));
+    }
+
+    #[test]
+    fn notifies_new_best() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let best = {
+            let seen = seen.clone();
+            BestSoFar::with_baseline(2)
+                .on_new_best(Some(Arc::new(move |b| seen.lock().unwrap().push(b.score))))
+        };
+        for score in [1, 3, 2, 5, 5, 4] {
+            best.offer(score, score as u64, 10);
+        }
+        assert_eq!(*seen.lock().unwrap(), [3, 5]);
+    }
+}
@@ -0,0 +1,182 @@
+//! A minimal arbitrary-precision unsigned integer, just enough to count the
+//! steps of accelerated runs beyond `u64` (see the `big-steps` feature).
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+
+/// An unsigned integer of any size. The limbs are stored least significant
+/// first, without trailing zeros, so that equal numbers have equal limbs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u64>,
+}
+
+/// The largest power of 10 fitting into a limb, for converting from and to
+/// decimal in chunks of 19 digits.
+const DECIMAL_CHUNK: u64 = 10_000_000_000_000_000_000;
+const DECIMAL_CHUNK_DIGITS: usize = 19;
+
+impl BigUint {
+    /// Returns the number if it fits into `u128`.
+    pub fn to_u128(&self) -> Option<u128> {
+        match *self.limbs {
+            [] => Some(0),
+            [low] => Some(low.into()),
+            [low, high] => Some(u128::from(high) << 64 | u128::from(low)),
+            _ => None,
+        }
+    }
+
+    pub fn add_u128(&mut self, v: u128) {
+        // `carry` is what is left to add, starting at the current limb.
+        let mut carry = v;
+        for limb in &mut self.limbs {
+            if carry == 0 {
+                break;
+            }
+            let sum = u128::from(*limb) + (carry as u64) as u128;
+            *limb = sum as u64;
+            carry = (carry >> 64) + (sum >> 64);
+        }
+        while carry > 0 {
+            self.limbs.push(carry as u64);
+            carry >>= 64;
+        }
+    }
+
+    /// Returns `self - other`, or `None` if `other` is larger.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+
+        let mut limbs = self.limbs.clone();
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let sub = other.limbs.get(i).copied().unwrap_or(0);
+            let (v, b1) = limb.overflowing_sub(sub);
+            let (v, b2) = v.overflowing_sub(borrow as u64);
+            *limb = v;
+            borrow = b1 || b2;
+        }
+        let mut out = Self { limbs };
+        out.normalize();
+        Some(out)
+    }
+
+    /// Sets `self` to `self * factor + summand`.
+    fn mul_add(&mut self, factor: u64, summand: u64) {
+        let mut carry = summand as u128;
+        for limb in &mut self.limbs {
+            let v = *limb as u128 * factor as u128 + carry;
+            *limb = v as u64;
+            carry = v >> 64;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u64);
+        }
+        self.normalize();
+    }
+
+    /// Sets `self` to `self / divisor` and returns the remainder.
+    fn div_rem(&mut self, divisor: u64) -> u64 {
+        let mut rem = 0u128;
+        for limb in self.limbs.iter_mut().rev() {
+            let v = rem << 64 | *limb as u128;
+            *limb = (v / divisor as u128) as u64;
+            rem = v % divisor as u128;
+        }
+        self.normalize();
+        rem as u64
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(v: u64) -> Self {
+        let mut out = Self { limbs: vec![v] };
+        out.normalize();
+        out
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = String;
+
+    /// Parses a decimal number. Underscores can be used as separators.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.replace('_', "");
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("invalid number '{}'", s));
+        }
+
+        let mut out = Self::default();
+        for chunk in digits.as_bytes().chunks(DECIMAL_CHUNK_DIGITS) {
+            let value = std::str::from_utf8(chunk).unwrap().parse::<u64>().unwrap();
+            out.mul_add(10u64.pow(chunk.len() as u32), value);
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = self.clone();
+        let mut chunks = vec![rest.div_rem(DECIMAL_CHUNK)];
+        while !rest.limbs.is_empty() {
+            chunks.push(rest.div_rem(DECIMAL_CHUNK));
+        }
+
+        let mut s = chunks.last().unwrap().to_string();
+        for chunk in chunks.iter().rev().skip(1) {
+            s += &format!("{:0width$}", chunk, width = DECIMAL_CHUNK_DIGITS);
+        }
+        f.pad(&s)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let big = "340282366920938463463374607431768211456".parse::<BigUint>().unwrap();
+        assert_eq!(big.to_string(), "340282366920938463463374607431768211456");
+        assert_eq!(big.to_u128(), None);
+        assert_eq!(BigUint::from(0).to_string(), "0");
+        assert_eq!("1_000".parse::<BigUint>().unwrap().to_u128(), Some(1000));
+        assert!("12a".parse::<BigUint>().is_err());
+
+        let mut v = BigUint::from(u64::MAX);
+        v.add_u128(1);
+        assert_eq!(v.to_string(), "18446744073709551616");
+        assert_eq!(v.to_u128(), Some(1 << 64));
+        let mut w = big.clone();
+        w.add_u128(u128::MAX);
+        assert_eq!(w.to_string(), "680564733841876926926749214863536422911");
+        assert!(v > BigUint::from(u64::MAX));
+        assert_eq!(v.checked_sub(&BigUint::from(1)), Some(BigUint::from(u64::MAX)));
+        assert_eq!(BigUint::from(1).checked_sub(&v), None);
+        assert_eq!(big.checked_sub(&big), Some(BigUint::from(0)));
+    }
+}
@@ -0,0 +1,166 @@
+//! Limits for how much work an analysis stage may spend on a single TM.
+
+use std::{fmt, str::FromStr, time::{Duration, Instant}};
+
+
+/// A per-TM budget for one analysis stage. TMs exceeding the budget are left
+/// undecided by that stage.
+///
+/// Parsed from strings like `100k-steps`, `2M-steps` (steps) or `10ms`, `1s`
+/// (wall-clock time). Supported suffixes for step counts are `k`, `M` and `G`;
+/// supported time units are `ns`, `us`, `ms` and `s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// At most this many steps per TM.
+    Steps(u64),
+
+    /// At most this much wall-clock time per TM. Note that this makes results
+    /// depend on the machine and its load.
+    Time(Duration),
+}
+
+impl Budget {
+    /// Returns the step limit or `None` if this is a time budget.
+    pub fn steps(&self) -> Option<u64> {
+        match *self {
+            Budget::Steps(steps) => Some(steps),
+            Budget::Time(_) => None,
+        }
+    }
+
+    /// Returns the time limit or `None` if this is a step budget.
+    pub fn time(&self) -> Option<Duration> {
+        match *self {
+            Budget::Steps(_) => None,
+            Budget::Time(time) => Some(time),
+        }
+    }
+}
+
+/// Keeps track of how much of a budget an analysis stage used for one TM.
+/// What a step is depends on the stage.
+pub struct Meter {
+    remaining_steps: u64,
+    deadline: Option<Instant>,
+    steps_since_check: u64,
+}
+
+impl Meter {
+    /// Number of steps between two checks whether the time budget is
+    /// exceeded, as reading the clock is comparatively expensive.
+    const CHECK_INTERVAL: u64 = 1 << 10;
+
+    /// Starts measuring. With `None`, the budget is unlimited.
+    pub fn start(budget: Option<Budget>) -> Self {
+        Self {
+            remaining_steps: budget.and_then(|b| b.steps()).unwrap_or(u64::MAX),
+            deadline: budget.and_then(|b| b.time()).map(|t| Instant::now() + t),
+            steps_since_check: 0,
+        }
+    }
+
+    /// Uses up one step. Returns `false` if the budget is exhausted.
+    pub fn step(&mut self) -> bool {
+        if self.remaining_steps == 0 {
+            return false;
+        }
+        self.remaining_steps -= 1;
+
+        if let Some(deadline) = self.deadline {
+            self.steps_since_check += 1;
+            if self.steps_since_check == Self::CHECK_INTERVAL {
+                self.steps_since_check = 0;
+                if Instant::now() >= deadline {
+                    self.remaining_steps = 0;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl FromStr for Budget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!(
+            "invalid budget '{}' (expected e.g. '100k-steps' or '10ms')",
+            s,
+        );
+
+        let digits_end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(digits_end);
+        let number: u64 = number.parse().map_err(|_| err())?;
+
+        if let Some(prefix) = unit.strip_suffix("-steps") {
+            let factor = match prefix {
+                "" => 1,
+                "k" => 1_000,
+                "M" => 1_000_000,
+                "G" => 1_000_000_000,
+                _ => return Err(err()),
+            };
+            let steps = number.checked_mul(factor)
+                .ok_or_else(|| format!("step budget '{}' is too large", s))?;
+
+            return Ok(Budget::Steps(steps));
+        }
+
+        let time = match unit {
+            "ns" => Duration::from_nanos(number),
+            "us" => Duration::from_micros(number),
+            "ms" => Duration::from_millis(number),
+            "s" => Duration::from_secs(number),
+            _ => return Err(err()),
+        };
+
+        Ok(Budget::Time(time))
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Budget::Steps(steps) => write!(f, "{}-steps", steps),
+            Budget::Time(time) => write!(f, "{:?}", time),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("300-steps".parse(), Ok(Budget::Steps(300)));
+        assert_eq!("100k-steps".parse(), Ok(Budget::Steps(100_000)));
+        assert_eq!("2M-steps".parse(), Ok(Budget::Steps(2_000_000)));
+        assert_eq!("10ms".parse(), Ok(Budget::Time(Duration::from_millis(10))));
+        assert_eq!("5us".parse(), Ok(Budget::Time(Duration::from_micros(5))));
+        assert_eq!("2s".parse(), Ok(Budget::Time(Duration::from_secs(2))));
+
+        assert!("".parse::<Budget>().is_err());
+        assert!("10".parse::<Budget>().is_err());
+        assert!("ms".parse::<Budget>().is_err());
+        assert!("10h".parse::<Budget>().is_err());
+        assert!("10x-steps".parse::<Budget>().is_err());
+        assert_eq!("5G-steps".parse(), Ok(Budget::Steps(5_000_000_000)));
+        assert!("100000000000G-steps".parse::<Budget>().is_err());
+    }
+
+    #[test]
+    fn meter() {
+        let mut meter = Meter::start(Some(Budget::Steps(3)));
+        assert!(meter.step() && meter.step() && meter.step());
+        assert!(!meter.step());
+
+        let mut meter = Meter::start(None);
+        assert!((0..10_000).all(|_| meter.step()));
+
+        let mut meter = Meter::start(Some(Budget::Time(Duration::ZERO)));
+        assert!(!(0..=Meter::CHECK_INTERVAL).all(|_| meter.step()));
+    }
+}
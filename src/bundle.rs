@@ -0,0 +1,236 @@
+//! Bundles of all files of a sweep in a single file (see `export-run` and
+//! `import-run`), e.g. to move a long running sweep from a laptop to a
+//! server and resume it there.
+//!
+//! The file starts with the header `beaver-bundle 1`, followed by the
+//! manifest as comments. Then, for each file, there is a line
+//! `<kind> <name> <length>`, followed by the `<length>` bytes of the file and
+//! a newline. The first file is the checkpoint (kind `checkpoint`), all
+//! others (e.g. written by `--undecided-out`) have the kind `file`. Names are
+//! plain file names without directories.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+use anyhow::{Context, Result, anyhow};
+
+use crate::manifest::Manifest;
+
+
+const HEADER: &str = "beaver-bundle 1";
+
+/// The role of a file in a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Checkpoint,
+    File,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Checkpoint => "checkpoint",
+            Self::File => "file",
+        }
+    }
+}
+
+/// Writes the header and manifest of a bundle. The files are then added
+/// with `write_entry`, the checkpoint first.
+pub fn write_header(w: &mut impl Write, manifest: &Manifest) -> io::Result<()> {
+    writeln!(w, "{}", HEADER)?;
+    manifest.write_comments(w)
+}
+
+/// Writes one file with `len` bytes, read from `content`.
+pub fn write_entry(
+    w: &mut impl Write,
+    kind: Kind,
+    name: &str,
+    len: u64,
+    content: &mut impl Read,
+) -> Result<()> {
+    check_name(name)?;
+    writeln!(w, "{} {} {}", kind.name(), name, len)?;
+    let copied = io::copy(&mut content.take(len), w)?;
+    if copied != len {
+        return Err(anyhow!("'{}' changed while it was bundled", name));
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+/// Reads a bundle and calls `f` for each file with its kind, name and
+/// content. `f` does not have to read the whole content.
+pub fn read_entries(
+    r: &mut impl BufRead,
+    mut f: impl FnMut(Kind, &str, &mut dyn Read) -> Result<()>,
+) -> Result<()> {
+    /// Reads the next line that is not a comment into `line`. Returns
+    /// `false` at the end.
+    fn next_line(r: &mut impl BufRead, line: &mut String) -> Result<bool> {
+        loop {
+            line.clear();
+            if r.read_line(line)? == 0 {
+                return Ok(false);
+            }
+            if !line.starts_with('#') {
+                return Ok(true);
+            }
+        }
+    }
+
+    let mut line = String::new();
+    if !next_line(r, &mut line)? || line.trim_end() != HEADER {
+        return Err(anyhow!("missing header '{}'", HEADER));
+    }
+
+    let mut first = true;
+    while next_line(r, &mut line)? {
+        let err = || anyhow!("invalid bundle entry '{}'", line.trim_end());
+        let mut parts = line.trim_end().split(' ');
+        let kind = match parts.next() {
+            Some("checkpoint") => Kind::Checkpoint,
+            Some("file") => Kind::File,
+            _ => return Err(err()),
+        };
+        let name = parts.next().ok_or_else(err)?.to_owned();
+        let len = parts.next().and_then(|len| len.parse::<u64>().ok()).ok_or_else(err)?;
+        if parts.next().is_some() || (kind == Kind::Checkpoint) != first {
+            return Err(err());
+        }
+        check_name(&name)?;
+
+        let mut content = (&mut *r).take(len);
+        f(kind, &name, &mut content)?;
+        io::copy(&mut content, &mut io::sink())?;
+        if content.limit() != 0 {
+            return Err(anyhow!("bundle is truncated in '{}'", name));
+        }
+        let mut newline = [0];
+        r.read_exact(&mut newline).context("bundle is truncated")?;
+        first = false;
+    }
+
+    if first {
+        return Err(anyhow!("bundle contains no checkpoint"));
+    }
+    Ok(())
+}
+
+/// Creates a bundle at `path` with the given checkpoint and other files.
+pub fn export(
+    path: &Path,
+    checkpoint: &Path,
+    files: &[PathBuf],
+    manifest: &Manifest,
+) -> Result<()> {
+    let out = File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    let mut w = BufWriter::new(out);
+    write_header(&mut w, manifest)?;
+
+    let entries = std::iter::once((Kind::Checkpoint, checkpoint))
+        .chain(files.iter().map(|f| (Kind::File, &**f)));
+    for (kind, file_path) in entries {
+        let name = file_path.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("'{}' has no valid file name", file_path.display()))?;
+        let mut file = File::open(file_path)
+            .with_context(|| format!("failed to open '{}'", file_path.display()))?;
+        let len = file.metadata()?.len();
+        write_entry(&mut w, kind, name, len, &mut file)
+            .with_context(|| format!("failed to bundle '{}'", file_path.display()))?;
+    }
+
+    w.flush().with_context(|| format!("failed to write '{}'", path.display()))
+}
+
+/// Extracts all files of the bundle at `path` into `dir` and returns their
+/// paths, the checkpoint first. Fails before writing anything if one of the
+/// files already exists, unless `overwrite` is set.
+pub fn import(path: &Path, dir: &Path, overwrite: bool) -> Result<Vec<PathBuf>> {
+    let open = || -> Result<_> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open '{}'", path.display()))?;
+        Ok(BufReader::new(file))
+    };
+
+    let mut names = Vec::new();
+    read_entries(&mut open()?, |_, name, _| {
+        let target = dir.join(name);
+        if names.contains(&target) {
+            return Err(anyhow!("'{}' is contained twice", name));
+        }
+        if !overwrite && target.exists() {
+            return Err(anyhow!("'{}' already exists", target.display()));
+        }
+        names.push(target);
+        Ok(())
+    }).with_context(|| format!("failed to read bundle '{}'", path.display()))?;
+
+    read_entries(&mut open()?, |_, name, content| {
+        let target = dir.join(name);
+        let mut file = File::create(&target)
+            .with_context(|| format!("failed to create '{}'", target.display()))?;
+        io::copy(content, &mut file)
+            .with_context(|| format!("failed to write '{}'", target.display()))?;
+        Ok(())
+    })?;
+
+    Ok(names)
+}
+
+/// Names are only file names, so extracting can't write anywhere else.
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(|c: char| c == '/' || c == '\\' || c.is_whitespace());
+    match valid {
+        true => Ok(()),
+        false => Err(anyhow!("invalid file name '{}' in bundle", name)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let files: [(Kind, &str, &[u8]); 3] = [
+            (Kind::Checkpoint, "run.checkpoint", b"beaver-checkpoint\n# comment\n"),
+            (Kind::File, "undecided.txt", b""),
+            (Kind::File, "bin", &[0, 10, 13, 35, 255]),
+        ];
+        let mut bundle = Vec::new();
+        write_header(&mut bundle, &Manifest::new(&[("n", "2".into())])).unwrap();
+        for (kind, name, content) in &files {
+            write_entry(&mut bundle, *kind, name, content.len() as u64, &mut &content[..])
+                .unwrap();
+        }
+
+        let mut read = Vec::new();
+        read_entries(&mut &bundle[..], |kind, name, content| {
+            let mut buf = Vec::new();
+            content.read_to_end(&mut buf)?;
+            read.push((kind, name.to_owned(), buf));
+            Ok(())
+        }).unwrap();
+        assert_eq!(read.len(), files.len());
+        for ((kind, name, content), (k, n, c)) in files.iter().zip(&read) {
+            assert_eq!((kind, *name, *content), (k, &**n, &**c));
+        }
+
+        // Not reading the content must work as well.
+        read_entries(&mut &bundle[..], |_, _, _| Ok(())).unwrap();
+
+        let truncated = &bundle[..bundle.len() - 3];
+        assert!(read_entries(&mut &truncated[..], |_, _, _| Ok(())).is_err());
+        assert!(write_entry(&mut Vec::new(), Kind::File, "../x", 0, &mut &[][..]).is_err());
+    }
+}
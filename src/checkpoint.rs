@@ -0,0 +1,567 @@
+//! Checkpoints of long running sweeps: which chunks of TMs are finished and
+//! the combined summary of those. A run can be resumed from a checkpoint with
+//! the `resume` command.
+//!
+//! The file starts with the header `beaver-checkpoint <format version>`,
+//! followed by the manifest as comments and `<key> <value>` lines with the
+//! settings, the report settings, the chunk size and the finished chunks. After a line `summary`,
+//! the state of the summary follows (see `Summary::write_state`).
+//!
+//! Besides the settings, a checkpoint stores what determines the meaning of
+//! its content: the versions of the enumeration order and of the deciders.
+//! If they differ from the ones of this program, resuming fails instead of
+//! silently mixing results.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use ahash::AHashMap;
+use anyhow::{Context, Result, anyhow};
+
+use crate::{
+    analyze::{DECIDERS, DECIDERS_VERSION, HaltConvention, OptionalDeciders},
+    budget::Budget,
+    dispatch,
+    gen::{ENUMERATION_VERSION, Generator, PartialTable},
+    manifest::Manifest,
+    summary::{HistogramCutoff, HistogramFilter, ReportOptions, Summary, TieBreak},
+};
+
+
+/// Version of the file format, increased on incompatible changes.
+const FORMAT_VERSION: u32 = 10;
+
+/// The settings of a sweep that influence its results. A run can only be
+/// resumed with exactly these settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub n: u8,
+    pub generator: Generator,
+    pub fixed: Option<PartialTable>,
+    pub max_steps: u64,
+    pub halt_convention: HaltConvention,
+    pub lin_recurrence: bool,
+    pub optional_deciders: OptionalDeciders,
+    pub run_budget: Option<Budget>,
+    pub cycle_budget: Option<Budget>,
+    pub ctl_budget: Option<Budget>,
+    pub tie_break: TieBreak,
+
+    /// See `SweepBuilder::max_champions`.
+    pub max_champions: usize,
+    pub prune: bool,
+    pub baseline_score: Option<u32>,
+}
+
+impl Settings {
+    /// Reads the settings from `<key> <value>` lines written by `write`.
+    /// `get` returns the value of a key.
+    pub fn read<'a>(get: impl Fn(&str) -> Result<&'a str>) -> Result<Self> {
+        let budget = |key| -> Result<Option<Budget>> {
+            match get(key)? {
+                "none" => Ok(None),
+                budget => Ok(Some(budget.parse().map_err(|e: String| anyhow!(e))?)),
+            }
+        };
+        let settings = Self {
+            n: get("n")?.parse()?,
+            generator: get("generator")?.parse().map_err(|e: &str| anyhow!(e))?,
+            fixed: match get("fixed")? {
+                "none" => None,
+                table => Some(table.parse().map_err(|e: String| anyhow!(e))?),
+            },
+            max_steps: get("max-steps")?.parse()?,
+            halt_convention: get("halt-convention")?.parse().map_err(|e: &str| anyhow!(e))?,
+            lin_recurrence: get("lin-recurrence")?.parse()?,
+            optional_deciders: get("optional-deciders")?.parse().map_err(|e: String| anyhow!(e))?,
+            run_budget: budget("run-budget")?,
+            cycle_budget: budget("cycle-budget")?,
+            ctl_budget: budget("ctl-budget")?,
+            tie_break: get("tie-break")?.parse().map_err(|e: &str| anyhow!(e))?,
+            max_champions: get("max-champions")?.parse()?,
+            prune: get("prune")?.parse()?,
+            baseline_score: match get("baseline-score")? {
+                "none" => None,
+                score => Some(score.parse()?),
+            },
+        };
+        if !dispatch::SUPPORTED.contains(&settings.n) {
+            return Err(anyhow!("invalid value for n: {}", settings.n));
+        }
+        if let Some(table) = &settings.fixed {
+            table.check(settings.n, &settings.generator.opt()).map_err(|e| anyhow!(e))?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Writes the settings as `<key> <value>` lines.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        // Times are written exactly, not rounded like by `Display`.
+        let budget = |budget| match budget {
+            None => "none".to_string(),
+            Some(Budget::Steps(steps)) => format!("{}-steps", steps),
+            Some(Budget::Time(time)) => format!("{}ns", time.as_nanos()),
+        };
+
+        writeln!(w, "n {}", self.n)?;
+        writeln!(w, "generator {}", self.generator)?;
+        match &self.fixed {
+            None => writeln!(w, "fixed none")?,
+            Some(table) => writeln!(w, "fixed {}", table)?,
+        }
+        writeln!(w, "max-steps {}", self.max_steps)?;
+        writeln!(w, "halt-convention {}", self.halt_convention)?;
+        writeln!(w, "lin-recurrence {}", self.lin_recurrence)?;
+        writeln!(w, "optional-deciders {}", self.optional_deciders)?;
+        writeln!(w, "run-budget {}", budget(self.run_budget))?;
+        writeln!(w, "cycle-budget {}", budget(self.cycle_budget))?;
+        writeln!(w, "ctl-budget {}", budget(self.ctl_budget))?;
+        writeln!(w, "tie-break {}", self.tie_break)?;
+        writeln!(w, "max-champions {}", self.max_champions)?;
+        writeln!(w, "prune {}", self.prune)?;
+        match self.baseline_score {
+            None => writeln!(w, "baseline-score none"),
+            Some(score) => writeln!(w, "baseline-score {}", score),
+        }
+    }
+}
+
+/// How the results of a sweep are printed (see `ReportOptions`). Unlike the
+/// `Settings`, these don't influence the results, but a resumed run prints
+/// its report like the interrupted one would have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportSettings {
+    pub standard_notation: bool,
+    pub histogram_height: u32,
+    pub histogram_cutoff: HistogramCutoff,
+    pub hide_histogram: bool,
+    pub histogram_filter: HistogramFilter,
+    pub first_action_stats: bool,
+    pub tape_patterns: bool,
+    pub stage_stats: bool,
+    pub stage_chart: bool,
+    pub simulated_only: bool,
+}
+
+impl ReportSettings {
+    /// Reads the report settings from `<key> <value>` lines written by
+    /// `write`. `get` returns the value of a key.
+    pub fn read<'a>(get: impl Fn(&str) -> Result<&'a str>) -> Result<Self> {
+        Ok(Self {
+            standard_notation: get("standard-notation")?.parse()?,
+            histogram_height: get("histogram-height")?.parse()?,
+            histogram_cutoff: get("histogram-cutoff")?.parse().map_err(|e: &str| anyhow!(e))?,
+            hide_histogram: get("hide-histogram")?.parse()?,
+            histogram_filter: get("histogram-filter")?.parse().map_err(|e: &str| anyhow!(e))?,
+            first_action_stats: get("first-action-stats")?.parse()?,
+            tape_patterns: get("tape-patterns")?.parse()?,
+            stage_stats: get("stage-stats")?.parse()?,
+            stage_chart: get("stage-chart")?.parse()?,
+            simulated_only: get("simulated-only")?.parse()?,
+        })
+    }
+
+    /// Writes the report settings as `<key> <value>` lines.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "standard-notation {}", self.standard_notation)?;
+        writeln!(w, "histogram-height {}", self.histogram_height)?;
+        writeln!(w, "histogram-cutoff {}", self.histogram_cutoff)?;
+        writeln!(w, "hide-histogram {}", self.hide_histogram)?;
+        writeln!(w, "histogram-filter {}", self.histogram_filter)?;
+        writeln!(w, "first-action-stats {}", self.first_action_stats)?;
+        writeln!(w, "tape-patterns {}", self.tape_patterns)?;
+        writeln!(w, "stage-stats {}", self.stage_stats)?;
+        writeln!(w, "stage-chart {}", self.stage_chart)?;
+        writeln!(w, "simulated-only {}", self.simulated_only)
+    }
+
+    /// The options to print the report of a sweep with `settings`.
+    pub fn options(&self, settings: &Settings) -> ReportOptions {
+        ReportOptions {
+            max_steps: settings.max_steps,
+            run_budget: settings.run_budget,
+            n: settings.n,
+            standard_notation: self.standard_notation,
+            listed_champions: settings.max_champions,
+            histogram_height: self.histogram_height,
+            histogram_cutoff: self.histogram_cutoff,
+            hide_histogram: self.hide_histogram,
+            histogram_filter: self.histogram_filter,
+            first_action_stats: self.first_action_stats,
+            tape_patterns: self.tape_patterns,
+            stage_stats: self.stage_stats,
+            stage_chart: self.stage_chart,
+            simulated_only: self.simulated_only,
+        }
+    }
+}
+
+/// Writes the versions of this program, the enumeration order and the
+/// deciders as `<key> <value>` lines.
+pub fn write_versions(w: &mut impl Write) -> io::Result<()> {
+    writeln!(w, "beaver-version {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(w, "enumeration-version {}", ENUMERATION_VERSION)?;
+    writeln!(w, "deciders-version {}", DECIDERS_VERSION)?;
+    writeln!(w, "deciders {}", DECIDERS.join(","))
+}
+
+/// Checks that the versions written by `write_versions` match the ones of
+/// this program, as results of different versions must not be mixed. `get`
+/// returns the value of a key.
+pub fn check_versions<'a>(get: impl Fn(&str) -> Result<&'a str>) -> Result<()> {
+    let written_by = get("beaver-version")?;
+    let expected = [
+        ("enumeration-version", ENUMERATION_VERSION.to_string(), "enumeration order"),
+        ("deciders-version", DECIDERS_VERSION.to_string(), "semantics of the deciders"),
+        ("deciders", DECIDERS.join(","), "set of deciders"),
+    ];
+    for (key, ours, what) in &expected {
+        let theirs = get(key)?;
+        if theirs != ours {
+            return Err(anyhow!(
+                "the {} changed since the file was written by beaver {} ('{}' is '{}' \
+                    in the file, but '{}' now), so its results cannot be combined with \
+                    the ones of this version",
+                what,
+                written_by,
+                key,
+                theirs,
+                ours,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A set of chunk indices, stored as sorted, disjoint and non-adjacent
+/// ranges. Chunks are mostly finished in order, so this stays small.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkSet(Vec<Range<u64>>);
+
+impl ChunkSet {
+    pub fn insert(&mut self, chunk: u64) {
+        let pos = self.0.partition_point(|r| r.end < chunk);
+        match self.0.get_mut(pos) {
+            Some(r) if r.contains(&chunk) => {}
+            Some(r) if r.end == chunk => {
+                r.end += 1;
+                if self.0.get(pos + 1).is_some_and(|next| next.start == chunk + 1) {
+                    let next = self.0.remove(pos + 1);
+                    self.0[pos].end = next.end;
+                }
+            }
+            Some(r) if r.start == chunk + 1 => r.start = chunk,
+            _ => self.0.insert(pos, chunk..chunk + 1),
+        }
+    }
+
+    /// All ranges of chunks below `num_chunks` that are not in this set.
+    pub fn missing(&self, num_chunks: u64) -> Vec<Range<u64>> {
+        let mut out = Vec::new();
+        let mut start = 0;
+        for r in &self.0 {
+            if start < r.start {
+                out.push(start..r.start);
+            }
+            start = r.end;
+        }
+        if start < num_chunks {
+            out.push(start..num_chunks);
+        }
+        out
+    }
+}
+
+impl fmt::Display for ChunkSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges = self.0.iter()
+            .map(|r| format!("{}..{}", r.start, r.end))
+            .collect::<Vec<_>>();
+        f.write_str(&ranges.join(" "))
+    }
+}
+
+impl FromStr for ChunkSet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut out = Self::default();
+        for range in s.split_whitespace() {
+            let (start, end) = range.split_once("..")
+                .ok_or_else(|| anyhow!("invalid chunk range '{}'", range))?;
+            let (start, end): (u64, u64) = (start.parse()?, end.parse()?);
+            if out.0.last().is_some_and(|last| last.end >= start) || start >= end {
+                return Err(anyhow!("chunk ranges are not sorted and disjoint: '{}'", s));
+            }
+            out.0.push(start..end);
+        }
+        Ok(out)
+    }
+}
+
+/// The state of a sweep: all finished chunks and their combined summary.
+pub struct Checkpoint {
+    pub settings: Settings,
+    pub report: ReportSettings,
+
+    /// The number of TMs per chunk, which has to stay the same when resuming.
+    pub chunk_size: u64,
+    pub done: ChunkSet,
+    pub summary: Summary,
+}
+
+impl Checkpoint {
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("failed to parse checkpoint '{}'", path.display()))
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+        let version = lines.next()
+            .and_then(|l| l.strip_prefix("beaver-checkpoint "))
+            .ok_or_else(|| anyhow!("missing header 'beaver-checkpoint <version>'"))?;
+        if version != FORMAT_VERSION.to_string() {
+            return Err(anyhow!(
+                "checkpoint has format version {}, but this version of beaver only \
+                    supports version {}",
+                version,
+                FORMAT_VERSION,
+            ));
+        }
+
+        let mut values = Vec::new();
+        for line in &mut lines {
+            if line == "summary" {
+                break;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            values.push((key, value));
+        }
+        let get = |key: &str| {
+            values.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| anyhow!("missing '{}'", key))
+        };
+
+        check_versions(get)?;
+        let settings = Settings::read(get)?;
+
+        Ok(Self {
+            report: ReportSettings::read(get)?,
+            chunk_size: get("chunk-size")?.parse()?,
+            done: get("done")?.parse()?,
+            summary: Summary::read_state(settings.tie_break, settings.max_champions, lines)?,
+            settings,
+        })
+    }
+
+    /// Writes the checkpoint to a temporary file first, which then replaces
+    /// the file at `path`. So a crash while writing never destroys the last
+    /// checkpoint.
+    pub fn write(&self, path: &Path, manifest: &Manifest) -> Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("failed to create '{}'", tmp_path.display()))?;
+        let mut w = BufWriter::new(file);
+        self.write_to(&mut w, manifest)?;
+
+        w.into_inner()
+            .map_err(|e| e.into_error())
+            .and_then(|f| f.sync_all())
+            .with_context(|| format!("failed to write '{}'", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to replace '{}'", path.display()))?;
+        Ok(())
+    }
+
+    fn write_to(&self, w: &mut impl Write, manifest: &Manifest) -> io::Result<()> {
+        writeln!(w, "beaver-checkpoint {}", FORMAT_VERSION)?;
+        manifest.write_comments(w)?;
+        write_versions(w)?;
+        self.settings.write(w)?;
+        self.report.write(w)?;
+        writeln!(w, "chunk-size {}", self.chunk_size)?;
+        writeln!(w, "done {}", self.done)?;
+        writeln!(w, "summary")?;
+        self.summary.write_state(w)
+    }
+}
+
+/// Where and how often a sweep writes checkpoints (see
+/// `SweepBuilder::checkpoint`).
+#[derive(Debug, Clone)]
+pub struct CheckpointTarget {
+    pub path: PathBuf,
+    pub interval: Duration,
+    pub manifest: Manifest,
+
+    /// Stored in the checkpoint for `resume`.
+    pub report: ReportSettings,
+}
+
+/// Collects the summaries of finished chunks during a sweep and writes a
+/// checkpoint whenever the interval has passed.
+pub struct CheckpointWriter {
+    target: CheckpointTarget,
+    last_write: Instant,
+    state: Checkpoint,
+
+    /// Chunks of which only some parts are finished: the number of their TMs
+    /// that are finished and the summary of those.
+    parts: AHashMap<u64, (u64, Summary)>,
+}
+
+impl CheckpointWriter {
+    pub fn new(target: CheckpointTarget, state: Checkpoint) -> Self {
+        Self { target, last_write: Instant::now(), state, parts: AHashMap::new() }
+    }
+
+    /// Records a finished part of a chunk with the summary of its `part_len`
+    /// TMs. The chunk is finished once parts with `chunk_len` TMs in total
+    /// are recorded.
+    pub fn complete(
+        &mut self,
+        chunk: u64,
+        part_len: u64,
+        chunk_len: u64,
+        summary: Summary,
+    ) -> Result<()> {
+        let settings = &self.state.settings;
+        let (tie_break, max_champions) = (settings.tie_break, settings.max_champions);
+        let (done, chunk_summary) = self.parts.entry(chunk)
+            .or_insert_with(|| (0, Summary::new(tie_break, max_champions)));
+        *done += part_len;
+        chunk_summary.add(summary);
+        if *done < chunk_len {
+            return Ok(());
+        }
+
+        let (_, chunk_summary) = self.parts.remove(&chunk).expect("chunk not found");
+        self.state.done.insert(chunk);
+        self.state.summary.add(chunk_summary);
+        if self.last_write.elapsed() >= self.target.interval {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Writes a final checkpoint and returns the summary of all finished
+    /// parts, including the ones of unfinished chunks (which are not part of
+    /// the checkpoint).
+    pub fn finish(mut self) -> Result<Summary> {
+        self.write()?;
+        for (_, (_, summary)) in self.parts {
+            self.state.summary.add(summary);
+        }
+        Ok(self.state.summary)
+    }
+
+    fn write(&mut self) -> Result<()> {
+        self.state.write(&self.target.path, &self.target.manifest)?;
+        self.last_write = Instant::now();
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::{
+        analyze::{HaltConvention, OptionalDeciders},
+        budget::Budget,
+        gen::Generator,
+        manifest::Manifest,
+        summary::{HistogramCutoff, HistogramFilter, Summary, TieBreak},
+    };
+    use super::{Checkpoint, ChunkSet, ReportSettings, Settings};
+
+    #[test]
+    fn chunk_set() {
+        let mut set = ChunkSet::default();
+        for &chunk in &[3, 0, 1, 5, 4, 9, 4] {
+            set.insert(chunk);
+        }
+        assert_eq!(set.to_string(), "0..2 3..6 9..10");
+        assert_eq!(set.missing(12), vec![2..3, 6..9, 10..12]);
+        assert_eq!(set.to_string().parse::<ChunkSet>().unwrap(), set);
+
+        set.insert(2);
+        assert_eq!(set.to_string(), "0..6 9..10");
+        assert!("3..5 0..2".parse::<ChunkSet>().is_err());
+    }
+
+    #[test]
+    fn incompatible() {
+        let checkpoint = Checkpoint {
+            settings: Settings {
+                n: 2,
+                generator: Generator::All,
+                fixed: Some("A0=1RB,B1=1LH".parse().unwrap()),
+                max_steps: 100,
+                halt_convention: HaltConvention::Include,
+                lin_recurrence: false,
+                optional_deciders: OptionalDeciders { ctl: true },
+                run_budget: None,
+                cycle_budget: Some(Budget::Steps(100_000)),
+                ctl_budget: Some(Budget::Time(Duration::from_millis(10))),
+                tie_break: TieBreak::FewestSteps,
+                max_champions: 5,
+                prune: false,
+                baseline_score: Some(4),
+            },
+            report: ReportSettings {
+                standard_notation: true,
+                histogram_height: 20,
+                histogram_cutoff: HistogramCutoff::Fixed(50),
+                hide_histogram: true,
+                histogram_filter: HistogramFilter::HighScore,
+                first_action_stats: false,
+                tape_patterns: true,
+                stage_stats: false,
+                stage_chart: true,
+                simulated_only: false,
+            },
+            chunk_size: 500,
+            done: "0..3".parse().unwrap(),
+            summary: Summary::new(TieBreak::FewestSteps, 0),
+        };
+        let mut out = Vec::new();
+        checkpoint.write_to(&mut out, &Manifest::new(&[])).unwrap();
+        let s = String::from_utf8(out).unwrap();
+        let read = Checkpoint::parse(&s).unwrap();
+        assert_eq!(read.settings, checkpoint.settings);
+        assert_eq!(read.report, checkpoint.report);
+        assert_eq!(read.done, checkpoint.done);
+
+        let changed = [
+            ("beaver-checkpoint 10", "beaver-checkpoint 9"),
+            ("histogram-cutoff 50", "histogram-cutoff none"),
+            ("cycle-budget 100000-steps", "cycle-budget 100000"),
+            ("optional-deciders ctl", "optional-deciders foo"),
+            ("lin-recurrence false", "lin-recurrence no"),
+            ("fixed A0=1RB,B1=1LH", "fixed C0=1RB"),
+            ("enumeration-version 1", "enumeration-version 0"),
+            ("deciders-version 5", "deciders-version 4"),
+            (",backward-reasoning", ""),
+        ];
+        for (from, to) in &changed {
+            assert!(Checkpoint::parse(&s.replacen(from, to, 1)).is_err(), "{}", to);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    SharedArgs,
+    budget::Budget,
+    cmd::check_n,
+    completion::{self, Options, Rank},
+    dispatch::{self, PerN},
+    gen::{Opt, PartialTable},
+    manifest::Manifest,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    #[structopt(flatten)]
+    shared: SharedArgs,
+
+    /// The fixed transitions, e.g. 'A0=1RB,A1=1LC' (state and read symbol,
+    /// then the action in standard notation). All other transitions are
+    /// searched.
+    table: PartialTable,
+
+    /// Budget for the whole search, either as number of steps over all runs
+    /// (e.g. '100M-steps') or as wall-clock time (e.g. '10s'). If it runs
+    /// out, the best completions found so far are reported.
+    #[structopt(long, default_value = "10s")]
+    budget: Budget,
+
+    /// The number of best completions that are listed.
+    #[structopt(long, default_value = "10")]
+    num_best: usize,
+
+    /// What completions are ranked by: 'steps' or 'ones'. The other one
+    /// breaks ties.
+    #[structopt(long, default_value = "steps", possible_values(&["steps", "ones"]))]
+    rank_by: Rank,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    check_n(args.shared.n)?;
+    args.table.check(args.shared.n, &Opt::None).map_err(|e| anyhow!(e))?;
+    dispatch::with_n(args.shared.n, args)
+        .expect("invalid value for n: argument parsing should catch this")
+}
+
+impl PerN for Args {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self)
+    }
+}
+
+fn do_run<const N: usize>(args: Args) -> Result<()> {
+    let manifest = Manifest::new(&[
+        ("n", N.to_string()),
+        ("table", args.table.to_string()),
+        ("max-steps", args.shared.max_steps.to_string()),
+        ("halt-convention", args.shared.halt_convention.to_string()),
+        ("budget", args.budget.to_string()),
+        ("rank-by", args.rank_by.to_string()),
+    ]);
+
+    println!();
+    bunt::println!(
+        "{$blue+bold}▸ Searching completions of {[intense]} with {[intense]} states \
+            up to {[intense]} steps...{/$}",
+        args.table,
+        N,
+        args.shared.max_steps,
+    );
+    println!();
+
+    let result = completion::search::<N>(&args.table, &Options {
+        max_steps: args.shared.max_steps,
+        halt_convention: args.shared.halt_convention,
+        budget: args.budget,
+        num_best: args.num_best,
+        rank: args.rank_by,
+    });
+
+    bunt::println!("{$blue+bold}▸ Results:{/$}");
+    bunt::println!("- {[green+bold]} halting completions were found", result.num_halting);
+    bunt::println!(
+        "- {[magenta+bold]} completions did not halt within {} steps",
+        result.num_undecided,
+        args.shared.max_steps,
+    );
+    println!("- {} partial TMs were run", result.num_runs);
+    if result.budget_exceeded {
+        bunt::println!(
+            "{$yellow}Note:{/$} the budget of {} ran out before all completions were \
+                searched, so better ones might exist.",
+            args.budget,
+        );
+    }
+    println!();
+
+    bunt::println!(
+        "{$blue+bold}▸ Best completions{/$} (by {}, '---' marks unused transitions):",
+        args.rank_by,
+    );
+    println!("    {: >10}{: >10}   TM", "steps", "ones");
+    for c in &result.best {
+        println!("    {: >10}{: >10}   {}", c.steps, c.ones, c.to_standard_notation::<N>());
+    }
+    if result.best.is_empty() {
+        println!("    (none)");
+    }
+    println!();
+
+    manifest.print();
+    println!();
+
+    Ok(())
+}
@@ -0,0 +1,518 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+use anyhow::{Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    cmd::tm_from_id,
+    analyze::{Analyzer, HaltConvention},
+    budget::Budget,
+    continuation::{self, Continuation, ContinuationFile, ContinuationSink},
+    dispatch::{self, PerN},
+    manifest::Manifest,
+    outcome::{Configuration, OutcomeSink},
+    pool::BufferPool,
+    sweep::panic_message,
+    tape::TapeLike,
+    tm::Tm,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// File with continuation records, as written by `full --continuations`.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+
+    /// Number of steps after which TMs are stopped. This includes the steps
+    /// that were already executed before the TM was aborted.
+    #[structopt(long, default_value = "1000000")]
+    max_steps: u64,
+
+    /// Whether the final transition into the halt state counts. See `full
+    /// --help`.
+    #[structopt(
+        long,
+        default_value = "include",
+        possible_values(&["include", "exclude"]),
+    )]
+    halt_convention: HaltConvention,
+
+    /// If specified, continuation records of all TMs that are still not
+    /// finished are written to this file.
+    #[structopt(long, parse(from_os_str))]
+    out: Option<PathBuf>,
+
+    /// Budget for running a single TM, either as number of steps (e.g.
+    /// '100k-steps') or as wall-clock time (e.g. '10ms'). TMs exceeding it are
+    /// counted as aborted, just like TMs reaching the maximum number of
+    /// steps. A step budget limits the steps executed in this run.
+    /// Note that time budgets make results depend on the machine and its load.
+    #[structopt(long)]
+    run_budget: Option<Budget>,
+
+    /// Number of threads to use. Defaults to the number of virtual CPUs.
+    #[structopt(long, short = "j")]
+    num_threads: Option<u32>,
+
+    /// If specified, TMs are run in time slices of this many steps: after a
+    /// slice, the TM is parked at the end of the queue and the next TM is run.
+    /// That way, a few very long running TMs don't block all threads while
+    /// others are waiting. Accepts suffixes like '1M'. Note that each slice
+    /// starts the detection of cycles and translated cyclers from scratch,
+    /// so cycles longer than a slice are never found.
+    #[structopt(long, conflicts_with("run-budget"), parse(try_from_str = parse_slice))]
+    time_slice: Option<u64>,
+}
+
+/// Parses a number of steps for `--time-slice`, like `100k` or `1M`.
+fn parse_slice(s: &str) -> Result<u64, String> {
+    let budget = format!("{}-steps", s).parse::<Budget>()?;
+    match budget.steps() {
+        Some(0) => Err("the time slice has to be at least 1 step".into()),
+        Some(steps) => Ok(steps),
+        None => Err(format!("invalid time slice '{}'", s)),
+    }
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    if args.num_threads == Some(0) {
+        return Err(anyhow!("at least one thread is needed"));
+    }
+    let (n, records) = continuation::read_file(&args.file)?;
+
+    let file = args.file.clone();
+    dispatch::with_n(n, Continue { args, records })
+        .unwrap_or_else(|| Err(anyhow!("invalid value for n in '{}': {}", file.display(), n)))
+}
+
+/// The arguments of `do_run`, to call it for the right `N`.
+struct Continue {
+    args: Args,
+    records: Vec<Continuation>,
+}
+
+impl PerN for Continue {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self.args, self.records)
+    }
+}
+
+fn do_run<const N: usize>(args: Args, records: Vec<Continuation>) -> Result<()>
+where
+    [bool; N]: Default,
+{
+    bunt::println!(
+        "{$blue+bold}▸ Continuing {[intense]} TMs with {[intense]} states \
+            up to {[intense]} steps...{/$}",
+        records.len(),
+        N,
+        args.max_steps,
+    );
+    println!();
+
+    let manifest = Manifest::new(&[
+        ("n", N.to_string()),
+        ("input", args.file.display().to_string()),
+        ("max-steps", args.max_steps.to_string()),
+        ("halt-convention", args.halt_convention.to_string()),
+        ("run-budget", args.run_budget.map_or("none".into(), |b| b.to_string())),
+    ]);
+    let out = args.out.as_ref()
+        .map(|path| ContinuationFile::create(path, N as u8, &manifest))
+        .transpose()?;
+
+    // Check all IDs upfront to not fail in the middle of the run.
+    for record in &records {
+        tm_from_id::<N>(record.id)?;
+    }
+
+    let reports = run_records::<N>(&args, records, out.as_ref())?;
+
+    if let Some(out) = &out {
+        out.flush()?;
+    }
+
+    let mut report = Report::default();
+    reports.iter().for_each(|r| report.add(r));
+    println!();
+    bunt::println!("{$blue+bold}▸ Results:{/$}");
+    bunt::println!("- {[green+bold]} TMs halted", report.num_halted);
+    bunt::println!("- {[magenta+bold]} TMs were caught in a run-away loop", report.num_runaway);
+    bunt::println!(
+        "- {[magenta+bold]} TMs repeated a previous configuration",
+        report.num_cycle,
+    );
+    bunt::println!(
+        "- {[magenta+bold]} TMs repeated their behavior shifted along the tape",
+        report.num_translated_cycler,
+    );
+    bunt::println!(
+        "- {[magenta+bold]} TMs can't reach a halting transition (backward reasoning)",
+        report.num_backward_reasoning,
+    );
+    if report.num_ctl > 0 {
+        bunt::println!(
+            "- {[magenta+bold]} TMs have a closed tape language without halting (CTL)",
+            report.num_ctl,
+        );
+    }
+    if report.num_lin_recurrence > 0 {
+        bunt::println!(
+            "- {[magenta+bold]} TMs repeated their behavior after the step limit \
+                (Lin recurrence)",
+            report.num_lin_recurrence,
+        );
+    }
+    bunt::println!(
+        "- {[red+bold]} TMs were aborted after the maximum number of steps ({})",
+        report.num_aborted,
+        args.max_steps,
+    );
+    if let Some(budget) = args.run_budget {
+        println!("  (or after exceeding the run budget of {})", budget);
+    }
+
+    println!();
+    manifest.print();
+
+    Ok(())
+}
+
+/// Runs all given TMs on multiple threads and returns the report of each
+/// thread. TMs still aborted are written to `out`. Fails if running a TM
+/// panics.
+fn run_records<const N: usize>(
+    args: &Args,
+    records: Vec<Continuation>,
+    out: Option<&ContinuationFile>,
+) -> Result<Vec<Report>>
+where
+    [bool; N]: Default,
+{
+    // All TMs that still have to be run. TMs that used up their time slice
+    // are sent to the back again, so that all TMs are run round-robin. Once
+    // all TMs are finished, one `None` per thread is sent to stop the
+    // workers.
+    let num_threads = args.num_threads.unwrap_or_else(|| num_cpus::get() as u32);
+    let num_unfinished = AtomicUsize::new(records.len());
+    let (queue_tx, queue_rx) = crossbeam_channel::unbounded();
+    let stop_workers = || (0..num_threads).for_each(|_| {
+        queue_tx.send(None).expect("receiver is never dropped");
+    });
+    if records.is_empty() {
+        stop_workers();
+    }
+    for record in records {
+        queue_tx.send(Some(record)).expect("receiver is never dropped");
+    }
+    let run_budget = args.time_slice.map(Budget::Steps).or(args.run_budget);
+
+    // The tapes of parked TMs are stored in buffers from this pool: every
+    // slice frees the tape of one record and stores the tape of another.
+    let pool = BufferPool::new(2 * num_threads as usize);
+
+    thread::scope(|scope| {
+        let handles = (0..num_threads).map(|_| scope.spawn(|| -> Result<Report> {
+            let mut analyzer = Analyzer::builder()
+                .max_steps(args.max_steps)
+                .halt_convention(args.halt_convention)
+                .run_budget(run_budget)
+                .build();
+            let mut sink = SliceSink {
+                slicing: args.time_slice.is_some(),
+                max_steps: args.max_steps,
+                pool: &pool,
+                parked: None,
+                inner: (Report::default(), out.cloned().map(ContinuationSink::new)),
+            };
+
+            // This blocks while all remaining TMs are being run by other
+            // threads, as they might be parked again. If a TM panics, its
+            // record is never finished, so all workers are stopped.
+            while let Some(record) = queue_rx.recv().expect("sender is never dropped") {
+                let tm = <Tm<N>>::from_id(record.id).expect("IDs were checked above");
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    analyzer.continue_tm(tm, &record, &mut sink);
+                }));
+                if let Err(payload) = res {
+                    stop_workers();
+                    return Err(anyhow!(
+                        "continuing TM {} panicked: {}",
+                        record.id,
+                        panic_message(&*payload),
+                    ));
+                }
+                pool.put(record.tape_runs);
+                match sink.parked.take() {
+                    Some(parked) => {
+                        queue_tx.send(Some(parked)).expect("receiver is never dropped");
+                    }
+                    None => {
+                        if num_unfinished.fetch_sub(1, Ordering::SeqCst) == 1 {
+                            stop_workers();
+                        }
+                    }
+                }
+            }
+
+            if let Some(continuations) = &mut sink.inner.1 {
+                continuations.flush()?;
+            }
+            Ok(sink.inner.0)
+        })).collect::<Vec<_>>();
+
+        handles.into_iter()
+            .map(|h| h.join().expect("panic in worker thread"))
+            .collect::<Result<Vec<_>>>()
+    })
+}
+
+/// Prints halted TMs and counts the different outcomes. Only outcomes of
+/// running a TM are possible when continuing.
+#[derive(Default)]
+struct Report {
+    num_halted: u64,
+    num_runaway: u64,
+    num_cycle: u64,
+    num_translated_cycler: u64,
+    num_backward_reasoning: u64,
+    num_ctl: u64,
+    num_lin_recurrence: u64,
+    num_aborted: u64,
+}
+
+impl Report {
+    fn add(&mut self, other: &Report) {
+        self.num_halted += other.num_halted;
+        self.num_runaway += other.num_runaway;
+        self.num_cycle += other.num_cycle;
+        self.num_translated_cycler += other.num_translated_cycler;
+        self.num_backward_reasoning += other.num_backward_reasoning;
+        self.num_ctl += other.num_ctl;
+        self.num_lin_recurrence += other.num_lin_recurrence;
+        self.num_aborted += other.num_aborted;
+    }
+}
+
+/// Wraps another sink and, if `slicing` is set, intercepts TMs that were
+/// stopped before reaching `max_steps`, i.e. that used up their time slice.
+/// Their configuration is stored in `parked` instead of being reported as
+/// aborted. Without slicing, TMs stopped early exceeded the run budget and
+/// are reported as aborted.
+struct SliceSink<'a, S> {
+    slicing: bool,
+    max_steps: u64,
+    pool: &'a BufferPool<u32>,
+    parked: Option<Continuation>,
+    inner: S,
+}
+
+impl<S: OutcomeSink<N>, const N: usize> OutcomeSink<N> for SliceSink<'_, S> {
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, tape: &impl TapeLike) {
+        self.inner.report_halted(tm, num_steps, num_ones, tape);
+    }
+
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32) {
+        self.inner.report_immediate_halt(tm, num_steps, num_ones);
+    }
+
+    fn report_no_halt_transition(&mut self, tm: Tm<N>) {
+        self.inner.report_no_halt_transition(tm);
+    }
+
+    fn report_simple_elope(&mut self, tm: Tm<N>) {
+        self.inner.report_simple_elope(tm);
+    }
+
+    fn report_halt_state_not_reachable(&mut self, tm: Tm<N>) {
+        self.inner.report_halt_state_not_reachable(tm);
+    }
+
+    fn report_run_away(&mut self, tm: Tm<N>) {
+        self.inner.report_run_away(tm);
+    }
+
+    fn report_cycle(&mut self, tm: Tm<N>) {
+        self.inner.report_cycle(tm);
+    }
+
+    fn report_translated_cycler(&mut self, tm: Tm<N>) {
+        self.inner.report_translated_cycler(tm);
+    }
+
+    fn report_backward_reasoning(&mut self, tm: Tm<N>) {
+        self.inner.report_backward_reasoning(tm);
+    }
+
+    fn report_ctl(&mut self, tm: Tm<N>) {
+        self.inner.report_ctl(tm);
+    }
+
+    fn report_lin_recurrence(&mut self, tm: Tm<N>) {
+        self.inner.report_lin_recurrence(tm);
+    }
+
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    ) {
+        if self.slicing && config.steps < self.max_steps {
+            let buffer = self.pool.take(config.tape.runs().count() + 1);
+            self.parked = Some(Continuation::with_buffer(tm, config, buffer));
+        } else {
+            self.inner.report_aborted_after_max_steps(tm, config);
+        }
+    }
+
+    fn report_pruned(&mut self, tm: Tm<N>, num_steps: u64) {
+        self.inner.report_pruned(tm, num_steps);
+    }
+
+    fn report_known(&mut self, tm: Tm<N>) {
+        self.inner.report_known(tm);
+    }
+}
+
+impl<const N: usize> OutcomeSink<N> for Report {
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, _: &impl TapeLike) {
+        self.num_halted += 1;
+        bunt::println!(
+            "TM {[blue]} halted after {[green+bold]} steps with {[green+bold]} ones",
+            tm.encoded,
+            num_steps,
+            num_ones,
+        );
+    }
+
+    fn report_immediate_halt(&mut self, _: Tm<N>, _: u64, _: u32) {
+        unreachable!("static check while continuing a TM")
+    }
+
+    fn report_no_halt_transition(&mut self, _: Tm<N>) {
+        unreachable!("static check while continuing a TM")
+    }
+
+    fn report_simple_elope(&mut self, _: Tm<N>) {
+        unreachable!("static check while continuing a TM")
+    }
+
+    fn report_halt_state_not_reachable(&mut self, _: Tm<N>) {
+        unreachable!("static check while continuing a TM")
+    }
+
+    fn report_run_away(&mut self, _: Tm<N>) {
+        self.num_runaway += 1;
+    }
+
+    fn report_cycle(&mut self, _: Tm<N>) {
+        self.num_cycle += 1;
+    }
+
+    fn report_translated_cycler(&mut self, _: Tm<N>) {
+        self.num_translated_cycler += 1;
+    }
+
+    fn report_backward_reasoning(&mut self, _: Tm<N>) {
+        self.num_backward_reasoning += 1;
+    }
+
+    fn report_ctl(&mut self, _: Tm<N>) {
+        self.num_ctl += 1;
+    }
+
+    fn report_lin_recurrence(&mut self, _: Tm<N>) {
+        self.num_lin_recurrence += 1;
+    }
+
+    fn report_aborted_after_max_steps(&mut self, _: Tm<N>, _: Configuration<'_, impl TapeLike>) {
+        self.num_aborted += 1;
+    }
+
+    fn report_pruned(&mut self, _: Tm<N>, _: u64) {
+        unreachable!("pruning is not enabled while continuing TMs")
+    }
+
+    fn report_known(&mut self, _: Tm<N>) {
+        unreachable!("continued TMs are always analyzed")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use structopt::StructOpt;
+    use crate::{continuation::Continuation, tape::CellId, tm::Tm};
+    use super::{Args, Report, run_records};
+
+
+    /// Runs the BB(2) champion (halting after 6 steps) from the start with
+    /// the given extra arguments.
+    fn run_champion(extra: &[&str]) -> Report {
+        let tm = Tm::<2>::from_standard("1RB1LB_1LA1RZ").unwrap();
+        let record = Continuation {
+            id: tm.encoded,
+            state: 0,
+            steps: 0,
+            head: CellId(0),
+            tape_start: CellId(0),
+            tape_runs: vec![],
+        };
+
+        let args = Args::from_iter(["continue", "records.txt", "-j", "2"].iter().chain(extra));
+        let mut report = Report::default();
+        for r in run_records::<2>(&args, vec![record], None).unwrap() {
+            report.add(&r);
+        }
+        report
+    }
+
+    #[test]
+    fn run_budget_aborts() {
+        let report = run_champion(&["--run-budget", "5-steps"]);
+        assert_eq!((report.num_halted, report.num_aborted), (0, 1));
+
+        let report = run_champion(&["--run-budget", "6-steps"]);
+        assert_eq!((report.num_halted, report.num_aborted), (1, 0));
+    }
+
+    #[test]
+    fn time_slices_continue() {
+        let report = run_champion(&["--time-slice", "2"]);
+        assert_eq!((report.num_halted, report.num_aborted), (1, 0));
+
+        let report = run_champion(&["--time-slice", "2", "--max-steps", "5"]);
+        assert_eq!((report.num_halted, report.num_aborted), (0, 1));
+    }
+
+    #[test]
+    fn panic_stops_all_workers() {
+        // A record with an invalid state makes the analyzer panic. The other
+        // workers still wait for TMs that might be parked again.
+        let tm = Tm::<2>::from_standard("1RB1LB_1LA1RZ").unwrap();
+        let record = |state| Continuation {
+            id: tm.encoded,
+            state,
+            steps: 0,
+            head: CellId(0),
+            tape_start: CellId(0),
+            tape_runs: vec![],
+        };
+        let mut records = vec![record(0); 20];
+        records.insert(10, record(7));
+
+        let args = Args::from_iter(&["continue", "records.txt", "-j", "4", "--time-slice", "1"]);
+        let err = run_records::<2>(&args, records, None).err().expect("panic was not reported");
+        assert!(err.to_string().contains("panicked"), "{}", err);
+    }
+}
@@ -0,0 +1,196 @@
+//! Compares two dumps (see the `dump` command), e.g. to see how a change or a
+//! different generator affects the results.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use anyhow::{Context, Result, anyhow};
+use structopt::StructOpt;
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The first dump.
+    #[structopt(parse(from_os_str))]
+    a: PathBuf,
+
+    /// The second dump.
+    #[structopt(parse(from_os_str))]
+    b: PathBuf,
+
+    /// Instead of listing the differences, subtract the second dump from the
+    /// first. This requires the TMs of the second run to be a subset of the
+    /// ones of the first, e.g. `-g all` minus `-g optimized`. The result
+    /// describes exactly the TMs that are only in the first run.
+    #[structopt(long)]
+    subtract: bool,
+}
+
+/// Keys of settings that have to be equal in both dumps for `--subtract`.
+const SUBTRACT_SETTINGS: &[&str] = &["n", "max-steps", "halt-convention"];
+
+/// Keys of values that are not additive (the best score of a set of TMs).
+const SCORES: &[&str] = &["sigma", "s"];
+
+/// A parsed dump file.
+struct Dump {
+    /// The lines before `total`, e.g. `n` and `generator`.
+    settings: BTreeMap<String, String>,
+
+    /// All other lines with a number as last token, keyed by everything
+    /// before that number, e.g. `histogram halted 3`.
+    values: BTreeMap<String, u64>,
+
+    /// All `sigma-champion` and `s-champion` lines.
+    champions: BTreeSet<String>,
+}
+
+impl Dump {
+    fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse(&content).with_context(|| format!("invalid dump '{}'", path.display()))
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        let mut lines = s.lines();
+        if lines.next() != Some("beaver-dump 1") {
+            return Err(anyhow!("missing 'beaver-dump 1' header"));
+        }
+
+        let mut out = Self {
+            settings: BTreeMap::new(),
+            values: BTreeMap::new(),
+            champions: BTreeSet::new(),
+        };
+        let mut in_settings = true;
+        for (i, line) in lines.enumerate() {
+            let (key, value) = line.rsplit_once(' ')
+                .ok_or_else(|| anyhow!("line {}: expected '<key> <value>'", i + 2))?;
+            in_settings &= key != "total";
+
+            if in_settings {
+                out.settings.insert(key.into(), value.into());
+            } else if key.contains("champion") {
+                out.champions.insert(line.into());
+            } else {
+                let value = value.parse()
+                    .with_context(|| format!("line {}: invalid number '{}'", i + 2, value))?;
+                out.values.insert(key.into(), value);
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let a = Dump::load(&args.a)?;
+    let b = Dump::load(&args.b)?;
+
+    if args.subtract {
+        subtract(&args, &a, &b)
+    } else {
+        print_differences(&args, &a, &b);
+        Ok(())
+    }
+}
+
+fn print_differences(args: &Args, a: &Dump, b: &Dump) {
+    bunt::println!(
+        "{$blue+bold}▸ Differences between '{}' (a) and '{}' (b):{/$}",
+        args.a.display(),
+        args.b.display(),
+    );
+
+    let settings = a.settings.keys().chain(b.settings.keys()).collect::<BTreeSet<_>>();
+    for key in settings {
+        let (va, vb) = (a.settings.get(key), b.settings.get(key));
+        if va != vb {
+            println!("    {: <32}{: >14}{: >14}", key, show(va), show(vb));
+        }
+    }
+
+    let keys = a.values.keys().chain(b.values.keys()).collect::<BTreeSet<_>>();
+    let mut num_differences = 0;
+    for key in keys {
+        let (va, vb) = (a.values.get(key), b.values.get(key));
+        if va != vb {
+            num_differences += 1;
+            println!("    {: <32}{: >14}{: >14}", key, show(va), show(vb));
+        }
+    }
+
+    for line in a.champions.symmetric_difference(&b.champions) {
+        num_differences += 1;
+        let side = if a.champions.contains(line) { "only in a" } else { "only in b" };
+        println!("    {: <46}{}", line, side);
+    }
+
+    if num_differences == 0 {
+        bunt::println!("{$green}The results are identical.{/$}");
+    }
+}
+
+fn subtract(args: &Args, a: &Dump, b: &Dump) -> Result<()> {
+    for &key in SUBTRACT_SETTINGS {
+        if a.settings.get(key) != b.settings.get(key) {
+            return Err(anyhow!("the dumps differ in '{}', so they cannot be subtracted", key));
+        }
+    }
+
+    bunt::println!(
+        "{$blue+bold}▸ '{}' minus '{}':{/$}",
+        args.a.display(),
+        args.b.display(),
+    );
+    for (key, &va) in &a.values {
+        if SCORES.contains(&&**key) {
+            continue;
+        }
+
+        let vb = b.values.get(key).copied().unwrap_or(0);
+        let diff = va.checked_sub(vb).ok_or_else(|| anyhow!(
+            "'{}' is larger in the second dump ({} > {}), so its TMs are not a subset \
+                of the first one's",
+            key,
+            vb,
+            va,
+        ))?;
+        if diff > 0 {
+            println!("    {: <32}{: >14}", key, diff);
+        }
+    }
+
+    // The scores of the remaining TMs are not known, unless some champions of
+    // the first run are among them.
+    for line in a.champions.difference(&b.champions) {
+        println!("    {}", line);
+    }
+
+    Ok(())
+}
+
+fn show(v: Option<&impl ToString>) -> String {
+    v.map_or("-".into(), |v| v.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Dump;
+
+    #[test]
+    fn parse() {
+        let dump = Dump::parse(include_str!("dump-n2.golden")).unwrap();
+        assert_eq!(dump.settings["generator"], "all");
+        assert_eq!(dump.values["total"], 20736);
+        assert_eq!(dump.values["count halted"], 2872);
+        assert_eq!(dump.values["histogram halted 6"], 40);
+        assert_eq!(dump.values["sigma"], 4);
+        assert!(dump.champions.contains("sigma-champion 327814 6 4"));
+        assert!(dump.values.keys().all(|k| !k.contains("champion")));
+    }
+}
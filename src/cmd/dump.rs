@@ -0,0 +1,97 @@
+//! Writes the key results of a sweep in a canonical text format, e.g. for
+//! golden-file regression tests.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+use anyhow::{Context, Result};
+use structopt::StructOpt;
+
+use crate::{
+    SharedArgs,
+    cmd::check_n,
+    gen::Generator,
+    summary::Summary,
+    sweep::SweepBuilder,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    #[structopt(flatten)]
+    shared: SharedArgs,
+
+    /// Set TM generator (see `full --help`).
+    #[structopt(short, long, default_value = "optimized")]
+    generator: Generator,
+
+    /// Number of threads to use. Defaults to the number of virtual CPUs. The
+    /// output does not depend on it.
+    #[structopt(long, short = "j")]
+    num_threads: Option<u32>,
+
+    /// The file to write the dump to. If not specified, it is printed to
+    /// stdout.
+    #[structopt(long, short, parse(from_os_str))]
+    out: Option<PathBuf>,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    check_n(args.shared.n)?;
+    let summary = SweepBuilder::new(args.shared.n)
+        .generator(args.generator)
+        .max_steps(args.shared.max_steps)
+        .halt_convention(args.shared.halt_convention)
+        .num_threads(args.num_threads)
+        .run()?;
+
+    match &args.out {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create '{}'", path.display()))?;
+            let mut w = BufWriter::new(file);
+            write(&mut w, &args, &summary)?;
+            w.flush().with_context(|| format!("failed to write '{}'", path.display()))?;
+        }
+        None => write(&mut io::stdout().lock(), &args, &summary)?,
+    }
+
+    Ok(())
+}
+
+/// Writes the settings (unlike a manifest, without anything that changes
+/// between runs) followed by the dump of the summary.
+fn write(w: &mut impl Write, args: &Args, summary: &Summary) -> io::Result<()> {
+    writeln!(w, "beaver-dump 1")?;
+    writeln!(w, "n {}", args.shared.n)?;
+    writeln!(w, "generator {}", args.generator)?;
+    writeln!(w, "max-steps {}", args.shared.max_steps)?;
+    writeln!(w, "halt-convention {}", args.shared.halt_convention)?;
+    summary.write_dump(w)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use structopt::StructOpt;
+    use super::{Args, write};
+    use crate::sweep::SweepBuilder;
+
+    /// Compares the dump of all TMs with 2 states to the golden file. If a
+    /// change of the results is intended, regenerate the file with
+    /// `beaver dump -n 2 -g all -o src/cmd/dump-n2.golden`.
+    #[test]
+    fn golden_n2() {
+        let args = Args::from_iter(&["dump", "-n", "2", "-g", "all"]);
+        let summary = SweepBuilder::new(2)
+            .generator(args.generator)
+            .run()
+            .unwrap();
+
+        let mut out = Vec::new();
+        write(&mut out, &args, &summary).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), include_str!("dump-n2.golden"));
+    }
+}
@@ -0,0 +1,47 @@
+//! Bundles the checkpoint of a sweep together with other files of it into a
+//! single file (see `bundle`), which `import-run` unpacks on another machine.
+
+use std::{fs, path::PathBuf};
+use anyhow::Result;
+use structopt::StructOpt;
+
+use crate::{bundle, checkpoint::Checkpoint, manifest::Manifest};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The checkpoint file of the sweep (see `full --checkpoint`).
+    #[structopt(parse(from_os_str))]
+    checkpoint: PathBuf,
+
+    /// The bundle file to write.
+    #[structopt(long, short, parse(from_os_str))]
+    out: PathBuf,
+
+    /// Other files of the sweep to include, e.g. the ones written by
+    /// `--undecided-out`, `--continuations` or `--summary-out`. They are
+    /// extracted next to the checkpoint.
+    #[structopt(long, parse(from_os_str))]
+    include: Vec<PathBuf>,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    // Only bundle checkpoints that can actually be resumed.
+    let checkpoint = Checkpoint::read(&args.checkpoint)?;
+
+    let manifest = Manifest::new(&[
+        ("checkpoint", args.checkpoint.display().to_string()),
+        ("n", checkpoint.settings.n.to_string()),
+    ]);
+    bundle::export(&args.out, &args.checkpoint, &args.include, &manifest)?;
+
+    let size = fs::metadata(&args.out)?.len();
+    bunt::println!(
+        "Wrote checkpoint and {} other files ({} bytes) to {[green+bold]}",
+        args.include.len(),
+        size,
+        args.out.display(),
+    );
+    println!("Continue the sweep elsewhere with `import-run` and `resume`.");
+    Ok(())
+}
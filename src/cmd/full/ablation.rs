@@ -0,0 +1,94 @@
+//! Measuring how fast each generator is on a sample of its TMs (see
+//! `--generator-ablation`), to estimate the total time of a full run.
+
+use std::{
+    cmp::min,
+    time::{Duration, Instant},
+};
+use anyhow::Result;
+
+use crate::{gen::Generator, sweep::SweepBuilder};
+use super::Args;
+
+
+/// The sample of each generator is split into this many blocks of
+/// consecutive TMs, spread evenly over all indices. A single block would be
+/// biased, as TMs with similar indices have similar transition tables.
+const NUM_BLOCKS: u64 = 16;
+
+/// Analyzes a sample of `sample_size` TMs with every generator and prints
+/// the throughput and projected time of a full run for each.
+pub(super) fn run(args: &Args, sample_size: u64) -> Result<()> {
+    println!();
+    bunt::println!(
+        "{$blue+bold}▸ Measuring the generators with {[intense]} states on samples of \
+            up to {[intense]} TMs...{/$}",
+        args.shared.n,
+        sample_size,
+    );
+    println!();
+    println!(
+        "    {: <16}{: >16}{: >12}{: >14}{: >18}",
+        "generator",
+        "TMs",
+        "sampled",
+        "TMs/s",
+        "projected time",
+    );
+
+    let generators = [Generator::All, Generator::NoSymmetries, Generator::Optimized];
+    let mut num_all = None;
+    for &generator in &generators {
+        let sweep = SweepBuilder::new(args.shared.n)
+            .generator(generator)
+            .max_steps(args.shared.max_steps)
+            .halt_convention(args.shared.halt_convention)
+            .run_budget(args.run_budget)
+            .cycle_budget(args.cycle_budget)
+            .ctl_budget(args.ctl_budget)
+            .num_threads(args.num_threads);
+        let num_tms = sweep.num_tms();
+        let num_all = *num_all.get_or_insert(num_tms);
+
+        // Small generators are analyzed completely in a single block.
+        let spacing = num_tms / NUM_BLOCKS;
+        let (num_blocks, block_len) = if sample_size >= num_tms || spacing == 0 {
+            (1, num_tms)
+        } else {
+            (NUM_BLOCKS, min(sample_size.div_ceil(NUM_BLOCKS), spacing))
+        };
+
+        let mut sampled = 0;
+        let mut elapsed = Duration::ZERO;
+        for i in 0..num_blocks {
+            let range = i * spacing..i * spacing + block_len;
+            sampled += block_len;
+
+            let before = Instant::now();
+            sweep.clone().range(Some(range)).run()?;
+            elapsed += before.elapsed();
+        }
+
+        let rate = sampled as f64 / elapsed.as_secs_f64();
+        let projected = Duration::from_secs_f64(num_tms as f64 / rate);
+        println!(
+            "    {: <16}{: >16}{: >12}{: >14.0}{: >18.2?}   ({:.1}% of 'all')",
+            generator.to_string(),
+            num_tms,
+            sampled,
+            rate,
+            projected,
+            100.0 * num_tms as f64 / num_all as f64,
+        );
+    }
+
+    println!();
+    println!(
+        "The projection assumes that the sample is representative and uses {} threads. \
+            The generators differ in which TMs they skip, so their TMs/s differ, too.",
+        SweepBuilder::new(args.shared.n).num_threads(args.num_threads).effective_num_threads(),
+    );
+    println!();
+
+    Ok(())
+}
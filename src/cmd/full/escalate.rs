@@ -0,0 +1,182 @@
+//! The second pass of a run with `--escalate-steps`: all TMs that were
+//! aborted after the maximum number of steps are run again with a much
+//! larger step limit and the summary is updated with their new outcomes.
+//!
+//! The TMs are run from scratch instead of continuing from where they were
+//! aborted, so the result is exactly the same as if the whole sweep was done
+//! with the larger limit.
+
+use std::{
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    thread,
+    time::Duration,
+};
+use anyhow::{Result, anyhow};
+use pbr::ProgressBar;
+
+use crate::{
+    analyze::Analyzer,
+    best::BestSoFar,
+    dispatch::{self, PerN},
+    summary::Summary,
+    tm::Tm,
+};
+use super::Args;
+
+
+/// The number of TMs a worker takes from the list at once.
+const BATCH_SIZE: usize = 64;
+
+/// What happened to the TMs that were run again.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Escalated {
+    /// The number of TMs that halted with the larger limit.
+    pub num_halted: u64,
+
+    /// The most steps of those TMs.
+    pub most_steps: u64,
+}
+
+/// Runs the aborted TMs, given as `(index, id)`, with `max_steps` and moves
+/// them from the aborted ones in `summary` to their new outcome.
+pub(super) fn run(
+    args: &Args,
+    max_steps: u64,
+    aborted: &[(u64, u64)],
+    summary: &mut Summary,
+) -> Result<Escalated> {
+    let n = args.shared.n;
+    dispatch::with_n(n, Escalation { args, max_steps, aborted, summary })
+        .unwrap_or_else(|| Err(anyhow!("invalid value for n: {}", n)))
+}
+
+/// The arguments of `run`, to call `do_run` for the right `N`.
+struct Escalation<'a> {
+    args: &'a Args,
+    max_steps: u64,
+    aborted: &'a [(u64, u64)],
+    summary: &'a mut Summary,
+}
+
+impl PerN for Escalation<'_> {
+    type Output = Result<Escalated>;
+
+    fn call<const N: usize>(self) -> Result<Escalated>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self.args, self.max_steps, self.aborted, self.summary)
+    }
+}
+
+fn do_run<const N: usize>(
+    args: &Args,
+    max_steps: u64,
+    aborted: &[(u64, u64)],
+    summary: &mut Summary,
+) -> Result<Escalated>
+where
+    [bool; N]: Default,
+{
+    let best = Arc::new(BestSoFar::with_baseline(summary.high_score()));
+    let next = AtomicUsize::new(0);
+    let num_threads = args.num_threads.unwrap_or_else(|| num_cpus::get() as u32);
+    let mut pb = ProgressBar::new(aborted.len() as u64);
+    pb.set_max_refresh_rate(Some(Duration::from_millis(10)));
+    let pb = Mutex::new(pb);
+
+    let rerun = thread::scope(|scope| {
+        let handles = (0..num_threads).map(|_| scope.spawn(|| {
+            let mut analyzer = Analyzer::builder()
+                .max_steps(max_steps)
+                .halt_convention(args.shared.halt_convention)
+                .lin_recurrence(args.detect_lin_recurrence)
+                .optional_deciders(args.deciders)
+                .accelerated(args.accelerated)
+                .cycle_budget(args.cycle_budget)
+                .ctl_budget(args.ctl_budget)
+                .prune(if args.prune { Some(best.clone()) } else { None })
+                .build();
+            let mut sink = (Summary::new(args.tie_break, args.list_winners), &*best);
+            loop {
+                let start = next.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+                if start >= aborted.len() {
+                    break;
+                }
+
+                let batch = &aborted[start..aborted.len().min(start + BATCH_SIZE)];
+                for &(index, id) in batch {
+                    // The TM already passed the static checks in the first
+                    // pass, so it is only run again.
+                    sink.0.set_index(index);
+                    analyzer.run_tm(<Tm<N>>::new_unchecked(id), &mut sink);
+                }
+                if !args.no_pb {
+                    pb.lock().expect("poisened lock").add(batch.len() as u64);
+                }
+            }
+            sink.0
+        })).collect::<Vec<_>>();
+
+        handles.into_iter()
+            .map(|h| h.join().expect("panic in worker thread"))
+            .collect::<Vec<_>>()
+    });
+    if !args.no_pb {
+        pb.into_inner().expect("poisened lock").finish();
+        println!();
+    }
+
+    summary.forget_aborted(aborted.iter().map(|&(_, id)| <Tm<N>>::new_unchecked(id)));
+    let mut escalated = Escalated { num_halted: 0, most_steps: 0 };
+    for thread_summary in rerun {
+        escalated.num_halted += thread_summary.num_halted();
+        escalated.most_steps = escalated.most_steps.max(thread_summary.most_steps());
+        summary.add(thread_summary);
+    }
+
+    Ok(escalated)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use structopt::StructOpt;
+    use crate::{gen::Generator, outcome::AnalysisResult, sweep::SweepBuilder};
+    use super::{Args, run};
+
+    #[test]
+    fn same_as_larger_limit() {
+        let sweep = |max_steps| {
+            SweepBuilder::new(3).generator(Generator::Optimized).max_steps(max_steps)
+        };
+        let aborted = Arc::new(Mutex::new(Vec::new()));
+        let mut summary = {
+            let aborted = aborted.clone();
+            sweep(10)
+                .on_result(move |index, id, result| {
+                    if let AnalysisResult::AbortedAfterMaxSteps { .. } = result {
+                        aborted.lock().unwrap().push((index, id));
+                    }
+                })
+                .run()
+                .unwrap()
+        };
+        let aborted = aborted.lock().unwrap().clone();
+
+        let args = Args::from_iter(&[
+            "full", "-n", "3", "--max-steps", "10", "--escalate-steps", "100", "--no-pb",
+        ]);
+        let escalated = run(&args, 100, &aborted, &mut summary).unwrap();
+        let expected = sweep(100).run().unwrap();
+
+        assert!(escalated.num_halted > 0);
+        assert_eq!(escalated.most_steps, 21);
+        assert_eq!(summary.num_total_tms(), expected.num_total_tms());
+        assert_eq!(summary.num_halted(), expected.num_halted());
+        assert_eq!(summary.num_aborted(), expected.num_aborted());
+        assert_eq!(summary.high_score(), expected.high_score());
+        assert_eq!(summary.most_steps(), expected.most_steps());
+    }
+}
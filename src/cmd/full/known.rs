@@ -0,0 +1,114 @@
+//! Reading the TMs that were already decided elsewhere (see `--known`).
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    mem,
+    path::Path,
+};
+use ahash::AHashSet;
+use anyhow::{Context, Result, anyhow};
+
+use crate::{
+    cmd::parse_tm,
+    dispatch::{self, PerN},
+};
+
+
+/// Reads the IDs of the known TMs with `n` states from `path`. The file is
+/// either a list with one TM per line (ID or standard notation, everything
+/// after the first whitespace is ignored) or a CSV file with an `id` column,
+/// like the ones written by `--raw-out` or `--examples-out`. Of the latter,
+/// only rows with a proven outcome of a TM that does not halt are used, so
+/// that the statistics of halted TMs stay complete.
+pub(super) fn read(path: &Path, n: u8) -> Result<AHashSet<u64>> {
+    dispatch::with_n(n, ReadKnown { path })
+        .expect("invalid value for n: argument parsing should catch this")
+}
+
+struct ReadKnown<'a> {
+    path: &'a Path,
+}
+
+impl PerN for ReadKnown<'_> {
+    type Output = Result<AHashSet<u64>>;
+
+    fn call<const N: usize>(self) -> Self::Output
+    where
+        [bool; N]: Default,
+    {
+        read_for::<N>(self.path)
+    }
+}
+
+fn read_for<const N: usize>(path: &Path) -> Result<AHashSet<u64>> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+
+    let mut ids = AHashSet::new();
+    let mut columns = None;
+    let mut first = true;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read '{}'", path.display()))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let context = || format!("line {} of '{}'", i + 1, path.display());
+
+        if mem::take(&mut first) && line.split(',').any(|f| f == "id") {
+            columns = Some(Columns::from_header(line));
+            continue;
+        }
+        let field = match &columns {
+            None => line.split_whitespace().next().unwrap(),
+            Some(columns) => {
+                let fields = line.split(',').collect::<Vec<_>>();
+                if fields.len() != columns.len {
+                    return Err(anyhow!("{}: wrong number of columns", context()));
+                }
+                if !columns.is_decided(&fields) {
+                    continue;
+                }
+                fields[columns.id]
+            }
+        };
+
+        let tm = parse_tm::<N>(field).with_context(context)?;
+        ids.insert(tm.encoded);
+    }
+
+    Ok(ids)
+}
+
+/// The positions of the columns used in a CSV file.
+struct Columns {
+    len: usize,
+    id: usize,
+    outcome: Option<usize>,
+    confidence: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &str) -> Self {
+        let names = header.split(',').collect::<Vec<_>>();
+        let find = |name: &str| names.iter().position(|&n| n == name);
+        Self {
+            len: names.len(),
+            id: find("id").expect("header without 'id' column"),
+            outcome: find("outcome"),
+            confidence: find("confidence"),
+        }
+    }
+
+    /// Whether the row says that the TM provably does not halt. Without
+    /// `outcome` and `confidence` columns, every TM is assumed to be decided.
+    fn is_decided(&self, fields: &[&str]) -> bool {
+        let outcome = self.outcome.map(|c| fields[c]);
+        let undecided = match self.confidence {
+            Some(c) => fields[c] != "proven",
+            None => matches!(outcome, Some("aborted" | "pruned")),
+        };
+        !undecided && !matches!(outcome, Some("halted" | "immediate-halt"))
+    }
+}
@@ -1,33 +1,78 @@
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 use std::{
-    cmp::min,
+    fs::File,
+    io::{BufWriter, Write},
     ops::Range,
-    str::FromStr,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    thread,
     time::{Duration, Instant},
 };
 use structopt::StructOpt;
-use pbr::ProgressBar;
 
-use crate::{SharedArgs, analyze::Analyzer, gen::{All, NoSymmetries, Optimized, TmGenerator}};
-
-mod summary;
+use crate::{
+    SharedArgs,
+    analyze::OptionalDeciders,
+    batch,
+    budget::Budget,
+    checkpoint::{Checkpoint, CheckpointTarget, ReportSettings},
+    cmd::{check_n, progress::ProgressDisplay},
+    continuation::ContinuationFile,
+    failed::{Failed, FailedFile},
+    gen::{Generator, PartialTable},
+    manifest::Manifest,
+    multi_symbol::ScoreDef,
+    outcome::{AnalysisResult, OUTCOME_NAMES},
+    output::{ExamplesOut, OutcomeOut, RawOut},
+    replay_file::{self, ReplayRecorder},
+    summary::{HistogramCutoff, HistogramFilter, Summary, TieBreak},
+    summary_file::SummaryFile,
+    sweep::{PanicPolicy, SweepBuilder},
+    tm,
+};
+#[cfg(feature = "gpu")]
+use crate::gpu::GpuSimulator;
 
-use self::summary::Summary;
+mod ablation;
+mod escalate;
+mod known;
+mod multi;
+mod progress_log;
 
+use self::{escalate::Escalated, progress_log::ProgressLog};
 
 #[derive(StructOpt, Debug, Clone)]
 pub struct Args {
     #[structopt(flatten)]
     shared: SharedArgs,
 
+    /// Number of symbols, e.g. 3 for BB(2,3). With more than 2 symbols, all
+    /// TMs are enumerated and only checked for a halting transition, run and
+    /// checked for cycles; only `--max-steps`, `--halt-convention`,
+    /// `--score-def`, `--num-threads` and `--standard-notation` apply then
+    /// and all other options are rejected. Not supported by `range`.
+    #[structopt(long, default_value = "2")]
+    symbols: u8,
+
+    /// How the score of TMs with more than 2 symbols is defined: 'nonzero'
+    /// counts the cells that are not blank, 'sum' adds up all symbols on the
+    /// tape. Both are reported for the champions.
+    #[structopt(long, default_value = "nonzero")]
+    score_def: ScoreDef,
+
     /// Set TM generator. 'all' blindly generates all possible TMs; 'no-symmetries'
     /// eliminates symmetric TMs that will result in the same outcome; 'optimized'
     /// also eliminates TMs that have on chance of winning busy beaver.
     #[structopt(short, long, default_value = "optimized")]
     generator: Generator,
 
+    /// If specified, only TMs with these transitions are analyzed, e.g.
+    /// 'A0=1RB,A1=1LH' (state and read symbol, then the action in standard
+    /// notation). Only the other transitions are enumerated, so this sweeps
+    /// a small sub-space even for large n. Symmetric TMs are only skipped if
+    /// just halt transitions are fixed.
+    #[structopt(long)]
+    fix: Option<PartialTable>,
+
     /// If specified, the progress bar is not shown.
     #[structopt(long)]
     pub no_pb: bool,
@@ -36,158 +81,670 @@ pub struct Args {
     #[structopt(long, short = "j")]
     pub num_threads: Option<u32>,
 
+    /// How the champion is chosen among all TMs reaching the high score:
+    /// 'fewest-steps', 'smallest-id' or 'earliest' (first in the generator's
+    /// order). All are independent of the number of threads.
+    #[structopt(long, default_value = "fewest-steps")]
+    pub tie_break: TieBreak,
+
     /// Set the height of the histogram that's printed in the end.
     #[structopt(long, default_value = "15")]
     pub histogram_height: u32,
 
     /// Set the max number of steps included in the histogram that's printed in
-    /// the end.
-    #[structopt(long, default_value = "30")]
-    pub histogram_cutoff: u32,
+    /// the end. 'auto' includes 99.9% of the halted TMs (at least the first 29
+    /// steps), with several steps per bar if necessary. TMs halting later are
+    /// shown in an extra bar.
+    #[structopt(long, default_value = "auto")]
+    pub histogram_cutoff: HistogramCutoff,
 
     /// If specified, the histogram is now shown.
     #[structopt(long)]
     pub hide_histogram: bool,
-}
 
-#[derive(Debug, Clone)]
-enum Generator {
-    All,
-    NoSymmetries,
-    Optimized,
+    /// Which halted TMs are included in the histogram: 'halted' includes all
+    /// of them; 'high-score' only the ones that reached the high score;
+    /// 'simulated' excludes the ones that halted immediately.
+    #[structopt(long, default_value = "halted")]
+    pub histogram_filter: HistogramFilter,
+
+    /// If specified, the outcomes are also reported split by the structure of
+    /// the start action (written value, movement and next state).
+    #[structopt(long)]
+    pub first_action_stats: bool,
+
+    /// If specified, the final tapes of all halted TMs are classified by
+    /// their blocks of 1s (blank, single block, alternating, k blocks) and
+    /// the number of TMs per pattern is printed.
+    #[structopt(long)]
+    pub tape_patterns: bool,
+
+    /// If specified, the champions are also printed in the standard notation
+    /// used by the bbchallenge and most papers (e.g. `1RB1LB_1LA1RH`).
+    #[structopt(long)]
+    pub standard_notation: bool,
+
+    /// The max number of Σ and S champions that are recorded and listed
+    /// (each), the first ones according to '--tie-break'. They are still all
+    /// counted. '0' records and lists all of them, which can be a lot for
+    /// small `--max-steps`.
+    #[structopt(long, default_value = "5", value_name("N"))]
+    pub list_winners: usize,
+
+    /// If specified, the IDs of all TMs that halted without writing a single
+    /// 1 are printed.
+    #[structopt(long)]
+    pub list_zero_ones: bool,
+
+    /// If specified, the high score and the most steps are compared with the
+    /// published values of Σ(n) and S(n) (known up to n = 5), and the number
+    /// of halted and immediately halting TMs with verified counts of the
+    /// generator (halted up to n = 4, immediate halts up to n = 5).
+    /// Contradictions indicate a bug in a generator or decider that tests
+    /// with small n don't catch. For a full check, `--max-steps` has to be at
+    /// least S(n).
+    #[structopt(long)]
+    pub check_reference: bool,
+
+    /// If specified, a table showing how many TMs were decided at which stage
+    /// of the analysis pipeline (static checks, running the TM) is printed.
+    #[structopt(long)]
+    pub stage_stats: bool,
+
+    /// Like `--stage-stats`, but additionally shows a bar chart.
+    #[structopt(long)]
+    pub stage_chart: bool,
+
+    /// If specified, the outcomes of TMs that were actually run are printed
+    /// again with percentages relative to those TMs only, i.e. excluding
+    /// TMs decided by the static checks (which usually dominate).
+    #[structopt(long)]
+    pub simulated_only: bool,
+
+    /// If specified, the sweep is stopped as soon as a TM is found that halts
+    /// with at least this many 1s on the tape. The TM is printed immediately
+    /// and the report only covers the TMs analyzed until then.
+    #[structopt(long)]
+    pub stop_at_score: Option<u32>,
+
+    /// Like `--stop-at-score`, but for the number of steps of halting TMs.
+    #[structopt(long)]
+    pub stop_at_steps: Option<u64>,
+
+    /// If specified, TMs are stopped as soon as they could not reach the high
+    /// score found so far anymore, even if they wrote a new 1 in every
+    /// remaining step. They are counted as pruned. This is faster, but the
+    /// statistics of halted TMs (e.g. the S champion) are incomplete.
+    #[structopt(long)]
+    pub prune: bool,
+
+    /// If specified, TMs that reach the maximum number of steps are run for
+    /// up to that many steps again, checking whether they repeat their
+    /// behavior (possibly shifted along the tape) since reaching the limit,
+    /// like in Lin's historical BB(3) search. This decides TMs with a long
+    /// start-up phase that are otherwise aborted, at the cost of running the
+    /// aborted ones twice as long.
+    #[structopt(long)]
+    pub detect_lin_recurrence: bool,
+
+    /// Optional deciders that are applied to TMs reaching the maximum number
+    /// of steps before they count as aborted, as comma separated list or
+    /// 'none'. Currently only 'ctl': searches a closed tape language built
+    /// from n-grams of up to 4 cells that does not contain a halting
+    /// configuration.
+    #[structopt(long, default_value = "none")]
+    pub deciders: OptionalDeciders,
+
+    /// Runs TMs with a long run (more than 65536 steps) with rule-based
+    /// acceleration: repeated patterns on the tape are detected, proven to
+    /// continue and then applied many times at once. This does not change any
+    /// result, but makes a '--max-steps' of billions feasible for TMs with a
+    /// regular behavior, like bouncers or the BB(5) champion.
+    #[structopt(long)]
+    pub accelerated: bool,
+
+    /// File with TMs that were already decided elsewhere, e.g. by a previous
+    /// run or by external deciders. They are not analyzed, but only counted
+    /// as 'known', so that the sweep focuses on the open TMs. Either one TM
+    /// per line (ID or standard notation, everything after the first
+    /// whitespace is ignored, lines starting with `#` are skipped), or a CSV
+    /// file with an `id` column like the one written by `--raw-out`, of which
+    /// only the TMs proven to never halt are used. Only TMs with exactly
+    /// these IDs are skipped, so the TMs should come from the same generator.
+    /// Halting TMs should not be listed, as they are missing from the Σ and S
+    /// statistics then.
+    #[structopt(long, parse(from_os_str), conflicts_with("checkpoint"))]
+    pub known: Option<PathBuf>,
+
+    /// A high score known from a previous run. `--prune` and the best TM
+    /// shown in the progress bar start from it instead of from zero, so only
+    /// better TMs are announced. With `--prune`, a value higher than the
+    /// actual high score prunes the champions!
+    #[structopt(long, conflicts_with("baseline-from"))]
+    pub baseline_score: Option<u32>,
+
+    /// Like `--baseline-score`, but uses the high score in the given
+    /// checkpoint of a previous run (see `--checkpoint`), which has to be for
+    /// the same `n` and `--halt-convention`.
+    #[structopt(long, parse(from_os_str))]
+    pub baseline_from: Option<PathBuf>,
+
+    /// What to do if analyzing a TM panics (i.e. hits a bug): 'fail' stops
+    /// the sweep immediately with an error; 'skip' skips the TM, continues
+    /// the sweep and lists all skipped TMs at the end, so that a single bad
+    /// TM does not kill a long run. Skipped TMs are missing in all statistics.
+    #[structopt(long, default_value = "fail")]
+    pub on_panic: PanicPolicy,
+
+    /// If specified, a checkpoint is written to this file regularly (see
+    /// `--checkpoint-interval`) and at the end. If the run is interrupted, it
+    /// can be continued with `resume`. Files written by `--continuations` and
+    /// the other outputs are not part of the checkpoint.
+    #[structopt(long, parse(from_os_str))]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Number of seconds between two checkpoints.
+    #[structopt(long, default_value = "600")]
+    pub checkpoint_interval: u64,
+
+    /// If specified, the exact configuration of all TMs that were aborted
+    /// after the maximum number of steps is written to this file. Those TMs
+    /// can then be continued with the `continue` subcommand.
+    #[structopt(long, parse(from_os_str))]
+    pub continuations: Option<PathBuf>,
+
+    /// If specified, the outcome of every TM is written to this CSV file
+    /// (columns `index,id,outcome,confidence,steps,ones,chunk,worker`, where
+    /// `confidence` is `undecided` for aborted and pruned TMs, which might
+    /// still halt, and `proven` otherwise). Rows are grouped
+    /// in chunks of consecutive indices, but the chunks are in no particular
+    /// order. `chunk` and `worker` say where each result was computed, so
+    /// that suspicious parts of the file can be traced and recomputed.
+    #[structopt(long, parse(from_os_str))]
+    pub raw_out: Option<PathBuf>,
+
+    /// TMs that halted after fewer than this many steps are not included in
+    /// `--raw-out` and `--list-zero-ones`, to keep them small when only long
+    /// running TMs are of interest. The statistics still include them.
+    #[structopt(long, default_value = "0")]
+    pub ignore_below_steps: u64,
+
+    /// If specified, the IDs of all TMs that were aborted (i.e. not decided)
+    /// are written to this file, one per line. It can be passed to `sheet`.
+    #[structopt(long, parse(from_os_str))]
+    pub undecided_out: Option<PathBuf>,
+
+    /// Writes the IDs of all TMs with this outcome to the file given by
+    /// `--dump-out`, one per line, like `--undecided-out` does for 'aborted'.
+    #[structopt(long, requires("dump-out"), possible_values(&OUTCOME_NAMES))]
+    pub dump_outcome: Option<String>,
+
+    /// The file for `--dump-outcome`.
+    #[structopt(long, requires("dump-outcome"), parse(from_os_str))]
+    pub dump_out: Option<PathBuf>,
+
+    /// Writes up to this many example TMs per outcome to the file given by
+    /// `--examples-out`. They are drawn uniformly at random from all TMs with
+    /// that outcome, no matter in which order the TMs were analyzed.
+    #[structopt(long, requires("examples-out"))]
+    pub examples: Option<usize>,
+
+    /// The file for `--examples` (columns `outcome,confidence,index,id`).
+    #[structopt(long, requires("examples"), parse(from_os_str))]
+    pub examples_out: Option<PathBuf>,
+
+    /// If specified, all TMs that were skipped with `--on-panic skip` are
+    /// written to this file together with their panic message, as soon as
+    /// they fail. They can be analyzed again with `replay-failed`.
+    #[structopt(long, parse(from_os_str))]
+    pub failed_out: Option<PathBuf>,
+
+    /// If specified, the settings and the order in which the results of all
+    /// jobs were passed to the outputs are written to this file. Running
+    /// again with `--replay` then writes exactly the same outputs (apart
+    /// from the manifest), e.g. to debug differences between two runs.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all(&["replay", "prune", "stop-at-score", "stop-at-steps", "checkpoint"]),
+    )]
+    pub record: Option<PathBuf>,
+
+    /// Reproduces a run recorded with `--record`: the results are passed to
+    /// the outputs in the recorded order and attributed to the same workers,
+    /// so that e.g. `--raw-out` and `--examples-out` are identical. The
+    /// settings have to match the recorded ones. The number of threads is
+    /// taken from the file unless specified.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all(&["prune", "stop-at-score", "stop-at-steps", "checkpoint"]),
+    )]
+    pub replay: Option<PathBuf>,
+
+    /// If specified, the summary of all analyzed TMs is written to this file
+    /// at the end. The summaries of several runs of `range` can be combined
+    /// with `merge`.
+    #[structopt(long, parse(from_os_str))]
+    pub summary_out: Option<PathBuf>,
+
+    /// If specified, the results (counts per outcome, Σ and S with all
+    /// champions, histograms and the manifest) are written as JSON to this
+    /// file at the end.
+    #[structopt(long, parse(from_os_str))]
+    pub json_out: Option<PathBuf>,
+
+    /// If specified, all TMs that were aborted after `--max-steps` are run
+    /// again with this larger step limit after the sweep, and the report
+    /// shows their new outcomes. As most TMs are decided within a few steps,
+    /// this is much faster than a sweep with the larger limit, but gives the
+    /// same results. Outputs written during the sweep (e.g. `--raw-out` or
+    /// `--continuations`) only contain the results of the first pass.
+    #[structopt(long, conflicts_with_all(&["checkpoint", "run-budget"]))]
+    pub escalate_steps: Option<u64>,
+
+    /// Budget for running a single TM, either as number of steps (e.g.
+    /// '100k-steps') or as wall-clock time (e.g. '10ms'). TMs exceeding it are
+    /// counted as aborted, just like TMs reaching the maximum number of steps.
+    /// Note that time budgets make results depend on the machine and its load.
+    #[structopt(long)]
+    pub run_budget: Option<Budget>,
+
+    /// Budget of the cycle detection for a single TM, like `--run-budget`.
+    /// Once it is used up, the TM keeps running without being checked for
+    /// cycles, so it is only decided by the other deciders or aborted.
+    #[structopt(long)]
+    pub cycle_budget: Option<Budget>,
+
+    /// Budget of the CTL decider (see `--deciders`) for a single TM, like
+    /// `--run-budget`. A step is computing the successors of one window of
+    /// cells. TMs exceeding it are not decided by CTL.
+    #[structopt(long)]
+    pub ctl_budget: Option<Budget>,
+
+    /// If specified, progress samples (time, fraction done, throughput and
+    /// high score so far) are written to this CSV file about once per
+    /// second, e.g. to diagnose slowdowns afterwards.
+    #[structopt(long, parse(from_os_str))]
+    pub progress_log: Option<PathBuf>,
+
+    /// If specified, no full run is done. Instead, a sample of TMs (see
+    /// `--ablation-sample`) is analyzed with each generator and the speed and
+    /// projected time of a full run are printed for each, to choose a
+    /// generator for large runs.
+    #[structopt(long)]
+    pub generator_ablation: bool,
+
+    /// The number of TMs per generator analyzed by `--generator-ablation`.
+    #[structopt(long, default_value = "1000000")]
+    pub ablation_sample: u64,
+
+    /// If specified, the tape memory is deallocated before each TM instead
+    /// of being reused, so that the tape stats show the "cold" behavior.
+    #[cfg(feature = "tape-stats")]
+    #[structopt(long)]
+    pub cold_tape: bool,
+
+    /// If specified, the TMs that pass the static checks are simulated in
+    /// batches of 16 running in lockstep with SIMD instructions first. Only
+    /// the ones that don't halt within `--simd-steps` steps (or leave the tiny
+    /// tape of a lane) are analyzed as usual, from scratch. The results are
+    /// the same as without it. Note: this is currently slower than without
+    /// it (by about 10-30%), as the TMs that don't halt in a lane are
+    /// simulated again by the analyzer.
+    #[structopt(long, conflicts_with_all(&["prune", "run-budget"]))]
+    pub simd: bool,
+
+    /// The maximum number of steps a TM is simulated with `--simd`.
+    #[structopt(long, default_value = "16")]
+    pub simd_steps: u32,
+
+    /// If specified, the TMs that pass the static checks are simulated on
+    /// the GPU in large batches first. Only the ones that don't halt within
+    /// `--gpu-steps` steps (or leave the small tape of the GPU) are analyzed
+    /// on the CPU. The results are the same as without the GPU.
+    #[cfg(feature = "gpu")]
+    #[structopt(long, conflicts_with_all(&["prune", "run-budget", "simd"]))]
+    pub gpu: bool,
+
+    /// The maximum number of steps a TM is simulated on the GPU with
+    /// `--gpu`. Most TMs that halt do so after a few steps, while the ones
+    /// that don't halt only waste GPU time until this limit.
+    #[cfg(feature = "gpu")]
+    #[structopt(long, default_value = "4096")]
+    pub gpu_steps: u32,
 }
 
-impl FromStr for Generator {
-    type Err = &'static str;
+pub(crate) fn run(args: Args) -> Result<()> {
+    if args.symbols != 2 {
+        return multi::run(&args);
+    }
+    check_n(args.shared.n)?;
+    if args.generator_ablation {
+        return ablation::run(&args, args.ablation_sample);
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "all" => Ok(Self::All),
-            "no-symmetries" => Ok(Self::NoSymmetries),
-            "optimized" => Ok(Self::Optimized),
-            _ => Err("invalid value for 'generator'"),
-        }
+    analyze(args, None)
+}
+
+/// Like `run`, but only analyzes the TMs with the given generator indices.
+pub(crate) fn run_range(args: Args, range: Range<u64>) -> Result<()> {
+    if args.symbols != 2 {
+        return Err(anyhow!("'range' only supports TMs with 2 symbols"));
     }
+    check_n(args.shared.n)?;
+    analyze(args, Some(range))
 }
 
+fn analyze(args: Args, range: Option<Range<u64>>) -> Result<()> {
+    if let Some(table) = &args.fix {
+        table.check(args.shared.n, &args.generator.opt()).map_err(|e| anyhow!(e))?;
+    }
+    if args.escalate_steps.is_some_and(|steps| steps <= args.shared.max_steps) {
+        return Err(anyhow!("'--escalate-steps' has to be larger than '--max-steps'"));
+    }
+    let baseline_score = match &args.baseline_from {
+        Some(path) => Some(read_baseline(path, &args)?),
+        None => args.baseline_score,
+    };
+    let known = args.known.as_ref().map(|path| known::read(path, args.shared.n)).transpose()?;
+    let replay = args.replay.as_deref().map(replay_file::read).transpose()?;
+    let num_known = known.as_ref().map_or(0, |k| k.len());
+    let report = ReportSettings {
+        standard_notation: args.standard_notation,
+        histogram_height: args.histogram_height,
+        histogram_cutoff: args.histogram_cutoff,
+        hide_histogram: args.hide_histogram,
+        histogram_filter: args.histogram_filter,
+        first_action_stats: args.first_action_stats,
+        tape_patterns: args.tape_patterns,
+        stage_stats: args.stage_stats,
+        stage_chart: args.stage_chart,
+        simulated_only: args.simulated_only,
+    };
+    let manifest = Manifest::new(&[
+        ("n", args.shared.n.to_string()),
+        ("generator", args.generator.to_string()),
+        ("fixed", args.fix.as_ref().map_or("none".into(), |t| t.to_string())),
+        ("max-steps", args.shared.max_steps.to_string()),
+        ("escalate-steps", args.escalate_steps.map_or("none".into(), |k| k.to_string())),
+        ("halt-convention", args.shared.halt_convention.to_string()),
+        ("lin-recurrence", args.detect_lin_recurrence.to_string()),
+        ("optional-deciders", args.deciders.to_string()),
+        ("accelerated", args.accelerated.to_string()),
+        ("run-budget", args.run_budget.map_or("none".into(), |b| b.to_string())),
+        ("cycle-budget", args.cycle_budget.map_or("none".into(), |b| b.to_string())),
+        ("ctl-budget", args.ctl_budget.map_or("none".into(), |b| b.to_string())),
+        ("tie-break", args.tie_break.to_string()),
+        ("max-champions", args.list_winners.to_string()),
+        ("prune", args.prune.to_string()),
+        ("range", range.as_ref().map_or("all".into(), |r| format!("{}..{}", r.start, r.end))),
+        ("baseline-score", baseline_score.map_or("none".into(), |k| k.to_string())),
+        ("known", args.known.as_ref().map_or("none".into(), |_| format!("{} TMs", num_known))),
+        ("on-panic", args.on_panic.to_string()),
+        ("ignore-below-steps", args.ignore_below_steps.to_string()),
+        ("stop-at-score", args.stop_at_score.map_or("none".into(), |k| k.to_string())),
+        ("stop-at-steps", args.stop_at_steps.map_or("none".into(), |k| k.to_string())),
+    ]);
+    let continuations = args.continuations.as_ref()
+        .map(|path| ContinuationFile::create(path, args.shared.n, &manifest))
+        .transpose()?;
+    let mut sweep = SweepBuilder::new(args.shared.n)
+        .generator(args.generator)
+        .fixed(args.fix.clone())
+        .max_steps(args.shared.max_steps)
+        .halt_convention(args.shared.halt_convention)
+        .lin_recurrence(args.detect_lin_recurrence)
+        .optional_deciders(args.deciders)
+        .accelerated(args.accelerated)
+        .run_budget(args.run_budget)
+        .cycle_budget(args.cycle_budget)
+        .ctl_budget(args.ctl_budget)
+        .num_threads(args.num_threads.or(replay.as_ref().map(|r| r.num_threads)))
+        .tie_break(args.tie_break)
+        .max_champions(args.list_winners)
+        .prune(args.prune)
+        .baseline_score(baseline_score)
+        .range(range.clone())
+        .panic_policy(args.on_panic)
+        .known(known)
+        .continuations(continuations)
+        .checkpoint(args.checkpoint.clone().map(|path| CheckpointTarget {
+            path,
+            interval: Duration::from_secs(args.checkpoint_interval),
+            manifest: manifest.clone(),
+            report: report.clone(),
+        }));
+    #[cfg(feature = "tape-stats")]
+    {
+        sweep = sweep.cold_tape(args.cold_tape);
+    }
+    sweep = sweep.simd(args.simd.then_some(args.simd_steps));
+    #[cfg(feature = "gpu")]
+    let gpu_name = {
+        let gpu = args.gpu.then(|| GpuSimulator::new(args.gpu_steps)).transpose()?;
+        let name = gpu.as_ref().map(|gpu| gpu.adapter_name().to_owned());
+        sweep = sweep.gpu(gpu);
+        name
+    };
+    let recorder = args.record.as_ref()
+        .map(|path| {
+            let num_threads = sweep.effective_num_threads();
+            ReplayRecorder::create(path, &sweep.settings(), num_threads, range.as_ref(), &manifest)
+        })
+        .transpose()?
+        .map(|recorder| Arc::new(Mutex::new(recorder)));
+    sweep = sweep.record(recorder.clone()).replay(replay);
+
+    if let Some(path) = &args.raw_out {
+        sweep = sweep.output(RawOut::create(path, &manifest, args.ignore_below_steps)?);
+    }
+    if let Some(path) = &args.undecided_out {
+        sweep = sweep.output(OutcomeOut::create(path, &manifest, "aborted")?);
+    }
+    if let (Some(outcome), Some(path)) = (&args.dump_outcome, &args.dump_out) {
+        sweep = sweep.output(OutcomeOut::create(path, &manifest, outcome)?);
+    }
+    if let (Some(k), Some(path)) = (args.examples, &args.examples_out) {
+        sweep = sweep.output(ExamplesOut::create(path, &manifest, k)?);
+    }
 
-pub(crate) fn run(args: Args) -> Result<()> {
-    macro_rules! dispatch_generator {
-        ($n:expr) => {
-            match args.generator {
-                Generator::All => do_run::<All<$n>, $n>(args),
-                Generator::NoSymmetries => do_run::<NoSymmetries<$n>, $n>(args),
-                Generator::Optimized => do_run::<Optimized<$n>, $n>(args),
+    let found = Arc::new(Mutex::new(Vec::new()));
+    if args.stop_at_score.is_some() || args.stop_at_steps.is_some() {
+        let found = found.clone();
+        let (min_ones, min_steps) = (args.stop_at_score, args.stop_at_steps);
+        sweep = sweep.stop_if(move |id, result| {
+            let (num_steps, num_ones) = match result {
+                AnalysisResult::Halted { num_steps, num_ones }
+                    | AnalysisResult::ImmediateHalt { num_steps, num_ones }
+                    => (num_steps, num_ones),
+                _ => return false,
+            };
+            let hit = min_ones.is_some_and(|k| num_ones >= k)
+                || min_steps.is_some_and(|k| num_steps >= k);
+            if hit {
+                println!();
+                bunt::println!(
+                    "{$green+bold}▸ Found TM {} that halted after {} steps with {} ones{/$}",
+                    id,
+                    num_steps,
+                    num_ones,
+                );
+                found.lock().expect("poisened lock").push(id);
             }
-        };
+            hit
+        });
     }
 
-    match args.shared.n {
-        1 => dispatch_generator!(1),
-        2 => dispatch_generator!(2),
-        3 => dispatch_generator!(3),
-        4 => dispatch_generator!(4),
-        5 => dispatch_generator!(5),
-        6 => dispatch_generator!(6),
-        _ => panic!("invalid value for n: argument parsing should catch this"),
+    let skipped = Arc::new(Mutex::new(Vec::new()));
+    let failed_file = args.failed_out.as_ref()
+        .map(|path| FailedFile::create(path, &sweep.settings(), &manifest))
+        .transpose()?
+        .map(|file| Arc::new(Mutex::new(file)));
+    {
+        let skipped = skipped.clone();
+        let failed_file = failed_file.clone();
+        sweep = sweep.on_skip(move |index, id, message| {
+            let failed = Failed { index, id, message: message.to_owned() };
+            if let Some(file) = &failed_file {
+                file.lock().expect("poisened lock").record(&failed);
+            }
+            skipped.lock().expect("poisened lock").push(failed);
+        });
     }
 
-    Ok(())
-}
+    let aborted = Arc::new(Mutex::new(Vec::new()));
+    if args.escalate_steps.is_some() {
+        let aborted = aborted.clone();
+        sweep = sweep.on_result(move |index, id, result| {
+            if let AnalysisResult::AbortedAfterMaxSteps { .. } = result {
+                aborted.lock().expect("poisened lock").push((index, id));
+            }
+        });
+    }
 
-#[inline(never)] // Useful for inspecting assembly
-fn do_run<G: TmGenerator<N>, const N: usize>(args: Args)
-where
-    [bool; N]: Default,
-{
-    let num_tms = G::num_tms();
+    let zero_ones_tms = Arc::new(Mutex::new(Vec::new()));
+    if args.list_zero_ones {
+        let zero_ones_tms = zero_ones_tms.clone();
+        let ignore_below_steps = args.ignore_below_steps;
+        sweep = sweep.on_result(move |_, id, result| {
+            if result.halt_steps().is_some_and(|steps| steps < ignore_below_steps) {
+                return;
+            }
+            if let AnalysisResult::Halted { num_ones: 0, .. }
+                | AnalysisResult::ImmediateHalt { num_ones: 0, .. } = result
+            {
+                zero_ones_tms.lock().expect("poisened lock").push(id);
+            }
+        });
+    }
+
+    let num_tms = sweep.num_tms();
+    let num_threads = sweep.effective_num_threads();
     println!("");
     bunt::println!(
         "{$blue+bold}▸ Analyzing {[intense]} TMs with {[intense]} states...{/$}",
         num_tms,
-        N,
+        args.shared.n,
     );
     println!("");
-    println!("... using the generator '{}'", G::description());
+    println!("... using the generator '{}'", sweep.generator_description());
+    if let Some(table) = &args.fix {
+        println!("... only with the fixed transitions {}", table);
+    }
+    if let Some(range) = &range {
+        println!(
+            "... only with the indices {}..{} of all {} TMs",
+            range.start,
+            range.end,
+            sweep.num_generated_tms(),
+        );
+    }
+    if let Some(budget) = args.run_budget {
+        println!("... with a run budget of {} per TM", budget);
+    }
+    if let Some(budget) = args.cycle_budget {
+        println!("... with a cycle detection budget of {} per TM", budget);
+    }
+    if let Some(budget) = args.ctl_budget {
+        println!("... with a CTL budget of {} per TM", budget);
+    }
+    if args.simd {
+        println!(
+            "... simulating {} TMs in lockstep for up to {} steps first",
+            batch::LANES,
+            args.simd_steps,
+        );
+    }
+    #[cfg(feature = "gpu")]
+    if let Some(name) = &gpu_name {
+        println!("... using the GPU '{}' for up to {} steps per TM", name, args.gpu_steps);
+    }
+    if let Some(path) = &args.known {
+        println!("... skipping the {} known TMs listed in '{}'", num_known, path.display());
+    }
     println!("");
 
 
     // ----- Run -------------------------------------------------------------
-    let mut pb = ProgressBar::new(num_tms);
-    pb.set_max_refresh_rate(Some(Duration::from_millis(10)));
-    let pb = Arc::new(Mutex::new(pb));
-
-    let before = Instant::now();
-
-    // Create a channel to pass pass the work to the workers. We bound it to 32
-    // to have always have some work ready, but to not use too much memory.
-    let (s, r) = crossbeam_channel::bounded::<Range<u64>>(32);
-
-    // Create the worker threads
-    let num_threads = args.num_threads.unwrap_or_else(|| num_cpus::get() as u32);
-    let join_handles = (0..num_threads).map(|_| {
-        let new_jobs = r.clone();
+    let pb = Arc::new(Mutex::new(ProgressDisplay::new(num_tms, num_threads)));
+    let progress_log = args.progress_log.as_ref()
+        .map(|path| ProgressLog::create(path, num_tms, &manifest))
+        .transpose()?
+        .map(|log| Arc::new(Mutex::new(log)));
+    {
         let pb = pb.clone();
-        let args = args.clone();
-        let args = args.clone();
-        thread::spawn(move || {
-            let mut analyzer = Analyzer::new(args.shared.max_steps);
-            let mut summary = Summary::new();
-
-            for range in new_jobs.iter() {
-                let job_len = range.end - range.start;
-
-                // Analyze each TM in this batch
-                let mut count = 0;
-                G::for_range(range.clone(), |tm| {
-                    count += 1;
-                    analyzer.analyze(tm, &mut summary);
-                });
-
-                // Advance progress bar
-                if !args.no_pb {
-                    pb.lock().expect("poisened lock").add(job_len);
-                }
+        let show_pb = !args.no_pb;
+        let n = args.shared.n;
+        sweep = sweep.on_new_best(move |best| {
+            let print = || bunt::println!(
+                "{$green}▸ New high score: {} ones after {} steps by TM {} ({}){/$}",
+                best.score,
+                best.steps,
+                best.id,
+                tm::standard_notation(best.id, n),
+            );
+            if show_pb {
+                let mut pb = pb.lock().expect("poisened lock");
+                pb.record_best(best);
+                pb.interrupt(print);
+            } else {
+                print();
             }
-
-            summary
-        })
-    }).collect::<Vec<_>>();
-
-    // So in theory, a very large number is best for performance. BUT the
-    // progress bar only changes when a whole chunk is done. So for super slow
-    // PCs, or debug builds, or runs with lots of debug output, or stuff like
-    // that -- we want the progress bar to still be useful.
-    let chunk_size = match N {
-        1 => 1,
-        2 => 500,
-        3 => 50_000,
-        _ => 1_000_000,
-    };
-
-    for start in (0..num_tms).step_by(chunk_size) {
-        let range = start..min(start + chunk_size as u64, num_tms);
-        s.send(range).expect("channel unexpectedly disconnected");
+        });
+    }
+    {
+        let pb = pb.clone();
+        let progress_log = progress_log.clone();
+        let show_pb = !args.no_pb;
+        sweep = sweep.on_progress(move |progress| {
+            if show_pb {
+                pb.lock().expect("poisened lock").record(progress);
+            }
+            if let Some(log) = &progress_log {
+                log.lock().expect("poisened lock").record(progress);
+            }
+        });
     }
 
-    // Join all threads
-    drop(s);
-    let mut summary = Summary::new();
-    for handle in join_handles {
-        let thread_summary = handle.join().expect("panic in worker thread");
-        summary.add(thread_summary);
+    let mut settings = sweep.settings();
+    let selected = range.clone().unwrap_or(0..sweep.num_generated_tms());
+    let before = Instant::now();
+    #[cfg(feature = "profile")]
+    let sampler = crate::profile::Sampler::start(Duration::from_millis(1));
+    let mut summary = sweep.run()?;
+    #[cfg(feature = "profile")]
+    let profile = sampler.stop();
+    if let Some(recorder) = recorder {
+        Arc::try_unwrap(recorder)
+            .ok()
+            .expect("replay recorder still shared after the sweep")
+            .into_inner()
+            .expect("poisened lock")
+            .finish()?;
+    }
+    if let Some(file) = failed_file {
+        Arc::try_unwrap(file)
+            .ok()
+            .expect("failed file still shared after the sweep")
+            .into_inner()
+            .expect("poisened lock")
+            .finish()?;
+    }
+    if let Some(log) = &progress_log {
+        log.lock().expect("poisened lock").finish()?;
     }
 
     if !args.no_pb {
         pb.lock().unwrap().finish();
-        println!();
     }
 
     println!();
     let elapsed = before.elapsed();
 
     // The `as u64` could technically overflow, but 2^64ns = 584 years, so...
-    let core_nanos_per_tm = (elapsed.as_nanos() * num_threads as u128) / num_tms as u128;
+    let num_analyzed = summary.num_total_tms();
+    let core_nanos_per_tm = (elapsed.as_nanos() * num_threads as u128) / num_analyzed as u128;
     let core_time_per_tm = Duration::from_nanos(core_nanos_per_tm as u64);
     println!(
         "  (That took {:.2?}, {:?} per TM on {} threads -> {:?} core time per TM)",
@@ -197,8 +754,175 @@ where
         core_time_per_tm,
     );
 
+    // ----- Second pass -----------------------------------------------------
+    let mut max_steps = args.shared.max_steps;
+    let mut escalated = None;
+    if let Some(escalate_steps) = args.escalate_steps {
+        let aborted = aborted.lock().expect("poisened lock");
+        println!();
+        bunt::println!(
+            "{$blue+bold}▸ Running {[intense]} aborted TMs again with up to {[intense]} \
+                steps...{/$}",
+            aborted.len(),
+            escalate_steps,
+        );
+        println!();
+
+        let before = Instant::now();
+        escalated = Some(escalate::run(&args, escalate_steps, &aborted, &mut summary)?);
+        println!("  (That took {:.2?})", before.elapsed());
+
+        // The summary is now the same as the one of a sweep with the larger
+        // limit.
+        max_steps = escalate_steps;
+        settings.max_steps = escalate_steps;
+    }
+
     // ----- Print results ---------------------------------------------------
     println!();
-    summary.print_report(&args);
+    let found = found.lock().expect("poisened lock");
+    if !found.is_empty() {
+        bunt::println!(
+            "{$yellow}Note:{/$} the sweep was stopped early after finding TM {}. \
+                The following statistics only cover the TMs analyzed until then.",
+            found[0],
+        );
+        println!();
+    }
+    summary.print_report(&report.options(&settings));
+
+    #[cfg(feature = "profile")]
+    {
+        profile.print();
+        println!();
+    }
+
+    let mut skipped = skipped.lock().expect("poisened lock").clone();
+    let complete = found.is_empty() && skipped.is_empty();
+    if !skipped.is_empty() {
+        skipped.sort_unstable_by_key(|failed| failed.index);
+        bunt::println!(
+            "{$red+bold}▸ {} TMs were skipped because analyzing them panicked:{/$}",
+            skipped.len(),
+        );
+        for failed in skipped {
+            println!("    TM {} (index {}): {}", failed.id, failed.index, failed.message);
+        }
+        println!();
+    }
+    warn_max_steps(&summary, &args, escalated);
+
+    if args.check_reference {
+        // TMs that were pruned, aborted, skipped or outside of the range or
+        // the fixed transitions might have reached the reference values.
+        let complete = complete
+            && !args.prune
+            && args.run_budget.is_none()
+            && range.is_none()
+            && args.fix.is_none();
+        summary.print_reference_comparison(
+            args.shared.n,
+            args.generator,
+            max_steps,
+            args.shared.halt_convention,
+            complete,
+        );
+        println!();
+    }
+
+    if args.list_zero_ones {
+        let mut ids = zero_ones_tms.lock().expect("poisened lock").clone();
+        ids.sort_unstable();
+
+        bunt::println!("{$blue+bold}▸ TMs that halted without writing a single 1:{/$}");
+        for id in ids {
+            println!("    {}", id);
+        }
+        println!();
+    }
+
+    if let Some(path) = &args.json_out {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut w = BufWriter::new(file);
+        summary.write_json(&mut w, args.shared.n, &manifest)?;
+        w.flush().with_context(|| format!("failed to write '{}'", path.display()))?;
+        bunt::println!("Wrote JSON results to {[green+bold]}", path.display());
+        println!();
+    }
+
+    if let Some(path) = &args.summary_out {
+        let file = SummaryFile { settings, range: selected, complete, summary };
+        file.write(path, &manifest)?;
+        bunt::println!("Wrote summary to {[green+bold]}", path.display());
+        if !complete {
+            bunt::println!(
+                "{$yellow}Note:{/$} the summary does not include all TMs of the range, as \
+                    the run was stopped early or TMs were skipped.",
+            );
+        }
+        println!();
+    }
+
+    manifest.print();
+    println!();
+
+    Ok(())
+}
+
+/// Warns if the step limit was clearly too low (see `Summary::warn_max_steps`)
+/// and suggests a larger limit, estimated from the TMs that halted with
+/// `--escalate-steps` if it was used.
+fn warn_max_steps(summary: &Summary, args: &Args, escalated: Option<Escalated>) {
+    let max_steps = args.escalate_steps.unwrap_or(args.shared.max_steps);
+    if !summary.warn_max_steps(max_steps) {
+        return;
+    }
+
+    let larger = max_steps.saturating_mul(10);
+    match escalated {
+        Some(escalated) if escalated.num_halted > 0 => println!(
+            "  With '--escalate-steps {}', {} of the TMs aborted after {} steps halted, the \
+                longest one after {} steps, so a single run needs at least '--max-steps {}'. \
+                The others might need much more, e.g. '--escalate-steps {}'.",
+            max_steps,
+            escalated.num_halted,
+            args.shared.max_steps,
+            escalated.most_steps,
+            escalated.most_steps,
+            larger,
+        ),
+        Some(_) => println!(
+            "  None of the TMs aborted after {} steps halted with '--escalate-steps {}', so \
+                the remaining ones likely never halt. A much larger limit like \
+                '--escalate-steps {}' might still decide some.",
+            args.shared.max_steps,
+            max_steps,
+            larger,
+        ),
+        None => println!(
+            "  Consider a larger limit like '--max-steps {}', or '--escalate-steps {}' to only \
+                run the aborted TMs again with it.",
+            larger,
+            larger,
+        ),
+    }
+    println!();
 }
 
+/// Reads the high score from the checkpoint of a previous run for
+/// `--baseline-from`.
+fn read_baseline(path: &Path, args: &Args) -> Result<u32> {
+    let checkpoint = Checkpoint::read(path)?;
+    let settings = &checkpoint.settings;
+    if settings.n != args.shared.n || settings.halt_convention != args.shared.halt_convention {
+        return Err(anyhow!(
+            "'{}' is for n = {} with '--halt-convention {}', which does not match this run",
+            path.display(),
+            settings.n,
+            settings.halt_convention,
+        ));
+    }
+
+    Ok(checkpoint.summary.high_score())
+}
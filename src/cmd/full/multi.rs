@@ -0,0 +1,360 @@
+//! Sweeps over TMs with more than two symbols (`--symbols`), using the simple
+//! generator and analysis of `multi_symbol` instead of `SweepBuilder`.
+
+use std::{
+    cmp::Reverse,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::Instant,
+};
+use anyhow::{Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    multi_symbol::{self, MultiGenerator, MultiHalted, MultiOutcome, ScoreDef},
+    sweep::SweepBuilder,
+};
+use super::Args;
+
+
+/// Number of consecutive TMs a worker thread takes at once.
+const CHUNK_SIZE: u64 = 4096;
+
+/// Counts and champions of the analyzed TMs.
+#[derive(Debug)]
+struct Report {
+    score_def: ScoreDef,
+    no_halt_transition: u64,
+    halted: u64,
+    cycle: u64,
+    aborted: u64,
+
+    /// The TMs with the highest score and with the most steps, as
+    /// `(index, result)`. Among equal TMs, the one with the smallest index
+    /// wins, so that the result does not depend on the number of threads.
+    score_champion: Option<(u64, MultiHalted)>,
+    steps_champion: Option<(u64, MultiHalted)>,
+}
+
+impl Report {
+    fn new(score_def: ScoreDef) -> Self {
+        Self {
+            score_def,
+            no_halt_transition: 0,
+            halted: 0,
+            cycle: 0,
+            aborted: 0,
+            score_champion: None,
+            steps_champion: None,
+        }
+    }
+
+    fn add(&mut self, index: u64, outcome: MultiOutcome) {
+        match outcome {
+            MultiOutcome::NoHaltTransition => self.no_halt_transition += 1,
+            MultiOutcome::Cycle => self.cycle += 1,
+            MultiOutcome::AbortedAfterMaxSteps => self.aborted += 1,
+            MultiOutcome::Halted(halted) => {
+                self.halted += 1;
+                let score_def = self.score_def;
+                update(&mut self.score_champion, index, halted, |h| h.score(score_def));
+                update(&mut self.steps_champion, index, halted, |h| h.steps);
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Report) {
+        self.no_halt_transition += other.no_halt_transition;
+        self.halted += other.halted;
+        self.cycle += other.cycle;
+        self.aborted += other.aborted;
+        if let Some((index, halted)) = other.score_champion {
+            let score_def = self.score_def;
+            update(&mut self.score_champion, index, halted, |h| h.score(score_def));
+        }
+        if let Some((index, halted)) = other.steps_champion {
+            update(&mut self.steps_champion, index, halted, |h| h.steps);
+        }
+    }
+}
+
+fn update(
+    champion: &mut Option<(u64, MultiHalted)>,
+    index: u64,
+    halted: MultiHalted,
+    key: impl Fn(&MultiHalted) -> u64,
+) {
+    let better = champion.as_ref().is_none_or(|(champion_index, champion)| {
+        (key(&halted), Reverse(index)) > (key(champion), Reverse(*champion_index))
+    });
+    if better {
+        *champion = Some((index, halted));
+    }
+}
+
+/// Checks that no option is used that has no effect with more than 2
+/// symbols, as it only works for binary TMs.
+fn check_args(args: &Args) -> Result<()> {
+    // Options with a default value can't be told apart from not being
+    // specified, so they are compared to the default instead.
+    let defaults = Args::from_iter_safe(["full", "-n", "2"]).expect("invalid default args");
+    #[allow(unused_mut)]
+    let mut ignored = vec![
+        ("--generator", args.generator != defaults.generator),
+        ("--fix", args.fix.is_some()),
+        ("--no-pb", args.no_pb),
+        ("--tie-break", args.tie_break != defaults.tie_break),
+        ("--histogram-height", args.histogram_height != defaults.histogram_height),
+        ("--histogram-cutoff", args.histogram_cutoff != defaults.histogram_cutoff),
+        ("--hide-histogram", args.hide_histogram),
+        ("--histogram-filter", args.histogram_filter != defaults.histogram_filter),
+        ("--first-action-stats", args.first_action_stats),
+        ("--tape-patterns", args.tape_patterns),
+        ("--list-winners", args.list_winners != defaults.list_winners),
+        ("--list-zero-ones", args.list_zero_ones),
+        ("--check-reference", args.check_reference),
+        ("--stage-stats", args.stage_stats),
+        ("--stage-chart", args.stage_chart),
+        ("--simulated-only", args.simulated_only),
+        ("--stop-at-score", args.stop_at_score.is_some()),
+        ("--stop-at-steps", args.stop_at_steps.is_some()),
+        ("--prune", args.prune),
+        ("--detect-lin-recurrence", args.detect_lin_recurrence),
+        ("--deciders", args.deciders != defaults.deciders),
+        ("--accelerated", args.accelerated),
+        ("--known", args.known.is_some()),
+        ("--baseline-score", args.baseline_score.is_some()),
+        ("--baseline-from", args.baseline_from.is_some()),
+        ("--on-panic", args.on_panic != defaults.on_panic),
+        ("--checkpoint", args.checkpoint.is_some()),
+        ("--checkpoint-interval", args.checkpoint_interval != defaults.checkpoint_interval),
+        ("--continuations", args.continuations.is_some()),
+        ("--raw-out", args.raw_out.is_some()),
+        ("--ignore-below-steps", args.ignore_below_steps != defaults.ignore_below_steps),
+        ("--undecided-out", args.undecided_out.is_some()),
+        ("--dump-out", args.dump_out.is_some()),
+        ("--examples-out", args.examples_out.is_some()),
+        ("--failed-out", args.failed_out.is_some()),
+        ("--record", args.record.is_some()),
+        ("--replay", args.replay.is_some()),
+        ("--summary-out", args.summary_out.is_some()),
+        ("--json-out", args.json_out.is_some()),
+        ("--escalate-steps", args.escalate_steps.is_some()),
+        ("--run-budget", args.run_budget.is_some()),
+        ("--cycle-budget", args.cycle_budget.is_some()),
+        ("--ctl-budget", args.ctl_budget.is_some()),
+        ("--progress-log", args.progress_log.is_some()),
+        ("--generator-ablation", args.generator_ablation),
+        ("--ablation-sample", args.ablation_sample != defaults.ablation_sample),
+        ("--simd", args.simd),
+        ("--simd-steps", args.simd_steps != defaults.simd_steps),
+    ];
+    #[cfg(feature = "tape-stats")]
+    ignored.push(("--cold-tape", args.cold_tape));
+    #[cfg(feature = "gpu")]
+    ignored.extend([
+        ("--gpu", args.gpu),
+        ("--gpu-steps", args.gpu_steps != defaults.gpu_steps),
+    ]);
+
+    if let Some((option, _)) = ignored.iter().find(|(_, used)| *used) {
+        return Err(anyhow!(
+            "'{}' is not supported with more than 2 symbols, only '--max-steps', \
+                '--halt-convention', '--score-def', '--num-threads' and \
+                '--standard-notation' are",
+            option,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Analyzes all TMs with `args.shared.n` states and `args.symbols` symbols
+/// and prints the counts per outcome and the champions.
+pub(super) fn run(args: &Args) -> Result<()> {
+    check_args(args)?;
+    let gen = MultiGenerator::new(args.shared.n, args.symbols).map_err(|e| anyhow!(e))?;
+
+    println!();
+    bunt::println!(
+        "{$blue+bold}▸ Analyzing {[intense]} TMs with {[intense]} states and {[intense]} \
+            symbols...{/$}",
+        gen.num_tms(),
+        args.shared.n,
+        args.symbols,
+    );
+    println!();
+
+    let before = Instant::now();
+    let report = sweep(&gen, args);
+    let elapsed = before.elapsed();
+
+    bunt::println!("{$blue+bold}▸ Results:{/$}");
+    println!();
+    let total = gen.num_tms();
+    let print_count = |label: &str, count: u64| {
+        println!(
+            "    {: <20}{: >16}  ({:.2}%)",
+            label,
+            count,
+            100.0 * count as f64 / total as f64,
+        );
+    };
+    print_count("no halt transition", report.no_halt_transition);
+    print_count("halted", report.halted);
+    print_count("cycle", report.cycle);
+    print_count("aborted", report.aborted);
+    println!();
+
+    let print_champion = |label: &str, champion: Option<(u64, MultiHalted)>| {
+        match champion {
+            Some((index, halted)) => {
+                let tm = gen.tm_at(index);
+                bunt::println!(
+                    "{}: {[green+bold]} steps, {[green+bold]} non-blank symbols, \
+                        symbol sum {[green+bold]}",
+                    label,
+                    halted.steps,
+                    halted.non_blank,
+                    halted.symbol_sum,
+                );
+                if args.standard_notation {
+                    println!("    {}", tm);
+                } else {
+                    print!("{}", tm.table());
+                }
+            }
+            None => println!("{}: none of the TMs halted", label),
+        }
+    };
+    let score_label = match args.score_def {
+        ScoreDef::NonZero => "Most non-blank symbols",
+        ScoreDef::Sum => "Highest symbol sum",
+    };
+    print_champion(score_label, report.score_champion);
+    println!();
+    print_champion("Most steps", report.steps_champion);
+    println!();
+
+    if report.aborted > 0 {
+        bunt::println!(
+            "{$yellow}Warning:{/$} {} TMs were aborted after {} steps, so the champions \
+                are only lower bounds.",
+            report.aborted,
+            args.shared.max_steps,
+        );
+        println!();
+    }
+    println!("Took {:.2?}", elapsed);
+
+    Ok(())
+}
+
+fn sweep(gen: &MultiGenerator, args: &Args) -> Report {
+    let num_threads = SweepBuilder::new(args.shared.n)
+        .num_threads(args.num_threads)
+        .effective_num_threads();
+    let (max_steps, halt_convention) = (args.shared.max_steps, args.shared.halt_convention);
+
+    let next_chunk = AtomicU64::new(0);
+    let report = Mutex::new(Report::new(args.score_def));
+    thread::scope(|s| {
+        for _ in 0..num_threads {
+            s.spawn(|| {
+                let mut local = Report::new(args.score_def);
+                loop {
+                    let start = next_chunk.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+                    if start >= gen.num_tms() {
+                        break;
+                    }
+                    for index in start..gen.num_tms().min(start + CHUNK_SIZE) {
+                        let tm = gen.tm_at(index);
+                        local.add(index, multi_symbol::analyze(&tm, max_steps, halt_convention));
+                    }
+                }
+                report.lock().expect("poisened lock").merge(local);
+            });
+        }
+    });
+
+    report.into_inner().expect("poisened lock")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_sweep(n: &str, symbols: &str, max_steps: &str, score_def: &str) -> Report {
+        let args = Args::from_iter(&[
+            "full", "-n", n, "--symbols", symbols, "--max-steps", max_steps, "-j", "3",
+            "--score-def", score_def,
+        ]);
+        let gen = MultiGenerator::new(args.shared.n, args.symbols).unwrap();
+        sweep(&gen, &args)
+    }
+
+    #[test]
+    fn binary_champions() {
+        let report = run_sweep("2", "2", "100", "nonzero");
+        let total = report.no_halt_transition + report.halted + report.cycle + report.aborted;
+        assert_eq!(total, 10u64.pow(4));
+
+        assert_eq!(report.steps_champion.unwrap().1.steps, 6);
+        assert_eq!(report.score_champion.unwrap().1.non_blank, 4);
+    }
+
+    // Takes a few minutes in debug mode.
+    #[test]
+    #[ignore]
+    fn two_states_three_symbols() {
+        // Known values: S(2,3) = 38 and Σ(2,3) = 9.
+        let report = run_sweep("2", "3", "100", "nonzero");
+        assert_eq!(report.steps_champion.unwrap().1.steps, 38);
+        assert_eq!(report.score_champion.unwrap().1.non_blank, 9);
+
+        // The champion above has a symbol sum of 17, but there is a TM with the
+        // same number of non-blank symbols and more 2s.
+        let champion = run_sweep("2", "3", "100", "sum").score_champion.unwrap().1;
+        assert_eq!((champion.non_blank, champion.symbol_sum), (9, 18));
+    }
+
+    #[test]
+    fn rejects_binary_only_options() {
+        let check = |extra: &[&str]| {
+            let args = ["full", "-n", "2", "--symbols", "3"].iter().chain(extra);
+            check_args(&Args::from_iter(args)).map_err(|e| e.to_string())
+        };
+        let rejected = |extra: &[&str], option: &str| {
+            let e = check(extra).expect_err(option);
+            assert!(e.starts_with(&format!("'{}' is not supported", option)), "{}", e);
+        };
+
+        assert!(check(&["--max-steps", "50", "--score-def", "sum", "-j", "2"]).is_ok());
+        assert!(check(&["--halt-convention", "exclude", "--standard-notation"]).is_ok());
+
+        // Options with a default are only rejected if they differ from it.
+        assert!(check(&["--generator", "optimized", "--list-winners", "5"]).is_ok());
+        rejected(&["--generator", "all"], "--generator");
+        rejected(&["--list-winners", "3"], "--list-winners");
+        rejected(&["--tie-break", "earliest"], "--tie-break");
+        rejected(&["--deciders", "ctl"], "--deciders");
+        rejected(&["--histogram-cutoff", "10"], "--histogram-cutoff");
+
+        for flag in [
+            "--prune",
+            "--stage-stats",
+            "--stage-chart",
+            "--tape-patterns",
+            "--first-action-stats",
+            "--check-reference",
+            "--hide-histogram",
+            "--list-zero-ones",
+        ] {
+            rejected(&[flag], flag);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Sampled progress history of a run, written as CSV file (see
+//! `--progress-log`).
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use anyhow::{Context, Result};
+
+use crate::{best::Best, manifest::Manifest, sweep::Progress};
+
+
+/// Minimum time between two samples.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ProgressLog {
+    writer: BufWriter<File>,
+    num_tms: u64,
+    start: Instant,
+    last_sample: Instant,
+    done_at_last_sample: u64,
+    done: u64,
+    best: Option<Best>,
+
+    /// The first error that occured while writing. Progress is reported from
+    /// a callback that cannot return errors, so we keep it for `finish`.
+    error: Option<io::Error>,
+}
+
+impl ProgressLog {
+    /// Creates the file and writes the manifest (as comments) and the CSV
+    /// header. `num_tms` is the total number of TMs of the run.
+    pub fn create(path: &Path, num_tms: u64, manifest: &Manifest) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        manifest.write_comments(&mut writer)?;
+        writeln!(
+            writer,
+            "unix_time,elapsed_secs,done,fraction_done,tms_per_sec,high_score,best_id",
+        )?;
+
+        let now = Instant::now();
+        Ok(Self {
+            writer,
+            num_tms,
+            start: now,
+            last_sample: now,
+            done_at_last_sample: 0,
+            done: 0,
+            best: None,
+            error: None,
+        })
+    }
+
+    /// Records the progress of one finished chunk and writes a sample if the
+    /// last one is at least `SAMPLE_INTERVAL` ago.
+    pub fn record(&mut self, progress: Progress) {
+        self.done += progress.num_tms;
+        self.best = progress.best;
+
+        if self.last_sample.elapsed() >= SAMPLE_INTERVAL {
+            self.sample();
+        }
+    }
+
+    /// Writes a final sample and flushes the file. Returns the first error
+    /// that occured while writing.
+    pub fn finish(&mut self) -> Result<()> {
+        self.sample();
+        if let Err(e) = self.writer.flush() {
+            self.error.get_or_insert(e);
+        }
+
+        match self.error.take() {
+            Some(e) => Err(e).context("failed to write progress log"),
+            None => Ok(()),
+        }
+    }
+
+    fn sample(&mut self) {
+        let now = Instant::now();
+        let since_last = now.duration_since(self.last_sample).as_secs_f64();
+        let rate = if since_last > 0.0 {
+            (self.done - self.done_at_last_sample) as f64 / since_last
+        } else {
+            0.0
+        };
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+
+        let res = writeln!(
+            self.writer,
+            "{:.3},{:.3},{},{:.6},{:.0},{},{}",
+            unix_time,
+            now.duration_since(self.start).as_secs_f64(),
+            self.done,
+            self.done as f64 / self.num_tms as f64,
+            rate,
+            self.best.map_or(String::new(), |b| b.score.to_string()),
+            self.best.map_or(String::new(), |b| b.id.to_string()),
+        );
+        if let Err(e) = res {
+            self.error.get_or_insert(e);
+        }
+
+        self.last_sample = now;
+        self.done_at_last_sample = self.done;
+    }
+}
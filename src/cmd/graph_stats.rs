@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use ahash::AHashMap;
+use anyhow::{Context, Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    SharedArgs,
+    cmd::check_n,
+    dispatch::{self, PerN},
+    gen::Generator,
+    graph::GraphStats,
+    manifest::Manifest,
+    sweep::SweepBuilder,
+    tm::Tm,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    #[structopt(flatten)]
+    shared: SharedArgs,
+
+    /// Set TM generator (see `full --help`).
+    #[structopt(short, long, default_value = "optimized")]
+    generator: Generator,
+
+    /// Only every n-th TM (in the generator's order) is included.
+    #[structopt(long, default_value = "1")]
+    sample_every: u64,
+
+    /// Number of threads to use. Defaults to the number of virtual CPUs.
+    #[structopt(long, short = "j")]
+    num_threads: Option<u32>,
+
+    /// The CSV file to write. It contains one row per combination of graph
+    /// statistics and analysis outcome with the number of TMs having it.
+    #[structopt(long, short, parse(from_os_str), default_value = "graph-stats.csv")]
+    out: PathBuf,
+}
+
+/// One row of the output: graph statistics of a TM and what the analysis
+/// decided for it.
+type Key = (GraphStats, &'static str);
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    check_n(args.shared.n)?;
+    dispatch::with_n(args.shared.n, args)
+        .expect("invalid value for n: argument parsing should catch this")
+}
+
+impl PerN for Args {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self)
+    }
+}
+
+fn do_run<const N: usize>(args: Args) -> Result<()> {
+    if args.sample_every == 0 {
+        return Err(anyhow!("'--sample-every' has to be at least 1"));
+    }
+
+    let manifest = Manifest::new(&[
+        ("n", N.to_string()),
+        ("generator", args.generator.to_string()),
+        ("sample-every", args.sample_every.to_string()),
+        ("max-steps", args.shared.max_steps.to_string()),
+        ("halt-convention", args.shared.halt_convention.to_string()),
+    ]);
+
+    let counts = Arc::new(Mutex::new(AHashMap::<Key, u64>::new()));
+    let sweep = {
+        let counts = counts.clone();
+        let sample_every = args.sample_every;
+        SweepBuilder::new(N as u8)
+            .generator(args.generator)
+            .max_steps(args.shared.max_steps)
+            .halt_convention(args.shared.halt_convention)
+            .num_threads(args.num_threads)
+            .on_result(move |index, id, result| {
+                if !index.is_multiple_of(sample_every) {
+                    return;
+                }
+
+                let tm = <Tm<N>>::from_id(id).expect("generator returned invalid TM");
+                let key = (GraphStats::of(tm), result.name());
+                *counts.lock().expect("poisened lock").entry(key).or_insert(0) += 1;
+            })
+    };
+
+    bunt::println!(
+        "{$blue+bold}▸ Collecting state graph statistics of {[intense]} TMs with \
+            {[intense]} states...{/$}",
+        sweep.num_tms().div_ceil(args.sample_every),
+        N,
+    );
+    sweep.run()?;
+
+    let mut rows = counts.lock().expect("poisened lock").drain().collect::<Vec<_>>();
+    rows.sort_by_key(|&((stats, outcome), _)| {
+        (stats.num_sccs, stats.num_reachable, stats.halt_distance, stats.halt_in_degree, outcome)
+    });
+
+    let path = &args.out;
+    let file = File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    let mut w = BufWriter::new(file);
+    manifest.write_comments(&mut w)?;
+    writeln!(w, "num_sccs,num_reachable,halt_distance,halt_in_degree,outcome,count")?;
+    for ((stats, outcome), count) in &rows {
+        let distance = stats.halt_distance.map(|d| d.to_string()).unwrap_or_default();
+        writeln!(
+            w,
+            "{},{},{},{},{},{}",
+            stats.num_sccs,
+            stats.num_reachable,
+            distance,
+            stats.halt_in_degree,
+            outcome,
+            count,
+        )?;
+    }
+    w.flush().with_context(|| format!("failed to write '{}'", path.display()))?;
+
+    println!("Wrote {} rows to '{}'", rows.len(), path.display());
+    println!();
+    manifest.print();
+
+    Ok(())
+}
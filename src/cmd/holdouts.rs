@@ -0,0 +1,650 @@
+//! Managing the TMs that are still undecided after a sweep ("holdouts") over
+//! many iterations of a BB(N) campaign: new holdouts are imported, the
+//! deciders are run on the open ones again (e.g. after improving them or with
+//! a larger step limit), and a report shows how the set shrank over time and
+//! which outcome decided each TM.
+//!
+//! All state lives in a directory. The file `holdouts` in it starts with the
+//! header `beaver-holdouts 1` and the line `n <n>`, followed by one line per
+//! iteration, `iteration <k> <unix time> <kind> <change> <remaining>
+//! <details>`, and one line per TM, `tm <id> <added> open` or `tm <id> <added>
+//! <decided> <outcome>` where `added` and `decided` are iterations. The file
+//! `remaining.txt` lists the IDs of all open TMs, one per line, e.g. for
+//! `sheet`. Both are rewritten after each command.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Mutex, atomic::{AtomicUsize, Ordering}},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use ahash::{AHashMap, AHashSet};
+use anyhow::{Context, Result, anyhow};
+use pbr::ProgressBar;
+use structopt::StructOpt;
+
+use crate::{
+    analyze::{Analyzer, HaltConvention, OptionalDeciders},
+    cmd::{check_n, parse_n, parse_tm, tm_from_id},
+    dispatch::{self, PerN},
+    outcome::{AnalysisResult, Confidence, FnSink},
+    tm,
+};
+
+
+const HEADER: &str = "beaver-holdouts 1";
+
+/// The number of TMs a worker takes from the list at once.
+const BATCH_SIZE: usize = 16;
+
+#[derive(StructOpt, Debug, Clone)]
+pub enum Args {
+    /// Adds the TMs listed in a file (e.g. written by `full --undecided-out`)
+    /// to the holdouts. The directory is created on the first import.
+    Import(ImportArgs),
+
+    /// Runs the deciders on all open holdouts again and records which ones
+    /// they decide.
+    Run(RunArgs),
+
+    /// Prints how the set of holdouts shrank over the iterations and which
+    /// outcomes decided them.
+    Report(ReportArgs),
+}
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct ImportArgs {
+    /// The directory of the holdouts.
+    #[structopt(parse(from_os_str))]
+    dir: PathBuf,
+
+    /// File with one TM per line (ID or standard notation, everything after
+    /// the first whitespace is ignored).
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+
+    /// Number of states of the TMs. Required for the first import, later it
+    /// has to match.
+    #[structopt(short, parse(try_from_str = parse_n))]
+    n: Option<u8>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct RunArgs {
+    /// The directory of the holdouts.
+    #[structopt(parse(from_os_str))]
+    dir: PathBuf,
+
+    /// Number of steps after which TMs are stopped.
+    #[structopt(long, default_value = "100000")]
+    max_steps: u64,
+
+    /// Whether the final transition into the halt state counts. See `full
+    /// --help`.
+    #[structopt(
+        long,
+        default_value = "include",
+        possible_values(&["include", "exclude"]),
+    )]
+    halt_convention: HaltConvention,
+
+    /// Checks TMs reaching the maximum number of steps for a Lin recurrence.
+    /// See `full --help`.
+    #[structopt(long)]
+    detect_lin_recurrence: bool,
+
+    /// Optional deciders that are applied to TMs reaching the maximum number
+    /// of steps. See `full --help`.
+    #[structopt(long, default_value = "none")]
+    deciders: OptionalDeciders,
+
+    /// Runs TMs with a long run with rule-based acceleration. See `full
+    /// --help`.
+    #[structopt(long)]
+    accelerated: bool,
+
+    /// Number of threads to use. Defaults to the number of virtual CPUs.
+    #[structopt(long, short = "j")]
+    num_threads: Option<u32>,
+
+    /// Don't show a progress bar.
+    #[structopt(long)]
+    no_pb: bool,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct ReportArgs {
+    /// The directory of the holdouts.
+    #[structopt(parse(from_os_str))]
+    dir: PathBuf,
+
+    /// Also list the open TMs (in standard notation) if there are at most
+    /// this many.
+    #[structopt(long, default_value = "20")]
+    list: usize,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    match args {
+        Args::Import(args) => import(args),
+        Args::Run(args) => run_deciders(args),
+        Args::Report(args) => report(args),
+    }
+}
+
+/// Whether an iteration added or decided TMs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Import,
+    Run,
+}
+
+impl FromStr for Kind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "import" => Ok(Self::Import),
+            "run" => Ok(Self::Run),
+            _ => Err(anyhow!("invalid kind of iteration '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Import => f.write_str("import"),
+            Self::Run => f.write_str("run"),
+        }
+    }
+}
+
+/// One command that changed the holdouts.
+#[derive(Debug, Clone)]
+struct Iteration {
+    unix_time: u64,
+    kind: Kind,
+
+    /// The number of TMs added or decided.
+    change: u64,
+
+    /// The number of open TMs afterwards.
+    remaining: u64,
+
+    /// The imported file or the settings of the run.
+    details: String,
+}
+
+#[derive(Debug, Clone)]
+struct Holdout {
+    id: u64,
+
+    /// The iteration (starting at 1) that imported the TM.
+    added: u32,
+
+    /// The iteration that decided the TM and its outcome.
+    decided: Option<(u32, String)>,
+}
+
+/// The content of the directory.
+struct Project {
+    dir: PathBuf,
+    n: u8,
+    iterations: Vec<Iteration>,
+    tms: Vec<Holdout>,
+}
+
+impl Project {
+    fn state_path(dir: &Path) -> PathBuf {
+        dir.join("holdouts")
+    }
+
+    /// Reads the project in `dir`, or returns `None` if there is none yet.
+    fn open(dir: &Path) -> Result<Option<Self>> {
+        let path = Self::state_path(dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse(dir, &content)
+            .map(Some)
+            .with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    /// Like `open`, but fails if there is no project.
+    fn open_existing(dir: &Path) -> Result<Self> {
+        Self::open(dir)?.ok_or_else(|| anyhow!(
+            "'{}' contains no holdouts, use 'holdouts import' first",
+            dir.display(),
+        ))
+    }
+
+    fn parse(dir: &Path, s: &str) -> Result<Self> {
+        let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("missing header '{}'", HEADER));
+        }
+        let n = lines.next()
+            .and_then(|l| l.strip_prefix("n "))
+            .ok_or_else(|| anyhow!("missing 'n'"))?
+            .parse()?;
+        check_n(n)?;
+
+        let mut project = Self { dir: dir.to_owned(), n, iterations: Vec::new(), tms: Vec::new() };
+        for line in lines {
+            let invalid = || anyhow!("invalid line '{}'", line);
+            let parts = line.splitn(7, ' ').collect::<Vec<_>>();
+            match parts[..] {
+                ["iteration", k, unix_time, kind, change, remaining, details] => {
+                    if k.parse::<usize>()? != project.iterations.len() + 1 {
+                        return Err(invalid());
+                    }
+                    project.iterations.push(Iteration {
+                        unix_time: unix_time.parse()?,
+                        kind: kind.parse()?,
+                        change: change.parse()?,
+                        remaining: remaining.parse()?,
+                        details: details.to_owned(),
+                    });
+                }
+                ["tm", id, added, "open"] => project.tms.push(Holdout {
+                    id: id.parse()?,
+                    added: added.parse()?,
+                    decided: None,
+                }),
+                ["tm", id, added, decided, outcome] => project.tms.push(Holdout {
+                    id: id.parse()?,
+                    added: added.parse()?,
+                    decided: Some((decided.parse()?, outcome.to_owned())),
+                }),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(project)
+    }
+
+    fn open_ids(&self) -> Vec<u64> {
+        self.tms.iter().filter(|h| h.decided.is_none()).map(|h| h.id).collect()
+    }
+
+    /// Adds an iteration. The number of remaining TMs is counted from `tms`.
+    fn push_iteration(&mut self, kind: Kind, change: u64, details: String) {
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let remaining = self.tms.iter().filter(|h| h.decided.is_none()).count() as u64;
+        self.iterations.push(Iteration { unix_time, kind, change, remaining, details });
+    }
+
+    /// Writes the state to a temporary file first, which then replaces the
+    /// old one (like checkpoints), and rewrites `remaining.txt`.
+    fn save(&self) -> Result<()> {
+        let path = Self::state_path(&self.dir);
+        let tmp_path = self.dir.join("holdouts.tmp");
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("failed to create '{}'", tmp_path.display()))?;
+        let mut w = BufWriter::new(file);
+        let write = |w: &mut BufWriter<File>| -> std::io::Result<()> {
+            writeln!(w, "{}", HEADER)?;
+            writeln!(w, "n {}", self.n)?;
+            for (i, it) in self.iterations.iter().enumerate() {
+                writeln!(
+                    w,
+                    "iteration {} {} {} {} {} {}",
+                    i + 1,
+                    it.unix_time,
+                    it.kind,
+                    it.change,
+                    it.remaining,
+                    it.details,
+                )?;
+            }
+            for h in &self.tms {
+                match &h.decided {
+                    None => writeln!(w, "tm {} {} open", h.id, h.added)?,
+                    Some((k, outcome)) => writeln!(w, "tm {} {} {} {}", h.id, h.added, k, outcome)?,
+                }
+            }
+            Ok(())
+        };
+        write(&mut w)
+            .and_then(|_| w.into_inner().map_err(|e| e.into_error()))
+            .and_then(|f| f.sync_all())
+            .with_context(|| format!("failed to write '{}'", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to replace '{}'", path.display()))?;
+
+        let remaining_path = self.dir.join("remaining.txt");
+        let mut w = BufWriter::new(
+            File::create(&remaining_path)
+                .with_context(|| format!("failed to create '{}'", remaining_path.display()))?,
+        );
+        self.open_ids().iter()
+            .try_for_each(|id| writeln!(w, "{}", id))
+            .and_then(|_| w.flush())
+            .with_context(|| format!("failed to write '{}'", remaining_path.display()))
+    }
+}
+
+fn import(args: ImportArgs) -> Result<()> {
+    let mut project = match Project::open(&args.dir)? {
+        Some(project) => {
+            if args.n.is_some_and(|n| n != project.n) {
+                return Err(anyhow!(
+                    "the holdouts in '{}' have {} states, not {}",
+                    args.dir.display(),
+                    project.n,
+                    args.n.unwrap(),
+                ));
+            }
+            project
+        }
+        None => {
+            let n = args.n.ok_or_else(|| {
+                anyhow!("'-n' is required to create the holdouts in '{}'", args.dir.display())
+            })?;
+            check_n(n)?;
+            fs::create_dir_all(&args.dir)
+                .with_context(|| format!("failed to create '{}'", args.dir.display()))?;
+            Project { dir: args.dir.clone(), n, iterations: Vec::new(), tms: Vec::new() }
+        }
+    };
+
+    let ids = dispatch::with_n(project.n, ReadIds { path: &args.file })
+        .expect("invalid n should be rejected when reading")?;
+    let iteration = project.iterations.len() as u32 + 1;
+    let mut known = project.tms.iter().map(|h| h.id).collect::<AHashSet<_>>();
+    let mut added = 0;
+    for &id in &ids {
+        if known.insert(id) {
+            project.tms.push(Holdout { id, added: iteration, decided: None });
+            added += 1;
+        }
+    }
+    project.push_iteration(Kind::Import, added, format!("from '{}'", args.file.display()));
+    project.save()?;
+
+    let duplicates = ids.len() as u64 - added;
+
+    bunt::println!(
+        "{$green+bold}Added {} of the {} TMs in '{}' ({} were already known).{/$}",
+        added,
+        ids.len(),
+        args.file.display(),
+        duplicates,
+    );
+    println!("{} TMs are open now.", project.open_ids().len());
+    Ok(())
+}
+
+/// Reads the TMs of `path` as IDs, in the order of the file.
+struct ReadIds<'a> {
+    path: &'a Path,
+}
+
+impl PerN for ReadIds<'_> {
+    type Output = Result<Vec<u64>>;
+
+    fn call<const N: usize>(self) -> Result<Vec<u64>>
+    where
+        [bool; N]: Default,
+    {
+        let path = self.path;
+        let file = File::open(path)
+            .with_context(|| format!("failed to open '{}'", path.display()))?;
+
+        let mut ids = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.with_context(|| format!("failed to read '{}'", path.display()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let field = line.split_whitespace().next().unwrap();
+            let tm = parse_tm::<N>(field)
+                .with_context(|| format!("line {} of '{}'", i + 1, path.display()))?;
+            ids.push(tm.encoded);
+        }
+        Ok(ids)
+    }
+}
+
+fn run_deciders(args: RunArgs) -> Result<()> {
+    let mut project = Project::open_existing(&args.dir)?;
+    let open = project.open_ids();
+    bunt::println!(
+        "{$blue+bold}▸ Running {[intense]} open holdouts with {} states up to {} steps...{/$}",
+        open.len(),
+        project.n,
+        args.max_steps,
+    );
+
+    let results = dispatch::with_n(project.n, Decide { args: &args, ids: &open })
+        .expect("invalid n should be rejected when reading")?;
+
+    let iteration = project.iterations.len() as u32 + 1;
+    let mut by_outcome = AHashMap::<&str, u64>::new();
+    for holdout in project.tms.iter_mut().filter(|h| h.decided.is_none()) {
+        let result = match results.get(&holdout.id) {
+            Some(result) if result.confidence() == Confidence::Proven => result,
+            _ => continue,
+        };
+        *by_outcome.entry(result.name()).or_default() += 1;
+        holdout.decided = Some((iteration, result.name().to_owned()));
+    }
+    let decided = by_outcome.values().sum();
+    let details = format!(
+        "max-steps={} halt-convention={} lin-recurrence={} deciders={} accelerated={}",
+        args.max_steps,
+        args.halt_convention,
+        args.detect_lin_recurrence,
+        args.deciders,
+        args.accelerated,
+    );
+    project.push_iteration(Kind::Run, decided, details);
+    project.save()?;
+
+    let remaining = open.len() as u64 - decided;
+
+    println!();
+    bunt::println!(
+        "{$green+bold}Decided {} of the {} open holdouts, {} remain.{/$}",
+        decided,
+        open.len(),
+        remaining,
+    );
+    let mut by_outcome = by_outcome.into_iter().collect::<Vec<_>>();
+    by_outcome.sort_by_key(|&(name, count)| (std::cmp::Reverse(count), name));
+    for (name, count) in by_outcome {
+        println!("  {:<20} {}", name, count);
+    }
+    Ok(())
+}
+
+/// Analyzes the TMs `ids` with the settings of `args` on several threads.
+struct Decide<'a> {
+    args: &'a RunArgs,
+    ids: &'a [u64],
+}
+
+impl PerN for Decide<'_> {
+    type Output = Result<AHashMap<u64, AnalysisResult>>;
+
+    fn call<const N: usize>(self) -> Self::Output
+    where
+        [bool; N]: Default,
+    {
+        let (args, ids) = (self.args, self.ids);
+        let tms = ids.iter().map(|&id| tm_from_id::<N>(id)).collect::<Result<Vec<_>>>()?;
+        let next = AtomicUsize::new(0);
+        let num_threads = args.num_threads.unwrap_or_else(|| num_cpus::get() as u32);
+        let mut pb = ProgressBar::new(tms.len() as u64);
+        pb.set_max_refresh_rate(Some(Duration::from_millis(10)));
+        let pb = Mutex::new(pb);
+
+        let results = thread::scope(|scope| {
+            let handles = (0..num_threads).map(|_| scope.spawn(|| {
+                let mut analyzer = Analyzer::builder()
+                    .max_steps(args.max_steps)
+                    .halt_convention(args.halt_convention)
+                    .lin_recurrence(args.detect_lin_recurrence)
+                    .optional_deciders(args.deciders)
+                    .accelerated(args.accelerated)
+                    .build();
+                let mut results = Vec::new();
+                loop {
+                    let start = next.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+                    if start >= tms.len() {
+                        break;
+                    }
+
+                    let batch = &tms[start..tms.len().min(start + BATCH_SIZE)];
+                    for &tm in batch {
+                        let mut sink = FnSink(|tm: tm::Tm<N>, result| {
+                            results.push((tm.encoded, result));
+                        });
+                        analyzer.analyze(tm, &mut sink);
+                    }
+                    if !args.no_pb {
+                        pb.lock().expect("poisened lock").add(batch.len() as u64);
+                    }
+                }
+                results
+            })).collect::<Vec<_>>();
+
+            handles.into_iter()
+                .flat_map(|h| h.join().expect("panic in worker thread"))
+                .collect()
+        });
+        if !args.no_pb {
+            pb.into_inner().expect("poisened lock").finish();
+            println!();
+        }
+
+        Ok(results)
+    }
+}
+
+fn report(args: ReportArgs) -> Result<()> {
+    let project = Project::open_existing(&args.dir)?;
+    let open = project.open_ids();
+
+    bunt::println!(
+        "{$blue+bold}▸ Holdouts with {} states in '{}':{/$}",
+        project.n,
+        args.dir.display(),
+    );
+    println!();
+    println!(
+        "  {:>3}  {:<10}  {:<6}  {:>10}  {:>10}  details",
+        "#",
+        "date",
+        "kind",
+        "change",
+        "remaining",
+    );
+    for (i, it) in project.iterations.iter().enumerate() {
+        let sign = match it.kind {
+            Kind::Import => '+',
+            Kind::Run => '-',
+        };
+        println!(
+            "  {:>3}  {:<10}  {:<6}  {:>10}  {:>10}  {}",
+            i + 1,
+            format_date(it.unix_time),
+            it.kind.to_string(),
+            format!("{}{}", sign, it.change),
+            it.remaining,
+            it.details,
+        );
+    }
+
+    // Which outcome decided how many TMs, over all iterations.
+    let mut by_outcome = AHashMap::<&str, u64>::new();
+    for (_, outcome) in project.tms.iter().filter_map(|h| h.decided.as_ref()) {
+        *by_outcome.entry(outcome).or_default() += 1;
+    }
+    if !by_outcome.is_empty() {
+        println!();
+        bunt::println!(
+            "{$blue+bold}▸ Decided {} of {} TMs:{/$}",
+            project.tms.len() - open.len(),
+            project.tms.len(),
+        );
+        let mut by_outcome = by_outcome.into_iter().collect::<Vec<_>>();
+        by_outcome.sort_by_key(|&(name, count)| (std::cmp::Reverse(count), name));
+        for (name, count) in by_outcome {
+            println!("  {:<20} {}", name, count);
+        }
+    }
+
+    println!();
+    let remaining_path = args.dir.join("remaining.txt");
+    bunt::println!(
+        "{$blue+bold}▸ {} TMs are open{/$} (listed in '{}')",
+        open.len(),
+        remaining_path.display(),
+    );
+    if !open.is_empty() && open.len() <= args.list {
+        for id in open {
+            println!("  {:<12} {}", id, tm::standard_notation(id, project.n));
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats a unix time as UTC date `YYYY-MM-DD`.
+fn format_date(unix_time: u64) -> String {
+    // Converts days since 1970-01-01 to a date in the proleptic Gregorian
+    // calendar, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (unix_time / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::{Project, format_date};
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_792_108_800), "2026-10-16");
+    }
+
+    #[test]
+    fn parse_state() {
+        let state = "beaver-holdouts 1\n\
+            n 2\n\
+            iteration 1 100 import 2 2 from 'x.txt'\n\
+            iteration 2 200 run 1 1 max-steps=100\n\
+            tm 3 1 open\n\
+            tm 5 1 2 cycle\n";
+        let project = Project::parse(Path::new("."), state).unwrap();
+        assert_eq!(project.open_ids(), [3]);
+        assert_eq!(project.iterations[1].details, "max-steps=100");
+        assert_eq!(project.tms[1].decided, Some((2, "cycle".to_owned())));
+
+        let skipped = state.replace("iteration 2", "iteration 3");
+        assert!(Project::parse(Path::new("."), &skipped).is_err());
+    }
+}
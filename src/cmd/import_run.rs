@@ -0,0 +1,41 @@
+//! Unpacks a bundle written by `export-run`, so that the sweep can be
+//! continued with `resume`.
+
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use structopt::StructOpt;
+
+use crate::{bundle, checkpoint::Checkpoint};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The bundle file written by `export-run`.
+    #[structopt(parse(from_os_str))]
+    bundle: PathBuf,
+
+    /// The directory to extract the files into.
+    #[structopt(long, default_value = ".", parse(from_os_str))]
+    dir: PathBuf,
+
+    /// Overwrites existing files. Without this, nothing is extracted if one
+    /// of the files already exists.
+    #[structopt(long)]
+    force: bool,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let files = bundle::import(&args.bundle, &args.dir, args.force)?;
+    for file in &files {
+        bunt::println!("Extracted {[green+bold]}", file.display());
+    }
+
+    // The checkpoint might have been written by a different version of
+    // beaver, so we check that it can be resumed here.
+    let checkpoint = &files[0];
+    Checkpoint::read(checkpoint)
+        .context("the checkpoint was extracted, but can't be resumed")?;
+    println!();
+    println!("Continue the sweep with `resume {}`.", checkpoint.display());
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! Explores the stored results of a sweep (see `full --raw-out`) long after
+//! it finished: counts per outcome, or statistics and random examples of one
+//! outcome.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+use anyhow::{Context, Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    outcome::OUTCOME_NAMES,
+    output::SplitMix64,
+    tm,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The CSV file written by `full --raw-out`.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+
+    /// The outcome to look at. Without it, only the number of TMs per outcome
+    /// is printed.
+    #[structopt(long, possible_values(&OUTCOME_NAMES))]
+    outcome: Option<String>,
+
+    /// Number of example TMs of `--outcome` to print. They are drawn
+    /// uniformly at random from all TMs with that outcome in the file.
+    #[structopt(long, default_value = "10")]
+    sample: usize,
+
+    /// Seed for drawing the examples. The same seed gives the same examples.
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+
+    /// Number of states of the TMs. Only needed if the file has no manifest
+    /// saying so.
+    #[structopt(short, possible_values(&["1", "2", "3", "4", "5", "6"]))]
+    n: Option<u8>,
+}
+
+/// One row of the file, as far as it is needed here.
+struct Row {
+    index: Option<u64>,
+    id: u64,
+    steps: Option<u64>,
+    ones: Option<u64>,
+}
+
+/// Minimum, maximum and sum of the values of one column.
+#[derive(Default)]
+struct Stats {
+    count: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl Stats {
+    fn add(&mut self, v: u64) {
+        if self.count == 0 || v < self.min {
+            self.min = v;
+        }
+        self.max = self.max.max(v);
+        self.sum += v as u128;
+        self.count += 1;
+    }
+
+    fn print(&self, name: &str) {
+        if self.count == 0 {
+            return;
+        }
+        bunt::println!(
+            "- {}: min {[intense]}, mean {[intense]}, max {[intense]}",
+            name,
+            self.min,
+            format!("{:.1}", self.sum as f64 / self.count as f64),
+            self.max,
+        );
+    }
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let file = File::open(&args.file)
+        .with_context(|| format!("failed to open '{}'", args.file.display()))?;
+    let path = args.file.display();
+
+    let mut n = args.n;
+    let mut columns = None;
+    let mut counts = vec![0u64; OUTCOME_NAMES.len()];
+    let mut steps = Stats::default();
+    let mut ones = Stats::default();
+    let mut examples = Vec::new();
+    let mut rng = SplitMix64(args.seed);
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read '{}'", path))?;
+        let err = |msg: &str| anyhow!("line {} of '{}': {}", i + 1, path, msg);
+
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(value) = comment.trim().strip_prefix("n:") {
+                n = n.or(value.trim().parse().ok());
+            }
+            continue;
+        }
+        let columns = match &columns {
+            Some(columns) => columns,
+            None => {
+                columns = Some(Columns::from_header(&line).map_err(|msg| err(&msg))?);
+                continue;
+            }
+        };
+
+        let fields = line.split(',').collect::<Vec<_>>();
+        if fields.len() != columns.len {
+            return Err(err("wrong number of columns"));
+        }
+        let outcome = OUTCOME_NAMES.iter()
+            .position(|&name| name == fields[columns.outcome])
+            .ok_or_else(|| err(&format!("unknown outcome '{}'", fields[columns.outcome])))?;
+        counts[outcome] += 1;
+        if args.outcome.as_deref() != Some(OUTCOME_NAMES[outcome]) {
+            continue;
+        }
+
+        let number = |column: Option<usize>| -> Result<Option<u64>> {
+            match column.map(|c| fields[c]) {
+                None | Some("") => Ok(None),
+                Some(s) => {
+                    s.parse().map(Some).map_err(|_| err(&format!("invalid number '{}'", s)))
+                }
+            }
+        };
+        let row = Row {
+            index: number(columns.index)?,
+            id: number(Some(columns.id))?.ok_or_else(|| err("missing TM ID"))?,
+            steps: number(columns.steps)?,
+            ones: number(columns.ones)?,
+        };
+        if let Some(v) = row.steps {
+            steps.add(v);
+        }
+        if let Some(v) = row.ones {
+            ones.add(v);
+        }
+
+        // Algorithm R, like `full --examples`.
+        let seen = counts[outcome];
+        if examples.len() < args.sample {
+            examples.push(row);
+        } else {
+            let slot = rng.below(seen);
+            if slot < args.sample as u64 {
+                examples[slot as usize] = row;
+            }
+        }
+    }
+
+    if columns.is_none() {
+        return Err(anyhow!("'{}' contains no CSV header", path));
+    }
+    let total = counts.iter().sum::<u64>();
+    let percent = |v: u64| format!("{:.2}%", 100.0 * v as f64 / total.max(1) as f64);
+
+    let outcome = match &args.outcome {
+        Some(outcome) => outcome,
+        None => {
+            bunt::println!(
+                "{$blue+bold}▸ Outcomes of the {[intense]} TMs in '{}':{/$}",
+                total,
+                path,
+            );
+            for (name, &count) in OUTCOME_NAMES.iter().zip(&counts) {
+                if count > 0 {
+                    bunt::println!("- {: <20}{[intense]: >12} ({})", name, count, percent(count));
+                }
+            }
+            return Ok(());
+        }
+    };
+
+    let n = n.ok_or_else(|| {
+        anyhow!("'{}' does not say the number of states, please pass '-n'", path)
+    })?;
+    let position = OUTCOME_NAMES.iter().position(|name| name == outcome);
+    let count = counts[position.expect("argument parsing should catch this")];
+    bunt::println!(
+        "{$blue+bold}▸ {[intense]} of {} TMs ({}) in '{}' have the outcome '{}'{/$}",
+        count,
+        total,
+        percent(count),
+        path,
+        outcome,
+    );
+    steps.print("steps");
+    ones.print("ones");
+    println!();
+
+    if examples.is_empty() {
+        return Ok(());
+    }
+    examples.sort_by_key(|row| (row.index, row.id));
+    bunt::println!("{$blue+bold}▸ {} random examples:{/$}", examples.len());
+    println!(
+        "    {: >12} {: >20} {: >12} {: >8}   standard notation",
+        "index", "id", "steps", "ones",
+    );
+    let or_dash = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_else(|| "-".into());
+    for row in &examples {
+        println!(
+            "    {: >12} {: >20} {: >12} {: >8}   {}",
+            or_dash(row.index),
+            row.id,
+            or_dash(row.steps),
+            or_dash(row.ones),
+            tm::standard_notation(row.id, n),
+        );
+    }
+
+    Ok(())
+}
+
+/// The positions of the used columns, found by name, so that files written
+/// by older versions (e.g. without `confidence`) can be read as well.
+struct Columns {
+    len: usize,
+    index: Option<usize>,
+    id: usize,
+    outcome: usize,
+    steps: Option<usize>,
+    ones: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &str) -> Result<Self, String> {
+        let names = header.split(',').collect::<Vec<_>>();
+        let find = |name: &str| names.iter().position(|&n| n == name);
+        let require = |name: &str| find(name).ok_or_else(|| format!("no column '{}'", name));
+        Ok(Self {
+            len: names.len(),
+            index: find("index"),
+            id: require("id")?,
+            outcome: require("outcome")?,
+            steps: find("steps"),
+            ones: find("ones"),
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Combines the summaries of several runs over different ranges of TMs (see
+//! `range` and `full --summary-out`) and prints the report of all of them.
+
+use std::path::PathBuf;
+use anyhow::{Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    manifest::Manifest,
+    summary::{HistogramCutoff, HistogramFilter, ReportOptions},
+    summary_file::SummaryFile,
+    sweep::SweepBuilder,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The summary files to combine. They must have been written with the
+    /// same settings and their ranges must not overlap.
+    #[structopt(parse(from_os_str), required = true)]
+    files: Vec<PathBuf>,
+
+    /// If specified, the combined high score, most steps and counts of halted
+    /// TMs are compared with the reference values (see `full
+    /// --check-reference`).
+    #[structopt(long)]
+    check_reference: bool,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let mut files = Vec::new();
+    for path in &args.files {
+        files.push((path, SummaryFile::read(path)?));
+    }
+
+    let settings = files[0].1.settings.clone();
+    for (path, file) in &files[1..] {
+        if file.settings != settings {
+            return Err(anyhow!(
+                "the settings of '{}' ({:?}) don't match the ones of '{}' ({:?})",
+                path.display(),
+                file.settings,
+                files[0].0.display(),
+                settings,
+            ));
+        }
+    }
+
+    files.sort_by_key(|(_, file)| file.range.start);
+    for pair in files.windows(2) {
+        let ((path_a, a), (path_b, b)) = (&pair[0], &pair[1]);
+        if a.range.end > b.range.start {
+            return Err(anyhow!(
+                "the ranges of '{}' ({}..{}) and '{}' ({}..{}) overlap",
+                path_a.display(),
+                a.range.start,
+                a.range.end,
+                path_b.display(),
+                b.range.start,
+                b.range.end,
+            ));
+        }
+    }
+
+    // Find the indices that are not covered by any file.
+    let num_tms = SweepBuilder::new(settings.n)
+        .generator(settings.generator)
+        .fixed(settings.fixed.clone())
+        .num_generated_tms();
+    let mut missing = Vec::new();
+    let mut next = 0;
+    for (_, file) in &files {
+        if file.range.start > next {
+            missing.push(next..file.range.start);
+        }
+        next = file.range.end;
+    }
+    if next < num_tms {
+        missing.push(next..num_tms);
+    }
+    let incomplete = files.iter()
+        .filter(|(_, file)| !file.complete)
+        .map(|(path, _)| path.display().to_string())
+        .collect::<Vec<_>>();
+
+    println!();
+    bunt::println!(
+        "{$blue+bold}▸ Merging {[intense]} summaries of TMs with {[intense]} states...{/$}",
+        files.len(),
+        settings.n,
+    );
+    for (path, file) in &files {
+        println!("    {}..{}  {}", file.range.start, file.range.end, path.display());
+    }
+    if !missing.is_empty() {
+        let missing = missing.iter()
+            .map(|r| format!("{}..{}", r.start, r.end))
+            .collect::<Vec<_>>();
+        bunt::println!(
+            "{$yellow}Note:{/$} the following ranges of the {} TMs are not covered: {}",
+            num_tms,
+            missing.join(", "),
+        );
+    }
+    if !incomplete.is_empty() {
+        bunt::println!(
+            "{$yellow}Note:{/$} the following summaries do not include all TMs of their \
+                range: {}",
+            incomplete.join(", "),
+        );
+    }
+    println!();
+
+    let complete = missing.is_empty() && incomplete.is_empty();
+    let mut files = files.into_iter().map(|(_, file)| file);
+    let mut summary = files.next().expect("at least one file is required").summary;
+    for file in files {
+        summary.add(file.summary);
+    }
+
+    summary.print_report(&ReportOptions {
+        max_steps: settings.max_steps,
+        run_budget: settings.run_budget,
+        n: settings.n,
+        standard_notation: false,
+        listed_champions: settings.max_champions,
+        histogram_height: 15,
+        histogram_cutoff: HistogramCutoff::Auto,
+        hide_histogram: false,
+        histogram_filter: HistogramFilter::Halted,
+        first_action_stats: false,
+        tape_patterns: false,
+        stage_stats: false,
+        stage_chart: false,
+        simulated_only: false,
+    });
+    if summary.warn_max_steps(settings.max_steps) {
+        println!(
+            "  Consider new runs with a larger limit like '--max-steps {}'.",
+            settings.max_steps.saturating_mul(10),
+        );
+        println!();
+    }
+
+    if args.check_reference {
+        let complete = complete
+            && !settings.prune
+            && settings.run_budget.is_none()
+            && settings.fixed.is_none();
+        summary.print_reference_comparison(
+            settings.n,
+            settings.generator,
+            settings.max_steps,
+            settings.halt_convention,
+            complete,
+        );
+        println!();
+    }
+
+    let manifest = Manifest::new(&[
+        ("n", settings.n.to_string()),
+        ("generator", settings.generator.to_string()),
+        ("fixed", settings.fixed.as_ref().map_or("none".into(), |t| t.to_string())),
+        ("max-steps", settings.max_steps.to_string()),
+        ("halt-convention", settings.halt_convention.to_string()),
+        ("files", args.files.len().to_string()),
+    ]);
+    manifest.print();
+    println!();
+
+    Ok(())
+}
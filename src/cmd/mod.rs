@@ -1,4 +1,159 @@
 //! The entry points for different subcommand of this application.
 
+pub(crate) mod complete;
+pub(crate) mod continuation;
+pub(crate) mod diff;
+pub(crate) mod dump;
+pub(crate) mod export_run;
 pub(crate) mod full;
+pub(crate) mod graph_stats;
+pub(crate) mod holdouts;
+pub(crate) mod import_run;
+pub(crate) mod inspect;
+pub(crate) mod merge;
+pub(crate) mod progress;
+pub(crate) mod range;
+pub(crate) mod replay;
+pub(crate) mod replay_failed;
+pub(crate) mod resume;
+pub(crate) mod sheet;
 pub(crate) mod single;
+pub(crate) mod verify;
+
+
+use std::ops::Range;
+use anyhow::{Result, anyhow};
+
+use crate::{
+    dispatch,
+    multi_symbol,
+    tm::{self, Tm},
+};
+
+
+/// Parses a TM ID given in decimal, hex (`0x...`) or binary (`0b...`).
+/// Underscores can be used as separators, e.g. to group the 5 bits of each
+/// action: `0b01010_00110`.
+pub(crate) fn parse_id(s: &str) -> Result<u64, String> {
+    let s = s.replace('_', "");
+    let (digits, radix) = if let Some(hex) = s.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        (bin, 2)
+    } else {
+        (&*s, 10)
+    };
+
+    u64::from_str_radix(digits, radix).map_err(|e| format!("invalid TM ID '{}': {}", s, e))
+}
+
+/// Parses the number of states given with `-n`. Everything beyond
+/// `dispatch::SUPPORTED` can only be used by `single` (see `check_n`).
+pub(crate) fn parse_n(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(n) if (1..=multi_symbol::MAX_STATES).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "invalid number of states '{}' (1 to {})",
+            s,
+            multi_symbol::MAX_STATES,
+        )),
+    }
+}
+
+/// Returns an error if TMs with `n` states don't fit into `Tm`, which all
+/// commands except `single` need.
+pub(crate) fn check_n(n: u8) -> Result<()> {
+    if !dispatch::SUPPORTED.contains(&n) {
+        return Err(anyhow!(
+            "TMs with {} states are not supported by this command (only 'single' supports \
+                more than {} states)",
+            n,
+            dispatch::SUPPORTED.end(),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a range of cells like `-50..50` (end exclusive) or `-50..=50` (end
+/// inclusive), as in Rust.
+pub(crate) fn parse_cell_range(s: &str) -> Result<Range<i64>, String> {
+    let err = || format!("invalid cell range '{}' (expected e.g. '-50..50' or '-50..=50')", s);
+    let (start, end) = s.split_once("..").ok_or_else(err)?;
+    let (end, inclusive) = match end.strip_prefix('=') {
+        Some(end) => (end, true),
+        None => (end, false),
+    };
+
+    let start = start.trim().parse::<i64>().map_err(|_| err())?;
+    let end = end.trim().parse::<i64>().map_err(|_| err())?;
+    let end = if inclusive { end + 1 } else { end };
+    if start >= end {
+        return Err(format!("cell range '{}' is empty", s));
+    }
+
+    Ok(start..end)
+}
+
+/// Returns the TM with the given ID or an error explaining why the ID is
+/// invalid for `N` states, with suggestions for valid alternatives.
+pub(crate) fn tm_from_id<const N: usize>(id: u64) -> Result<Tm<N>> {
+    if let Some(tm) = <Tm<N>>::from_id(id) {
+        return Ok(tm);
+    }
+
+    let reason = tm::check_id(id, N).expect_err("`from_id` failed for a valid ID");
+
+    let mut msg = format!("Turing machine ID {} is not valid for N = {}: {}", id, N, reason);
+    msg += &format!(
+        "\n  hint: the nearest valid ID is {} (with unused bits cleared and invalid \
+            transitions going to the halt state instead)",
+        tm::nearest_valid_id(id, N),
+    );
+    if let Some(n) = tm::smallest_valid_n(id) {
+        msg += &format!(
+            "\n  hint: the ID is valid for N = {} (but note that state #{} is then a \
+                regular state instead of the halt state)",
+            n,
+            N,
+        );
+    }
+
+    Err(anyhow!(msg))
+}
+
+/// Parses a TM given by its ID (see `parse_id`) or in standard notation.
+pub(crate) fn parse_tm<const N: usize>(s: &str) -> Result<Tm<N>> {
+    if s.contains(['L', 'R', '-']) {
+        Tm::from_standard(s).map_err(|e| anyhow!(e))
+    } else {
+        tm_from_id(parse_id(s).map_err(|e| anyhow!(e))?)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cell_range, parse_id};
+
+    #[test]
+    fn parse_ids() {
+        assert_eq!(parse_id("327814"), Ok(327814));
+        assert_eq!(parse_id("0x50086"), Ok(327814));
+        assert_eq!(parse_id("0b01010_00000_00100_00110"), Ok(327814));
+        assert_eq!(parse_id("1_000"), Ok(1000));
+        assert!(parse_id("").is_err());
+        assert!(parse_id("0x").is_err());
+        assert!(parse_id("0b102").is_err());
+        assert!(parse_id("12ab").is_err());
+    }
+
+    #[test]
+    fn parse_cell_ranges() {
+        assert_eq!(parse_cell_range("-50..50"), Ok(-50..50));
+        assert_eq!(parse_cell_range("-50..=50"), Ok(-50..51));
+        assert_eq!(parse_cell_range("3..4"), Ok(3..4));
+        assert!(parse_cell_range("4..4").is_err());
+        assert!(parse_cell_range("-50").is_err());
+        assert!(parse_cell_range("a..b").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! The live progress display of sweeps (`full`, `resume`): a progress bar
+//! with throughput, estimated time remaining and the best TM so far, plus a
+//! line with how the TMs were decided and how fast each worker thread is.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{best::Best, sweep::Progress};
+
+
+/// Minimum time between two redraws.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The current throughput (and thus the ETA) is measured over this time, so
+/// that it follows changes in speed, e.g. towards the end of a sweep.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Width of the bar in characters.
+const BAR_WIDTH: usize = 30;
+
+pub struct ProgressDisplay {
+    num_tms: u64,
+    done: u64,
+    simulated: u64,
+    best: Option<Best>,
+
+    /// The number of TMs done by each worker thread.
+    per_worker: Vec<u64>,
+
+    start: Instant,
+    last_draw: Option<Instant>,
+
+    /// `(time, done)` samples of the last `RATE_WINDOW`.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ProgressDisplay {
+    /// `num_tms` is the number of TMs analyzed in this run.
+    pub fn new(num_tms: u64, num_threads: u32) -> Self {
+        let now = Instant::now();
+        Self {
+            num_tms,
+            done: 0,
+            simulated: 0,
+            best: None,
+            per_worker: vec![0; num_threads as usize],
+            start: now,
+            last_draw: None,
+            samples: VecDeque::from([(now, 0)]),
+        }
+    }
+
+    /// Records a finished job and redraws if the last redraw is at least
+    /// `REFRESH_INTERVAL` ago.
+    pub fn record(&mut self, progress: Progress) {
+        self.done += progress.num_tms;
+        self.simulated += progress.num_simulated;
+        if let Some(best) = progress.best {
+            self.record_best(best);
+        }
+        if let Some(done) = self.per_worker.get_mut(progress.worker as usize) {
+            *done += progress.num_tms;
+        }
+
+        let now = Instant::now();
+        if self.last_draw.is_none_or(|last| now - last >= REFRESH_INTERVAL) {
+            self.samples.push_back((now, self.done));
+            while self.samples.len() > 2 && now - self.samples[1].0 >= RATE_WINDOW {
+                self.samples.pop_front();
+            }
+            self.draw(now);
+        }
+    }
+
+    /// Records a new best TM without redrawing. Reports of worse TMs, which
+    /// can arrive late from other threads, are ignored.
+    pub fn record_best(&mut self, best: Best) {
+        if self.best.is_none_or(|b| best.score > b.score) {
+            self.best = Some(best);
+        }
+    }
+
+    /// Removes the display, calls `f` to print something and draws the
+    /// display again below it.
+    pub fn interrupt(&mut self, f: impl FnOnce()) {
+        if self.last_draw.is_some() {
+            print!("\x1b[1A\r\x1b[J");
+            self.last_draw = None;
+            f();
+            self.draw(Instant::now());
+        } else {
+            f();
+        }
+    }
+
+    /// Draws the final state. The average throughput of the whole run is
+    /// shown instead of the current one.
+    pub fn finish(&mut self) {
+        self.samples.truncate(1);
+        self.samples.push_back((Instant::now(), self.done));
+        self.draw(Instant::now());
+        println!();
+    }
+
+    fn draw(&mut self, now: Instant) {
+        let fraction = match self.num_tms {
+            0 => 1.0,
+            num_tms => self.done as f64 / num_tms as f64,
+        };
+        let filled = ((fraction * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+
+        let rate = {
+            let (first, last) = (self.samples[0], self.samples[self.samples.len() - 1]);
+            let secs = (last.0 - first.0).as_secs_f64();
+            if secs > 0.0 { (last.1 - first.1) as f64 / secs } else { 0.0 }
+        };
+        let eta = if self.done >= self.num_tms {
+            "done".to_owned()
+        } else if rate > 0.0 {
+            format_duration(Duration::from_secs_f64((self.num_tms - self.done) as f64 / rate))
+        } else {
+            "?".to_owned()
+        };
+        let best = match self.best {
+            Some(best) => format!("best: {} ones (TM {})", best.score, best.id),
+            None => "best: -".to_owned(),
+        };
+
+        // Per-thread throughput over the whole run, to spot imbalances.
+        let elapsed = (now - self.start).as_secs_f64().max(f64::MIN_POSITIVE);
+        let min = self.per_worker.iter().min().copied().unwrap_or(0);
+        let max = self.per_worker.iter().max().copied().unwrap_or(0);
+        let statically = self.done - self.simulated;
+
+        // The first draw only prints, later ones replace the two lines.
+        if self.last_draw.is_some() {
+            print!("\x1b[1A\r");
+        }
+        println!(
+            "\x1b[K{} {:>6.2}%  {}/{}  {}/s  ETA {}  {}",
+            bar,
+            100.0 * fraction,
+            format_count(self.done),
+            format_count(self.num_tms),
+            format_count(rate as u64),
+            eta,
+            best,
+        );
+        print!(
+            "\x1b[K  decided statically: {} ({:.1}%)  simulated: {}  \
+                {} threads: {}/s to {}/s each",
+            format_count(statically),
+            100.0 * statically as f64 / self.done.max(1) as f64,
+            format_count(self.simulated),
+            self.per_worker.len(),
+            format_count((min as f64 / elapsed) as u64),
+            format_count((max as f64 / elapsed) as u64),
+        );
+        let _ = io::stdout().flush();
+        self.last_draw = Some(now);
+    }
+}
+
+/// Formats large numbers with a metric suffix, e.g. `2.41M`.
+fn format_count(v: u64) -> String {
+    match v {
+        0..=9_999 => v.to_string(),
+        10_000..=999_999 => format!("{:.1}k", v as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.2}M", v as f64 / 1e6),
+        _ => format!("{:.2}G", v as f64 / 1e9),
+    }
+}
+
+/// Formats a duration as `1h02m03s`, leaving out leading zero units.
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match (h, m) {
+        (0, 0) => format!("{}s", s),
+        (0, _) => format!("{}m{:02}s", m, s),
+        _ => format!("{}h{:02}m{:02}s", h, m, s),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::{format_count, format_duration};
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_count(9_999), "9999");
+        assert_eq!(format_count(50_000), "50.0k");
+        assert_eq!(format_count(2_413_404), "2.41M");
+        assert_eq!(format_duration(Duration::from_secs(7)), "7s");
+        assert_eq!(format_duration(Duration::from_secs(62)), "1m02s");
+        assert_eq!(format_duration(Duration::from_secs(3 * 3600 + 5)), "3h00m05s");
+    }
+}
@@ -0,0 +1,32 @@
+//! Runs `full` on a sub-range of the TMs, so that a large run can be split
+//! over several machines by hand.
+
+use anyhow::{Result, anyhow};
+use structopt::StructOpt;
+
+use super::full;
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The index (in the generator's order, not the ID!) of the first TM to
+    /// analyze.
+    #[structopt(long)]
+    from: u64,
+
+    /// The index of the first TM that is not analyzed anymore. Splitting a
+    /// run into `0..a`, `a..b`, `b..<all>` covers each TM exactly once.
+    #[structopt(long)]
+    to: u64,
+
+    #[structopt(flatten)]
+    full: full::Args,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    if args.from > args.to {
+        return Err(anyhow!("'--from' ({}) is larger than '--to' ({})", args.from, args.to));
+    }
+
+    full::run_range(args.full, args.from..args.to)
+}
@@ -0,0 +1,303 @@
+//! Runs one TM of a sweep again with the settings recorded in a raw-out file
+//! (see `full --raw-out`) and checks that it gets the stored outcome. If not,
+//! the simulation is traced, e.g. to debug a decider or an output.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+use anyhow::{Context, Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    analyze::Analyzer,
+    checkpoint::Settings,
+    cmd::{parse_id, single::print_trace_line, tm_from_id},
+    dispatch::{self, PerN},
+    machine::Machine,
+    outcome::FnSink,
+    output::raw_steps_and_ones,
+    tm::Tm,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The CSV file written by `full --raw-out`, including its manifest.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+
+    /// Replay the TM with this index (not ID!) in the generator's order.
+    #[structopt(long, required_unless("id"), conflicts_with("id"))]
+    index: Option<u64>,
+
+    /// Replay the TM with this ID. Accepts the same formats as `single`.
+    #[structopt(long, parse(try_from_str = parse_id))]
+    id: Option<u64>,
+
+    /// Print the trace even if the outcome matches.
+    #[structopt(long)]
+    trace: bool,
+
+    /// Number of steps shown in the trace.
+    #[structopt(long, default_value = "100")]
+    trace_steps: u64,
+
+    /// The cells from -w to w are shown in the trace.
+    #[structopt(long, default_value = "15")]
+    window: u32,
+}
+
+/// One row of the file, as far as it is needed here.
+struct Row {
+    index: u64,
+    id: u64,
+    outcome: String,
+    steps: String,
+    ones: String,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let (settings, accelerated, row) = read(&args)?;
+    dispatch::with_n(settings.n, Replay { args: &args, settings: &settings, accelerated, row })
+        .ok_or_else(|| anyhow!("invalid number of states {} in the manifest", settings.n))?
+}
+
+/// Reads the settings from the manifest and the row of the requested TM.
+fn read(args: &Args) -> Result<(Settings, bool, Row)> {
+    let path = args.file.display();
+    let file = File::open(&args.file).with_context(|| format!("failed to open '{}'", path))?;
+
+    let mut manifest = Vec::new();
+    let mut header = None;
+    let mut found = None;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read '{}'", path))?;
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some((key, value)) = comment.split_once(':') {
+                manifest.push((key.trim().to_owned(), value.trim().to_owned()));
+            }
+            continue;
+        }
+        let names = match &header {
+            Some(names) => names,
+            None => {
+                header = Some(line.split(',').map(str::to_owned).collect::<Vec<_>>());
+                continue;
+            }
+        };
+
+        let fields = line.split(',').collect::<Vec<_>>();
+        if fields.len() != names.len() {
+            return Err(anyhow!("line {} of '{}': wrong number of columns", i + 1, path));
+        }
+        let field = |name: &str| {
+            names.iter()
+                .position(|n| n == name)
+                .map(|c| fields[c].to_owned())
+                .ok_or_else(|| anyhow!("'{}' has no column '{}'", path, name))
+        };
+        let number = |name: &str| -> Result<u64> {
+            let value = field(name)?;
+            value.parse()
+                .map_err(|_| anyhow!("line {} of '{}': invalid {} '{}'", i + 1, path, name, value))
+        };
+
+        let (index, id) = (number("index")?, number("id")?);
+        if args.index == Some(index) || args.id == Some(id) {
+            found = Some(Row {
+                index,
+                id,
+                outcome: field("outcome")?,
+                steps: field("steps")?,
+                ones: field("ones")?,
+            });
+            break;
+        }
+    }
+
+    let row = found.ok_or_else(|| match (args.index, args.id) {
+        (Some(index), _) => anyhow!("'{}' contains no TM with index {}", path, index),
+        (_, id) => anyhow!("'{}' contains no TM with ID {}", path, id.unwrap()),
+    })?;
+
+    let get = |key: &str| {
+        manifest.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .ok_or_else(|| anyhow!("missing '{}'", key))
+    };
+    let settings = Settings::read(get)
+        .with_context(|| format!("'{}' has no complete manifest", path))?;
+    let accelerated = get("accelerated").map_or(Ok(false), |v| v.parse())
+        .with_context(|| format!("invalid manifest in '{}'", path))?;
+
+    Ok((settings, accelerated, row))
+}
+
+/// The arguments of `do_run`, to call it for the right `N`.
+struct Replay<'a> {
+    args: &'a Args,
+    settings: &'a Settings,
+    accelerated: bool,
+    row: Row,
+}
+
+impl PerN for Replay<'_> {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self)
+    }
+}
+
+fn do_run<const N: usize>(replay: Replay<'_>) -> Result<()>
+where
+    [bool; N]: Default,
+{
+    let Replay { args, settings, accelerated, row } = replay;
+    let tm = tm_from_id::<N>(row.id)?;
+    bunt::println!(
+        "{$blue+bold}▸ Replaying TM {[intense]} (index {}) with {} states, up to {} steps...{/$}",
+        row.id,
+        row.index,
+        N,
+        settings.max_steps,
+    );
+    println!();
+    print!("{}", tm.table());
+    println!();
+    println!("Standard notation: {}", tm.to_standard_notation());
+    println!();
+
+    let mut analyzer = Analyzer::builder()
+        .max_steps(settings.max_steps)
+        .halt_convention(settings.halt_convention)
+        .lin_recurrence(settings.lin_recurrence)
+        .optional_deciders(settings.optional_deciders)
+        .run_budget(settings.run_budget)
+        .cycle_budget(settings.cycle_budget)
+        .ctl_budget(settings.ctl_budget)
+        .accelerated(accelerated)
+        .build();
+    let mut outcome = None;
+    analyzer.analyze(tm, &mut FnSink(|_, result| outcome = Some(result)));
+    let outcome = outcome.ok_or_else(|| anyhow!("the analysis finished without a result"))?;
+
+    let (steps, ones) = raw_steps_and_ones(&outcome);
+    let or_dash = |s: &str| if s.is_empty() { "-".to_owned() } else { s.to_owned() };
+    println!(
+        "stored:   {: <20} steps {: >12}   ones {: >8}",
+        row.outcome,
+        or_dash(&row.steps),
+        or_dash(&row.ones),
+    );
+    println!(
+        "replayed: {: <20} steps {: >12}   ones {: >8}",
+        outcome.name(),
+        or_dash(&steps),
+        or_dash(&ones),
+    );
+    println!();
+
+    // These outcomes depend on the other TMs of the sweep or on the
+    // machine's load, so they can't be reproduced with this TM alone.
+    let reason = match row.outcome.as_str() {
+        "pruned" => Some("it was pruned against the high score of the sweep (see '--prune')"),
+        "known" => Some("it was skipped as known (see '--known')"),
+        _ if [settings.run_budget, settings.cycle_budget, settings.ctl_budget]
+            .iter()
+            .any(|b| b.is_some_and(|b| b.time().is_some())) =>
+        {
+            Some("the sweep used a time budget (see '--run-budget', '--cycle-budget' and \
+                '--ctl-budget')")
+        }
+        _ => None,
+    };
+    let matches = (row.outcome.as_str(), row.steps.as_str(), row.ones.as_str())
+        == (outcome.name(), &*steps, &*ones);
+
+    if !matches || args.trace {
+        print_trace::<N>(tm, args, settings.max_steps);
+    }
+
+    match (matches, reason) {
+        (true, _) => {
+            bunt::println!("{$green+bold}The replayed outcome matches the stored one.{/$}");
+            Ok(())
+        }
+        (false, Some(reason)) => {
+            bunt::println!(
+                "{$yellow+bold}The replayed outcome differs, but can't be compared:{/$} {}",
+                reason,
+            );
+            Ok(())
+        }
+        (false, None) => {
+            Err(anyhow!("the replayed outcome of TM {} differs from the stored one", row.id))
+        }
+    }
+}
+
+/// Prints the first `--trace-steps` steps of the plain simulation.
+fn print_trace<const N: usize>(tm: Tm<N>, args: &Args, max_steps: u64) {
+    let steps = args.trace_steps.min(max_steps);
+    bunt::println!("{$blue+bold}▸ Trace of the first {} steps:{/$}", steps);
+    let mut machine = Machine::new(tm);
+    print_trace_line(&machine, args.window);
+    while machine.steps() < steps && machine.step().is_some() {
+        print_trace_line(&machine, args.window);
+    }
+    println!();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use structopt::StructOpt;
+    use crate::cmd::full;
+    use super::{Args, run};
+
+    #[test]
+    fn stored_outcome() {
+        let dir = env::temp_dir();
+        let path = dir.join(format!("beaver-replay-test-{}.csv", process::id()));
+        let tampered = dir.join(format!("beaver-replay-test-{}-tampered.csv", process::id()));
+        let path_str = path.to_str().unwrap();
+        let args = full::Args::from_iter(&["full", "-n", "2", "--raw-out", path_str, "--no-pb"]);
+        full::run(args).unwrap();
+
+        // Change the steps of the first halted TM.
+        let content = fs::read_to_string(&path).unwrap();
+        let halted = content.lines()
+            .find(|line| line.split(',').nth(2) == Some("halted"))
+            .unwrap();
+        let fields = halted.split(',').collect::<Vec<_>>();
+        let mut changed = fields.clone();
+        let wrong_steps = format!("{}0", fields[4]);
+        changed[4] = &wrong_steps;
+        fs::write(&tampered, content.replacen(halted, &changed.join(","), 1)).unwrap();
+
+        let replay = |file: &str, index: &str| {
+            run(Args::from_iter(&["replay", file, "--index", index]))
+        };
+        let results = (
+            replay(path_str, fields[0]),
+            replay(tampered.to_str().unwrap(), fields[0]),
+            replay(path_str, "1000000"),
+        );
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&tampered).unwrap();
+
+        assert!(results.0.is_ok());
+        let err = results.1.unwrap_err().to_string();
+        assert!(err.contains("differs from the stored one"), "{}", err);
+        let err = results.2.unwrap_err().to_string();
+        assert!(err.contains("contains no TM with index 1000000"), "{}", err);
+    }
+}
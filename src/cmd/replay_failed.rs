@@ -0,0 +1,134 @@
+//! Analyzes the TMs whose analysis panicked in a sweep again (see `full
+//! --failed-out`), one after another on the main thread, e.g. to debug the
+//! panic or to check a fix.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+};
+use anyhow::{Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    analyze::Analyzer,
+    checkpoint::Settings,
+    cmd::tm_from_id,
+    dispatch::{self, PerN},
+    failed::{self, Failed},
+    outcome::FnSink,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The file written by `full --failed-out`.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+
+    /// Only replay the TM with this index (not ID!).
+    #[structopt(long)]
+    index: Option<u64>,
+
+    /// Instead of stopping at the first panic (so that a debugger or
+    /// `RUST_BACKTRACE=1` shows where it happened), all TMs are replayed and
+    /// the ones that still panic are listed in the end.
+    #[structopt(long)]
+    keep_going: bool,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let (settings, mut tms) = failed::read(&args.file)?;
+    if let Some(index) = args.index {
+        tms.retain(|failed| failed.index == index);
+        if tms.is_empty() {
+            return Err(anyhow!("'{}' contains no TM with index {}", args.file.display(), index));
+        }
+    }
+
+    dispatch::with_n(settings.n, Replay { args: &args, settings: &settings, tms: &tms })
+        .expect("invalid n should be rejected when reading")
+}
+
+/// The arguments of `do_run`, to call it for the right `N`.
+struct Replay<'a> {
+    args: &'a Args,
+    settings: &'a Settings,
+    tms: &'a [Failed],
+}
+
+impl PerN for Replay<'_> {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self.args, self.settings, self.tms)
+    }
+}
+
+fn do_run<const N: usize>(args: &Args, settings: &Settings, tms: &[Failed]) -> Result<()>
+where
+    [bool; N]: Default,
+{
+    bunt::println!(
+        "{$blue+bold}▸ Replaying {[intense]} TMs with {} states (generator '{}', \
+            up to {} steps)...{/$}",
+        tms.len(),
+        N,
+        settings.generator,
+        settings.max_steps,
+    );
+    if settings.prune {
+        println!("  (the sweep used '--prune', which is not replayed)");
+    }
+
+    let mut analyzer = Analyzer::builder()
+        .max_steps(settings.max_steps)
+        .halt_convention(settings.halt_convention)
+        .lin_recurrence(settings.lin_recurrence)
+        .optional_deciders(settings.optional_deciders)
+        .run_budget(settings.run_budget)
+        .cycle_budget(settings.cycle_budget)
+        .ctl_budget(settings.ctl_budget)
+        .build();
+    let mut still_failing = Vec::new();
+    for failed in tms {
+        println!();
+        println!("TM {} (index {}) failed with: {}", failed.id, failed.index, failed.message);
+        let tm = tm_from_id::<N>(failed.id)?;
+
+        let mut outcome = None;
+        let mut analyze = || analyzer.analyze(tm, &mut FnSink(|_, result| outcome = Some(result)));
+        if args.keep_going {
+            if panic::catch_unwind(AssertUnwindSafe(analyze)).is_err() {
+                bunt::println!("  {$red+bold}panicked again{/$}");
+                still_failing.push(failed);
+                continue;
+            }
+        } else {
+            analyze();
+        }
+
+        match outcome {
+            Some(result) => bunt::println!("  {$green}now finished:{/$} {:?}", result),
+            None => bunt::println!("  {$yellow}finished without a result{/$}"),
+        }
+    }
+
+    println!();
+    if still_failing.is_empty() {
+        bunt::println!("{$green+bold}All {} TMs were analyzed without panicking.{/$}", tms.len());
+    } else {
+        bunt::println!(
+            "{$red+bold}{} of {} TMs still panic:{/$}",
+            still_failing.len(),
+            tms.len(),
+        );
+        for failed in still_failing {
+            println!("    TM {} (index {})", failed.id, failed.index);
+        }
+    }
+
+    Ok(())
+}
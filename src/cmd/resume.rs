@@ -0,0 +1,133 @@
+//! Continues a full run that was interrupted, from a checkpoint written by
+//! `full --checkpoint`.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use anyhow::Result;
+use structopt::StructOpt;
+
+use crate::{
+    checkpoint::{Checkpoint, CheckpointTarget},
+    cmd::progress::ProgressDisplay,
+    manifest::Manifest,
+    sweep::SweepBuilder,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// The checkpoint file. It is updated while the run continues.
+    #[structopt(parse(from_os_str))]
+    checkpoint: PathBuf,
+
+    /// Number of threads to use. Defaults to the number of virtual CPUs.
+    #[structopt(long, short = "j")]
+    num_threads: Option<u32>,
+
+    /// If specified, no progress bar is shown.
+    #[structopt(long)]
+    no_pb: bool,
+
+    /// Number of seconds between two checkpoints.
+    #[structopt(long, default_value = "600")]
+    checkpoint_interval: u64,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    let checkpoint = Checkpoint::read(&args.checkpoint)?;
+    let settings = checkpoint.settings.clone();
+    let report = checkpoint.report.clone();
+    let manifest = Manifest::new(&[
+        ("n", settings.n.to_string()),
+        ("generator", settings.generator.to_string()),
+        ("fixed", settings.fixed.as_ref().map_or("none".into(), |t| t.to_string())),
+        ("max-steps", settings.max_steps.to_string()),
+        ("halt-convention", settings.halt_convention.to_string()),
+        ("lin-recurrence", settings.lin_recurrence.to_string()),
+        ("optional-deciders", settings.optional_deciders.to_string()),
+        ("run-budget", settings.run_budget.map_or("none".into(), |b| b.to_string())),
+        ("cycle-budget", settings.cycle_budget.map_or("none".into(), |b| b.to_string())),
+        ("ctl-budget", settings.ctl_budget.map_or("none".into(), |b| b.to_string())),
+        ("tie-break", settings.tie_break.to_string()),
+        ("max-champions", settings.max_champions.to_string()),
+        ("prune", settings.prune.to_string()),
+        ("baseline-score", settings.baseline_score.map_or("none".into(), |k| k.to_string())),
+        ("resumed-from", args.checkpoint.display().to_string()),
+    ]);
+
+    let mut sweep = SweepBuilder::new(settings.n)
+        .generator(settings.generator)
+        .fixed(settings.fixed.clone())
+        .max_steps(settings.max_steps)
+        .halt_convention(settings.halt_convention)
+        .lin_recurrence(settings.lin_recurrence)
+        .optional_deciders(settings.optional_deciders)
+        .run_budget(settings.run_budget)
+        .cycle_budget(settings.cycle_budget)
+        .ctl_budget(settings.ctl_budget)
+        .num_threads(args.num_threads)
+        .tie_break(settings.tie_break)
+        .max_champions(settings.max_champions)
+        .prune(settings.prune)
+        .baseline_score(settings.baseline_score)
+        .resume(checkpoint)
+        .checkpoint(Some(CheckpointTarget {
+            path: args.checkpoint.clone(),
+            interval: Duration::from_secs(args.checkpoint_interval),
+            manifest: manifest.clone(),
+            report: report.clone(),
+        }));
+
+    let num_tms = sweep.num_tms();
+    let num_resumed = sweep.num_resumed_tms();
+    println!();
+    bunt::println!(
+        "{$blue+bold}▸ Resuming the analysis of {[intense]} TMs with {[intense]} states \
+            ({[intense]} already done)...{/$}",
+        num_tms,
+        settings.n,
+        num_resumed,
+    );
+    println!();
+
+
+    // ----- Run -------------------------------------------------------------
+    // The progress bar only counts the remaining TMs, so that its speed and
+    // ETA are correct.
+    let num_threads = sweep.effective_num_threads();
+    let pb = Arc::new(Mutex::new(ProgressDisplay::new(num_tms - num_resumed, num_threads)));
+    if !args.no_pb {
+        let pb = pb.clone();
+        sweep = sweep.on_progress(move |progress| {
+            pb.lock().expect("poisened lock").record(progress);
+        });
+    }
+
+    let before = Instant::now();
+    let summary = sweep.run()?;
+    if !args.no_pb {
+        pb.lock().unwrap().finish();
+    }
+    println!();
+    println!("  (That took {:.2?})", before.elapsed());
+
+
+    // ----- Print results ---------------------------------------------------
+    println!();
+    summary.print_report(&report.options(&settings));
+    if summary.warn_max_steps(settings.max_steps) {
+        println!(
+            "  Consider a new run with a larger limit like '--max-steps {}'.",
+            settings.max_steps.saturating_mul(10),
+        );
+        println!();
+    }
+
+    manifest.print();
+    println!();
+
+    Ok(())
+}
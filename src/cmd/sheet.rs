@@ -0,0 +1,135 @@
+use std::{fs, path::PathBuf};
+use anyhow::{Context, Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    SharedArgs,
+    cmd::{check_n, parse_id, tm_from_id},
+    dispatch::{self, PerN},
+    manifest::Manifest,
+    render::{self, Image},
+    space_time::SpaceTime,
+    tm::Tm,
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    #[structopt(flatten)]
+    shared: SharedArgs,
+
+    /// File with the IDs of the TMs to render. One ID (decimal, hex or
+    /// binary) per line, everything after the first whitespace is ignored.
+    /// Empty lines and lines starting with `#` are skipped.
+    #[structopt(parse(from_os_str))]
+    list: PathBuf,
+
+    /// The PNG file to write the contact sheet to.
+    #[structopt(long, short, parse(from_os_str), default_value = "sheet.png")]
+    out: PathBuf,
+
+    /// Number of cells shown per thumbnail, centered around the start cell.
+    /// The height of each thumbnail is `--max-steps` + 1.
+    #[structopt(long, default_value = "100")]
+    cells: u32,
+
+    /// Number of thumbnails per row.
+    #[structopt(long, default_value = "10")]
+    columns: usize,
+}
+
+/// Gap between two thumbnails in pixels.
+const GAP: usize = 4;
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    check_n(args.shared.n)?;
+    dispatch::with_n(args.shared.n, args)
+        .expect("invalid value for n: argument parsing should catch this")
+}
+
+impl PerN for Args {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self)
+    }
+}
+
+fn do_run<const N: usize>(args: Args) -> Result<()> {
+    if args.columns == 0 {
+        return Err(anyhow!("'--columns' has to be at least 1"));
+    }
+
+    let tms = read_list::<N>(&args)?;
+    if tms.is_empty() {
+        return Err(anyhow!("'{}' does not contain any TM IDs", args.list.display()));
+    }
+
+    let left = -(args.cells as i64 / 2);
+    let window = left..left + args.cells as i64;
+    let thumb_width = args.cells as usize;
+    let thumb_height = args.shared.max_steps as usize + 1;
+
+    let columns = args.columns.min(tms.len());
+    let rows = tms.len().div_ceil(columns);
+    let mut sheet = Image::new(
+        GAP + columns * (thumb_width + GAP),
+        GAP + rows * (thumb_height + GAP),
+        render::BACKGROUND,
+    );
+
+    bunt::println!(
+        "{$blue+bold}▸ Rendering {[intense]} TMs into a {}x{} grid...{/$}",
+        tms.len(),
+        columns,
+        rows,
+    );
+    for (i, (id, tm)) in tms.into_iter().enumerate() {
+        let (row, column) = (i / columns, i % columns);
+        // Thumbnails of TMs that halt early are shorter, the rest of their
+        // space stays background.
+        let capture = SpaceTime::capture(tm, args.shared.max_steps, window.clone());
+        let thumb = render::space_time(&capture);
+        sheet.blit(&thumb, GAP + column * (thumb_width + GAP), GAP + row * (thumb_height + GAP));
+        println!("  row {: >3}, column {: >3}: {}", row + 1, column + 1, id);
+    }
+
+    let manifest = Manifest::new(&[
+        ("n", N.to_string()),
+        ("list", args.list.display().to_string()),
+        ("max-steps", args.shared.max_steps.to_string()),
+        ("cells", args.cells.to_string()),
+    ]);
+    sheet.write_png(&args.out, &manifest)?;
+    bunt::println!(
+        "Wrote {[intense]}x{[intense]} image to {[green+bold]}",
+        sheet.width(),
+        sheet.height(),
+        args.out.display(),
+    );
+
+    Ok(())
+}
+
+fn read_list<const N: usize>(args: &Args) -> Result<Vec<(u64, Tm<N>)>> {
+    let content = fs::read_to_string(&args.list)
+        .with_context(|| format!("failed to read '{}'", args.list.display()))?;
+
+    let mut out = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let field = line.split_whitespace().next().unwrap();
+        let id = parse_id(field).map_err(|e| anyhow!("line {}: {}", i + 1, e))?;
+        let tm = tm_from_id::<N>(id).with_context(|| format!("invalid TM in line {}", i + 1))?;
+        out.push((id, tm));
+    }
+
+    Ok(out)
+}
@@ -1,7 +1,36 @@
-use anyhow::Result;
+use std::{
+    cmp::{max, min},
+    convert::TryFrom,
+    fs::File,
+    io::{BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+use anyhow::{Context, Result, anyhow};
 use structopt::StructOpt;
 
-use crate::{SharedArgs, tm::Tm};
+#[cfg(feature = "big-steps")]
+use crate::big_uint::BigUint;
+use crate::{
+    SharedArgs,
+    cmd::{parse_cell_range, parse_id, tm_from_id},
+    dispatch::{self, PerN},
+    analyze::{Analyzer, HaltConvention, StepHook},
+    events::{self, Detail},
+    machine::{Machine, Step},
+    manifest::Manifest,
+    outcome::FnSink,
+    heatmap::HeadHeatmap,
+    macro_machine::{self, RunResult, StepCount},
+    multi_symbol::{self, MultiMachine, MultiTm},
+    render,
+    space_time::SpaceTime,
+    tape::{CellId, Tape, TapeLike},
+    tm::{Action, Tm, state_name},
+};
 
 
 #[derive(StructOpt, Debug, Clone)]
@@ -9,30 +38,850 @@ pub struct Args {
     #[structopt(flatten)]
     shared: SharedArgs,
 
-    /// The ID of the turing machine.
-    id: u64,
+    /// The turing machine, in the format given by `--format`.
+    #[structopt(required_unless("compare"))]
+    tm: Option<String>,
+
+    /// How TMs are specified: 'id' is the ID in decimal, hex (`0x...`) or
+    /// binary (`0b...`); 'standard' is the notation used by the bbchallenge
+    /// and most papers, e.g. `1RB1LB_1LA1RZ`.
+    #[structopt(long, default_value = "id")]
+    format: TmFormat,
+
+    /// Number of symbols of the TM, e.g. 3 for BB(2,3). TMs with more than 2
+    /// symbols (or more than 6 states) have to be given with `--format
+    /// standard` and can only be run with `--trace` (or without any further
+    /// output).
+    #[structopt(long, default_value = "2")]
+    symbols: u8,
+
+    /// Compares two TMs instead: prints both transition tables side by side
+    /// and runs both TMs to find the first step in which they diverge.
+    #[structopt(
+        long,
+        number_of_values(2),
+        value_names(&["TM1", "TM2"]),
+        conflicts_with("tm"),
+    )]
+    compare: Vec<String>,
+
+    /// Prints the configuration of the TM after each step (or every
+    /// `--trace-every` steps).
+    #[structopt(long)]
+    trace: bool,
+
+    /// Only print every n-th step in the trace or diagram.
+    #[structopt(long, default_value = "1")]
+    trace_every: u64,
+
+    /// Prints a space-time diagram of the TM: one line per step (or every
+    /// `--trace-every` steps) showing the cells from -w to w (see `--window`),
+    /// with 1s as full blocks and the head highlighted.
+    #[structopt(long, conflicts_with_all(&["trace", "trace-changes"]))]
+    diagram: bool,
+
+    /// Writes the space-time diagram to this image file (PNG or SVG,
+    /// depending on the extension): one row of pixels per step until the TM
+    /// halts or `--max-steps` is reached, showing the cells from -w to w (see
+    /// `--window`).
+    #[structopt(long, parse(from_os_str))]
+    diagram_out: Option<PathBuf>,
+
+    /// The size of each cell in the image written by `--diagram-out`, in
+    /// pixels.
+    #[structopt(long, default_value = "4")]
+    diagram_scale: usize,
+
+    /// Prints a heatmap of the head position over time: each line is one of
+    /// `--heatmap-rows` equal time slices of the run and the darker a
+    /// character, the more steps the head spent on those cells. The cells
+    /// visited are grouped into at most 100 columns.
+    #[structopt(long)]
+    heatmap: bool,
+
+    /// Writes the heatmap (see `--heatmap`) to this image file (PNG or SVG),
+    /// with one column of pixels per cell.
+    #[structopt(long, parse(from_os_str))]
+    heatmap_out: Option<PathBuf>,
+
+    /// The number of time slices of the heatmap.
+    #[structopt(long, default_value = "32")]
+    heatmap_rows: usize,
+
+    /// Writes the notable events of the run until the TM halts or
+    /// `--max-steps` is reached to this CSV file (columns
+    /// `step,event,cell,detail`): state changes, direction reversals, new
+    /// leftmost or rightmost cells and writes of 1.
+    #[structopt(long, parse(from_os_str))]
+    events_out: Option<PathBuf>,
+
+    /// Runs the TM with the analyzer (like `full` does) and prints how often
+    /// each transition was used until the analyzer decided the outcome.
+    #[structopt(long)]
+    transition_counts: bool,
+
+    /// Like `--trace`, but instead of a window of the tape, only the cells
+    /// that changed since the previously printed step are printed (with their
+    /// positions). Useful for TMs with a wide tape.
+    #[structopt(long)]
+    trace_changes: bool,
+
+    /// The cells from -w to w are shown in the trace and the diagram.
+    #[structopt(long, default_value = "15")]
+    window: u32,
+
+    /// Stops the TM as soon as its head leaves the given range of cells,
+    /// e.g. '-50..50' or '-50..=50', and prints its configuration. Useful to
+    /// catch the moment a TM starts to run away.
+    #[structopt(
+        long,
+        allow_hyphen_values(true),
+        value_name("RANGE"),
+        parse(try_from_str = parse_cell_range),
+    )]
+    break_outside: Option<Range<i64>>,
+
+    /// Limits `--trace`, `--trace-changes` and `--diagram` to this many
+    /// (simulated) steps per second, e.g. for demos. Fractions like '0.5' are
+    /// allowed.
+    #[structopt(long, value_name("STEPS_PER_SECOND"))]
+    throttle: Option<f64>,
+
+    /// Runs the TM with a macro machine that detects repeated patterns on the
+    /// tape, proves that they continue and applies them many times at once.
+    /// This runs e.g. the BB(5) champion to its halt in milliseconds. Only
+    /// the result is printed, so this can't be combined with a trace.
+    #[structopt(
+        long,
+        conflicts_with_all(&["trace", "trace-changes", "diagram", "break-outside"]),
+    )]
+    accelerated: bool,
+
+    /// Like `--max-steps`, but of any size, for `--accelerated` runs of TMs
+    /// that run longer than 2^64 steps. The steps are then counted exactly.
+    #[cfg(feature = "big-steps")]
+    #[structopt(long, requires("accelerated"))]
+    max_big_steps: Option<BigUint>,
 }
 
 pub(crate) fn run(args: Args) -> Result<()> {
-    match args.shared.n {
-        1 => do_run::<1>(args),
-        2 => do_run::<2>(args),
-        3 => do_run::<3>(args),
-        4 => do_run::<4>(args),
-        5 => do_run::<5>(args),
-        6 => do_run::<6>(args),
-        _ => panic!("invalid value for n: argument parsing should catch this"),
+    if args.symbols != 2 || !dispatch::SUPPORTED.contains(&args.shared.n) {
+        return run_multi_symbol(&args);
+    }
+
+    dispatch::with_n(args.shared.n, args)
+        .expect("invalid value for n: argument parsing should catch this")
+}
+
+impl PerN for Args {
+    type Output = Result<()>;
+
+    fn call<const N: usize>(self) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        do_run::<N>(self)
+    }
+}
+
+fn do_run<const N: usize>(args: Args) -> Result<()>
+where
+    [bool; N]: Default,
+{
+    if let [a, b] = &args.compare[..] {
+        let (a, b) = (args.format.parse::<N>(a)?, args.format.parse::<N>(b)?);
+        return compare::<N>(a, b, &args);
+    }
+
+    if let Some(rate) = args.throttle {
+        if rate.is_nan() || rate <= 0.0 {
+            return Err(anyhow!("'--throttle' has to be positive"));
+        }
+        if !(args.trace || args.trace_changes || args.diagram) {
+            return Err(anyhow!(
+                "'--throttle' requires '--trace', '--trace-changes' or '--diagram'",
+            ));
+        }
+    }
+
+    let tm = args.tm.as_ref().expect("argument parsing should require a TM");
+    let tm = args.format.parse::<N>(tm)?;
+    let id = tm.encoded;
+
+    bunt::println!("{$blue+bold}▸ Turing machine for ID {[intense]}:{/$}", id);
+    println!();
+    print!("{}", tm.table());
+    println!();
+    println!("Standard notation: {}", tm.to_standard_notation());
+    println!();
+
+    if args.accelerated {
+        run_accelerated(tm, &args);
+    } else {
+        run_tm(tm, &args);
+    }
+
+    if let Some(path) = &args.diagram_out {
+        write_diagram(tm, id, path, &args)?;
+    }
+
+    if args.heatmap || args.heatmap_out.is_some() {
+        let heatmap = HeadHeatmap::capture(tm, args.shared.max_steps, args.heatmap_rows);
+        if args.heatmap {
+            print_heatmap(&heatmap);
+        }
+        if let Some(path) = &args.heatmap_out {
+            write_heatmap(&heatmap, id, path, &args)?;
+        }
+    }
+
+    if let Some(path) = &args.events_out {
+        write_events(tm, path, &args)?;
+    }
+
+    if args.transition_counts {
+        print_transition_counts(tm, &args);
+    }
+
+    Ok(())
+}
+
+/// Runs a TM with more than 2 symbols or more than 6 states (see
+/// `multi_symbol`). Only the result and optionally a trace are printed.
+fn run_multi_symbol(args: &Args) -> Result<()> {
+    let binary_only = args.accelerated
+        || !args.compare.is_empty()
+        || args.trace_changes
+        || args.diagram
+        || args.diagram_out.is_some()
+        || args.heatmap
+        || args.heatmap_out.is_some()
+        || args.events_out.is_some()
+        || args.transition_counts
+        || args.break_outside.is_some()
+        || args.throttle.is_some();
+    if binary_only {
+        return Err(anyhow!(
+            "TMs with more than 2 symbols or more than 6 states can only be run with '--trace'",
+        ));
+    }
+    if let TmFormat::Id = args.format {
+        return Err(anyhow!(
+            "TMs with more than 2 symbols or more than 6 states have to be given with \
+                '--format standard'",
+        ));
+    }
+
+    let tm = args.tm.as_ref().expect("argument parsing should require a TM");
+    let tm = MultiTm::from_standard(tm, args.symbols).map_err(|e| anyhow!(e))?;
+    if tm.n() != args.shared.n {
+        return Err(anyhow!(
+            "'{}' describes a TM with {} states, but n = {}",
+            tm,
+            tm.n(),
+            args.shared.n,
+        ));
+    }
+
+    bunt::println!(
+        "{$blue+bold}▸ Turing machine with {[intense]} symbols:{/$}",
+        tm.symbols(),
+    );
+    println!();
+    print!("{}", tm.table());
+    println!();
+    println!("Standard notation: {}", tm);
+    println!();
+
+    let max_steps = args.shared.max_steps;
+    let trace_every = max(args.trace_every, 1);
+    let window = args.window as i64;
+    let print_trace_line = |machine: &MultiMachine| {
+        let state = multi_symbol::state_name(machine.state(), tm.n());
+        print!("{: >9}  {}  {: >6}   ", machine.steps(), state, machine.head());
+        for cell in -window..=window {
+            let value = machine.get(cell);
+            if cell == machine.head() {
+                bunt::print!("{[black+bg:yellow]}", value);
+            } else {
+                print!("{}", value);
+            }
+        }
+        println!();
+    };
+
+    bunt::println!("{$blue+bold}▸ Running TM (up to {} steps):{/$}", max_steps);
+    let mut machine = MultiMachine::new(&tm);
+    if args.trace {
+        print_trace_line(&machine);
+    }
+    while machine.steps() < max_steps && machine.step() {
+        let halted = machine.state().is_none();
+        if args.trace && (machine.steps().is_multiple_of(trace_every) || halted) {
+            print_trace_line(&machine);
+        }
+    }
+    println!();
+
+    let halted = match machine.halted(args.shared.halt_convention) {
+        Some(halted) => halted,
+        None => {
+            bunt::println!(
+                "The TM did not halt within {[red+bold]} steps (the maximum number of steps)",
+                max_steps,
+            );
+            return Ok(());
+        }
+    };
+
+    bunt::println!(
+        "The TM halted after {[green+bold]} steps with {[green+bold]} non-blank symbols \
+            on the tape (symbol sum {[green+bold]})",
+        halted.steps,
+        halted.non_blank,
+        halted.symbol_sum,
+    );
+
+    Ok(())
+}
+
+/// How TMs are specified on the command line.
+#[derive(Debug, Clone, Copy)]
+enum TmFormat {
+    Id,
+    Standard,
+}
+
+impl TmFormat {
+    fn parse<const N: usize>(self, s: &str) -> Result<Tm<N>> {
+        match self {
+            Self::Id => tm_from_id::<N>(parse_id(s).map_err(|e| anyhow!(e))?),
+            Self::Standard => Tm::from_standard(s).map_err(|e| anyhow!(e)),
+        }
+    }
+}
+
+impl FromStr for TmFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(Self::Id),
+            "standard" => Ok(Self::Standard),
+            _ => Err("invalid value for 'format'"),
+        }
+    }
+}
+
+/// Counts how often each transition (state and read value) is used.
+struct TransitionCounts<const N: usize>([[u64; 2]; N]);
+
+impl<const N: usize> StepHook<N> for TransitionCounts<N> {
+    fn on_step(&mut self, step: &Step<N>, _: &impl TapeLike) {
+        self.0[step.state as usize][step.read.0 as usize] += 1;
+    }
+}
+
+/// Runs the TM with the analyzer and prints the usage of each transition.
+fn print_transition_counts<const N: usize>(tm: Tm<N>, args: &Args)
+where
+    [bool; N]: Default,
+{
+    let mut analyzer = Analyzer::builder()
+        .max_steps(args.shared.max_steps)
+        .halt_convention(args.shared.halt_convention)
+        .build();
+    let mut counts = TransitionCounts([[0; 2]; N]);
+    let mut outcome = None;
+    analyzer.run_tm_with_hook(tm, &mut FnSink(|_, result| outcome = Some(result)), &mut counts);
+
+    println!();
+    bunt::println!("{$blue+bold}▸ Transition counts:{/$}");
+    println!("    │  {: >12}  {: >12}", 0, 1);
+    println!("────┼────────────────────────────");
+    for (state, row) in counts.0.iter().enumerate() {
+        println!("  {} │  {: >12}  {: >12}", state_name::<N>(state as u8), row[0], row[1]);
+    }
+    if let Some(outcome) = outcome {
+        println!("(analyzer outcome: {:?})", outcome);
+    }
+}
+
+/// Renders the space-time diagram into an image file.
+fn write_diagram<const N: usize>(tm: Tm<N>, id: u64, path: &Path, args: &Args) -> Result<()> {
+    let window = args.window as i64;
+    let capture = SpaceTime::capture(tm, args.shared.max_steps, -window..window + 1);
+    let image = render::space_time(&capture);
+    let manifest = Manifest::new(&[
+        ("n", N.to_string()),
+        ("id", id.to_string()),
+        ("max-steps", args.shared.max_steps.to_string()),
+        ("window", args.window.to_string()),
+    ]);
+    image.write(path, max(args.diagram_scale, 1), &manifest)?;
+
+    println!();
+    bunt::println!(
+        "Wrote space-time diagram ({} steps, {} cells) to {[green+bold]}",
+        capture.rows() - 1,
+        2 * window + 1,
+        path.display(),
+    );
+    Ok(())
+}
+
+/// Prints the heatmap with shades of gray as characters.
+fn print_heatmap(heatmap: &HeadHeatmap) {
+    const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+    const MAX_COLUMNS: u64 = 100;
+
+    let cells = heatmap.cells();
+    let width = (cells.end - cells.start) as u64;
+    let cells_per_column = width.div_ceil(MAX_COLUMNS) as i64;
+    let columns = width.div_ceil(cells_per_column as u64) as i64;
+    let column_count = |row, column| {
+        let start = cells.start + column * cells_per_column;
+        (start..min(start + cells_per_column, cells.end))
+            .map(|cell| heatmap.get(row, cell))
+            .sum::<u32>()
+    };
+    let max_count = (0..heatmap.rows())
+        .flat_map(|row| (0..columns).map(move |column| (row, column)))
+        .map(|(row, column)| column_count(row, column))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    println!();
+    bunt::println!(
+        "{$blue+bold}▸ Head heatmap{/$} ({} steps in {} rows, cells {}..{}, {} per column):",
+        heatmap.steps(),
+        heatmap.rows(),
+        cells.start,
+        cells.end,
+        cells_per_column,
+    );
+    for row in 0..heatmap.rows() {
+        let line = (0..columns)
+            .map(|column| {
+                let count = column_count(row, column);
+                if count == 0 {
+                    return SHADES[0];
+                }
+                let shade = (count as u64 * (SHADES.len() as u64 - 1)).div_ceil(max_count as u64);
+                SHADES[shade as usize]
+            })
+            .collect::<String>();
+        println!("    │{}│", line);
+    }
+}
+
+/// Renders the heatmap into an image file.
+fn write_heatmap(heatmap: &HeadHeatmap, id: u64, path: &Path, args: &Args) -> Result<()> {
+    let image = render::heatmap(heatmap);
+    let manifest = Manifest::new(&[
+        ("n", args.shared.n.to_string()),
+        ("id", id.to_string()),
+        ("max-steps", args.shared.max_steps.to_string()),
+        ("heatmap-rows", args.heatmap_rows.to_string()),
+    ]);
+    image.write(path, max(args.diagram_scale, 1), &manifest)?;
+
+    println!();
+    bunt::println!(
+        "Wrote head heatmap ({} rows, cells {}..{}) to {[green+bold]}",
+        heatmap.rows(),
+        heatmap.cells().start,
+        heatmap.cells().end,
+        path.display(),
+    );
+    Ok(())
+}
+
+/// Writes the events of the run (see `events::record`) as CSV file.
+fn write_events<const N: usize>(tm: Tm<N>, path: &Path, args: &Args) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    let mut w = BufWriter::new(file);
+    let manifest = Manifest::new(&[
+        ("n", N.to_string()),
+        ("id", tm.encoded.to_string()),
+        ("max-steps", args.shared.max_steps.to_string()),
+    ]);
+    manifest.write_comments(&mut w)?;
+    writeln!(w, "step,event,cell,detail")?;
+
+    // The callback can't return errors, so we keep the first one.
+    let mut result = Ok(());
+    let mut num_events = 0u64;
+    let steps = events::record(tm, args.shared.max_steps, |e| {
+        num_events += 1;
+        if result.is_ok() {
+            let detail = Detail::<N>(e.kind);
+            result = writeln!(w, "{},{},{},{}", e.step, e.kind.name(), e.cell, detail);
+        }
+    });
+    result.and_then(|_| w.flush())
+        .with_context(|| format!("failed to write '{}'", path.display()))?;
+
+    println!();
+    bunt::println!(
+        "Wrote {} events ({} steps) to {[green+bold]}",
+        num_events,
+        steps,
+        path.display(),
+    );
+    Ok(())
+}
+
+/// Runs the TM, optionally printing a trace, and prints the result.
+fn run_tm<const N: usize>(tm: Tm<N>, args: &Args) {
+    let max_steps = args.shared.max_steps;
+    let trace = args.trace || args.trace_changes;
+    let trace_every = max(args.trace_every, 1);
+
+    bunt::println!("{$blue+bold}▸ Running TM (up to {} steps):{/$}", max_steps);
+    let throttle = Throttle::new(args.throttle);
+    let mut machine = Machine::new(tm);
+    let mut snapshot = Snapshot::of(machine.tape());
+    if trace {
+        print_trace_line(&machine, args.window);
+    }
+
+    let mut last_step = None;
+    while machine.steps() < max_steps {
+        match machine.step() {
+            None => break,
+            Some(step) => last_step = Some(step),
+        }
+
+        let halted = machine.state().is_none();
+        let outside = args.break_outside.as_ref()
+            .is_some_and(|range| !halted && !range.contains(&machine.head().0));
+        if trace && (machine.steps() % trace_every == 0 || halted || outside) {
+            throttle.wait(machine.steps());
+            if args.trace_changes {
+                print_changes_line(&machine, &snapshot);
+                snapshot = Snapshot::of(machine.tape());
+            } else {
+                print_trace_line(&machine, args.window);
+            }
+        }
+
+        if outside {
+            let range = args.break_outside.as_ref().unwrap();
+            if args.diagram {
+                print_diagram(tm, machine.steps(), args);
+            } else if !trace {
+                print_trace_line(&machine, args.window);
+            }
+            println!();
+            bunt::println!(
+                "Stopped: the head left the cells {}..{} in step {[yellow+bold]} \
+                    (it is now on cell {[yellow+bold]})",
+                range.start,
+                range.end,
+                machine.steps(),
+                machine.head().0,
+            );
+            return;
+        }
+
+        if halted {
+            break;
+        }
+    }
+    if args.diagram {
+        print_diagram(tm, machine.steps(), args);
+    }
+    println!();
+
+    let halt = machine.state().is_none().then(|| {
+        let step: Step<N> = last_step.expect("halted TM without steps");
+        (step.read, step.action.write_value())
+    });
+    let result = RunResult { steps: machine.steps(), ones: machine.tape().ones(), halt };
+    print_result(result, &args.shared.max_steps, args);
+}
+
+/// Like `run_tm`, but with the macro machine and rules (see `--accelerated`).
+fn run_accelerated<const N: usize>(tm: Tm<N>, args: &Args) {
+    #[cfg(feature = "big-steps")]
+    if let Some(max_steps) = &args.max_big_steps {
+        let run = || macro_machine::run_to_halt_big(tm, max_steps.clone());
+        return run_timed(max_steps, run, args);
+    }
+
+    let max_steps = args.shared.max_steps;
+    run_timed(&max_steps, || macro_machine::run_to_halt(tm, max_steps), args);
+}
+
+/// Prints the header, the time `run` took and its result.
+fn run_timed<S: StepCount>(max_steps: &S, run: impl FnOnce() -> RunResult<S>, args: &Args) {
+    bunt::println!("{$blue+bold}▸ Running TM accelerated (up to {} steps):{/$}", max_steps);
+    let before = Instant::now();
+    let result = run();
+    println!("  (That took {:.2?})", before.elapsed());
+    println!();
+    print_result(result, max_steps, args);
+}
+
+/// Prints whether the TM halted and with how many steps and ones.
+fn print_result<S: StepCount>(result: RunResult<S>, max_steps: &S, args: &Args) {
+    let (read, written) = match result.halt {
+        Some(halt) => halt,
+        None => {
+            bunt::println!(
+                "The TM did not halt within {[red+bold]} steps (the maximum number of steps)",
+                max_steps,
+            );
+            return;
+        }
+    };
+
+    // The halting transition is always executed, so we undo it if it should
+    // not be counted.
+    let (mut steps, mut ones) = (result.steps, result.ones);
+    if args.shared.halt_convention == HaltConvention::Exclude {
+        steps.sub_steps(1);
+        ones = ones + read.0 as u64 - written.0 as u64;
+    }
+
+    bunt::println!(
+        "The TM halted after {[green+bold]} steps with {[green+bold]} ones on the tape",
+        steps,
+        ones,
+    );
+}
+
+/// Prints the current step, state, head position and the cells in
+/// `-window..=window` with the head highlighted.
+pub(crate) fn print_trace_line<const N: usize>(machine: &Machine<N>, window: u32) {
+    print_step_prefix(machine);
+    let window = window as i64;
+    for cell in -window..=window {
+        let value = if machine.tape().get(CellId(cell)).0 { '1' } else { '0' };
+        if cell == machine.head().0 {
+            bunt::print!("{[black+bg:yellow]}", value);
+        } else {
+            print!("{}", value);
+        }
+    }
+    println!();
+}
+
+/// Prints the space-time diagram of the first `steps` steps: every
+/// `--trace-every`-th row (and the last one) with the step number and the
+/// cells in `-window..=window` as blocks, with the head highlighted.
+fn print_diagram<const N: usize>(tm: Tm<N>, steps: u64, args: &Args) {
+    let window = args.window as i64;
+    let capture = SpaceTime::capture(tm, steps, -window..window + 1);
+    let every = max(args.trace_every, 1) as usize;
+    let last = capture.rows() - 1;
+    let throttle = Throttle::new(args.throttle);
+    for row in (0..=last).filter(|&row| row % every == 0 || row == last) {
+        throttle.wait(row as u64);
+        print!("{: >9} ▕", row);
+        for cell in capture.window() {
+            let value = if capture.get(row, cell) { '█' } else { ' ' };
+            if cell == capture.head(row) {
+                bunt::print!("{[yellow+bg:blue]}", value);
+            } else {
+                print!("{}", value);
+            }
+        }
+        println!("▏");
+    }
+}
+
+/// Prints the current step, state, head position and all cells that have a
+/// different value than in `snapshot`.
+fn print_changes_line<const N: usize>(machine: &Machine<N>, snapshot: &Snapshot) {
+    print_step_prefix(machine);
+
+    let tape = machine.tape();
+    let r = tape.written_range();
+    let start = min(r.start.0, snapshot.start);
+    let end = max(r.end.0, snapshot.start + snapshot.cells.len() as i64);
+    let changes = (start..end)
+        .filter(|&cell| tape.get(CellId(cell)).0 != snapshot.get(cell))
+        .collect::<Vec<_>>();
+
+    if changes.is_empty() {
+        bunt::println!("{$dimmed}(no changes){/$}");
+        return;
     }
+    let changes = changes.into_iter()
+        .map(|cell| {
+            let (old, new) = (snapshot.get(cell) as u8, tape.get(CellId(cell)).0 as u8);
+            format!("{}:{}→{}", cell, old, new)
+        })
+        .collect::<Vec<_>>();
+    println!("{}", changes.join("  "));
 }
 
-fn do_run<const N: usize>(args: Args) -> Result<()> {
-    let tm = <Tm<N>>::from_id(args.id)
-        .ok_or(anyhow::anyhow!("Turing machine ID is not valid for N = {}", N))?;
+fn print_step_prefix<const N: usize>(machine: &Machine<N>) {
+    let state = machine.state().unwrap_or(N as u8);
+    print!(
+        "{: >9}  {}  {: >6}   ",
+        machine.steps(),
+        state_name::<N>(state),
+        machine.head().0,
+    );
+}
+
+/// Slows down printing to a number of steps per second (see `--throttle`).
+struct Throttle {
+    start: Instant,
+    steps_per_second: Option<f64>,
+}
+
+impl Throttle {
+    fn new(steps_per_second: Option<f64>) -> Self {
+        Self { start: Instant::now(), steps_per_second }
+    }
 
-    bunt::println!("Turing machine for ID {[blue]}:", args.id);
-    bunt::println!("{:#?}", tm);
+    /// Sleeps until it is time to print step `step`.
+    fn wait(&self, step: u64) {
+        if let Some(rate) = self.steps_per_second {
+            let due = self.start + Duration::from_secs_f64(step as f64 / rate);
+            if let Some(remaining) = due.checked_duration_since(Instant::now()) {
+                thread::sleep(remaining);
+            }
+        }
+    }
+}
 
-    // TODO: run TM
+/// A copy of the written part of a tape.
+struct Snapshot {
+    /// The cell ID of `cells[0]`.
+    start: i64,
+    cells: Vec<bool>,
+}
+
+impl Snapshot {
+    fn of(tape: &Tape) -> Self {
+        Self {
+            start: tape.written_range().start.0,
+            cells: tape.iter_written().map(|(_, value)| value.0).collect(),
+        }
+    }
+
+    fn get(&self, cell: i64) -> bool {
+        usize::try_from(cell - self.start)
+            .ok()
+            .and_then(|idx| self.cells.get(idx))
+            .copied()
+            .unwrap_or(false)
+    }
+}
+
+fn compare<const N: usize>(a: Tm<N>, b: Tm<N>, args: &Args) -> Result<()> {
+    let (id_a, id_b) = (a.encoded, b.encoded);
+
+    // ----- Transition tables -----------------------------------------------
+    bunt::println!("{$blue+bold}▸ Comparing TMs {} and {}:{/$}", id_a, id_b);
+    println!();
+    println!("         TM {: <31}TM {}", id_a, id_b);
+    for i in 0..N as u8 {
+        let (sa, sb) = (a.state(i), b.state(i));
+        print!("    {}    ", state_name::<N>(i));
+        let differs = [!same_effect(sa.on_0(), sb.on_0()), !same_effect(sa.on_1(), sb.on_1())];
+        print_action(0, sa.on_0(), differs[0]);
+        print_action(1, sa.on_1(), differs[1]);
+        print!("   │   ");
+        print_action(0, sb.on_0(), differs[0]);
+        print_action(1, sb.on_1(), differs[1]);
+        println!();
+    }
+    println!();
+
+    // ----- Co-simulation ---------------------------------------------------
+    //
+    // Both TMs start in the same configuration. As long as they execute the
+    // same actions, their configurations stay the same. So the first step with
+    // actions that have a different effect (see `same_effect`) is exactly the
+    // step in which they diverge.
+    bunt::println!("{$blue+bold}▸ Running both TMs:{/$}");
+    let mut ma = Machine::new(a);
+    let mut mb = Machine::new(b);
+    loop {
+        if ma.steps() == args.shared.max_steps {
+            bunt::println!(
+                "Both TMs behave identically for the first {[green+bold]} steps \
+                    (the maximum number of steps)",
+                args.shared.max_steps,
+            );
+            break;
+        }
+
+        match (ma.step(), mb.step()) {
+            (None, None) => {
+                bunt::println!(
+                    "Both TMs behave identically and halt after {[green+bold]} steps",
+                    ma.steps(),
+                );
+                break;
+            }
+            (Some(step_a), Some(step_b)) if !same_effect(step_a.action, step_b.action) => {
+                bunt::println!(
+                    "The TMs diverge in step {[yellow+bold]}: in state {} reading {} at \
+                        cell {}, TM {} executes {[red+bold]:?} while TM {} executes \
+                        {[red+bold]:?}",
+                    ma.steps(),
+                    state_name::<N>(step_a.state),
+                    if step_a.read.0 { 1 } else { 0 },
+                    step_a.head.0,
+                    id_a,
+                    step_a.action,
+                    id_b,
+                    step_b.action,
+                );
+                break;
+            }
+            (Some(_), Some(_)) => {}
+            _ => unreachable!("TMs in the same configuration halted at different steps"),
+        }
+    }
 
     Ok(())
 }
+
+/// Whether executing `a` and `b` has the same effect: they write the same
+/// value and go to the same state. Halting TMs don't move anymore, so the
+/// direction only matters if the actions don't halt.
+fn same_effect<const N: usize>(a: Action<N>, b: Action<N>) -> bool {
+    a.write_value() == b.write_value()
+        && a.next_state() == b.next_state()
+        && (a.will_halt() || a.movement() == b.movement())
+}
+
+/// Prints an action in the form `0 → Br1` (padded), highlighted if `differs`
+/// is `true`.
+fn print_action<const N: usize>(read: u8, action: Action<N>, differs: bool) {
+    print!("{} → ", read);
+    if differs {
+        bunt::print!("{[red+bold]:?}", action);
+    } else {
+        print!("{:?}", action);
+    }
+    print!("    ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_effect_ignores_direction_of_halting_actions() {
+        let a = <Tm<2>>::from_standard("1RB1LB_1LA1RH").unwrap();
+        let b = <Tm<2>>::from_standard("1RB1LB_1LA1LH").unwrap();
+        let c = <Tm<2>>::from_standard("1RB1LB_1RA1RH").unwrap();
+        let d = <Tm<2>>::from_standard("1RB1LB_0LA1RH").unwrap();
+
+        assert!(same_effect(a.state(1).on_1(), b.state(1).on_1()));
+        assert!(!same_effect(a.state(1).on_0(), c.state(1).on_0()));
+        assert!(!same_effect(a.state(1).on_0(), d.state(1).on_0()));
+        assert!(same_effect(a.state(0).on_0(), c.state(0).on_0()));
+    }
+}
@@ -0,0 +1,405 @@
+//! Checks the deciders (the static checks, the run-away detection and the
+//! deciders applied at the step limit) against plain simulation for all TMs
+//! with few states.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use anyhow::{Context, Result, anyhow};
+use structopt::StructOpt;
+
+use crate::{
+    analyze::{HaltConvention, OptionalDeciders},
+    dispatch::{self, PerN},
+    gen::Generator,
+    machine::{Machine, Step},
+    manifest::Manifest,
+    outcome::AnalysisResult,
+    sweep::SweepBuilder,
+    tm::{Tm, state_name},
+};
+
+
+#[derive(StructOpt, Debug, Clone)]
+pub struct Args {
+    /// All TMs with 1 up to this many states are checked.
+    #[structopt(long, default_value = "3", possible_values(&["1", "2", "3"]))]
+    max_n: u8,
+
+    /// Number of steps after which the analyzer stops TMs.
+    #[structopt(long, default_value = "200")]
+    max_steps: u64,
+
+    /// Number of steps for which each TM is simulated to get the ground
+    /// truth. Has to be at least `--max-steps`.
+    #[structopt(long, default_value = "500")]
+    truth_steps: u64,
+
+    /// Whether the final transition into the halt state counts (see `full
+    /// --help`).
+    #[structopt(
+        long,
+        default_value = "include",
+        possible_values(&["include", "exclude"]),
+    )]
+    halt_convention: HaltConvention,
+
+    /// Applies Lin's recurrence check to TMs reaching `--max-steps` (see
+    /// `full --help`).
+    #[structopt(long)]
+    detect_lin_recurrence: bool,
+
+    /// Optional deciders that are applied to TMs reaching `--max-steps` (see
+    /// `full --help`).
+    #[structopt(long, default_value = "none")]
+    deciders: OptionalDeciders,
+
+    /// The TM generator (see `full --help`). Only 'all' checks every TM.
+    #[structopt(short, long, default_value = "all")]
+    generator: Generator,
+
+    /// Number of threads to use. Defaults to the number of virtual CPUs.
+    #[structopt(long, short = "j")]
+    num_threads: Option<u32>,
+
+    /// If any disagreements are found, a reproduction bundle for each of them
+    /// (TM, settings, both outcomes and the start of the trace) is written to
+    /// this file.
+    #[structopt(long, parse(from_os_str), default_value = "disagreements.txt")]
+    dump: PathBuf,
+
+    /// Number of steps of the trace included in the reproduction bundles.
+    #[structopt(long, default_value = "100")]
+    dump_trace_steps: u64,
+}
+
+/// The outcome of simulating a TM for `--truth-steps` steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Truth {
+    Halted { num_steps: u64, num_ones: u32 },
+    StillRunning,
+}
+
+impl fmt::Display for Truth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Halted { num_steps, num_ones } => {
+                write!(f, "halted after {} steps with {} ones", num_steps, num_ones)
+            }
+            Self::StillRunning => f.write_str("still running"),
+        }
+    }
+}
+
+/// A TM for which the analyzer's result contradicts the ground truth.
+#[derive(Debug, Clone)]
+struct Disagreement {
+    n: u8,
+    id: u64,
+    decided: AnalysisResult,
+    truth: Truth,
+
+    /// The actions of all states in standard notation, e.g. `1RB 1LB 1LA 1RH`.
+    actions: String,
+
+    /// The first `--dump-trace-steps` steps, one line per step.
+    trace: Vec<String>,
+}
+
+pub(crate) fn run(args: Args) -> Result<()> {
+    if args.truth_steps < args.max_steps {
+        return Err(anyhow!("'--truth-steps' has to be at least '--max-steps'"));
+    }
+
+    let manifest = Manifest::new(&[
+        ("max-n", args.max_n.to_string()),
+        ("generator", args.generator.to_string()),
+        ("max-steps", args.max_steps.to_string()),
+        ("truth-steps", args.truth_steps.to_string()),
+        ("halt-convention", args.halt_convention.to_string()),
+        ("lin-recurrence", args.detect_lin_recurrence.to_string()),
+        ("optional-deciders", args.deciders.to_string()),
+    ]);
+
+    bunt::println!(
+        "{$blue+bold}▸ Checking deciders against {} steps of simulation...{/$}",
+        args.truth_steps,
+    );
+    let mut all_disagreements = Vec::new();
+    for n in 1..=args.max_n {
+        let (num_tms, disagreements) = dispatch::with_n(n, Verify(&args))
+            .expect("invalid value for n: argument parsing should catch this")?;
+
+        if disagreements.is_empty() {
+            bunt::println!("- N = {}: {[green+bold]} TMs, no disagreements", n, num_tms);
+        } else {
+            bunt::println!(
+                "- N = {}: {[red+bold]} of {} TMs disagree:",
+                n,
+                disagreements.len(),
+                num_tms,
+            );
+            for d in &disagreements {
+                println!("    TM {}: analyzer: {:?}, simulation: {}", d.id, d.decided, d.truth);
+            }
+        }
+        all_disagreements.extend(disagreements);
+    }
+
+    if !all_disagreements.is_empty() {
+        write_dump(&args, &manifest, &all_disagreements)?;
+        println!();
+        println!("Reproduction bundles were written to '{}'", args.dump.display());
+    }
+
+    println!();
+    manifest.print();
+
+    if !all_disagreements.is_empty() {
+        return Err(anyhow!(
+            "deciders disagree with the ground truth for {} TMs",
+            all_disagreements.len(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Analyzes all TMs with `N` states and returns the number of analyzed TMs and
+/// all disagreements.
+/// Calls `verify` for the right `N`.
+struct Verify<'a>(&'a Args);
+
+impl PerN for Verify<'_> {
+    type Output = Result<(u64, Vec<Disagreement>)>;
+
+    fn call<const N: usize>(self) -> Self::Output
+    where
+        [bool; N]: Default,
+    {
+        verify::<N>(self.0)
+    }
+}
+
+fn verify<const N: usize>(args: &Args) -> Result<(u64, Vec<Disagreement>)> {
+    let disagreements = Arc::new(Mutex::new(Vec::new()));
+    let sweep = {
+        let disagreements = disagreements.clone();
+        let truth_steps = args.truth_steps;
+        let halt_convention = args.halt_convention;
+        SweepBuilder::new(N as u8)
+            .generator(args.generator)
+            .max_steps(args.max_steps)
+            .halt_convention(args.halt_convention)
+            .lin_recurrence(args.detect_lin_recurrence)
+            .optional_deciders(args.deciders)
+            .num_threads(args.num_threads)
+            .on_result(move |_, id, decided| {
+                let tm = <Tm<N>>::from_id(id).expect("generator returned invalid TM");
+                let truth = simulate(tm, truth_steps, halt_convention);
+                if contradicts(decided, truth, halt_convention) {
+                    disagreements.lock().expect("poisened lock").push((id, decided, truth));
+                }
+            })
+    };
+
+    let num_tms = sweep.num_tms();
+    sweep.run()?;
+
+    let mut disagreements = disagreements.lock().expect("poisened lock")
+        .iter()
+        .map(|&(id, decided, truth)| {
+            let tm = <Tm<N>>::from_id(id).expect("generator returned invalid TM");
+            let actions = (0..N as u8)
+                .flat_map(|i| {
+                    let state = tm.state(i);
+                    vec![state.on_0().to_string(), state.on_1().to_string()]
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            Disagreement {
+                n: N as u8,
+                id,
+                decided,
+                truth,
+                actions,
+                trace: trace(tm, args.dump_trace_steps),
+            }
+        })
+        .collect::<Vec<_>>();
+    disagreements.sort_by_key(|d| d.id);
+    Ok((num_tms, disagreements))
+}
+
+/// Returns whether the analyzer's result is impossible given the ground
+/// truth. `truth` has to come from simulating at least as many steps as the
+/// analyzer did.
+fn contradicts(decided: AnalysisResult, truth: Truth, convention: HaltConvention) -> bool {
+    match decided {
+        AnalysisResult::Halted { num_steps, num_ones }
+            | AnalysisResult::ImmediateHalt { num_steps, num_ones }
+            => truth != Truth::Halted { num_steps, num_ones },
+        AnalysisResult::NoHaltTransition
+            | AnalysisResult::SimpleElope
+            | AnalysisResult::HaltStateNotReachable
+            | AnalysisResult::RunAway
+            | AnalysisResult::CycleDetected
+            | AnalysisResult::TranslatedCycler
+            | AnalysisResult::BackwardReasoning
+            | AnalysisResult::ClosedTapeLanguage
+            | AnalysisResult::LinRecurrence
+            => truth != Truth::StillRunning,
+        // The step limit also applies to a halting transition that is not
+        // counted.
+        AnalysisResult::AbortedAfterMaxSteps { num_steps, .. } => {
+            let uncounted = (convention == HaltConvention::Exclude) as u64;
+            matches!(truth, Truth::Halted { num_steps: s, .. } if s + uncounted <= num_steps)
+        }
+
+        // Pruning is not enabled here, but pruned TMs could do anything.
+        // Known TMs are not analyzed at all.
+        AnalysisResult::Pruned { .. } | AnalysisResult::Known => false,
+    }
+}
+
+/// Simulates the TM for at most `max_steps` steps, the slow and simple way.
+fn simulate<const N: usize>(tm: Tm<N>, max_steps: u64, convention: HaltConvention) -> Truth {
+    let mut machine = Machine::new(tm);
+    let mut last_step = None;
+    while machine.state().is_some() && machine.steps() < max_steps {
+        last_step = machine.step();
+    }
+
+    if machine.state().is_some() {
+        return Truth::StillRunning;
+    }
+
+    let tape = machine.tape();
+    let mut num_ones = tape.iter_written().filter(|(_, value)| value.0).count() as u32;
+    let mut num_steps = machine.steps();
+
+    // The machine always executes the halting transition, so we undo it if it
+    // should not be counted.
+    if convention == HaltConvention::Exclude {
+        let step: Step<N> = last_step.expect("halted TM without steps");
+        num_steps -= 1;
+        num_ones = num_ones + step.read.0 as u32 - step.action.write_value().0 as u32;
+    }
+
+    Truth::Halted { num_steps, num_ones }
+}
+
+/// Returns the first `steps` steps of the TM as lines of the form `<step>
+/// <state> <head> <read> <action>`, e.g. `3 B -1 0 1LA`.
+fn trace<const N: usize>(tm: Tm<N>, steps: u64) -> Vec<String> {
+    let mut machine = Machine::new(tm);
+    let mut out = Vec::new();
+    while machine.steps() < steps {
+        let step = match machine.step() {
+            Some(step) => step,
+            None => break,
+        };
+        out.push(format!(
+            "{} {} {} {} {}",
+            machine.steps(),
+            state_name::<N>(step.state),
+            step.head.0,
+            step.read.0 as u8,
+            step.action,
+        ));
+    }
+
+    out
+}
+
+/// Writes the reproduction bundles of all disagreements to the file given by
+/// `--dump`. The file starts with the manifest as `#` comments, followed by
+/// one block of `key: value` lines per disagreement. Blocks are separated by
+/// empty lines.
+fn write_dump(args: &Args, manifest: &Manifest, disagreements: &[Disagreement]) -> Result<()> {
+    let path = &args.dump;
+    let file = File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    let mut w = BufWriter::new(file);
+    manifest.write_comments(&mut w)?;
+
+    for d in disagreements {
+        writeln!(w)?;
+        writeln!(w, "n: {}", d.n)?;
+        writeln!(w, "id: {}", d.id)?;
+        writeln!(w, "actions: {}", d.actions)?;
+        writeln!(w, "max-steps: {}", args.max_steps)?;
+        writeln!(w, "truth-steps: {}", args.truth_steps)?;
+        writeln!(w, "halt-convention: {}", args.halt_convention)?;
+        writeln!(w, "analyzer: {:?}", d.decided)?;
+        writeln!(w, "simulation: {}", d.truth)?;
+        writeln!(
+            w,
+            "replay: beaver single -n {} {} --max-steps {} --halt-convention {} --trace",
+            d.n,
+            d.id,
+            args.truth_steps,
+            args.halt_convention,
+        )?;
+        for line in &d.trace {
+            writeln!(w, "trace: {}", line)?;
+        }
+    }
+
+    w.flush().with_context(|| format!("failed to write '{}'", path.display()))?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use structopt::StructOpt;
+    use super::{Args, verify};
+
+    /// Calls `f` with the arguments of all decider configurations to check.
+    /// With few steps, many TMs reach the limit, so the deciders applied
+    /// there are checked as well. Each optional one on its own, as they
+    /// would shadow each other.
+    fn for_all_configs(mut f: impl FnMut(&Args)) {
+        let optional: &[&[&str]] = &[&[], &["--detect-lin-recurrence"], &["--deciders", "ctl"]];
+        for convention in &["include", "exclude"] {
+            for max_steps in &["200", "8"] {
+                for extra in optional {
+                    let mut cli = vec![
+                        "verify-deciders",
+                        "--halt-convention",
+                        convention,
+                        "--max-steps",
+                        max_steps,
+                    ];
+                    cli.extend_from_slice(extra);
+                    f(&Args::from_iter(&cli));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deciders_agree_with_simulation() {
+        for_all_configs(|args| {
+            assert!(verify::<1>(args).unwrap().1.is_empty());
+            assert!(verify::<2>(args).unwrap().1.is_empty());
+        });
+    }
+
+    /// Like above, but for all TMs with 3 states, as checked by the command.
+    /// Takes several minutes even in release mode.
+    #[test]
+    #[ignore]
+    fn deciders_agree_with_simulation_n3() {
+        for_all_configs(|args| {
+            let disagreements = verify::<3>(args).unwrap().1;
+            assert!(disagreements.is_empty(), "{:?}", disagreements);
+        });
+    }
+}
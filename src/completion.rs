@@ -0,0 +1,304 @@
+//! Searching the completions of a partially specified TM (see `complete`):
+//! TMs that have the fixed transitions and whatever actions make them run
+//! the longest or write the most 1s.
+//!
+//! The search runs the TM until it reaches a transition that is not defined
+//! yet. There, it branches: either the transition halts, which gives a
+//! completion, or it gets one of the non-halting actions and the run
+//! continues. Transitions that are never reached stay free, so each
+//! completion stands for all TMs that differ only in those.
+
+use std::{cmp::Reverse, fmt, str::FromStr, time::Instant};
+
+use crate::{
+    analyze::HaltConvention,
+    budget::Budget,
+    gen::PartialTable,
+    machine::Machine,
+    tm::Tm,
+};
+
+
+/// What completions are ranked by. The other value breaks ties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rank {
+    Steps,
+    Ones,
+}
+
+impl FromStr for Rank {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "steps" => Ok(Self::Steps),
+            "ones" => Ok(Self::Ones),
+            _ => Err("invalid value for 'rank-by'"),
+        }
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Steps => f.write_str("steps"),
+            Self::Ones => f.write_str("ones"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The number of steps after which a completion is considered to not
+    /// halt.
+    pub max_steps: u64,
+    pub halt_convention: HaltConvention,
+
+    /// The budget for the whole search. Steps are counted over all runs.
+    pub budget: Budget,
+
+    /// How many of the best completions are kept.
+    pub num_best: usize,
+    pub rank: Rank,
+}
+
+/// A halting TM in which the transitions in `free` are never used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completion {
+    /// The TM with arbitrary actions for free transitions.
+    pub encoded: u64,
+
+    /// Bit `i` is set if transition `i` (in units of 5 bits of `encoded`) is
+    /// free.
+    pub free: u16,
+    pub steps: u64,
+    pub ones: u32,
+}
+
+impl Completion {
+    /// Formats the TM in the standard notation (see `Tm::from_standard`),
+    /// with `---` for free transitions.
+    pub fn to_standard_notation<const N: usize>(self) -> String {
+        let tm = <Tm<N>>::new_unchecked(self.encoded);
+        (0..N as u8)
+            .map(|state| {
+                let action = |read: u8, action| {
+                    if self.free & (1 << (2 * state + read)) != 0 {
+                        "---".to_string()
+                    } else {
+                        format!("{}", action)
+                    }
+                };
+                let state_actions = tm.state(state);
+                action(0, state_actions.on_0()) + &action(1, state_actions.on_1())
+            })
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    fn rank_key(&self, rank: Rank) -> (u64, u64, Reverse<u64>) {
+        match rank {
+            Rank::Steps => (self.steps, u64::from(self.ones), Reverse(self.encoded)),
+            Rank::Ones => (u64::from(self.ones), self.steps, Reverse(self.encoded)),
+        }
+    }
+}
+
+pub struct SearchResult {
+    /// The best completions, best first.
+    pub best: Vec<Completion>,
+
+    /// The number of halting completions found.
+    pub num_halting: u64,
+
+    /// The number of completions that did not halt within the maximum number
+    /// of steps.
+    pub num_undecided: u64,
+
+    /// The number of partial TMs that were run.
+    pub num_runs: u64,
+
+    /// Whether the budget ran out before all completions were searched.
+    pub budget_exceeded: bool,
+}
+
+/// A partial TM in the search tree: `encoded` with the transitions in `free`
+/// not defined yet.
+#[derive(Clone, Copy)]
+struct Node {
+    encoded: u64,
+    free: u16,
+}
+
+/// Searches the completions of `table` depth-first. States that are not used
+/// by any defined transition yet are interchangeable, so only the first of
+/// them is tried as next state.
+pub fn search<const N: usize>(table: &PartialTable, options: &Options) -> SearchResult {
+    let mut root = Node { encoded: 0, free: (1 << (2 * N)) - 1 };
+    for (slot, action) in table.encoded_actions::<N>() {
+        root.encoded |= action << (5 * slot);
+        root.free &= !(1 << slot);
+    }
+
+    let mut out = SearchResult {
+        best: Vec::new(),
+        num_halting: 0,
+        num_undecided: 0,
+        num_runs: 0,
+        budget_exceeded: false,
+    };
+    let start = Instant::now();
+    let mut spent_steps = 0u64;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let exceeded = match options.budget {
+            Budget::Steps(steps) => spent_steps >= steps,
+            Budget::Time(time) => start.elapsed() >= time,
+        };
+        if exceeded {
+            out.budget_exceeded = true;
+            break;
+        }
+
+        out.num_runs += 1;
+        let mut machine = Machine::new(<Tm<N>>::new_unchecked(node.encoded));
+        let mut last_step = None;
+        let free_slot = loop {
+            let state = match machine.state() {
+                Some(state) => state,
+                None => break None,
+            };
+            let read = machine.tape().get(machine.head());
+            let slot = 2 * state as usize + read.0 as usize;
+            if node.free & (1 << slot) != 0 {
+                break Some((slot, read.0));
+            }
+            if machine.steps() >= options.max_steps {
+                break None;
+            }
+            last_step = machine.step();
+        };
+        spent_steps += machine.steps();
+
+        let ones = machine.tape().iter_written().filter(|(_, value)| value.0).count() as u32;
+        let (slot, read_one) = match free_slot {
+            Some(free) => free,
+
+            // The TM halted on a fixed halt transition or ran out of steps.
+            None if machine.state().is_some() => {
+                out.num_undecided += 1;
+                continue;
+            }
+            None => {
+                let step = last_step.expect("halted TM without steps");
+                let (steps, ones) = match options.halt_convention {
+                    HaltConvention::Include => (machine.steps(), ones),
+                    HaltConvention::Exclude => (
+                        machine.steps() - 1,
+                        ones + step.read.0 as u32 - step.action.write_value().0 as u32,
+                    ),
+                };
+                let completion = Completion { encoded: node.encoded, free: node.free, steps, ones };
+                record(&mut out, completion, options);
+                continue;
+            }
+        };
+
+        // Halt on the free transition (writing 1, the direction doesn't
+        // matter).
+        let free = node.free & !(1 << slot);
+        let offset = 5 * slot;
+        let with_action = |action: u64| node.encoded & !(0b11111 << offset) | action << offset;
+        let (steps, ones) = match options.halt_convention {
+            HaltConvention::Include => (machine.steps() + 1, ones + !read_one as u32),
+            HaltConvention::Exclude => (machine.steps(), ones),
+        };
+        let halting = Completion { encoded: with_action((N as u64) << 2), free, steps, ones };
+        record(&mut out, halting, options);
+
+        // All non-halting actions, with the next state being a used state or
+        // the first unused one.
+        let used = (0..2 * N)
+            .filter(|&s| free & (1 << s) == 0)
+            .flat_map(|s| [s / 2, ((node.encoded >> (5 * s + 2)) & 0b111) as usize])
+            .chain([0, slot / 2])
+            .filter(|&state| state < N)
+            .fold(0u8, |acc, state| acc | 1 << state);
+        let first_unused = (0..N).find(|&state| used & (1 << state) == 0);
+        for next in (0..N).rev().filter(|&s| used & (1 << s) != 0 || Some(s) == first_unused) {
+            for action in (0..4).rev() {
+                stack.push(Node { encoded: with_action((next as u64) << 2 | action), free });
+            }
+        }
+    }
+
+    out
+}
+
+/// Counts the halting completion and keeps it if it is one of the best.
+fn record(out: &mut SearchResult, completion: Completion, options: &Options) {
+    out.num_halting += 1;
+    let key = completion.rank_key(options.rank);
+    if out.best.len() == options.num_best
+        && out.best.last().is_some_and(|worst| worst.rank_key(options.rank) >= key)
+    {
+        return;
+    }
+
+    let pos = out.best.partition_point(|c| c.rank_key(options.rank) > key);
+    out.best.insert(pos, completion);
+    out.best.truncate(options.num_best);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_champions() {
+        let options = |rank| Options {
+            max_steps: 200,
+            halt_convention: HaltConvention::Include,
+            budget: Budget::Steps(u64::MAX),
+            num_best: 3,
+            rank,
+        };
+
+        // Starting only from the first transition, the search finds the
+        // champions of BB(2) and BB(3).
+        let table = "A0=1RB".parse::<PartialTable>().unwrap();
+        let result = search::<2>(&table, &options(Rank::Steps));
+        assert!(!result.budget_exceeded);
+        assert_eq!((result.best[0].steps, result.best[0].ones), (6, 4));
+
+        let result = search::<3>(&table, &options(Rank::Steps));
+        assert_eq!(result.best[0].steps, 21);
+        let result = search::<3>(&table, &options(Rank::Ones));
+        assert_eq!(result.best[0].ones, 6);
+
+        // BB(2) with one transition left free.
+        let table = "A0=1RB,A1=1LB,B0=1LA".parse::<PartialTable>().unwrap();
+        let result = search::<2>(&table, &options(Rank::Steps));
+        assert_eq!(result.best[0].to_standard_notation::<2>(), "1RB1LB_1LA1LH");
+        assert_eq!(result.num_halting, 1);
+
+        let options = Options { budget: Budget::Steps(50), ..options(Rank::Steps) };
+        assert!(search::<3>(&table, &options).budget_exceeded);
+    }
+
+    #[test]
+    fn free_transitions() {
+        let table = "A0=1RB,B0=1LH".parse::<PartialTable>().unwrap();
+        let result = search::<2>(&table, &Options {
+            max_steps: 100,
+            halt_convention: HaltConvention::Include,
+            budget: Budget::Steps(u64::MAX),
+            num_best: 10,
+            rank: Rank::Steps,
+        });
+        assert_eq!(result.num_halting, 1);
+        assert_eq!(result.best[0].to_standard_notation::<2>(), "1RB---_1LH---");
+        assert_eq!((result.best[0].steps, result.best[0].ones), (2, 2));
+    }
+}
@@ -0,0 +1,337 @@
+//! Continuation records: the exact configuration of a TM that was aborted after
+//! the maximum number of steps. These can be written to a file and later be
+//! used to continue running those TMs (with a larger step limit) instead of
+//! starting from scratch.
+//!
+//! The file format is line based. The first line is `n <N>`, every following
+//! line is one record:
+//!
+//! ```text
+//! <id> <state> <steps> <head> <tape start>:<run lengths>
+//! ```
+//!
+//! The tape is stored run-length encoded: the comma separated run lengths
+//! alternate between runs of 0s and runs of 1s, starting with 0s. Lines
+//! starting with `#` are ignored.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::{
+    manifest::Manifest,
+    outcome::{Configuration, OutcomeSink},
+    tape::{CellId, CellValue, Tape, TapeLike},
+    tm::Tm,
+};
+
+
+/// The configuration of one aborted TM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continuation {
+    /// The ID of the TM.
+    pub id: u64,
+
+    /// The state the TM was in when it was stopped.
+    pub state: u8,
+
+    /// The number of steps that the TM already executed.
+    pub steps: u64,
+
+    /// The cell the head was on.
+    pub head: CellId,
+
+    /// The first cell of the written range of the tape.
+    pub tape_start: CellId,
+
+    /// The written range of the tape, run-length encoded. The runs alternate
+    /// between 0s and 1s, starting with 0s (so the first run might have length
+    /// 0).
+    pub tape_runs: Vec<u32>,
+}
+
+impl Continuation {
+    /// Creates a record from the configuration of a running TM.
+    pub fn new<const N: usize>(tm: Tm<N>, config: Configuration<'_, impl TapeLike>) -> Self {
+        Self::with_buffer(tm, config, Vec::new())
+    }
+
+    /// Like `new`, but stores the tape in `buffer` (e.g. taken from a
+    /// `BufferPool`) instead of allocating.
+    pub fn with_buffer<const N: usize>(
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+        mut tape_runs: Vec<u32>,
+    ) -> Self {
+        let range = config.tape.written_range();
+        // The first run always consists of 0s, so it's empty if the tape
+        // starts with a 1.
+        tape_runs.clear();
+        tape_runs.push(0);
+        for (i, (value, cells)) in config.tape.runs().enumerate() {
+            let len = (cells.end.0 - cells.start.0) as u32;
+            if i == 0 && !value.0 {
+                tape_runs[0] = len;
+            } else {
+                tape_runs.push(len);
+            }
+        }
+
+        Self {
+            id: tm.encoded,
+            state: config.state,
+            steps: config.steps,
+            head: config.head,
+            tape_start: range.start,
+            tape_runs,
+        }
+    }
+
+    /// Clears the given tape and writes the stored tape contents to it.
+    pub fn restore_tape(&self, tape: &mut Tape) {
+        tape.clear();
+
+        let mut id = self.tape_start.0;
+        let mut value = CellValue(false);
+        for &len in &self.tape_runs {
+            for _ in 0..len {
+                tape.write(CellId(id), value);
+                id += 1;
+            }
+            value = CellValue(!value.0);
+        }
+    }
+}
+
+impl fmt::Display for Continuation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}:",
+            self.id,
+            self.state,
+            self.steps,
+            self.head.0,
+            self.tape_start.0,
+        )?;
+
+        for (i, len) in self.tape_runs.iter().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", len)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Continuation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            bail!("expected 5 fields, found {}", fields.len());
+        }
+
+        let (tape_start, runs) = fields[4].split_once(':')
+            .ok_or(anyhow!("tape field is missing ':'"))?;
+        let tape_runs = if runs.is_empty() {
+            vec![]
+        } else {
+            runs.split(',').map(|r| r.parse()).collect::<Result<_, _>>()
+                .context("invalid run length")?
+        };
+
+        Ok(Self {
+            id: fields[0].parse().context("invalid ID")?,
+            state: fields[1].parse().context("invalid state")?,
+            steps: fields[2].parse().context("invalid step count")?,
+            head: CellId(fields[3].parse().context("invalid head position")?),
+            tape_start: CellId(tape_start.parse().context("invalid tape start")?),
+            tape_runs,
+        })
+    }
+}
+
+/// Reads a file with continuation records. Returns `N` and all records.
+pub fn read_file(path: &Path) -> Result<(u8, Vec<Continuation>)> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open '{}'", path.display()))?;
+    read(BufReader::new(file))
+        .with_context(|| format!("failed to read '{}'", path.display()))
+}
+
+/// Reads continuation records, see `read_file`.
+fn read(r: impl BufRead) -> Result<(u8, Vec<Continuation>)> {
+    let mut n = None;
+    let mut records = Vec::new();
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match n {
+            None => {
+                let v = line.strip_prefix("n ")
+                    .ok_or(anyhow!("expected 'n <N>' as first line"))?;
+                n = Some(v.trim().parse().context("invalid value for N")?);
+            }
+            Some(n) => {
+                let record: Continuation = line.parse()
+                    .with_context(|| format!("invalid record in line {}", i + 1))?;
+                if record.state >= n {
+                    bail!(
+                        "invalid record in line {}: state {} is out of range for N = {}",
+                        i + 1,
+                        record.state,
+                        n,
+                    );
+                }
+                records.push(record);
+            }
+        }
+    }
+
+    let n = n.ok_or(anyhow!("file is empty"))?;
+    Ok((n, records))
+}
+
+/// A file that continuation records can be written to from multiple threads.
+#[derive(Clone)]
+pub struct ContinuationFile(Arc<Mutex<BufWriter<File>>>);
+
+impl ContinuationFile {
+    /// Creates the file (truncating it if it exists) and writes the header
+    /// followed by the manifest as comments.
+    pub fn create(path: &Path, n: u8, manifest: &Manifest) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut w = BufWriter::new(file);
+        writeln!(w, "n {}", n)?;
+        manifest.write_comments(&mut w)?;
+        Ok(Self(Arc::new(Mutex::new(w))))
+    }
+
+    /// Writes all given records.
+    pub fn write(&self, records: &[Continuation]) -> Result<()> {
+        let mut w = self.0.lock().expect("poisened lock");
+        for record in records {
+            writeln!(w, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes all buffered records to the file.
+    pub fn flush(&self) -> Result<()> {
+        self.0.lock().expect("poisened lock").flush()?;
+        Ok(())
+    }
+}
+
+/// A sink that collects continuation records of all aborted TMs and writes
+/// them to a `ContinuationFile` whenever `flush` is called.
+pub struct ContinuationSink {
+    file: ContinuationFile,
+    buffer: Vec<Continuation>,
+}
+
+impl ContinuationSink {
+    pub fn new(file: ContinuationFile) -> Self {
+        Self {
+            file,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes all records collected so far to the file.
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.write(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<const N: usize> OutcomeSink<N> for ContinuationSink {
+    fn report_halted(&mut self, _: Tm<N>, _: u64, _: u32, _: &impl TapeLike) {}
+    fn report_immediate_halt(&mut self, _: Tm<N>, _: u64, _: u32) {}
+    fn report_no_halt_transition(&mut self, _: Tm<N>) {}
+    fn report_simple_elope(&mut self, _: Tm<N>) {}
+    fn report_halt_state_not_reachable(&mut self, _: Tm<N>) {}
+    fn report_run_away(&mut self, _: Tm<N>) {}
+    fn report_cycle(&mut self, _: Tm<N>) {}
+    fn report_translated_cycler(&mut self, _: Tm<N>) {}
+    fn report_backward_reasoning(&mut self, _: Tm<N>) {}
+    fn report_ctl(&mut self, _: Tm<N>) {}
+    fn report_lin_recurrence(&mut self, _: Tm<N>) {}
+
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    ) {
+        self.buffer.push(Continuation::new(tm, config));
+    }
+
+    // Pruned TMs are not interesting anymore, so they are not continued.
+    fn report_pruned(&mut self, _: Tm<N>, _: u64) {}
+    fn report_known(&mut self, _: Tm<N>) {}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Continuation, read};
+    use crate::{outcome::Configuration, tape::{CellId, CellValue, Tape}, tm::Tm};
+
+
+    #[test]
+    fn roundtrip() {
+        let mut tape = Tape::new();
+        for &i in &[-3, -2, 1, 4, 5, 6] {
+            tape.write(CellId(i), CellValue(true));
+        }
+        tape.write(CellId(7), CellValue(false));
+
+        let config = Configuration {
+            state: 2,
+            head: CellId(3),
+            steps: 1234,
+            tape: &tape,
+        };
+        let record = Continuation::new(<Tm<3>>::new_unchecked(0b10110_01001), config);
+        assert_eq!(record.tape_start, CellId(-3));
+        assert_eq!(record.tape_runs, [0, 2, 2, 1, 2, 3, 1]);
+
+        let line = record.to_string();
+        assert_eq!(line, "713 2 1234 3 -3:0,2,2,1,2,3,1");
+        assert_eq!(line.parse::<Continuation>().unwrap(), record);
+
+        let mut restored = Tape::new();
+        record.restore_tape(&mut restored);
+        assert_eq!(restored.written_range(), tape.written_range());
+        for i in -10..10 {
+            assert_eq!(restored.get(CellId(i)), tape.get(CellId(i)), "at cell {}", i);
+        }
+    }
+
+    #[test]
+    fn read_checks_state() {
+        let (n, records) = read("n 2\n# comment\n13 1 40 2 0:1,3\n".as_bytes()).unwrap();
+        assert_eq!(n, 2);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].state, 1);
+
+        let err = read("n 2\n13 1 40 2 0:1,3\n13 2 40 2 0:1,3\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+    }
+}
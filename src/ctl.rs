@@ -0,0 +1,139 @@
+//! A closed tape language (CTL) decider: it constructs a set of
+//! configurations that contains the start configuration, is closed under the
+//! step relation of the TM and does not contain a configuration in which the
+//! TM halts. The TM is then in that set forever, so it never halts.
+//!
+//! The set is a regular language described by n-grams (the "n-gram closed
+//! position set"): a configuration is in the set if the `2n + 1` cells
+//! around the head together with the state are one of the known windows and
+//! every `n` consecutive cells left (right) of the head are one of the known
+//! left (right) n-grams. Windows and n-grams are added until nothing changes
+//! anymore, which always happens as there are only finitely many.
+//!
+//! The search can be limited by a `Budget`, in which a step is computing the
+//! successors of one window.
+
+use crate::{
+    budget::{Budget, Meter},
+    tape::CellValue,
+    tm::{Move, NextState, Tm},
+};
+
+
+/// The largest `n` that is tried.
+const MAX_N: u32 = 4;
+
+/// Returns `true` if a CTL as described above was found for some `n` up to
+/// `MAX_N`, i.e. if the TM provably never halts. Gives up (returns `false`)
+/// once `budget` is exhausted.
+pub fn decide<const N: usize>(tm: Tm<N>, budget: Option<Budget>) -> bool {
+    let mut meter = Meter::start(budget);
+    for n in 1..=MAX_N {
+        match decide_with::<N>(tm, n, &mut meter) {
+            Some(true) => return true,
+            Some(false) => {}
+            None => return false,
+        }
+    }
+
+    false
+}
+
+/// Tries to find the CTL with n-grams of length `n`. Windows and n-grams are
+/// stored as bits, with the leftmost cell in the least significant bit.
+/// Returns `None` if the budget of `meter` is exhausted.
+fn decide_with<const N: usize>(tm: Tm<N>, n: u32, meter: &mut Meter) -> Option<bool> {
+    let window_mask = (1u32 << (2 * n + 1)) - 1;
+    let windows_per_state = 1usize << (2 * n + 1);
+    let mut windows = vec![false; N * windows_per_state];
+    let mut left = vec![false; 1 << n];
+    let mut right = vec![false; 1 << n];
+
+    // The start configuration: state A on the empty tape.
+    windows[0] = true;
+    left[0] = true;
+    right[0] = true;
+
+    // Adding a n-gram can give new successors of windows that were already
+    // visited, so we repeat until nothing changes.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..windows.len() {
+            if !windows[index] {
+                continue;
+            }
+            if !meter.step() {
+                return None;
+            }
+
+            let state = (index / windows_per_state) as u8;
+            let window = (index % windows_per_state) as u32;
+            let read = (window >> n) & 1 == 1;
+            let action = tm.state(state).action_for(CellValue(read));
+            let next_state = match action.next_state() {
+                NextState::HaltState => return Some(false),
+                NextState::State(s) => s as usize,
+            };
+            let written = window & !(1 << n) | (action.write_value().0 as u32) << n;
+
+            let mut add = |vec: &mut Vec<bool>, i: usize| {
+                if !vec[i] {
+                    vec[i] = true;
+                    changed = true;
+                }
+            };
+            match action.movement() {
+                Move::Right => {
+                    // The left half now ends with the cell just written. The
+                    // new rightmost cell of the window is any that can follow
+                    // the rest of the right half.
+                    add(&mut left, ((written >> 1) & ((1 << n) - 1)) as usize);
+                    let rest = written >> (n + 2);
+                    for x in 0..2 {
+                        if right[(rest | x << (n - 1)) as usize] {
+                            let next = written >> 1 | x << (2 * n);
+                            add(&mut windows, next_state * windows_per_state + next as usize);
+                        }
+                    }
+                }
+                Move::Left => {
+                    add(&mut right, ((written >> n) & ((1 << n) - 1)) as usize);
+                    let rest = written & ((1 << (n - 1)) - 1);
+                    for x in 0..2 {
+                        if left[(x | rest << 1) as usize] {
+                            let next = (written << 1) & window_mask | x;
+                            add(&mut windows, next_state * windows_per_state + next as usize);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Some(true)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decides() {
+        // 1RA1RH: runs away to the right, writing 1s.
+        assert!(decide(<Tm<1>>::from_standard("1RA1RH").unwrap(), None));
+
+        // The BB(2) champion halts, so no CTL can exist.
+        assert!(!decide(<Tm<2>>::new_unchecked(327814), None));
+    }
+
+    #[test]
+    fn budget() {
+        // The start window and the one after the first step have to be
+        // visited at least.
+        let tm = <Tm<1>>::from_standard("1RA1RH").unwrap();
+        assert!(decide(tm, Some(Budget::Steps(100))));
+        assert!(!decide(tm, Some(Budget::Steps(1))));
+    }
+}
@@ -0,0 +1,35 @@
+//! Calling code that is generic over the number of states `N` with an `n`
+//! only known at runtime. This is the only place that lists the supported
+//! values: every entry point implementing `PerN` is monomorphized once for
+//! each of them, so supporting another `N` only requires changing `with_n`
+//! and `SUPPORTED`.
+
+use std::ops::RangeInclusive;
+
+
+/// The supported numbers of states.
+pub const SUPPORTED: RangeInclusive<u8> = 1..=6;
+
+/// An entry point that is generic over the number of states, usually a
+/// struct holding the arguments.
+pub trait PerN {
+    type Output;
+
+    fn call<const N: usize>(self) -> Self::Output
+    where
+        [bool; N]: Default;
+}
+
+/// Calls `f` with `N = n`. Returns `None` if `n` is not in `SUPPORTED`, so
+/// that callers can report that in the way fitting for where `n` came from.
+pub fn with_n<F: PerN>(n: u8, f: F) -> Option<F::Output> {
+    match n {
+        1 => Some(f.call::<1>()),
+        2 => Some(f.call::<2>()),
+        3 => Some(f.call::<3>()),
+        4 => Some(f.call::<4>()),
+        5 => Some(f.call::<5>()),
+        6 => Some(f.call::<6>()),
+        _ => None,
+    }
+}
@@ -0,0 +1,148 @@
+//! Notable events of a TM run with the step they happened in, e.g. for
+//! analyzing long runs with external tools without storing a full trace.
+
+use std::fmt;
+
+use crate::{
+    machine::Machine,
+    tm::{Move, Tm, state_name},
+};
+
+
+/// Something that happened during step `step` (starting at 1) on `cell`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    pub step: u64,
+    pub cell: i64,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// The TM went to a different state. The halt state is `N`. `cell` is
+    /// the cell the transition was executed on.
+    StateChange { from: u8, to: u8 },
+
+    /// The head moved in the other direction than in the previous step.
+    /// `cell` is the cell it moved to.
+    Reversal(Move),
+
+    /// The head moved further left (or right) than ever before. `cell` is the
+    /// new cell.
+    NewLeftmost,
+    NewRightmost,
+
+    /// A 1 was written to `cell`, even if it already contained a 1.
+    WroteOne,
+}
+
+impl EventKind {
+    /// The name used in files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StateChange { .. } => "state-change",
+            Self::Reversal(_) => "reversal",
+            Self::NewLeftmost => "new-leftmost",
+            Self::NewRightmost => "new-rightmost",
+            Self::WroteOne => "wrote-one",
+        }
+    }
+}
+
+/// Formats the details of an event for the TM with `N` states: the states
+/// for state changes (e.g. `A->B`) and the new direction for reversals.
+/// Other events have no details.
+pub struct Detail<const N: usize>(pub EventKind);
+
+impl<const N: usize> fmt::Display for Detail<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            EventKind::StateChange { from, to } => {
+                write!(f, "{}->{}", state_name::<N>(from), state_name::<N>(to))
+            }
+            EventKind::Reversal(Move::Left) => f.write_str("L"),
+            EventKind::Reversal(Move::Right) => f.write_str("R"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Runs the TM for at most `max_steps` steps (or until it halts) and calls
+/// `f` for each event, in the order of the steps. Events of the same step
+/// are reported in the order of `EventKind`. Returns the number of executed
+/// steps.
+pub fn record<const N: usize>(tm: Tm<N>, max_steps: u64, mut f: impl FnMut(Event)) -> u64 {
+    let mut machine = Machine::new(tm);
+    let (mut leftmost, mut rightmost) = (0, 0);
+    let mut direction = None;
+    while machine.steps() < max_steps {
+        let step = match machine.step() {
+            Some(step) => step,
+            None => break,
+        };
+        let event = |cell, kind| Event { step: machine.steps(), cell, kind };
+
+        let to = machine.state().unwrap_or(N as u8);
+        if to != step.state {
+            f(event(step.head.0, EventKind::StateChange { from: step.state, to }));
+        }
+
+        // The head does not move on the halting transition.
+        let head = machine.head().0;
+        if head != step.head.0 {
+            let movement = if head < step.head.0 { Move::Left } else { Move::Right };
+            if direction.is_some_and(|d| d != movement) {
+                f(event(head, EventKind::Reversal(movement)));
+            }
+            direction = Some(movement);
+        }
+        if head < leftmost {
+            leftmost = head;
+            f(event(head, EventKind::NewLeftmost));
+        }
+        if head > rightmost {
+            rightmost = head;
+            f(event(head, EventKind::NewRightmost));
+        }
+
+        if step.action.write_value().0 {
+            f(event(step.head.0, EventKind::WroteOne));
+        }
+    }
+
+    machine.steps()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_champion() {
+        // The BB(2) champion: 1RB1LB_1LA1RH.
+        let tm = <Tm<2>>::new_unchecked(327814);
+        let mut events = Vec::new();
+        assert_eq!(record(tm, 100, |e| events.push(e)), 6);
+
+        let kinds = |step| events.iter()
+            .filter(|e| e.step == step)
+            .map(|e| (e.cell, e.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(kinds(1), [
+            (0, EventKind::StateChange { from: 0, to: 1 }),
+            (1, EventKind::NewRightmost),
+            (0, EventKind::WroteOne),
+        ]);
+        assert_eq!(kinds(2), [
+            (1, EventKind::StateChange { from: 1, to: 0 }),
+            (0, EventKind::Reversal(Move::Left)),
+            (1, EventKind::WroteOne),
+        ]);
+        assert_eq!(kinds(6), [
+            (-1, EventKind::StateChange { from: 1, to: 2 }),
+            (-1, EventKind::WroteOne),
+        ]);
+        assert_eq!(Detail::<2>(kinds(6)[0].1).to_string(), "B->H");
+    }
+}
@@ -0,0 +1,147 @@
+//! Files listing the TMs whose analysis panicked during a sweep (see `full
+//! --failed-out`), so that they can be analyzed again with `replay-failed`
+//! once the bug is found.
+//!
+//! The file starts with the header `beaver-failed 5`, followed by the
+//! manifest as comments and the settings of the sweep as `<key> <value>`
+//! lines. Then there is one line `failed <index> <id> <message>` per TM.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use anyhow::{Context, Result, anyhow};
+
+use crate::{checkpoint::Settings, manifest::Manifest};
+
+
+const HEADER: &str = "beaver-failed 5";
+
+/// A TM whose analysis panicked.
+#[derive(Debug, Clone)]
+pub struct Failed {
+    /// The index of the TM in the generator.
+    pub index: u64,
+    pub id: u64,
+    pub message: String,
+}
+
+/// A file to which failed TMs are written while the sweep is running.
+pub struct FailedFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+
+    /// The first error while writing, returned by `finish`.
+    error: Option<anyhow::Error>,
+}
+
+impl FailedFile {
+    /// Creates the file and writes the header, manifest and settings.
+    pub fn create(path: &Path, settings: &Settings, manifest: &Manifest) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", HEADER)?;
+        manifest.write_comments(&mut writer)?;
+        settings.write(&mut writer)?;
+        writer.flush()?;
+
+        Ok(Self { writer, path: path.to_owned(), error: None })
+    }
+
+    /// Appends a failed TM. The file is flushed immediately, so that the
+    /// entry is not lost if the sweep is killed later. Errors are returned
+    /// by `finish`, as this is called from the worker threads.
+    pub fn record(&mut self, failed: &Failed) {
+        let message = failed.message.replace('\n', " ");
+        let res = writeln!(self.writer, "failed {} {} {}", failed.index, failed.id, message)
+            .and_then(|_| self.writer.flush());
+        if let Err(e) = res {
+            let e = anyhow::Error::from(e)
+                .context(format!("failed to write '{}'", self.path.display()));
+            self.error.get_or_insert(e);
+        }
+    }
+
+    /// Returns the first error that occured while writing, if any.
+    pub fn finish(self) -> Result<()> {
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+/// Reads a file written by `FailedFile`.
+pub fn read(path: &Path) -> Result<(Settings, Vec<Failed>)> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    parse(&content).with_context(|| format!("failed to parse '{}'", path.display()))
+}
+
+fn parse(s: &str) -> Result<(Settings, Vec<Failed>)> {
+    let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+    if lines.next() != Some(HEADER) {
+        return Err(anyhow!("missing header '{}'", HEADER));
+    }
+
+    let mut values = Vec::new();
+    let mut tms = Vec::new();
+    for line in lines {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key != "failed" {
+            values.push((key, value));
+            continue;
+        }
+
+        let mut parts = value.splitn(3, ' ');
+        let mut next = || parts.next().ok_or_else(|| anyhow!("invalid line '{}'", line));
+        tms.push(Failed {
+            index: next()?.parse()?,
+            id: next()?.parse()?,
+            message: next().unwrap_or("").to_owned(),
+        });
+    }
+
+    let settings = Settings::read(|key| {
+        values.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| anyhow!("missing '{}'", key))
+    })?;
+    Ok((settings, tms))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use crate::{manifest::Manifest, sweep::SweepBuilder};
+    use super::{Failed, FailedFile, parse, read};
+
+    #[test]
+    fn round_trip() {
+        let path = env::temp_dir().join(format!("beaver-failed-test-{}", process::id()));
+        let settings = SweepBuilder::new(3).max_steps(1234).settings();
+        let failed = [
+            Failed { index: 17, id: 0x1234, message: "index out of bounds".into() },
+            Failed { index: 5, id: 99, message: "two\nlines".into() },
+        ];
+
+        let mut file = FailedFile::create(&path, &settings, &Manifest::new(&[])).unwrap();
+        for f in &failed {
+            file.record(f);
+        }
+        file.finish().unwrap();
+        let (read_settings, read_failed) = read(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_settings, settings);
+        let entries = read_failed.iter()
+            .map(|f| (f.index, f.id, f.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [(17, 0x1234, "index out of bounds"), (5, 99, "two lines")]);
+
+        assert!(parse(&content.replacen("beaver-failed", "beaver-checkpoint", 1)).is_err());
+        assert!(parse(&content.replacen("failed 5 99", "failed 5", 1)).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::tm::{STATE_NAMES, Tm};
+
+use super::{Cursor, Opt, TmGenerator};
+
+
+/// Some transitions of a TM with fixed actions, like `A0=1RB,A1=1LH`. Each
+/// transition is named by the state and the read symbol, the action is given
+/// in standard notation (see `Tm::from_standard`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialTable {
+    /// Sorted by state and read symbol, without duplicates.
+    transitions: Vec<FixedTransition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FixedTransition {
+    state: u8,
+    read: u8,
+    write: bool,
+    right: bool,
+
+    /// `None` is the halt state.
+    next: Option<u8>,
+}
+
+impl FixedTransition {
+    /// The index of the transition in the encoded TM, in units of 5 bits.
+    fn slot(&self) -> usize {
+        2 * self.state as usize + self.read as usize
+    }
+
+    /// The 5 bit encoding of the action (see `tm::Action`).
+    fn encoded_action<const N: usize>(&self) -> u64 {
+        let next = self.next.unwrap_or(N as u8) as u64;
+        (!self.write as u64) | (self.right as u64) << 1 | next << 2
+    }
+}
+
+impl PartialTable {
+    /// Returns the index (in units of 5 bits, see `Tm::encoded`) and the
+    /// 5 bit encoded action of each fixed transition, for TMs with `N`
+    /// states.
+    pub fn encoded_actions<const N: usize>(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.transitions.iter().map(|t| (t.slot(), t.encoded_action::<N>()))
+    }
+
+    /// Checks that the table fits TMs with `n` states and the TMs `opt`
+    /// generates: the generators that skip symmetries only generate halt
+    /// transitions moving left, and `Opt::AlsoSkipHaltZero` only ones writing
+    /// 1.
+    pub fn check(&self, n: u8, opt: &Opt) -> Result<(), String> {
+        for t in &self.transitions {
+            let name = STATE_NAMES[t.state as usize];
+            if t.state >= n || t.next.is_some_and(|next| next >= n) {
+                return Err(format!(
+                    "the fixed transition of {}{} refers to a state that TMs with {} \
+                        states don't have",
+                    name,
+                    t.read,
+                    n,
+                ));
+            }
+            if t.next.is_none() && t.right && *opt != Opt::None {
+                return Err(format!(
+                    "the generator only generates halt transitions moving left, but {}{} \
+                        moves right (the direction does not matter, use 'L')",
+                    name,
+                    t.read,
+                ));
+            }
+            if t.next.is_none() && !t.write && *opt == Opt::AlsoSkipHaltZero {
+                return Err(format!(
+                    "the generator 'optimized' does not generate halt transitions writing \
+                        0, but {}{} does (use another generator)",
+                    name,
+                    t.read,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for PartialTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut transitions = Vec::new();
+        for part in s.split(',') {
+            let invalid = || format!("invalid fixed transition '{}', expected e.g. 'A0=1RB'", part);
+            let (key, action) = part.trim().split_once('=').ok_or_else(invalid)?;
+            let state_index = |c| STATE_NAMES.iter().position(|&name| name == c);
+
+            let (state, read) = match *key.chars().collect::<Vec<_>>() {
+                [state, read @ ('0' | '1')] => (state_index(state), read as u8 - b'0'),
+                _ => return Err(invalid()),
+            };
+            let (write, right, next) = match *action.chars().collect::<Vec<_>>() {
+                [write @ ('0' | '1'), movement @ ('L' | 'R'), next] => {
+                    let next = match next {
+                        'H' | 'Z' => None,
+                        c => Some(state_index(c).ok_or_else(invalid)? as u8),
+                    };
+                    (write == '1', movement == 'R', next)
+                }
+                _ => return Err(invalid()),
+            };
+
+            let state = state.ok_or_else(invalid)? as u8;
+            transitions.push(FixedTransition { state, read, write, right, next });
+        }
+
+        transitions.sort_by_key(|t| t.slot());
+        for pair in transitions.windows(2) {
+            if pair[0].slot() == pair[1].slot() {
+                return Err(format!(
+                    "the transition {}{} is fixed multiple times",
+                    STATE_NAMES[pair[0].state as usize],
+                    pair[0].read,
+                ));
+            }
+        }
+
+        Ok(Self { transitions })
+    }
+}
+
+/// Formats the table like it is parsed, sorted by state and read symbol.
+impl fmt::Display for PartialTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, t) in self.transitions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(
+                f,
+                "{}{}={}{}{}",
+                STATE_NAMES[t.state as usize],
+                t.read,
+                if t.write { '1' } else { '0' },
+                if t.right { 'R' } else { 'L' },
+                t.next.map_or('H', |next| STATE_NAMES[next as usize]),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates only the TMs of another generator (given by its `Opt`) whose
+/// transitions match a `PartialTable`. Only the other transitions are
+/// enumerated, so sweeping such a sub-space is fast even for large N.
+///
+/// The order is like the one of `Gen`: the first transition (`A0`) changes
+/// fastest. Symmetric TMs (see `Opt::SkipSymmetries`) are only skipped if the
+/// table doesn't fix the head movement of any non-halting transition and the
+/// transition `on_1` of the last state is not fixed, as the mirrored TMs
+/// don't match the table otherwise.
+pub struct Fixed<const N: usize> {
+    /// For each transition, all actions (5 bit encoded) that are enumerated.
+    /// This is a single action for fixed transitions.
+    actions: Arc<[Vec<u64>]>,
+}
+
+impl<const N: usize> Fixed<N> {
+    /// Creates the generator. The table has to be valid for `N` and `opt` (see
+    /// `PartialTable::check`).
+    pub fn new(table: &PartialTable, opt: Opt) -> Self {
+        debug_assert!(table.check(N as u8, &opt).is_ok());
+
+        let last_slot = 2 * N - 1;
+        let skip_mirrored = opt != Opt::None
+            && table.transitions.iter().all(|t| t.next.is_none() && t.slot() != last_slot);
+
+        let actions: Vec<_> = (0..2 * N)
+            .map(|slot| {
+                if let Some(t) = table.transitions.iter().find(|t| t.slot() == slot) {
+                    return vec![t.encoded_action::<N>()];
+                }
+
+                // See `Gen::num_possible_actions`: the excluded halt actions
+                // are the last ones.
+                let num_actions = match opt {
+                    Opt::None => 4 * (N as u64 + 1),
+                    Opt::SkipSymmetries => 4 * (N as u64 + 1) - 2,
+                    Opt::AlsoSkipHaltZero => 4 * (N as u64 + 1) - 3,
+                };
+                (0..num_actions)
+                    .filter(|action| !(skip_mirrored && slot == last_slot && action & 0b10 != 0))
+                    .collect()
+            })
+            .collect();
+
+        Self { actions: actions.into() }
+    }
+}
+
+impl<const N: usize> TmGenerator<N> for Fixed<N> {
+    type Cursor = FixedCursor<N>;
+
+    fn description(&self) -> &'static str {
+        "TMs with a fixed partial transition table"
+    }
+
+    fn num_tms(&self) -> u64 {
+        self.actions.iter().map(|actions| actions.len() as u64).product()
+    }
+
+    fn tm_at(&self, index: u64) -> Tm<N> {
+        assert!(index < self.num_tms());
+        self.cursor_at(index).next_tm()
+    }
+
+    fn index_of(&self, tm: Tm<N>) -> Option<u64> {
+        let mut index = 0;
+        for (slot, actions) in self.actions.iter().enumerate().rev() {
+            let action = (tm.encoded >> (5 * slot)) & 0b11111;
+            let digit = actions.iter().position(|&a| a == action)?;
+            index = index * actions.len() as u64 + digit as u64;
+        }
+
+        (tm.encoded >> (10 * N) == 0).then_some(index)
+    }
+
+    fn cursor_at(&self, index: u64) -> Self::Cursor {
+        let mut cursor = FixedCursor {
+            actions: self.actions.clone(),
+            index,
+            digits: [0; 12],
+            current: 0,
+        };
+        cursor.seek(index);
+        cursor
+    }
+}
+
+/// Cursor of `Fixed`: the index of the action of each transition, which are
+/// incremented like the digits of a number, and the encoded TM.
+#[derive(Clone)]
+pub struct FixedCursor<const N: usize> {
+    actions: Arc<[Vec<u64>]>,
+    index: u64,
+    digits: [usize; 12],
+    current: u64,
+}
+
+impl<const N: usize> FixedCursor<N> {
+    fn seek(&mut self, mut index: u64) {
+        self.index = index;
+        for (digit, actions) in self.digits.iter_mut().zip(&*self.actions) {
+            *digit = (index % actions.len() as u64) as usize;
+            index /= actions.len() as u64;
+        }
+        self.current = self.actions.iter()
+            .zip(&self.digits)
+            .enumerate()
+            .map(|(slot, (actions, &digit))| actions[digit] << (5 * slot))
+            .fold(0, |acc, action| acc | action);
+    }
+}
+
+impl<const N: usize> Cursor<N> for FixedCursor<N> {
+    fn next_tm(&mut self) -> Tm<N> {
+        let out = Tm::new_unchecked(self.current);
+        self.index += 1;
+
+        // Increment the first digit and carry over to the next ones.
+        for (slot, actions) in self.actions.iter().enumerate() {
+            let offset = 5 * slot;
+            self.current &= !(0b11111 << offset);
+            self.digits[slot] += 1;
+            if self.digits[slot] == actions.len() {
+                self.digits[slot] = 0;
+                self.current |= actions[0] << offset;
+            } else {
+                self.current |= actions[self.digits[slot]] << offset;
+                break;
+            }
+        }
+
+        out
+    }
+
+    fn skip(&mut self, n: u64) {
+        self.seek(self.index + n);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::gen::{All, Opt, Optimized, TmGenerator};
+    use super::{Fixed, PartialTable};
+
+    #[test]
+    fn parse() {
+        let table = "A1=1LH, A0=1RB".parse::<PartialTable>().unwrap();
+        assert_eq!(table.to_string(), "A0=1RB,A1=1LH");
+        assert_eq!("B0=0LZ".parse::<PartialTable>().unwrap().to_string(), "B0=0LH");
+
+        for invalid in &["", "A0", "A2=1RB", "A0=1RX", "A0=2RB", "A0=1RB,A0=1LB"] {
+            assert!(invalid.parse::<PartialTable>().is_err(), "{}", invalid);
+        }
+
+        let table = "C0=1RA".parse::<PartialTable>().unwrap();
+        assert!(table.check(3, &Opt::None).is_ok());
+        assert!(table.check(2, &Opt::None).is_err());
+        let table = "A1=0LH".parse::<PartialTable>().unwrap();
+        assert!(table.check(2, &Opt::SkipSymmetries).is_ok());
+        assert!(table.check(2, &Opt::AlsoSkipHaltZero).is_err());
+    }
+
+    #[test]
+    fn matches_filtered_generator() {
+        fn imp<const N: usize>(table: &str, opt: Opt, expected: impl Fn(u64) -> bool) {
+            let table = table.parse::<PartialTable>().unwrap();
+            let gen = <Fixed<N>>::new(&table, opt);
+
+            let mut fixed = Vec::new();
+            gen.for_all(|tm| fixed.push(tm.encoded));
+            assert_eq!(fixed.len() as u64, gen.num_tms());
+            for (index, &id) in fixed.iter().enumerate() {
+                assert_eq!(gen.tm_at(index as u64).encoded, id);
+            }
+
+            let mut chunked = Vec::new();
+            for start in (0..gen.num_tms()).step_by(7) {
+                let end = std::cmp::min(gen.num_tms(), start + 7);
+                gen.for_range(start..end, |tm| chunked.push(tm.encoded));
+            }
+            assert_eq!(fixed, chunked);
+
+            let mut all = Vec::new();
+            <All<N>>::default().for_all(|tm| if expected(tm.encoded) { all.push(tm.encoded) });
+            fixed.sort_unstable();
+            all.sort_unstable();
+            assert_eq!(fixed, all);
+        }
+
+        // A0 = 1RB and A1 = 1LH.
+        let a0_a1 = |id| id & 0b11111_11111 == 0b01000_00110;
+        imp::<2>("A0=1RB,A1=1LH", Opt::None, a0_a1);
+
+        // Mirrored TMs can't be skipped here, so only the halt transitions are
+        // restricted.
+        let halts_left_writing_1 = |n: usize| move |id: u64| {
+            (0..2 * n).all(|slot| {
+                let action = (id >> (5 * slot)) & 0b11111;
+                action >> 2 != n as u64 || action & 0b11 == 0
+            })
+        };
+        imp::<2>("A0=1RB", Opt::AlsoSkipHaltZero, move |id| {
+            id & 0b11111 == 0b00110 && halts_left_writing_1(2)(id)
+        });
+
+        // Only a halting transition is fixed: the same as the optimized
+        // generator, except for the TMs it misses as its number of possible
+        // actions is odd.
+        let table = "B0=1LH".parse::<PartialTable>().unwrap();
+        let mut fixed = Vec::new();
+        <Fixed<2>>::new(&table, Opt::AlsoSkipHaltZero).for_all(|tm| fixed.push(tm.encoded));
+        let mut optimized = Vec::new();
+        <Optimized<2>>::default().for_all(|tm| {
+            if (tm.encoded >> 10) & 0b11111 == 0b01000 {
+                optimized.push(tm.encoded);
+            }
+        });
+        assert!(optimized.iter().all(|id| fixed.contains(id)));
+        assert!(fixed.iter().all(|&id| (id >> 15) & 0b10 == 0));
+    }
+}
@@ -6,34 +6,73 @@ use crate::tm::Tm;
 #[macro_use]
 mod tests;
 
+mod fixed;
 mod simple;
+mod space;
 
 pub use self::{
-    simple::{All, NoSymmetries, Optimized},
+    fixed::{Fixed, PartialTable},
+    simple::{All, NoSymmetries, Opt, Optimized},
+    space::{Generator, PerGenerator, Space, partition, partition_weighted, with_generator},
 };
 
+/// Has to be increased whenever the order in which any generator enumerates
+/// TMs changes: checkpoints store finished ranges of indices, which then refer
+/// to different TMs.
+pub const ENUMERATION_VERSION: u32 = 1;
+
 /// Something that can generate N state Turing machines.
 pub trait TmGenerator<const N: usize> {
-    fn description() -> &'static str;
+    /// See `cursor_at`.
+    type Cursor: Cursor<N>;
+
+    fn description(&self) -> &'static str;
 
     /// The number of different Turing machines this generator can generate in
     /// total.
-    fn num_tms() -> u64;
-
-    /// The number of different actions that Turing machines returned by this
-    /// generated can have.
-    fn num_possible_actions() -> u64;
+    fn num_tms(&self) -> u64;
 
-    /// Returns one specific TM for an index between 0 and `Self::num_tms
+    /// Returns one specific TM for an index between 0 and `self.num_tms
     /// ()`. Note that this is an index in the arbitrary (but fixed) order in
     /// which this generator generates TMs, and NOT the ID of the TM.
-    fn tm_at(index: u64) -> Tm<N>;
+    fn tm_at(&self, index: u64) -> Tm<N>;
+
+    /// Returns a cursor at the given index, from which the TMs can be
+    /// generated one after another. Seeking to an index might be expensive
+    /// for some generators, so a sweep only seeks once and hands clones of
+    /// the cursor (advanced with `Cursor::skip`) to the workers.
+    fn cursor_at(&self, index: u64) -> Self::Cursor;
+
+    /// Returns the index of `tm` in the order of this generator, i.e. the
+    /// inverse of `tm_at`. `None` if this generator does not generate `tm`.
+    fn index_of(&self, tm: Tm<N>) -> Option<u64>;
 
     /// Generates all TMs in the given range of indices (not TM IDs!).
-    fn for_range<F: FnMut(Tm<N>)>(range: Range<u64>, f: F);
+    fn for_range<F: FnMut(Tm<N>)>(&self, range: Range<u64>, mut f: F) {
+        assert!(range.end <= self.num_tms());
+        if range.is_empty() {
+            return;
+        }
+
+        let mut cursor = self.cursor_at(range.start);
+        for _ in range {
+            f(cursor.next_tm());
+        }
+    }
 
     /// Generates all TMs this generate can generate.
-    fn for_all<F: FnMut(Tm<N>)>(f: F) {
-        Self::for_range(0..Self::num_tms(), f)
+    fn for_all<F: FnMut(Tm<N>)>(&self, f: F) {
+        self.for_range(0..self.num_tms(), f)
     }
 }
+
+/// A position in the order of a generator (see `TmGenerator::cursor_at`).
+pub trait Cursor<const N: usize>: Clone + Send + 'static {
+    /// Returns the TM at the current index and advances to the next one. Must
+    /// not be called at the end of the generator.
+    fn next_tm(&mut self) -> Tm<N>;
+
+    /// Advances by `n` indices. The cursor may end up at the end of the
+    /// generator, but not behind it.
+    fn skip(&mut self, n: u64);
+}
@@ -1,15 +1,16 @@
-use std::ops::Range;
+use std::marker::PhantomData;
 
 use crate::tm::Tm;
 
-use super::TmGenerator;
+use super::{Cursor, TmGenerator};
 
 
 /// This is a pretty generic generator whose behavior can be configured via the
-/// const parameter `OPTS`.
-pub struct Gen<const OPTS: Opt, const N: usize>;
+/// type parameter `O` (see `Opts`).
+#[derive(Default)]
+pub struct Gen<O, const N: usize>(PhantomData<O>);
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opt {
     /// No optimization, every single possible TM is yielded.
     None,
@@ -39,39 +40,43 @@ pub enum Opt {
 }
 
 
-pub type All<const N: usize> = Gen<{ Opt::None }, N>;
-pub type NoSymmetries<const N: usize> = Gen<{ Opt::SkipSymmetries }, N>;
-pub type Optimized<const N: usize> = Gen<{ Opt::AlsoSkipHaltZero }, N>;
+/// Selects the `Opt` of a `Gen` at compile time. This is a type instead of a
+/// value so that the inner loops are compiled separately for each `Opt`.
+pub trait Opts: Clone + Default + Send + 'static {
+    const OPT: Opt;
+}
 
-impl<const OPTS: Opt, const N: usize> TmGenerator<N> for Gen<OPTS, N> {
-    fn description() -> &'static str {
-        match OPTS {
-            Opt::None => "All TMs",
-            Opt::SkipSymmetries => "All TMs but symmetric pairs deduplicated",
-            Opt::AlsoSkipHaltZero
-                => "All TMs without symmetry and without TMs with H_0 transitions",
-        }
-    }
+#[derive(Clone, Default)]
+pub struct OptNone;
+impl Opts for OptNone {
+    const OPT: Opt = Opt::None;
+}
 
-    fn num_tms() -> u64 {
-        // There are N states and 2 actions per state.
-        let mut out = Self::num_possible_actions().pow(2 * N as u32);
+#[derive(Clone, Default)]
+pub struct OptSkipSymmetries;
+impl Opts for OptSkipSymmetries {
+    const OPT: Opt = Opt::SkipSymmetries;
+}
 
-        // See symmetry (1) in the type docs. This cuts the number of TMs in
-        // half.
-        if OPTS != Opt::None {
-            out /= 2;
-        }
+#[derive(Clone, Default)]
+pub struct OptAlsoSkipHaltZero;
+impl Opts for OptAlsoSkipHaltZero {
+    const OPT: Opt = Opt::AlsoSkipHaltZero;
+}
 
-        out
-    }
+pub type All<const N: usize> = Gen<OptNone, N>;
+pub type NoSymmetries<const N: usize> = Gen<OptSkipSymmetries, N>;
+pub type Optimized<const N: usize> = Gen<OptAlsoSkipHaltZero, N>;
 
+impl<O: Opts, const N: usize> Gen<O, N> {
+    /// The number of different actions that Turing machines returned by this
+    /// generated can have.
     fn num_possible_actions() -> u64 {
         // These are `N + 1` (due to halt state) possible new states, each with
         // two different values to write and two different directions to move.
         let out = (1 + N as u64) * 2 * 2;
 
-        match OPTS {
+        match O::OPT {
             Opt::None => out,
 
             // Skipping symmetries here means that we ignore transitions to the
@@ -85,9 +90,35 @@ impl<const OPTS: Opt, const N: usize> TmGenerator<N> for Gen<OPTS, N> {
             Opt::AlsoSkipHaltZero => out - 3,
         }
     }
+}
+
+impl<O: Opts, const N: usize> TmGenerator<N> for Gen<O, N> {
+    type Cursor = GenCursor<O, N>;
+
+    fn description(&self) -> &'static str {
+        match O::OPT {
+            Opt::None => "All TMs",
+            Opt::SkipSymmetries => "All TMs but symmetric pairs deduplicated",
+            Opt::AlsoSkipHaltZero
+                => "All TMs without symmetry and without TMs with H_0 transitions",
+        }
+    }
 
-    fn tm_at(mut index: u64) -> Tm<N> {
-        assert!(index < Self::num_tms());
+    fn num_tms(&self) -> u64 {
+        // There are N states and 2 actions per state.
+        let mut out = Self::num_possible_actions().pow(2 * N as u32);
+
+        // See symmetry (1) in the type docs. This cuts the number of TMs in
+        // half.
+        if O::OPT != Opt::None {
+            out /= 2;
+        }
+
+        out
+    }
+
+    fn tm_at(&self, mut index: u64) -> Tm<N> {
+        assert!(index < self.num_tms());
 
         let mut out = 0;
         for i in 0..2 * N {
@@ -97,10 +128,10 @@ impl<const OPTS: Opt, const N: usize> TmGenerator<N> for Gen<OPTS, N> {
         }
 
         // If we skip symmetries, the calculated value has to be adjusted.
-        if OPTS != Opt::None {
+        if O::OPT != Opt::None {
             // The highest action (`on_1` on last state) is incorrect because
             // the 2nd bit can be 0 or 1. But it needs to always be 0. On the
-            // other hand, since `Self::num_tms()` is half of the value we had
+            // other hand, since `self.num_tms()` is half of the value we had
             // without the optimization, we are missing half the possible
             // actions. The solution is actually quite easy: we just take all
             // bits above and including the bit of the direction (which should
@@ -134,47 +165,90 @@ impl<const OPTS: Opt, const N: usize> TmGenerator<N> for Gen<OPTS, N> {
         Tm::new_unchecked(out)
     }
 
-    fn for_range<F: FnMut(Tm<N>)>(range: Range<u64>, mut f: F) {
-        assert!(range.end <= Self::num_tms());
-
-        let mut current = Self::tm_at(range.start).encoded;
-        for _ in range {
-            f(<Tm<N>>::new_unchecked(current));
-
-            for i in 0..2 * N {
-                let offset = 5 * i;
-
-                // Increment by 1 (generally speaking)
-                if i + 1 == 2 * N && OPTS != Opt::None {
-                    // If this is the last digit AND the "skip symmetry"
-                    // optimization is enabled, we need to skip actions that
-                    // move right here. The second to last bit is always 0 due
-                    // to the `if` in `tm_at` and due to what's following.
-                    if (current >> offset) & 0b1 == 0 {
-                        current += 1 << offset;
-                    } else {
-                        // Add 1 and skip 2. E.g. from `b00001` to `b00100`.
-                        current += 3 << offset;
-                    }
-                } else {
-                    current += 1 << offset;
-                }
+    fn index_of(&self, tm: Tm<N>) -> Option<u64> {
+        // Undo the insertion of the 0 bit in `tm_at`.
+        let mut encoded = tm.encoded;
+        if O::OPT != Opt::None {
+            let bad_bit_pos = 5 * (2 * N - 1) + 1;
+            if (encoded >> bad_bit_pos) & 1 != 0 {
+                return None;
+            }
+            let lower_mask = (1 << bad_bit_pos) - 1;
+            encoded = ((encoded >> 1) & !lower_mask) | (encoded & lower_mask);
+        }
 
-                // Check for overflow in this digit. If so, we continue to carry and
-                // set this digit to 0.
-                if (current >> offset) & 0b11111 == Self::num_possible_actions() {
-                    // If we are at the last digit and have a carry, we are done.
-                    if i + 1 == 2 * N {
-                        return;
-                    }
+        let mut index = 0;
+        for i in (0..2 * N).rev() {
+            let action = (encoded >> (i * 5)) & 0b11111;
+            if action >= Self::num_possible_actions() {
+                return None;
+            }
+            index = index * Self::num_possible_actions() + action;
+        }
+
+        // This also rejects TMs with bits set above the actions.
+        (index < self.num_tms() && self.tm_at(index) == tm).then_some(index)
+    }
 
-                    current &= !(0b11111 << offset);
+    fn cursor_at(&self, index: u64) -> Self::Cursor {
+        let current = if index < self.num_tms() { self.tm_at(index).encoded } else { 0 };
+        GenCursor { index, current, _opts: PhantomData }
+    }
+}
+
+/// Cursor of `Gen`: the index and the encoded TM at that index. Advancing is
+/// incrementing the actions like digits of a number.
+#[derive(Clone)]
+pub struct GenCursor<O, const N: usize> {
+    index: u64,
+    current: u64,
+    _opts: PhantomData<O>,
+}
+
+impl<O: Opts, const N: usize> Cursor<N> for GenCursor<O, N> {
+    fn next_tm(&mut self) -> Tm<N> {
+        let out = <Tm<N>>::new_unchecked(self.current);
+        self.index += 1;
+
+        for i in 0..2 * N {
+            let offset = 5 * i;
+
+            // Increment by 1 (generally speaking)
+            if i + 1 == 2 * N && O::OPT != Opt::None {
+                // If this is the last digit AND the "skip symmetry"
+                // optimization is enabled, we need to skip actions that
+                // move right here. The second to last bit is always 0 due
+                // to the `if` in `tm_at` and due to what's following.
+                if (self.current >> offset) & 0b1 == 0 {
+                    self.current += 1 << offset;
                 } else {
-                    // Otherwise we are done advancing the iterator.
+                    // Add 1 and skip 2. E.g. from `b00001` to `b00100`.
+                    self.current += 3 << offset;
+                }
+            } else {
+                self.current += 1 << offset;
+            }
+
+            // Check for overflow in this digit. If so, we continue to carry and
+            // set this digit to 0.
+            if (self.current >> offset) & 0b11111 == Gen::<O, N>::num_possible_actions() {
+                // If we are at the last digit and have a carry, we are done.
+                if i + 1 == 2 * N {
                     break;
                 }
+
+                self.current &= !(0b11111 << offset);
+            } else {
+                // Otherwise we are done advancing the iterator.
+                break;
             }
         }
+
+        out
+    }
+
+    fn skip(&mut self, n: u64) {
+        *self = Gen::<O, N>::default().cursor_at(self.index + n);
     }
 }
 
@@ -0,0 +1,328 @@
+//! Selecting a generator at runtime, and the index math of the selected one:
+//! converting between indices (in the generator's order) and TM IDs, and
+//! splitting the indices into ranges. This is all that is needed to
+//! distribute a sweep over many machines, e.g. as cluster job array where
+//! each job runs `beaver range --from .. --to ..`.
+
+use std::{cmp::Reverse, fmt, ops::Range, str::FromStr};
+
+use crate::{
+    dispatch::{self, PerN},
+    tm::Tm,
+};
+use super::{All, Fixed, NoSymmetries, Opt, Optimized, PartialTable, TmGenerator};
+
+
+/// The TM generators that can be selected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    /// Blindly generates all possible TMs (see `gen::All`).
+    All,
+
+    /// Eliminates symmetric TMs that will result in the same outcome (see
+    /// `gen::NoSymmetries`).
+    NoSymmetries,
+
+    /// Also eliminates TMs that have no chance of winning busy beaver (see
+    /// `gen::Optimized`).
+    Optimized,
+}
+
+impl Generator {
+    /// The options of the generator, e.g. for `gen::Fixed`.
+    pub fn opt(self) -> Opt {
+        match self {
+            Self::All => Opt::None,
+            Self::NoSymmetries => Opt::SkipSymmetries,
+            Self::Optimized => Opt::AlsoSkipHaltZero,
+        }
+    }
+}
+
+impl FromStr for Generator {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "no-symmetries" => Ok(Self::NoSymmetries),
+            "optimized" => Ok(Self::Optimized),
+            _ => Err("invalid value for 'generator'"),
+        }
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("all"),
+            Self::NoSymmetries => f.write_str("no-symmetries"),
+            Self::Optimized => f.write_str("optimized"),
+        }
+    }
+}
+
+/// The TMs enumerated by a sweep: all TMs with `n` states of a generator,
+/// optionally only the ones matching a partial transition table (like `full
+/// --fix`). Indices refer to the order of exactly this selection.
+///
+/// ```
+/// use beaver_core::gen::{Generator, Space};
+///
+/// let space = Space::new(2, Generator::Optimized, None).unwrap();
+/// assert_eq!(space.num_tms(), 3280);
+///
+/// let id = space.id_at(1234).unwrap();
+/// assert_eq!(space.index_of(id), Some(1234));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Space {
+    n: u8,
+    generator: Generator,
+    fixed: Option<PartialTable>,
+}
+
+impl Space {
+    /// Returns an error if `n` is not supported or if `fixed` is not valid
+    /// for `n` and the generator (see `PartialTable::check`).
+    pub fn new(n: u8, generator: Generator, fixed: Option<PartialTable>) -> Result<Self, String> {
+        if !dispatch::SUPPORTED.contains(&n) {
+            return Err(format!("unsupported number of states {}", n));
+        }
+        if let Some(table) = &fixed {
+            table.check(n, &generator.opt())?;
+        }
+        Ok(Self { n, generator, fixed })
+    }
+
+    /// The number of TMs, i.e. the end of the range of valid indices.
+    pub fn num_tms(&self) -> u64 {
+        self.dispatch(NumTms)
+    }
+
+    /// The ID of the TM with the given index, or `None` if the index is out
+    /// of range.
+    pub fn id_at(&self, index: u64) -> Option<u64> {
+        self.dispatch(IdAt(index))
+    }
+
+    /// The index of the TM with the given ID, or `None` if that TM is not
+    /// part of this space (e.g. because it is skipped as symmetric to
+    /// another TM, or because the ID is invalid for `n`).
+    pub fn index_of(&self, id: u64) -> Option<u64> {
+        self.dispatch(IndexOf(id))
+    }
+
+    /// Splits all indices into `parts` ranges, see `partition`.
+    pub fn partition(&self, parts: u64) -> Vec<Range<u64>> {
+        partition(0..self.num_tms(), parts)
+    }
+
+    /// Splits all indices into ranges proportional to `weights`, see
+    /// `partition_weighted`.
+    pub fn partition_weighted(&self, weights: &[u64]) -> Vec<Range<u64>> {
+        partition_weighted(0..self.num_tms(), weights)
+    }
+
+    fn dispatch<F: PerGenerator>(&self, f: F) -> F::Output {
+        with_generator(self.n, self.generator, self.fixed.as_ref(), f)
+            .unwrap_or_else(|| unreachable!("invalid N = {}", self.n))
+    }
+}
+
+/// Splits `range` into `parts` consecutive ranges whose lengths differ by at
+/// most one, the longer ones first. If `range` has fewer than `parts`
+/// elements, the last ranges are empty.
+///
+/// ```
+/// use beaver_core::gen::partition;
+///
+/// assert_eq!(partition(0..10, 3), vec![0..4, 4..7, 7..10]);
+/// ```
+pub fn partition(range: Range<u64>, parts: u64) -> Vec<Range<u64>> {
+    assert!(parts > 0, "cannot partition into zero parts");
+    let len = range.end.saturating_sub(range.start);
+    let (size, rest) = (len / parts, len % parts);
+
+    let mut start = range.start;
+    (0..parts)
+        .map(|i| {
+            let end = start + size + (i < rest) as u64;
+            let part = start..end;
+            start = end;
+            part
+        })
+        .collect()
+}
+
+/// Splits `range` into consecutive ranges, one per weight, whose lengths are
+/// proportional to the weights. With the measured throughput of each worker
+/// as weights, all workers need about the same time for their range. The
+/// lengths are rounded such that they add up to the length of `range`, with
+/// the remaining elements going to the largest remainders (the earlier
+/// weight first if equal). If all weights are 0, this is like `partition`.
+///
+/// ```
+/// use beaver_core::gen::partition_weighted;
+///
+/// assert_eq!(partition_weighted(0..10, &[3, 1, 1]), vec![0..6, 6..8, 8..10]);
+/// ```
+pub fn partition_weighted(range: Range<u64>, weights: &[u64]) -> Vec<Range<u64>> {
+    assert!(!weights.is_empty(), "cannot partition into zero parts");
+    let total = weights.iter().map(|&w| w as u128).sum::<u128>();
+    if total == 0 {
+        return partition(range, weights.len() as u64);
+    }
+
+    let len = range.end.saturating_sub(range.start) as u128;
+    let mut lens = weights.iter().map(|&w| len * w as u128 / total).collect::<Vec<_>>();
+    let mut by_remainder = (0..weights.len()).collect::<Vec<_>>();
+    by_remainder.sort_by_key(|&i| Reverse(len * weights[i] as u128 % total));
+    let rest = len - lens.iter().sum::<u128>();
+    for &i in &by_remainder[..rest as usize] {
+        lens[i] += 1;
+    }
+
+    let mut start = range.start;
+    lens.into_iter()
+        .map(|len| {
+            let end = start + len as u64;
+            let part = start..end;
+            start = end;
+            part
+        })
+        .collect()
+}
+
+/// Code that is generic over the TM generator `G` and `N`, called with the
+/// ones selected at runtime by `with_generator`.
+pub trait PerGenerator {
+    type Output;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> Self::Output
+    where
+        [bool; N]: Default;
+}
+
+/// Calls `f` with the generator matching the arguments and `N = n`. Returns
+/// `None` if `n` is not supported. The fixed transitions have to be valid
+/// (see `PartialTable::check`).
+pub fn with_generator<F: PerGenerator>(
+    n: u8,
+    generator: Generator,
+    fixed: Option<&PartialTable>,
+    f: F,
+) -> Option<F::Output> {
+    dispatch::with_n(n, WithGenerator { generator, fixed, f })
+}
+
+/// Picks the generator once `N` is known (see `with_generator`).
+struct WithGenerator<'a, F> {
+    generator: Generator,
+    fixed: Option<&'a PartialTable>,
+    f: F,
+}
+
+impl<F: PerGenerator> PerN for WithGenerator<'_, F> {
+    type Output = F::Output;
+
+    fn call<const N: usize>(self) -> F::Output
+    where
+        [bool; N]: Default,
+    {
+        match (self.fixed, self.generator) {
+            (Some(table), generator) => self.f.call(Fixed::<N>::new(table, generator.opt())),
+            (None, Generator::All) => self.f.call(All::<N>::default()),
+            (None, Generator::NoSymmetries) => self.f.call(NoSymmetries::<N>::default()),
+            (None, Generator::Optimized) => self.f.call(Optimized::<N>::default()),
+        }
+    }
+}
+
+struct NumTms;
+
+impl PerGenerator for NumTms {
+    type Output = u64;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> u64 {
+        generator.num_tms()
+    }
+}
+
+struct IdAt(u64);
+
+impl PerGenerator for IdAt {
+    type Output = Option<u64>;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> Option<u64> {
+        (self.0 < generator.num_tms()).then(|| generator.tm_at(self.0).encoded)
+    }
+}
+
+struct IndexOf(u64);
+
+impl PerGenerator for IndexOf {
+    type Output = Option<u64>;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> Option<u64> {
+        generator.index_of(Tm::<N>::from_id(self.0)?)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_covers_range() {
+        assert_eq!(partition(5..5, 2), vec![5..5, 5..5]);
+        assert_eq!(partition(0..2, 3), vec![0..1, 1..2, 2..2]);
+        assert_eq!(partition(3..9, 2), vec![3..6, 6..9]);
+
+        let parts = partition(0..1_000_003, 7);
+        assert_eq!(parts.len(), 7);
+        assert_eq!(parts[0].start, 0);
+        assert_eq!(parts[6].end, 1_000_003);
+        assert!(parts.windows(2).all(|w| w[0].end == w[1].start));
+    }
+
+    #[test]
+    fn partition_weighted_is_proportional() {
+        assert_eq!(partition_weighted(0..9, &[1, 1, 1]), partition(0..9, 3));
+        assert_eq!(partition_weighted(0..10, &[1, 1, 1]), partition(0..10, 3));
+        assert_eq!(partition_weighted(2..12, &[0, 0]), partition(2..12, 2));
+        assert_eq!(partition_weighted(0..100, &[1, 0, 3]), vec![0..25, 25..25, 25..100]);
+        assert_eq!(partition_weighted(0..10, &[1, 2, 2]), vec![0..2, 2..6, 6..10]);
+        assert_eq!(partition_weighted(0..11, &[1, 2, 2]), vec![0..2, 2..7, 7..11]);
+
+        // Large numbers don't overflow.
+        let parts = partition_weighted(0..u64::MAX, &[u64::MAX, 1, u64::MAX / 2]);
+        assert_eq!(parts[0].start, 0);
+        assert_eq!(parts[2].end, u64::MAX);
+        assert!(parts.windows(2).all(|w| w[0].end == w[1].start));
+        assert!(parts[1].end - parts[1].start <= 1);
+    }
+
+    #[test]
+    fn space_round_trip() {
+        let table = "A0=1RB".parse::<PartialTable>().unwrap();
+        for fixed in [None, Some(table)] {
+            let space = Space::new(2, Generator::NoSymmetries, fixed).unwrap();
+            for index in 0..space.num_tms() {
+                let id = space.id_at(index).unwrap();
+                assert_eq!(space.index_of(id), Some(index));
+            }
+            assert_eq!(space.id_at(space.num_tms()), None);
+        }
+
+        // The mirrored TM is skipped.
+        let space = Space::new(2, Generator::NoSymmetries, None).unwrap();
+        let tm = Tm::<2>::from_standard("1RB1LB_1LA1LH").unwrap();
+        let mirrored = Tm::<2>::from_standard("1LB1RB_1RA1RH").unwrap();
+        assert!(space.index_of(tm.encoded).is_some());
+        assert_eq!(space.index_of(mirrored.encoded), None);
+
+        assert!(Space::new(7, Generator::All, None).is_err());
+    }
+}
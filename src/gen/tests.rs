@@ -6,23 +6,25 @@ macro_rules! gen_tests {
 
         #[test]
         fn total_count_fits() {
-            fn count_all<const N: usize>() -> u64 {
+            fn imp<const N: usize>() {
+                let gen = <$gen<N>>::default();
                 let mut count = 0;
-                <$gen<N>>::for_all(|_| count += 1);
-                count
+                gen.for_all(|_| count += 1);
+                assert_eq!(count, gen.num_tms());
             }
 
-            assert_eq!(count_all::<1>(), <$gen<1>>::num_tms());
-            assert_eq!(count_all::<2>(), <$gen<2>>::num_tms());
-            assert_eq!(count_all::<3>(), <$gen<3>>::num_tms());
+            imp::<1>();
+            imp::<2>();
+            imp::<3>();
             // assert_eq!(count::<4>(FULL_RANGE), num_machines(4));
         }
 
         #[test]
         fn chunked_equals_full() {
             fn imp<const N: usize>() {
+                let gen = <$gen<N>>::default();
                 let mut all = Vec::new();
-                <$gen<N>>::for_all(|tm| all.push(tm));
+                gen.for_all(|tm| all.push(tm));
 
                 let chunk_size = match N {
                     1 => 5,
@@ -31,9 +33,9 @@ macro_rules! gen_tests {
                 };
 
                 let mut chunked = Vec::new();
-                for start in (0..<$gen<N>>::num_tms()).step_by(chunk_size as usize) {
-                    let end = std::cmp::min(<$gen<N>>::num_tms(), start + chunk_size);
-                    <$gen<N>>::for_range(start..end, |tm| chunked.push(tm));
+                for start in (0..gen.num_tms()).step_by(chunk_size as usize) {
+                    let end = std::cmp::min(gen.num_tms(), start + chunk_size);
+                    gen.for_range(start..end, |tm| chunked.push(tm));
                 }
 
                 assert_eq!(all, chunked);
@@ -45,14 +47,42 @@ macro_rules! gen_tests {
             // imp::<4>();
         }
 
+        #[test]
+        fn skipping_cursor_equals_full() {
+            fn imp<const N: usize>() {
+                let gen = <$gen<N>>::default();
+                let mut all = Vec::new();
+                gen.for_all(|tm| all.push(tm));
+
+                // Like the dispatcher of a sweep: one cursor that is skipped
+                // forward and cloned for each chunk.
+                let mut cursor = gen.cursor_at(0);
+                let mut pos = 0;
+                for start in (0..gen.num_tms()).step_by(7) {
+                    cursor.skip(start - pos);
+                    pos = start;
+                    let mut chunk_cursor = cursor.clone();
+                    let end = std::cmp::min(gen.num_tms(), start + 7);
+                    for index in start..end {
+                        assert_eq!(chunk_cursor.next_tm(), all[index as usize]);
+                    }
+                }
+            }
+
+            imp::<1>();
+            imp::<2>();
+            imp::<3>();
+        }
+
         #[test]
         fn single_tm_equals_ranged() {
             fn imp<const N: usize>() {
+                let gen = <$gen<N>>::default();
                 let mut all = Vec::new();
-                <$gen<N>>::for_all(|tm| all.push(tm));
+                gen.for_all(|tm| all.push(tm));
 
-                for index in 0..<$gen<N>>::num_tms() {
-                    assert_eq!(all[index as usize], <$gen<N>>::tm_at(index));
+                for index in 0..gen.num_tms() {
+                    assert_eq!(all[index as usize], gen.tm_at(index));
                 }
             }
 
@@ -62,11 +92,25 @@ macro_rules! gen_tests {
             // imp::<4>();
         }
 
+        #[test]
+        fn index_of_inverts_tm_at() {
+            fn imp<const N: usize>() {
+                let gen = <$gen<N>>::default();
+                for index in 0..gen.num_tms() {
+                    assert_eq!(gen.index_of(gen.tm_at(index)), Some(index));
+                }
+            }
+
+            imp::<1>();
+            imp::<2>();
+            imp::<3>();
+        }
+
         #[test]
         fn all_unique() {
             fn imp<const N: usize>() {
                 let mut all = Vec::new();
-                <$gen<N>>::for_all(|tm| all.push(tm.encoded));
+                <$gen<N>>::default().for_all(|tm| all.push(tm.encoded));
 
                 all.sort();
                 assert!((0..all.len() - 1).all(|i| all[i] != all[i + 1]));
@@ -0,0 +1,259 @@
+//! Simulation of TMs on the GPU (feature `gpu`), see `full --gpu`.
+//!
+//! For larger `n`, most of the time of a sweep is spent simulating TMs that
+//! passed the static checks. Most of them halt after a few steps, long
+//! before any of the deciders would be useful. So these simulations are done
+//! on the GPU in large batches: every invocation of the compute shader runs
+//! one TM for at most `step_budget` steps on a small, fixed tape of
+//! `TAPE_CELLS` cells. TMs that halt within that budget are reported from the
+//! result, all others are analyzed on the CPU as usual, from scratch. So the
+//! outcomes are exactly the same as without the GPU.
+//!
+//! The TMs are passed as four `u32` per TM, holding one byte per transition
+//! (state `s` reading `v` is byte `2s + v`): bit 0 is the value written,
+//! bit 1 is set for moving right and bits 2 to 4 are the next state, with 7
+//! for the halt state.
+
+use std::borrow::Cow;
+use anyhow::{Context, Result, anyhow};
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    analyze::HaltConvention,
+    batch::Halted,
+    tape::CellId,
+    tm::{Move, NextState, Tm},
+};
+
+
+/// The number of cells of the tape on the GPU. The head starts in the middle.
+/// At most `batch::MAX_CELLS`.
+const TAPE_CELLS: usize = 256;
+
+/// The number of TMs per workgroup, as in `simulate.wgsl`.
+const WORKGROUP_SIZE: usize = 64;
+
+/// The number of TMs that are simulated with one dispatch at most. Larger
+/// batches are split.
+const MAX_BATCH: usize = 1 << 18;
+
+/// The halt state in the encoding of the TMs.
+const HALT: u8 = 7;
+
+/// The result of simulating one TM on the GPU, as `Outcome` in
+/// `simulate.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuResult {
+    halted: u32,
+
+    /// The number of steps, according to the halt convention.
+    steps: u32,
+
+    /// The leftmost and rightmost cell that was written, as index into
+    /// `tape`.
+    lo: u32,
+    hi: u32,
+
+    /// The final tape, one bit per cell.
+    tape: [u32; TAPE_CELLS / 32],
+}
+
+impl GpuResult {
+    /// Returns `None` if the TM did not halt within the budget, i.e. it either
+    /// exceeded the step budget or the tape and has to be run on the CPU.
+    fn into_halted(self) -> Option<Halted> {
+        if self.halted == 0 {
+            return None;
+        }
+
+        let offset = TAPE_CELLS as i64 / 2;
+        let written = CellId(self.lo as i64 - offset)..CellId(self.hi as i64 + 1 - offset);
+        Some(Halted::new(self.steps as u64, written, |id| {
+            let i = (id.0 + offset) as usize;
+            (self.tape[i / 32] >> (i % 32)) & 1 != 0
+        }))
+    }
+}
+
+/// The parameters of one dispatch, as `Params` in `simulate.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Params {
+    num_tms: u32,
+    max_steps: u32,
+    include_halt: u32,
+    padding: u32,
+}
+
+/// A GPU, ready to simulate TMs.
+pub struct GpuSimulator {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    adapter_name: String,
+
+    /// The maximum number of steps a TM is simulated on the GPU.
+    step_budget: u32,
+}
+
+impl GpuSimulator {
+    /// Opens the default GPU and compiles the shader. Fails if there is no
+    /// suitable GPU.
+    pub fn new(step_budget: u32) -> Result<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default()))
+            .ok_or_else(|| anyhow!("no GPU found"))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&Default::default(), None))
+            .context("failed to open the GPU")?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("simulate"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("simulate.wgsl"))),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("simulate"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            adapter_name: adapter.get_info().name,
+            step_budget,
+        })
+    }
+
+    /// The name of the GPU.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Simulates all `tms` for at most `max_steps` (or the step budget, if
+    /// that is lower) and calls `on_halted` with the index in `tms` of each
+    /// TM that halted, like `batch::simulate`.
+    pub fn simulate<const N: usize>(
+        &self,
+        tms: &[Tm<N>],
+        max_steps: u64,
+        halt_convention: HaltConvention,
+        mut on_halted: impl FnMut(usize, Halted),
+    ) -> Result<()> {
+        assert!(N < HALT as usize, "too many states for the GPU");
+
+        let max_steps = max_steps.min(self.step_budget as u64) as u32;
+        for (i, chunk) in tms.chunks(MAX_BATCH).enumerate() {
+            let params = Params {
+                num_tms: chunk.len() as u32,
+                max_steps,
+                include_halt: (halt_convention == HaltConvention::Include) as u32,
+                padding: 0,
+            };
+            let encoded = chunk.iter().flat_map(|&tm| encode(tm)).collect::<Vec<_>>();
+            let results = self.dispatch(params, &encoded)?;
+            for (j, result) in results.into_iter().enumerate() {
+                if let Some(halted) = result.into_halted() {
+                    on_halted(i * MAX_BATCH + j, halted);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(&self, params: Params, tms: &[u32]) -> Result<Vec<GpuResult>> {
+        let params_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let tms_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("tms"),
+            contents: bytemuck::cast_slice(tms),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let size = (params.num_tms as usize * std::mem::size_of::<GpuResult>()) as u64;
+        let results_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("results"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let download_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("download"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: tms_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: results_buffer.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let num_groups = (params.num_tms as usize).div_ceil(WORKGROUP_SIZE);
+            pass.dispatch_workgroups(num_groups as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&results_buffer, 0, &download_buffer, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = download_buffer.slice(..);
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        slice.map_async(wgpu::MapMode::Read, move |r| { let _ = tx.send(r); });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .context("the GPU did not return any results")?
+            .context("failed to read the results from the GPU")?;
+
+        let results = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        download_buffer.unmap();
+        Ok(results)
+    }
+}
+
+/// Encodes the transitions of `tm` as described in the module docs.
+fn encode<const N: usize>(tm: Tm<N>) -> [u32; 4] {
+    let mut encoded = [0; 4];
+    for state in 0..N {
+        let state_actions = tm.state(state as u8);
+        for (read, action) in [state_actions.on_0(), state_actions.on_1()].iter().enumerate() {
+            let next = match action.next_state() {
+                NextState::HaltState => HALT,
+                NextState::State(s) => s,
+            };
+            let byte = action.write_value().0 as u8
+                | ((action.movement() == Move::Right) as u8) << 1
+                | next << 2;
+            let index = 2 * state + read;
+            encoded[index / 4] |= (byte as u32) << (8 * (index % 4));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let tm = <Tm<2>>::from_standard("1RB1LB_1LA1LH").unwrap();
+        assert_eq!(encode(tm), [7 | 5 << 8 | 1 << 16 | 29 << 24, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,90 @@
+// Simulates one TM per invocation for at most `params.max_steps` steps on a
+// tape of `TAPE_CELLS` cells, starting in the middle. The TM stops early if
+// its head would leave the tape. See `gpu/mod.rs` for the buffer layouts.
+
+const TAPE_CELLS: u32 = 256u;
+const TAPE_WORDS: u32 = 8u;
+const HALT: u32 = 7u;
+
+struct Params {
+    num_tms: u32,
+    max_steps: u32,
+    include_halt: u32,
+    padding: u32,
+}
+
+struct Outcome {
+    halted: u32,
+    steps: u32,
+    lo: u32,
+    hi: u32,
+    tape: array<u32, 8>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> tms: array<u32>;
+@group(0) @binding(2) var<storage, read_write> outcomes: array<Outcome>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.num_tms {
+        return;
+    }
+
+    var tape: array<u32, 8>;
+    var head = TAPE_CELLS / 2u;
+    var lo = head;
+    var hi = head;
+    var state = 0u;
+    var steps = 0u;
+    var halted = 0u;
+    loop {
+        if steps >= params.max_steps {
+            break;
+        }
+        steps += 1u;
+
+        let word = head / 32u;
+        let bit = head % 32u;
+        let read = (tape[word] >> bit) & 1u;
+        let index = state * 2u + read;
+        let action = (tms[i * 4u + index / 4u] >> ((index % 4u) * 8u)) & 0xffu;
+        tape[word] = (tape[word] & ~(1u << bit)) | ((action & 1u) << bit);
+        lo = min(lo, head);
+        hi = max(hi, head);
+
+        let next = action >> 2u;
+        if next == HALT {
+            // Like on the CPU, the halting transition is undone if it should
+            // not be counted.
+            if params.include_halt == 0u {
+                steps -= 1u;
+                tape[word] = (tape[word] & ~(1u << bit)) | (read << bit);
+            }
+            halted = 1u;
+            break;
+        }
+        state = next;
+
+        if (action & 2u) != 0u {
+            if head == TAPE_CELLS - 1u {
+                break;
+            }
+            head += 1u;
+        } else {
+            if head == 0u {
+                break;
+            }
+            head -= 1u;
+        }
+    }
+
+    outcomes[i].halted = halted;
+    outcomes[i].steps = steps;
+    outcomes[i].lo = lo;
+    outcomes[i].hi = hi;
+    for (var w = 0u; w < TAPE_WORDS; w += 1u) {
+        outcomes[i].tape[w] = tape[w];
+    }
+}
@@ -0,0 +1,117 @@
+//! Graph-theoretic properties of the state graph of a TM. The state graph has
+//! one node per state plus one for the halt state, and one edge per action
+//! (i.e. two outgoing edges per state).
+
+use std::collections::VecDeque;
+
+use crate::tm::{NextState, Tm};
+
+
+/// Statistics about the state graph of a single TM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphStats {
+    /// Number of strongly connected components, including the halt state
+    /// (which always forms its own component).
+    pub num_sccs: u8,
+
+    /// Number of states (excluding the halt state) reachable from the start
+    /// state, including the start state itself.
+    pub num_reachable: u8,
+
+    /// The minimal number of transitions from the start state to the halt
+    /// state, or `None` if the halt state is not reachable.
+    pub halt_distance: Option<u8>,
+
+    /// The number of actions that transition to the halt state.
+    pub halt_in_degree: u8,
+}
+
+impl GraphStats {
+    pub fn of<const N: usize>(tm: Tm<N>) -> Self {
+        // Node `N` is the halt state. With at most 7 nodes, adjacency and
+        // reachability fit into a bitmask per node.
+        let halt = N;
+        let mut edges = [0u8; 7];
+        let mut halt_in_degree = 0;
+        for (i, out) in edges.iter_mut().enumerate().take(N) {
+            let state = tm.state(i as u8);
+            for action in [state.on_0(), state.on_1()] {
+                let target = match action.next_state() {
+                    NextState::HaltState => {
+                        halt_in_degree += 1;
+                        halt
+                    }
+                    NextState::State(s) => s as usize,
+                };
+                *out |= 1 << target;
+            }
+        }
+
+        // Transitive closure (Floyd–Warshall on bitmasks). Every node reaches
+        // itself.
+        let mut reach = edges;
+        for (i, r) in reach.iter_mut().enumerate().take(N + 1) {
+            *r |= 1 << i;
+        }
+        for k in 0..=N {
+            for i in 0..=N {
+                if reach[i] & (1 << k) != 0 {
+                    reach[i] |= reach[k];
+                }
+            }
+        }
+
+        // Two nodes are in the same SCC if they reach each other. We count the
+        // distinct SCC masks by only counting the smallest member of each.
+        let num_sccs = (0..=N)
+            .filter(|&i| {
+                let scc = (0..=N)
+                    .filter(|&j| reach[i] & (1 << j) != 0 && reach[j] & (1 << i) != 0)
+                    .fold(0u8, |mask, j| mask | 1 << j);
+                scc.trailing_zeros() as usize == i
+            })
+            .count() as u8;
+
+        let num_reachable = (reach[0] & !(1 << halt)).count_ones() as u8;
+
+        // Breadth first search from the start state.
+        let mut distance = [None; 7];
+        distance[0] = Some(0u8);
+        let mut queue = VecDeque::from(vec![0]);
+        while let Some(node) = queue.pop_front() {
+            let d = distance[node].expect("queued node without distance");
+            for (target, dist) in distance.iter_mut().enumerate().take(N + 1) {
+                if edges[node] & (1 << target) != 0 && dist.is_none() {
+                    *dist = Some(d + 1);
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        Self {
+            num_sccs,
+            num_reachable,
+            halt_distance: distance[halt],
+            halt_in_degree,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::GraphStats;
+    use crate::tm::Tm;
+
+    #[test]
+    fn champion_2() {
+        // 1RB 1LB / 1LA 1RH: A and B form a cycle, B can halt.
+        let tm = <Tm<2>>::from_id(327814).unwrap();
+        assert_eq!(GraphStats::of(tm), GraphStats {
+            num_sccs: 2,
+            num_reachable: 2,
+            halt_distance: Some(2),
+            halt_in_degree: 1,
+        });
+    }
+}
@@ -0,0 +1,100 @@
+//! Where the head of a TM spends its time: the number of steps executed on
+//! each cell, split into equal time slices of the run. Sweeps show up as
+//! diagonal bands and counters as a hot spot at one end of the tape.
+
+use std::ops::Range;
+
+use crate::{machine::Machine, tm::Tm};
+
+
+/// Row `i` counts the steps of the `i`-th time slice per cell in `cells`.
+pub struct HeadHeatmap {
+    cells: Range<i64>,
+    steps: u64,
+    rows: usize,
+    counts: Vec<u32>,
+}
+
+impl HeadHeatmap {
+    /// Runs the TM for at most `max_steps` steps and counts the head
+    /// positions in `rows` time slices (fewer if the TM ran for fewer steps).
+    /// The TM is run twice: first to find the number of steps and the cells
+    /// visited, then to count.
+    pub fn capture<const N: usize>(tm: Tm<N>, max_steps: u64, rows: usize) -> Self {
+        let mut machine = Machine::new(tm);
+        let (mut min, mut max) = (0, 0);
+        while machine.steps() < max_steps && machine.step().is_some() {
+            min = min.min(machine.head().0);
+            max = max.max(machine.head().0);
+        }
+
+        let steps = machine.steps();
+        let rows = rows.clamp(1, (steps as usize).max(1));
+        let cells = min..max + 1;
+        let width = (max + 1 - min) as usize;
+        let mut counts = vec![0; rows * width];
+        let mut machine = Machine::new(tm);
+        while machine.steps() < steps {
+            let row = (machine.steps() * rows as u64 / steps) as usize;
+            counts[row * width + (machine.head().0 - min) as usize] += 1;
+            machine.step();
+        }
+
+        Self { cells, steps, rows, counts }
+    }
+
+    /// All cells the head visited.
+    pub fn cells(&self) -> Range<i64> {
+        self.cells.clone()
+    }
+
+    /// The number of executed steps.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The number of time slices.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of steps executed on `cell` during time slice `row`.
+    pub fn get(&self, row: usize, cell: i64) -> u32 {
+        if !self.cells.contains(&cell) {
+            return 0;
+        }
+
+        let width = (self.cells.end - self.cells.start) as usize;
+        self.counts[row * width + (cell - self.cells.start) as usize]
+    }
+
+    /// The largest count of all rows and cells.
+    pub fn max(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_champion() {
+        // The BB(2) champion: 1RB1LB_1LA1RH.
+        let tm = <Tm<2>>::new_unchecked(327814);
+        let heatmap = HeadHeatmap::capture(tm, 100, 100);
+        assert_eq!(heatmap.steps(), 6);
+        assert_eq!(heatmap.rows(), 6);
+        assert_eq!(heatmap.cells(), -2..2);
+        let total = (0..heatmap.rows())
+            .flat_map(|row| heatmap.cells().map(move |cell| (row, cell)))
+            .map(|(row, cell)| heatmap.get(row, cell))
+            .sum::<u32>();
+        assert_eq!(total, 6);
+        assert_eq!(heatmap.max(), 1);
+
+        let heatmap = HeadHeatmap::capture(tm, 100, 2);
+        assert_eq!((heatmap.get(0, 0), heatmap.get(0, 1), heatmap.get(0, -1)), (2, 1, 0));
+    }
+}
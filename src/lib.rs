@@ -0,0 +1,152 @@
+//! The core of beaver, usable without the command line interface: Turing
+//! machines with N states and their encoding (`tm`), the tape they operate
+//! on (`tape`), generators enumerating all TMs with N states (`gen`) and the
+//! analyzer that simulates TMs and applies the deciders (`analyze`),
+//! reporting its results to an `OutcomeSink` (`outcome`). Sweeps analyze all
+//! TMs of a generator on several threads (`sweep`) and aggregate the results
+//! into a `Summary` (`summary`).
+//!
+//! These seven modules are the public API. The other modules are only public
+//! for the `beaver` binary and can change at any time. Printing reports to
+//! the terminal is only available with the `cli` feature.
+//!
+//! To use the library without the dependencies of the CLI, disable the
+//! default features:
+//!
+//! ```toml
+//! beaver = { git = "https://github.com/LukasKalbertodt/beaver", default-features = false }
+//! ```
+//!
+//! # Examples
+//!
+//! Analyzing a single TM, here the BB(2) champion:
+//!
+//! ```
+//! use beaver_core::{
+//!     analyze::Analyzer,
+//!     outcome::{AnalysisResult, FnSink},
+//!     tm::Tm,
+//! };
+//!
+//! let tm = Tm::<2>::from_standard("1RB1LB_1LA1RZ").unwrap();
+//! let mut analyzer = Analyzer::<2>::builder().max_steps(100).build();
+//!
+//! let mut result = None;
+//! analyzer.analyze(tm, &mut FnSink(|_, r| result = Some(r)));
+//! assert_eq!(result, Some(AnalysisResult::Halted { num_steps: 6, num_ones: 4 }));
+//! ```
+//!
+//! Analyzing all TMs with 2 states (without the ones that are symmetric to
+//! others, e.g. all halting transitions move left) and finding the one that
+//! writes the most 1s:
+//!
+//! ```
+//! use beaver_core::{
+//!     analyze::Analyzer,
+//!     gen::{Optimized, TmGenerator},
+//!     outcome::{AnalysisResult, FnSink},
+//! };
+//!
+//! let mut analyzer = Analyzer::<2>::builder().build();
+//! let mut best = None;
+//! let mut sink = FnSink(|tm, result| {
+//!     if let AnalysisResult::Halted { num_ones, .. } = result {
+//!         if best.map_or(true, |(ones, _)| num_ones > ones) {
+//!             best = Some((num_ones, tm));
+//!         }
+//!     }
+//! });
+//! Optimized::<2>::default().for_all(|tm| analyzer.analyze(tm, &mut sink));
+//!
+//! let (ones, tm) = best.unwrap();
+//! assert_eq!(ones, 4);
+//! assert_eq!(tm.to_standard_notation(), "1RB1LB_1LA1LH");
+//! ```
+//!
+//! The same for all TMs with 3 states, but with a sweep, which uses all CPUs
+//! and collects the statistics of all outcomes:
+//!
+//! ```
+//! use beaver_core::{gen::Generator, sweep::SweepBuilder, tm::Tm};
+//!
+//! let summary = SweepBuilder::new(3)
+//!     .generator(Generator::Optimized)
+//!     .max_steps(100)
+//!     .run()
+//!     .unwrap();
+//!
+//! assert_eq!(summary.high_score(), 6);
+//! assert_eq!(summary.most_steps(), 21);
+//! let champion = summary.step_champion().unwrap();
+//! let tm = Tm::<3>::from_id(champion.id).unwrap();
+//! println!("{} halts after {} steps", tm.to_standard_notation(), champion.steps);
+//! ```
+//!
+//! Inspecting a TM and its encoding:
+//!
+//! ```
+//! use beaver_core::tm::{Move, NextState, Tm};
+//!
+//! let tm = Tm::<2>::from_standard("1RB1LB_1LA1RZ").unwrap();
+//! assert_eq!(Tm::<2>::from_id(tm.encoded), Some(tm));
+//!
+//! let action = tm.state(1).on_1();
+//! assert!(action.will_halt());
+//! assert_eq!(action.movement(), Move::Right);
+//! assert_eq!(tm.state(0).on_0().next_state(), NextState::State(1));
+//! ```
+
+pub mod analyze;
+pub mod gen;
+pub mod outcome;
+pub mod summary;
+pub mod sweep;
+pub mod tape;
+pub mod tm;
+
+#[doc(hidden)]
+pub mod batch;
+#[doc(hidden)]
+pub mod best;
+#[cfg(feature = "big-steps")]
+#[doc(hidden)]
+pub mod big_uint;
+#[doc(hidden)]
+pub mod budget;
+#[doc(hidden)]
+pub mod checkpoint;
+#[doc(hidden)]
+pub mod completion;
+#[doc(hidden)]
+pub mod continuation;
+#[doc(hidden)]
+pub mod ctl;
+#[doc(hidden)]
+pub mod dispatch;
+#[doc(hidden)]
+pub mod events;
+#[cfg(feature = "gpu")]
+#[doc(hidden)]
+pub mod gpu;
+#[doc(hidden)]
+pub mod graph;
+#[doc(hidden)]
+pub mod heatmap;
+#[doc(hidden)]
+pub mod macro_machine;
+#[doc(hidden)]
+pub mod machine;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod multi_symbol;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod pool;
+#[doc(hidden)]
+pub mod profile;
+#[doc(hidden)]
+pub mod replay_file;
+#[doc(hidden)]
+pub mod space_time;
@@ -0,0 +1,95 @@
+//! A TM together with its configuration that can be executed step by step.
+
+use crate::{
+    tape::{CellId, CellValue, Tape},
+    tm::{Action, Move, NextState, Tm},
+};
+
+
+/// A running TM. This is a lot slower than `Analyzer::run_tm`, but much more
+/// flexible, which makes it useful for inspecting single TMs.
+pub struct Machine<const N: usize> {
+    tm: Tm<N>,
+    tape: Tape,
+    head: CellId,
+
+    /// The current state or `None` if the TM has halted.
+    state: Option<u8>,
+    steps: u64,
+}
+
+/// Everything that happened in one step.
+#[derive(Debug, Clone, Copy)]
+pub struct Step<const N: usize> {
+    /// The state the TM was in before the step.
+    pub state: u8,
+
+    /// The cell the head was on before the step.
+    pub head: CellId,
+
+    /// The value that was read from the tape.
+    pub read: CellValue,
+
+    /// The action that was executed.
+    pub action: Action<N>,
+}
+
+impl<const N: usize> Machine<N> {
+    /// Creates a machine in the start configuration: state A and the head on
+    /// cell 0 of an empty tape.
+    pub fn new(tm: Tm<N>) -> Self {
+        Self {
+            tm,
+            tape: Tape::new(),
+            head: CellId(0),
+            state: Some(0),
+            steps: 0,
+        }
+    }
+
+    /// Executes one step. Returns `None` if the TM already halted.
+    pub fn step(&mut self) -> Option<Step<N>> {
+        let state = self.state?;
+        let head = self.head;
+        let read = self.tape.get(head);
+        let action = self.tm.state(state).action_for(read);
+
+        self.tape.write(head, action.write_value());
+        self.steps += 1;
+        self.state = match action.next_state() {
+            NextState::HaltState => None,
+            NextState::State(s) => Some(s),
+        };
+
+        // Like `Analyzer::run_tm`, we don't move the head on the halting
+        // transition.
+        if self.state.is_some() {
+            match action.movement() {
+                Move::Left => self.head.0 -= 1,
+                Move::Right => self.head.0 += 1,
+            }
+        }
+
+        Some(Step { state, head, read, action })
+    }
+
+    /// The number of steps executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The current state or `None` if the TM has halted.
+    pub fn state(&self) -> Option<u8> {
+        self.state
+    }
+
+    /// The cell the head is currently on.
+    pub fn head(&self) -> CellId {
+        self.head
+    }
+
+    /// The current tape.
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+}
@@ -0,0 +1,888 @@
+//! A macro machine for simulating long runs quickly. The tape is split into
+//! blocks of `k` cells, and the TM is simulated block by block: when the head
+//! enters a block in some state from one side, the TM is run inside that
+//! block until it leaves it again. The result of that (the new block
+//! contents, the new state, the side it left and the number of steps) is
+//! compiled once and cached.
+//!
+//! The tape is stored as runs of equal blocks on both sides of the head. If
+//! the TM passes through a block and leaves it on the other side in the same
+//! state it entered, it does the same for all blocks of the run, so the whole
+//! run is handled in one go. This makes the typical sweeps over long runs of
+//! equal cells cost a single macro step instead of millions of steps.
+//!
+//! Optionally, the machine also detects rules (see `Rule`): if the runs have
+//! the same blocks as some macro steps before and only their counts changed,
+//! it tries to prove that this repeats for all large enough counts, and then
+//! applies the repetition many times at once. This is what makes the
+//! 47 million steps of the BB(5) champion a few thousand macro steps.
+
+use std::{cmp::min, convert::TryFrom, fmt, time::Instant};
+use ahash::AHashMap;
+
+#[cfg(feature = "big-steps")]
+use crate::big_uint::BigUint;
+use crate::{
+    tape::{CellId, CellValue, RleTape, TapeLike},
+    machine::Machine,
+    tm::{Move, NextState, Tm},
+};
+
+
+/// The largest supported block size.
+pub const MAX_BLOCK_SIZE: u32 = 16;
+
+/// The block size that works well for most TMs.
+pub const DEFAULT_BLOCK_SIZE: u32 = 3;
+
+/// How many macro steps are executed between checks of the deadline.
+const TIME_CHECK_INTERVAL: u64 = 1 << 10;
+
+/// The largest block size `best_block_size` considers.
+const MAX_PROBED_BLOCK_SIZE: u32 = 8;
+
+/// The number of steps `run_to_halt` simulates normally to choose the block
+/// size.
+const PROBE_STEPS: u64 = 1 << 12;
+
+/// Repetitions that take more macro steps than this are not tried as rules.
+const MAX_RULE_PERIOD: u64 = 1 << 12;
+
+/// Configurations with more runs than this are not checked for rules, as
+/// comparing them gets too expensive. TMs with that many runs usually don't
+/// compress well into blocks anyway.
+const MAX_RULE_RUNS: usize = 64;
+
+/// Rules are not applied beyond this many repetitions of a run, so that cell
+/// positions still fit into `i64`: there are at most `MAX_RULE_RUNS` runs, each
+/// of blocks with at most 32 cells.
+const MAX_RUN_COUNT: u64 = 1 << 50;
+
+/// The number of remembered shapes after which they are all forgotten, so
+/// that TMs without rules don't use more and more memory.
+const MAX_HISTORY: usize = 1 << 12;
+
+/// What happens when the head enters a block.
+#[derive(Debug, Clone, Copy)]
+enum Transition {
+    /// The TM leaves the block after `steps` steps.
+    Exit { block: u32, state: u8, right: bool, steps: u64 },
+
+    /// The TM halts inside the block or never leaves it. The caller has to
+    /// simulate that cell by cell.
+    Stop,
+}
+
+/// The macro machine, reused for many TMs to avoid allocations.
+pub struct MacroMachine {
+    block_size: u32,
+
+    /// Runs of equal blocks (contents, count) left and right of the head,
+    /// with the runs next to the head at the end.
+    left: Vec<(u32, u64)>,
+    right: Vec<(u32, u64)>,
+
+    /// Compiled transitions of the current TM, by `key`.
+    transitions: AHashMap<u64, Transition>,
+
+    /// Whether rules are detected and applied.
+    rules: bool,
+
+    /// What is known about each shape seen in the current run, by the hash
+    /// of the shape (see `shape`).
+    history: AHashMap<u64, Seen>,
+
+    /// The number of macro steps spent on proving rules in the current run.
+    /// It's kept below the number of macro steps, so that failing proofs at
+    /// most double the run time.
+    proof_steps: u64,
+}
+
+/// An entry of `MacroMachine::history`.
+enum Seen {
+    /// The shape was last seen after that many macro steps, with these counts
+    /// of the runs (the left ones first).
+    At { macro_step: u64, counts: Vec<u64> },
+
+    /// A rule was proven for the shape. As different shapes can have the
+    /// same hash, the full shape is stored with it.
+    Rule(Vec<u32>, Rule),
+}
+
+/// A proven rule: starting in a configuration with a given shape, where the
+/// `i`-th run has `min[i] + x[i]` blocks (with any `x[i] >= 0`), the TM gets
+/// to the same shape with `min[i] + x[i] + delta[i]` blocks after `steps`
+/// steps, during which the head moves by `shift` blocks. Runs with a `delta`
+/// of 0 have to have exactly `min` blocks instead.
+#[derive(Debug, Clone)]
+struct Rule {
+    min: Vec<u64>,
+    delta: Vec<i64>,
+    steps: Expr,
+    shift: Expr,
+}
+
+/// A linear expression `constant + sum(coeffs[i] * x[i])` in the unknown run
+/// counts of a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expr {
+    constant: i64,
+    coeffs: Vec<i64>,
+}
+
+impl MacroMachine {
+    /// Creates a macro machine with blocks of `block_size` cells. Panics if
+    /// that is 0 or larger than `MAX_BLOCK_SIZE`.
+    pub fn new(block_size: u32) -> Self {
+        assert!((1..=MAX_BLOCK_SIZE).contains(&block_size), "invalid block size");
+        Self {
+            block_size,
+            left: Vec::new(),
+            right: Vec::new(),
+            transitions: AHashMap::new(),
+            rules: false,
+            history: AHashMap::new(),
+            proof_steps: 0,
+        }
+    }
+
+    /// Sets the block size. Panics if that is 0 or larger than
+    /// `MAX_BLOCK_SIZE`.
+    pub fn set_block_size(&mut self, block_size: u32) {
+        assert!((1..=MAX_BLOCK_SIZE).contains(&block_size), "invalid block size");
+        self.block_size = block_size;
+    }
+
+    /// Sets whether rules are detected and applied (see `Rule`). Default:
+    /// `false`.
+    pub fn set_rules(&mut self, rules: bool) {
+        self.rules = rules;
+    }
+
+    /// Continues the run of `tm`, which is in `state` with the head on
+    /// `head` after `steps` steps on tape `from`. It stops shortly before the
+    /// TM halts, gets stuck inside a block, would exceed `max_steps` or when
+    /// `deadline` has passed. Returns state, head and steps at that point and
+    /// writes the exact tape to `to` (after clearing it), so the caller can
+    /// simply continue step by step. Its written range spans the one of
+    /// `from` and all 1s, but not the blank cells visited in between, which
+    /// are not known exactly.
+    pub fn run<const N: usize, S: StepCount>(
+        &mut self,
+        tm: Tm<N>,
+        (mut state, head, steps): (u8, CellId, S),
+        max_steps: S,
+        deadline: Option<Instant>,
+        from: &impl TapeLike,
+        to: &mut impl TapeLike,
+    ) -> (u8, CellId, S) {
+        let (size, k) = (self.block_size, self.block_size as i64);
+        self.transitions.clear();
+        self.history.clear();
+        self.proof_steps = 0;
+        self.load(head.0, from);
+
+        // The head is either on the first cell right of `boundary` (facing
+        // right) or the last cell left of it (facing left), entering the
+        // block next to `boundary` on that side.
+        let mut boundary = head.0;
+        let mut facing_right = true;
+        let mut total = steps;
+        let mut macro_steps = 0u64;
+        let mut swept = false;
+        loop {
+            macro_steps += 1;
+            if macro_steps & (TIME_CHECK_INTERVAL - 1) == 0
+                && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                break;
+            }
+
+            // Rules are only looked for right after the TM swept over a run, as
+            // looking for them is expensive and growing runs have to be swept
+            // over at some point anyway.
+            if self.rules && swept {
+                let remaining = total.headroom(&max_steps);
+                if let Some((steps, shift)) =
+                    self.accelerate(tm, state, facing_right, macro_steps, remaining)
+                {
+                    total.add_steps(steps);
+                    boundary += shift * k;
+                    continue;
+                }
+            }
+
+            let (ahead, behind) = match facing_right {
+                true => (&mut self.right, &mut self.left),
+                false => (&mut self.left, &mut self.right),
+            };
+            // Beyond the stored runs, there are infinitely many blank blocks.
+            let (block, available) = ahead.last().copied().unwrap_or((0, u64::MAX));
+
+            let key = (state as u64) << 33 | (facing_right as u64) << 32 | block as u64;
+            let transition = *self.transitions.entry(key).or_insert_with(|| {
+                compile(tm, size, state, block, facing_right)
+            });
+            let (new_block, new_state, exit_right, block_steps) = match transition {
+                Transition::Exit { block, state, right, steps } => (block, state, right, steps),
+                Transition::Stop => break,
+            };
+
+            // If the TM passes through the block without changing its state,
+            // it does so for the whole run.
+            let passes = exit_right == facing_right;
+            let count = match passes && new_state == state {
+                true => available,
+                false => 1,
+            };
+            let remaining = min(total.headroom(&max_steps), u64::MAX.into()) as u64;
+            let count = min(count, remaining / block_steps);
+            if count == 0 {
+                break;
+            }
+
+            if let Some(run) = ahead.last_mut() {
+                run.1 -= count;
+                if run.1 == 0 {
+                    ahead.pop();
+                }
+            }
+            if passes {
+                push(behind, new_block, count);
+                boundary += if facing_right { count as i64 * k } else { -(count as i64) * k };
+            } else {
+                push(ahead, new_block, 1);
+            }
+
+            total.add_steps((count * block_steps).into());
+            state = new_state;
+            facing_right = exit_right;
+            swept = count > 1;
+        }
+
+        self.store(boundary, to);
+        let range = from.written_range();
+        if range.start < range.end {
+            for id in [range.start, CellId(range.end.0 - 1)] {
+                to.write(id, to.get(id));
+            }
+        }
+        let head = CellId(if facing_right { boundary } else { boundary - 1 });
+        (state, head, total)
+    }
+
+    /// Tries to apply a rule to the current configuration, recording its
+    /// shape or proving a new rule otherwise. Returns the number of steps and
+    /// the movement of the head in blocks if a rule was applied.
+    fn accelerate<const N: usize>(
+        &mut self,
+        tm: Tm<N>,
+        state: u8,
+        facing_right: bool,
+        macro_step: u64,
+        remaining: u128,
+    ) -> Option<(u128, i64)> {
+        let Self { block_size, left, right, transitions, history, proof_steps, .. } = self;
+        if left.len() + right.len() > MAX_RULE_RUNS {
+            return None;
+        }
+
+        // Computing the full shape allocates, so only its hash is computed in
+        // each macro step.
+        let hash = [state as u32, facing_right as u32, left.len() as u32].iter().copied()
+            .chain(left.iter().chain(&*right).map(|run| run.0))
+            .fold(0u64, |hash, x| {
+                (hash.rotate_left(5) ^ x as u64).wrapping_mul(0x517c_c1b7_2722_0a95)
+            });
+
+        let seen = match history.get_mut(&hash) {
+            Some(seen) => seen,
+            None => {
+                if history.len() >= MAX_HISTORY {
+                    history.clear();
+                }
+                let counts = left.iter().chain(&*right).map(|run| run.1).collect();
+                history.insert(hash, Seen::At { macro_step, counts });
+                return None;
+            }
+        };
+
+        let (before, counts) = match seen {
+            Seen::Rule(rule_shape, rule) => {
+                return match *rule_shape == shape(state, facing_right, left, right) {
+                    true => apply(rule, left, right, remaining),
+                    false => None,
+                };
+            }
+            Seen::At { macro_step, counts } => (macro_step, counts),
+        };
+        let period = macro_step - *before;
+        if period <= MAX_RULE_PERIOD && *proof_steps + period <= macro_step {
+            *proof_steps += period;
+            let start = Start { state, facing_right, left: &left[..], right: &right[..] };
+            if let Some(rule) = prove(tm, *block_size, transitions, start, counts, period) {
+                let rule_shape = shape(state, facing_right, left, right);
+                let out = apply(&rule, left, right, remaining);
+                *seen = Seen::Rule(rule_shape, rule);
+                return out;
+            }
+        }
+
+        *before = macro_step;
+        counts.clear();
+        counts.extend(left.iter().chain(&*right).map(|run| run.1));
+        None
+    }
+
+    /// Splits the written range of `tape` into blocks, aligned such that a
+    /// block starts at `head`.
+    fn load(&mut self, head: i64, tape: &impl TapeLike) {
+        let k = self.block_size as i64;
+        let range = tape.written_range();
+        let block_at = |start: i64| {
+            (0..k).fold(0, |block, i| block | (tape.get(CellId(start + i)).0 as u32) << i)
+        };
+
+        self.left.clear();
+        self.right.clear();
+        let mut end = head;
+        while end > range.start.0 {
+            push(&mut self.left, block_at(end - k), 1);
+            end -= k;
+        }
+        let mut start = head;
+        while start < range.end.0 {
+            push(&mut self.right, block_at(start), 1);
+            start += k;
+        }
+
+        // The loops above push the blocks next to `head` first, but they have
+        // to be at the end.
+        self.left.reverse();
+        self.right.reverse();
+    }
+
+    /// Writes all blocks to the cleared `tape`, with the blocks left of
+    /// `boundary` ending there. Only the 1s are written, so that the written
+    /// range doesn't depend on the block size.
+    fn store(&self, boundary: i64, tape: &mut impl TapeLike) {
+        let k = self.block_size as i64;
+        let full = (1u32 << self.block_size) - 1;
+        tape.clear();
+
+        // Runs of full blocks are written at once, which is much faster for
+        // an `RleTape`.
+        let mut write = |start: i64, block: u32, count: u64| {
+            if block == full {
+                let end = start + count as i64 * k;
+                tape.write_range(CellId(start)..CellId(end), CellValue(true));
+                return;
+            }
+            for b in 0..count as i64 {
+                for i in (0..k).filter(|i| block >> i & 1 == 1) {
+                    tape.write(CellId(start + b * k + i), CellValue(true));
+                }
+            }
+        };
+
+        let mut end = boundary;
+        for &(block, count) in self.left.iter().rev() {
+            end -= count as i64 * k;
+            write(end, block, count);
+        }
+        let mut start = boundary;
+        for &(block, count) in self.right.iter().rev() {
+            write(start, block, count);
+            start += count as i64 * k;
+        }
+    }
+}
+
+/// A counter for the steps of `run_to_halt`: `u64`, or `BigUint` for runs
+/// that might exceed it (see `run_to_halt_big`).
+pub trait StepCount: Clone + Ord + From<u64> + fmt::Display {
+    /// Adds `steps`, which are at most `headroom` to some maximum.
+    fn add_steps(&mut self, steps: u128);
+    fn sub_steps(&mut self, steps: u64);
+
+    /// The number of steps left until `max`, or `u128::MAX` if that's more.
+    fn headroom(&self, max: &Self) -> u128;
+}
+
+impl StepCount for u64 {
+    fn add_steps(&mut self, steps: u128) {
+        *self += u64::try_from(steps).expect("added more steps than the headroom");
+    }
+
+    fn sub_steps(&mut self, steps: u64) {
+        *self -= steps;
+    }
+
+    fn headroom(&self, max: &Self) -> u128 {
+        max.saturating_sub(*self).into()
+    }
+}
+
+#[cfg(feature = "big-steps")]
+impl StepCount for BigUint {
+    fn add_steps(&mut self, steps: u128) {
+        self.add_u128(steps);
+    }
+
+    fn sub_steps(&mut self, steps: u64) {
+        *self = self.checked_sub(&steps.into()).expect("step count would be negative");
+    }
+
+    fn headroom(&self, max: &Self) -> u128 {
+        max.checked_sub(self).map_or(0, |rest| rest.to_u128().unwrap_or(u128::MAX))
+    }
+}
+
+/// The result of `run_to_halt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunResult<S = u64> {
+    /// The number of executed steps, including the halting transition. If
+    /// the TM did not halt, this is the maximum number of steps.
+    pub steps: S,
+
+    /// The number of 1s on the tape.
+    pub ones: u64,
+
+    /// The values read and written by the halting transition, or `None` if
+    /// the TM did not halt within the maximum number of steps.
+    pub halt: Option<(CellValue, CellValue)>,
+}
+
+/// Runs `tm` from the empty tape for up to `max_steps` steps, like `Machine`
+/// would, but with a macro machine and rules for almost all of the run, so
+/// that even runs with billions of steps finish quickly. The block size is
+/// chosen by `best_block_size` after `PROBE_STEPS` steps.
+pub fn run_to_halt<const N: usize>(tm: Tm<N>, max_steps: u64) -> RunResult {
+    run_counted(tm, max_steps)
+}
+
+/// Like `run_to_halt`, but counts the steps exactly even beyond `u64`.
+#[cfg(feature = "big-steps")]
+pub fn run_to_halt_big<const N: usize>(tm: Tm<N>, max_steps: BigUint) -> RunResult<BigUint> {
+    run_counted(tm, max_steps)
+}
+
+fn run_counted<const N: usize, S: StepCount>(tm: Tm<N>, max_steps: S) -> RunResult<S> {
+    let mut machine = Machine::new(tm);
+    let probe_steps = min(S::from(0).headroom(&max_steps), PROBE_STEPS.into()) as u64;
+    while machine.steps() < probe_steps {
+        let step = match machine.step() {
+            Some(step) => step,
+            None => break,
+        };
+        if machine.state().is_none() {
+            let halt = Some((step.read, step.action.write_value()));
+            let ones = machine.tape().ones();
+            return RunResult { steps: machine.steps().into(), ones, halt };
+        }
+    }
+
+    let head = machine.head();
+    let mut macro_machine = MacroMachine::new(best_block_size(head, machine.tape()));
+    macro_machine.set_rules(true);
+    let mut tape = RleTape::new();
+    let start = (machine.state().unwrap(), head, machine.steps().into());
+    let (mut state, mut head, mut steps)
+        = macro_machine.run(tm, start, max_steps.clone(), None, machine.tape(), &mut tape);
+
+    // The macro machine stopped because the TM halts inside a block, never
+    // leaves it or is close to `max_steps`. In the first two cases, this is
+    // clear after at most as many steps as `compile` needs.
+    let size = macro_machine.block_size;
+    let compile_steps = N as u128 * u128::from(size) * (1 << size) + 1;
+    for executed in 1..=min(steps.headroom(&max_steps), compile_steps) {
+        let read = tape.get(head);
+        let action = tm.state(state).action_for(read);
+        tape.write(head, action.write_value());
+        match action.next_state() {
+            NextState::HaltState => {
+                steps.add_steps(executed);
+                let halt = Some((read, action.write_value()));
+                return RunResult { steps, ones: tape.ones(), halt };
+            }
+            NextState::State(s) => state = s,
+        }
+        head.0 += match action.movement() {
+            Move::Left => -1,
+            Move::Right => 1,
+        };
+    }
+
+    RunResult { steps: max_steps, ones: tape.ones(), halt: None }
+}
+
+/// Returns the largest block size (up to `MAX_PROBED_BLOCK_SIZE`) for which
+/// `tape` consists of the fewest runs, with a block starting at `head`. Rules are
+/// found best with that block size, as they only consider runs.
+pub fn best_block_size(head: CellId, tape: &impl TapeLike) -> u32 {
+    let mut macro_machine = MacroMachine::new(1);
+    // Larger blocks are preferred, as each macro step then does more.
+    (1..=MAX_PROBED_BLOCK_SIZE).rev()
+        .min_by_key(|&size| {
+            macro_machine.block_size = size;
+            macro_machine.load(head.0, tape);
+            macro_machine.left.len() + macro_machine.right.len()
+        })
+        .unwrap()
+}
+
+/// Adds `count` blocks `block` next to the head.
+fn push(runs: &mut Vec<(u32, u64)>, block: u32, count: u64) {
+    match runs.last_mut() {
+        Some(run) if run.0 == block => run.1 += count,
+        _ => runs.push((block, count)),
+    }
+}
+
+/// The configuration without the run counts: the state, the direction and
+/// the blocks of all runs.
+fn shape(state: u8, facing_right: bool, left: &[(u32, u64)], right: &[(u32, u64)]) -> Vec<u32> {
+    let mut shape = vec![state as u32, facing_right as u32, left.len() as u32];
+    shape.extend(left.iter().chain(right).map(|run| run.0));
+    shape
+}
+
+/// The configuration a rule is proven from.
+struct Start<'a> {
+    state: u8,
+    facing_right: bool,
+    left: &'a [(u32, u64)],
+    right: &'a [(u32, u64)],
+}
+
+/// Tries to prove a rule for the shape of `start`, which was seen `period`
+/// macro steps before with the run counts `before`. The runs whose counts
+/// changed are replaced by unknowns, and the TM is simulated symbolically for
+/// `period` macro steps. That only works if the simulation never depends on
+/// the unknowns, e.g. on whether a run is used up.
+fn prove<const N: usize>(
+    tm: Tm<N>,
+    size: u32,
+    transitions: &mut AHashMap<u64, Transition>,
+    start: Start<'_>,
+    before: &[u64],
+    period: u64,
+) -> Option<Rule> {
+    let runs = || start.left.iter().chain(start.right);
+    let vars = before.len();
+    if runs().count() != vars {
+        return None;
+    }
+    let delta = runs().zip(before)
+        .map(|(run, &before)| {
+            i64::try_from(run.1).ok()?.checked_sub(i64::try_from(before).ok()?)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    // A run that shrinks by `d` has to have more than `d` blocks, as it would
+    // vanish otherwise and change the shape. Runs that don't change have to
+    // have exactly the same count.
+    let min = runs().zip(&delta)
+        .map(|(run, &d)| match d {
+            0 => run.1,
+            d if d < 0 => d.unsigned_abs() + 1,
+            _ => 1,
+        })
+        .collect::<Vec<_>>();
+    let count_expr = |i: usize| {
+        let mut e = Expr::constant(i64::try_from(min[i]).ok()?, vars);
+        if delta[i] != 0 {
+            e.coeffs[i] = 1;
+        }
+        Some(e)
+    };
+    let mut left = (0..start.left.len())
+        .map(|i| Some((start.left[i].0, count_expr(i)?)))
+        .collect::<Option<Vec<_>>>()?;
+    let mut right = (0..start.right.len())
+        .map(|i| Some((start.right[i].0, count_expr(start.left.len() + i)?)))
+        .collect::<Option<Vec<_>>>()?;
+
+    // Like the loop in `MacroMachine::run`.
+    let mut state = start.state;
+    let mut facing_right = start.facing_right;
+    let mut steps = Expr::constant(0, vars);
+    let mut shift = Expr::constant(0, vars);
+    for _ in 0..period {
+        let (ahead, behind) = match facing_right {
+            true => (&mut right, &mut left),
+            false => (&mut left, &mut right),
+        };
+        let block = ahead.last().map_or(0, |run| run.0);
+        let key = (state as u64) << 33 | (facing_right as u64) << 32 | block as u64;
+        let transition = *transitions.entry(key).or_insert_with(|| {
+            compile(tm, size, state, block, facing_right)
+        });
+        let (new_block, new_state, exit_right, block_steps) = match transition {
+            Transition::Exit { block, state, right, steps } => (block, state, right, steps),
+            Transition::Stop => return None,
+        };
+
+        let passes = exit_right == facing_right;
+        let sign = if facing_right { 1 } else { -1 };
+        if passes && new_state == state {
+            // Passing the infinitely many blanks is no rule.
+            let (_, count) = ahead.pop()?;
+            steps.add_scaled(&count, block_steps as i64);
+            shift.add_scaled(&count, sign);
+            push_expr(behind, new_block, count);
+        } else {
+            if let Some(run) = ahead.last_mut() {
+                run.1.constant -= 1;
+                if run.1.is_zero() {
+                    ahead.pop();
+                } else if run.1.constant <= 0 {
+                    return None;
+                }
+            }
+            steps.constant += block_steps as i64;
+            let one = Expr::constant(1, vars);
+            if passes {
+                shift.constant += sign;
+                push_expr(behind, new_block, one);
+            } else {
+                push_expr(ahead, new_block, one);
+            }
+        }
+
+        state = new_state;
+        facing_right = exit_right;
+    }
+
+    let same_shape = (state, facing_right) == (start.state, start.facing_right)
+        && left.len() == start.left.len()
+        && right.len() == start.right.len();
+    if !same_shape {
+        return None;
+    }
+    for (i, (run, end)) in runs().zip(left.iter().chain(&right)).enumerate() {
+        let mut expected = count_expr(i)?;
+        expected.constant += delta[i];
+        if run.0 != end.0 || expected != end.1 {
+            return None;
+        }
+    }
+
+    Some(Rule { min, delta, steps, shift })
+}
+
+/// Like `push`, for the symbolic simulation in `prove`.
+fn push_expr(runs: &mut Vec<(u32, Expr)>, block: u32, count: Expr) {
+    match runs.last_mut() {
+        Some(run) if run.0 == block => run.1.add_scaled(&count, 1),
+        _ => runs.push((block, count)),
+    }
+}
+
+/// Applies `rule` as often as possible, but without exceeding `remaining`
+/// steps. Returns the steps and the movement of the head in blocks, or `None`
+/// if the rule can't be applied at all.
+fn apply(
+    rule: &Rule,
+    left: &mut [(u32, u64)],
+    right: &mut [(u32, u64)],
+    remaining: u128,
+) -> Option<(u128, i64)> {
+    // How often the rule can be applied before a shrinking run gets too short.
+    let mut max_n = min(remaining, i128::MAX as u128) as i128;
+    let mut x = Vec::with_capacity(rule.min.len());
+    for ((run, &min_count), &delta) in left.iter().chain(&*right).zip(&rule.min).zip(&rule.delta) {
+        if run.1 < min_count || (delta == 0 && run.1 != min_count) {
+            return None;
+        }
+        let free = (run.1 - min_count) as i128;
+        x.push(free);
+        if delta < 0 {
+            max_n = max_n.min(free / -delta as i128 + 1);
+        } else if delta > 0 {
+            max_n = max_n.min(MAX_RUN_COUNT.saturating_sub(run.1) as i128 / delta as i128);
+        }
+    }
+
+    // The counts change by `delta` with each application, so the steps (and
+    // the shift) of `n` applications are an arithmetic series. With a large
+    // `remaining`, the series of the upper bound can overflow even `i128`,
+    // which just means `n` is too large.
+    let total = |e: &Expr, n: i128| -> Option<i128> {
+        let first = e.constant as i128
+            + e.coeffs.iter().zip(&x).map(|(&c, &x)| c as i128 * x).sum::<i128>();
+        let growth = e.coeffs.iter().zip(&rule.delta).map(|(&c, &d)| (c * d) as i128).sum::<i128>();
+        let pairs = n.checked_mul(n - 1)? / 2;
+        n.checked_mul(first)?.checked_add(growth.checked_mul(pairs)?)
+    };
+    let (mut lo, mut hi) = (0, max_n);
+    while lo < hi {
+        let mid = lo + (hi - lo + 1) / 2;
+        if total(&rule.steps, mid).is_some_and(|steps| steps as u128 <= remaining) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    if lo == 0 {
+        return None;
+    }
+
+    let steps = total(&rule.steps, lo)?;
+    let shift = total(&rule.shift, lo)?;
+    for (run, &delta) in left.iter_mut().chain(right.iter_mut()).zip(&rule.delta) {
+        run.1 = (run.1 as i128 + delta as i128 * lo) as u64;
+    }
+    Some((steps as u128, shift as i64))
+}
+
+impl Expr {
+    fn constant(constant: i64, vars: usize) -> Self {
+        Self { constant, coeffs: vec![0; vars] }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.constant == 0 && self.coeffs.iter().all(|&c| c == 0)
+    }
+
+    /// Adds `factor * other`.
+    fn add_scaled(&mut self, other: &Expr, factor: i64) {
+        self.constant += factor * other.constant;
+        for (c, o) in self.coeffs.iter_mut().zip(&other.coeffs) {
+            *c += factor * o;
+        }
+    }
+}
+
+/// Runs the TM inside a block of `size` cells with contents `block`, starting
+/// on its leftmost cell (or rightmost if `!from_left`) in `state`.
+fn compile<const N: usize>(
+    tm: Tm<N>,
+    size: u32,
+    mut state: u8,
+    mut block: u32,
+    from_left: bool,
+) -> Transition {
+    // If the TM runs longer than there are configurations inside the block,
+    // one of them repeats, so it never leaves.
+    let limit = N as u64 * size as u64 * (1u64 << size);
+    let mut pos = if from_left { 0 } else { size as i32 - 1 };
+    let mut steps = 0;
+    loop {
+        if steps > limit {
+            return Transition::Stop;
+        }
+
+        let read = CellValue(block >> pos & 1 == 1);
+        let action = tm.state(state).action_for(read);
+        state = match action.next_state() {
+            NextState::HaltState => return Transition::Stop,
+            NextState::State(s) => s,
+        };
+        block = block & !(1 << pos) | (action.write_value().0 as u32) << pos;
+        steps += 1;
+        pos += match action.movement() {
+            Move::Left => -1,
+            Move::Right => 1,
+        };
+
+        if pos < 0 || pos >= size as i32 {
+            return Transition::Exit { block, state, right: pos >= 0, steps };
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{machine::Machine, tape::RleTape};
+
+    /// Compares the macro machine with plain simulation, with stops after
+    /// various numbers of steps.
+    fn check<const N: usize>(tm: Tm<N>, start: u64, max_steps: u64) {
+        for (block_size, rules) in (1..=6).flat_map(|k| [(k, false), (k, true)]) {
+            let mut machine = Machine::new(tm);
+            while machine.steps() < start {
+                machine.step();
+            }
+
+            let mut tape = RleTape::new();
+            let before = machine.tape().written_range();
+            let start = (machine.state().unwrap(), machine.head(), machine.steps());
+            let mut macro_machine = MacroMachine::new(block_size);
+            macro_machine.set_rules(rules);
+            let (state, head, steps)
+                = macro_machine.run(tm, start, max_steps, None, machine.tape(), &mut tape);
+
+            assert!(steps <= max_steps);
+            while machine.steps() < steps {
+                machine.step();
+            }
+            assert_eq!((machine.state(), machine.head()), (Some(state), head));
+            let cells = machine.tape().written_range().start.0 - 20
+                ..machine.tape().written_range().end.0 + 20;
+            for id in cells {
+                assert_eq!(machine.tape().get(CellId(id)), tape.get(CellId(id)));
+            }
+
+            // The written range ends with a 1 or a cell written before (or the
+            // start cell), so that it doesn't depend on the block size.
+            let range = tape.written_range();
+            let known = |id| id == CellId(0) || before.contains(&id) || tape.get(id).0;
+            assert!(range.is_empty() || known(range.start) && known(CellId(range.end.0 - 1)));
+            assert!(before.is_empty() || range.start <= before.start && before.end <= range.end);
+        }
+    }
+
+    #[test]
+    fn same_as_simulation() {
+        // The BB(4) champion halts after 107 steps.
+        let tm = <Tm<4>>::from_standard("1RB1LB_1LA0LC_1RH1LD_1RD0RA").unwrap();
+        for start in [0, 1, 10, 50] {
+            for max_steps in [start, start + 1, 60, 100, 107, 1000] {
+                check(tm, start, max_steps);
+            }
+        }
+
+        // A binary counter, which never halts.
+        let tm = <Tm<3>>::from_standard("1LB0RC_1RA1LB_0LA1RH").unwrap();
+        for max_steps in [0, 5, 100, 1000, 10_000] {
+            check(tm, 0, max_steps);
+        }
+    }
+
+    #[test]
+    fn runs_to_halt() {
+        // The BB(5) champion.
+        let tm = <Tm<5>>::from_standard("1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RH0LA").unwrap();
+        let halt = Some((CellValue(false), CellValue(true)));
+        let result = run_to_halt(tm, 50_000_000);
+        assert_eq!(result, RunResult { steps: 47_176_870, ones: 4098, halt });
+        let result = run_to_halt(tm, 47_176_869);
+        assert_eq!((result.steps, result.halt), (47_176_869, None));
+
+        // The BB(4) champion halts before the block size is chosen.
+        let tm = <Tm<4>>::from_standard("1RB1LB_1LA0LC_1RH1LD_1RD0RA").unwrap();
+        let halt = Some((CellValue(false), CellValue(true)));
+        assert_eq!(run_to_halt(tm, 1000), RunResult { steps: 107, ones: 13, halt });
+
+        // A bouncer, which never halts, and a TM that stays on a few cells.
+        let tm = <Tm<3>>::from_standard("0LC0RB_1RA1LH_1LB1LA").unwrap();
+        let result = run_to_halt(tm, 1 << 40);
+        assert_eq!((result.steps, result.halt), (1 << 40, None));
+        let tm = <Tm<3>>::from_standard("1RB1LA_0LA0RB_1RH1RH").unwrap();
+        for max_steps in [0, 1, 1000, 100_000] {
+            assert_eq!(run_to_halt(tm, max_steps).halt, None);
+        }
+    }
+
+    #[cfg(feature = "big-steps")]
+    #[test]
+    fn runs_beyond_u64() {
+        let tm = <Tm<2>>::from_standard("1RB1LA_1LA1RB").unwrap();
+        let max_steps = "1_000_000_000_000_000_000_000_000".parse::<BigUint>().unwrap();
+        let result = run_to_halt_big(tm, max_steps.clone());
+        assert_eq!((result.steps, result.halt), (max_steps, None));
+    }
+}
@@ -1,14 +1,46 @@
-#![feature(adt_const_params)]
-#![allow(incomplete_features)]
-
 use structopt::StructOpt;
 
-mod analyze;
+// The core lives in the library (see `lib.rs`). Importing its modules here
+// lets the CLI refer to them as `crate::tm` etc., like to its own modules.
+#[cfg(feature = "big-steps")]
+use beaver_core::big_uint;
+#[cfg(feature = "gpu")]
+use beaver_core::gpu;
+#[cfg(feature = "profile")]
+use beaver_core::profile;
+use beaver_core::{
+    analyze::{self, HaltConvention},
+    batch,
+    best,
+    budget,
+    checkpoint,
+    completion,
+    continuation,
+    dispatch,
+    events,
+    gen,
+    graph,
+    heatmap,
+    macro_machine,
+    machine,
+    manifest,
+    multi_symbol,
+    outcome,
+    output,
+    pool,
+    replay_file,
+    space_time,
+    summary,
+    sweep,
+    tape,
+    tm,
+};
+
+mod bundle;
 mod cmd;
-mod gen;
-mod outcome;
-mod tape;
-mod tm;
+mod failed;
+mod render;
+mod summary_file;
 
 
 fn main() {
@@ -17,17 +49,35 @@ fn main() {
     let res = match args {
         Args::Single(args) => cmd::single::run(args),
         Args::Full(args) => cmd::full::run(args),
+        Args::Range(args) => cmd::range::run(args),
+        Args::Merge(args) => cmd::merge::run(args),
+        Args::Continue(args) => cmd::continuation::run(args),
+        Args::Complete(args) => cmd::complete::run(args),
+        Args::Resume(args) => cmd::resume::run(args),
+        Args::ExportRun(args) => cmd::export_run::run(args),
+        Args::ImportRun(args) => cmd::import_run::run(args),
+        Args::ReplayFailed(args) => cmd::replay_failed::run(args),
+        Args::Replay(args) => cmd::replay::run(args),
+        Args::Sheet(args) => cmd::sheet::run(args),
+        Args::VerifyDeciders(args) => cmd::verify::run(args),
+        Args::GraphStats(args) => cmd::graph_stats::run(args),
+        Args::Dump(args) => cmd::dump::run(args),
+        Args::Diff(args) => cmd::diff::run(args),
+        Args::Inspect(args) => cmd::inspect::run(args),
+        Args::Holdouts(args) => cmd::holdouts::run(args),
     };
 
     if let Err(e) = res {
         bunt::eprintln!("{$red}An error occured!{/$}");
         eprintln!("{:?}", e);
+        std::process::exit(1);
     }
 }
 
 /// Simple program to play the Busy Beaver game. That is: to simulate Turing
 /// machines (TMs) with N states on a binary tape (each cell is either 0 or 1,
-/// with all being initialized to 0). TMs up to N=6 are supported.
+/// with all being initialized to 0). Single TMs with up to N=25 states can be
+/// run, all other commands support up to N=6.
 #[derive(StructOpt, Debug, Clone)]
 pub enum Args {
     /// Shows information about and runs a single Turing machine, specified by
@@ -36,16 +86,102 @@ pub enum Args {
 
     /// Analyzes the full class of TMs with N states.
     Full(cmd::full::Args),
+
+    /// Like `full`, but only analyzes the TMs with the given indices in the
+    /// generator's order, e.g. to split a large run over several machines.
+    Range(cmd::range::Args),
+
+    /// Combines the summaries of several runs over different ranges of TMs
+    /// (see `range --summary-out`) and prints the report of all of them.
+    Merge(cmd::merge::Args),
+
+    /// Continues running TMs that were aborted after the maximum number of
+    /// steps in a previous run (see `full --continuations`).
+    Continue(cmd::continuation::Args),
+
+    /// Searches the completions of a partially specified TM: the actions of
+    /// its other transitions that make it run the longest (or write the most
+    /// 1s), e.g. to extend a known champion to more states.
+    Complete(cmd::complete::Args),
+
+    /// Resumes an interrupted run of `full` from a checkpoint (see
+    /// `full --checkpoint`) and prints the report of the complete run.
+    Resume(cmd::resume::Args),
+
+    /// Bundles the checkpoint of a sweep (see `full --checkpoint`) and other
+    /// files of it into a single file, to move the sweep to another machine.
+    ExportRun(cmd::export_run::Args),
+
+    /// Unpacks a bundle written by `export-run`, so that the sweep can be
+    /// continued with `resume`.
+    ImportRun(cmd::import_run::Args),
+
+    /// Analyzes the TMs that panicked in a run of `full` again (see `full
+    /// --failed-out`), single-threaded, e.g. to debug the panic or to check
+    /// that it is fixed.
+    ReplayFailed(cmd::replay_failed::Args),
+
+    /// Runs one TM of a sweep again with the settings stored in its raw
+    /// output (see `full --raw-out`) and checks that it gets the stored
+    /// outcome. On a mismatch, a trace of the TM is printed.
+    Replay(cmd::replay::Args),
+
+    /// Renders small space-time diagrams of many TMs into a grid in a single
+    /// PNG image (a "contact sheet"), useful for visually triaging a list of
+    /// TMs.
+    Sheet(cmd::sheet::Args),
+
+    /// Analyzes all TMs with up to 3 states and compares the results to plain
+    /// simulation with a higher step limit. Fails if any decider contradicts
+    /// this ground truth.
+    VerifyDeciders(cmd::verify::Args),
+
+    /// Analyzes the full class of TMs with N states (or a sample) and writes
+    /// statistics of their state graphs (strongly connected components,
+    /// distance to the halt state, ...) together with the outcomes as CSV.
+    GraphStats(cmd::graph_stats::Args),
+
+    /// Analyzes the full class of TMs with N states and writes the key
+    /// results (counts, champions, histograms) in a canonical, sorted text
+    /// format. Useful for regression tests: the output only changes if the
+    /// results change.
+    Dump(cmd::dump::Args),
+
+    /// Compares two dumps (see `dump`) or subtracts one from the other, e.g.
+    /// to see which TMs a generator optimization removes.
+    Diff(cmd::diff::Args),
+
+    /// Explores the stored results of a sweep (see `full --raw-out`): prints
+    /// the number of TMs per outcome or, with `--outcome`, statistics and
+    /// random examples of one outcome.
+    Inspect(cmd::inspect::Args),
+
+    /// Manages a directory of TMs that are still undecided ("holdouts") over
+    /// many iterations: importing new ones, running the deciders on them
+    /// again and reporting how the set shrinks over time.
+    Holdouts(cmd::holdouts::Args),
 }
 
 #[derive(StructOpt, Debug, Clone)]
 pub struct SharedArgs {
-    /// Number of states of the Turing machine.
-    #[structopt(short, possible_values(&["1", "2", "3", "4", "5", "6"]))]
+    /// Number of states of the Turing machine. All commands support 1 to 6
+    /// states, only 'single' supports up to 25.
+    #[structopt(short, parse(try_from_str = cmd::parse_n))]
     pub n: u8,
 
     /// Number of steps after which TMs are stopped.
     #[structopt(long, default_value = "200", global = true)]
-    pub max_steps: u32,
+    pub max_steps: u64,
+
+    /// Whether the final transition into the halt state counts: 'include'
+    /// counts it as a step and counts the symbol it writes (the usual
+    /// convention); 'exclude' ignores the halting transition completely.
+    #[structopt(
+        long,
+        default_value = "include",
+        possible_values(&["include", "exclude"]),
+        global = true,
+    )]
+    pub halt_convention: HaltConvention,
 }
 
@@ -0,0 +1,82 @@
+//! Information about how an output artifact was produced, embedded into all
+//! files this program writes, so that they can be reproduced later.
+
+use std::{
+    env, fmt,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+
+/// A list of key-value pairs describing a run: the program version, command
+/// line and host, plus the settings of the run.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    entries: Vec<(&'static str, String)>,
+}
+
+impl Manifest {
+    /// Creates a manifest with the general information about this program
+    /// and the machine it runs on, followed by the given run settings.
+    pub fn new(settings: &[(&'static str, String)]) -> Self {
+        let command = env::args()
+            .map(|arg| {
+                if arg.contains(char::is_whitespace) { format!("'{}'", arg) } else { arg }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs().to_string())
+            .unwrap_or_else(|_| "unknown".into());
+        let host = format!(
+            "{}/{}, {} CPUs",
+            env::consts::OS,
+            env::consts::ARCH,
+            num_cpus::get(),
+        );
+        let revision = option_env!("BEAVER_GIT_REVISION").unwrap_or("unknown");
+
+        let mut entries = vec![
+            ("version", env!("CARGO_PKG_VERSION").to_string()),
+            ("git-revision", revision.to_string()),
+            ("command", command),
+            ("created-unix-time", created),
+            ("host", host),
+        ];
+        entries.extend(settings.iter().cloned());
+
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[(&'static str, String)] {
+        &self.entries
+    }
+
+    /// Writes the manifest as comment lines (`# key: value`).
+    pub fn write_comments(&self, w: &mut impl Write) -> io::Result<()> {
+        for (key, value) in &self.entries {
+            writeln!(w, "# {}: {}", key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the manifest to stdout as part of a report.
+    #[cfg(feature = "cli")]
+    pub fn print(&self) {
+        bunt::println!("{$blue+bold}▸ Manifest:{/$}");
+        for (key, value) in &self.entries {
+            bunt::println!("    {[dimmed]}: {}", key, value);
+        }
+    }
+}
+
+/// Formats the manifest as `key: value` lines.
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.entries {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        Ok(())
+    }
+}
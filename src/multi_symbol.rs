@@ -0,0 +1,594 @@
+//! TMs that don't fit into the packed encoding of `Tm`: with more than two
+//! symbols (e.g. for BB(2,3) or BB(2,4)) or more than 6 states. The actions
+//! are simply stored in a `Vec`.
+//!
+//! `Tm`, its tape and everything built on them (the deciders, the optimized
+//! generators, batch simulation, ...) stay binary. Instead, this module has
+//! its own simple generator (`MultiGenerator`) and analysis (`analyze`),
+//! which only checks for a halting transition, simulates the TM and detects
+//! cycles. That's what sweeps over TMs with more symbols (`full --symbols`)
+//! use.
+
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use crate::analyze::HaltConvention;
+
+
+/// The most symbols a TM can have, as the standard notation writes each
+/// symbol as a single digit.
+pub const MAX_SYMBOLS: u8 = 10;
+
+/// The most states a TM can have, as the standard notation names them by the
+/// letters `A` to `Y` (`Z` is the halt state).
+pub const MAX_STATES: u8 = 25;
+
+/// What a `MultiTm` does in one step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiAction {
+    pub write: u8,
+    pub right: bool,
+
+    /// The next state, or `None` for the halt state.
+    pub next: Option<u8>,
+}
+
+/// A TM with `n` states operating on a tape with `symbols` symbols, where 0
+/// is the blank symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiTm {
+    n: u8,
+    symbols: u8,
+
+    /// The action of state `s` when reading `v` is at `s * symbols + v`.
+    actions: Vec<MultiAction>,
+}
+
+impl MultiTm {
+    /// Parses a TM in the standard notation (see `Tm::from_standard`) with
+    /// one action per symbol and state, e.g. `1RB2LB1RZ_2LA2RB1LB` for the
+    /// BB(2,3) champion. Like for binary TMs, undefined transitions (`---`)
+    /// are treated as `1RH`.
+    pub fn from_standard(s: &str, symbols: u8) -> Result<Self, String> {
+        if !(2..=MAX_SYMBOLS).contains(&symbols) {
+            return Err(format!("invalid number of symbols {} (2 to {})", symbols, MAX_SYMBOLS));
+        }
+
+        let states = s.trim().split('_').collect::<Vec<_>>();
+        if states.len() > MAX_STATES as usize {
+            return Err(format!("'{}' has more than {} states", s, MAX_STATES));
+        }
+        let n = states.len() as u8;
+
+        let mut actions = Vec::new();
+        for state in &states {
+            let chars = state.chars().collect::<Vec<_>>();
+            if chars.len() != 3 * symbols as usize {
+                return Err(format!(
+                    "invalid state '{}' in '{}': expected {} actions like '1RB'",
+                    state,
+                    s,
+                    symbols,
+                ));
+            }
+            for action in chars.chunks(3) {
+                let action = parse_action(action, n, symbols)
+                    .ok_or_else(|| format!("invalid action in '{}' in '{}'", state, s))?;
+                actions.push(action);
+            }
+        }
+
+        Ok(Self { n, symbols, actions })
+    }
+
+    pub fn n(&self) -> u8 {
+        self.n
+    }
+
+    pub fn symbols(&self) -> u8 {
+        self.symbols
+    }
+
+    pub fn state(&self, index: u8) -> MultiState<'_> {
+        let start = index as usize * self.symbols as usize;
+        MultiState(&self.actions[start..start + self.symbols as usize])
+    }
+
+    /// Whether any transition goes into the halt state.
+    pub fn has_halt_transition(&self) -> bool {
+        self.actions.iter().any(|action| action.next.is_none())
+    }
+
+    /// Returns a value that formats the TM as table with one row per state
+    /// and one column per read symbol, like `Tm::table`.
+    pub fn table(&self) -> MultiTable<'_> {
+        MultiTable(self)
+    }
+}
+
+/// Parses an action like `2LB` or `---`.
+fn parse_action(s: &[char], n: u8, symbols: u8) -> Option<MultiAction> {
+    let (write, movement, next) = match *s {
+        ['-', '-', '-'] => ('1', 'R', 'Z'),
+        [write, movement, next] => (write, movement, next),
+        _ => return None,
+    };
+
+    let write = write.to_digit(10).filter(|&v| v < symbols as u32)? as u8;
+    let right = match movement {
+        'L' => false,
+        'R' => true,
+        _ => return None,
+    };
+    let next = match next {
+        'Z' => None,
+        'H' if state_name(None, n) == 'H' => None,
+        c if c.is_ascii_uppercase() && (c as u8 - b'A') < n => Some(c as u8 - b'A'),
+        _ => return None,
+    };
+
+    Some(MultiAction { write, right, next })
+}
+
+/// The name of a state (`None` being the halt state) of a TM with `n` states
+/// in the standard notation. Like for `Tm`, the halt state is `H`, unless
+/// that is the name of a regular state.
+pub fn state_name(state: Option<u8>, n: u8) -> char {
+    match state {
+        Some(state) => (b'A' + state) as char,
+        None if n < 8 => 'H',
+        None => 'Z',
+    }
+}
+
+impl MultiAction {
+    /// Formats the action in the standard notation, e.g. `2LB`, for a TM
+    /// with `n` states.
+    pub fn to_standard(self, n: u8) -> String {
+        let direction = if self.right { 'R' } else { 'L' };
+        format!("{}{}{}", self.write, direction, state_name(self.next, n))
+    }
+}
+
+/// The actions of one state of a `MultiTm`, one per symbol.
+#[derive(Clone, Copy)]
+pub struct MultiState<'a>(&'a [MultiAction]);
+
+impl MultiState<'_> {
+    /// Returns the action when reading `symbol`.
+    pub fn action_for(self, symbol: u8) -> MultiAction {
+        self.0[symbol as usize]
+    }
+}
+
+/// Formats the TM in the standard notation.
+impl fmt::Display for MultiTm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, state) in self.actions.chunks(self.symbols as usize).enumerate() {
+            if i > 0 {
+                f.write_str("_")?;
+            }
+            for action in state {
+                f.write_str(&action.to_standard(self.n))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// See `MultiTm::table`.
+pub struct MultiTable<'a>(&'a MultiTm);
+
+impl fmt::Display for MultiTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tm = self.0;
+        let symbols = (0..tm.symbols).map(|s| s.to_string()).collect::<Vec<_>>();
+        writeln!(f, "    │  {}", symbols.join("    "))?;
+        writeln!(f, "────┼{}", "─────".repeat(tm.symbols as usize))?;
+        for (i, state) in tm.actions.chunks(tm.symbols as usize).enumerate() {
+            let actions = state.iter().map(|a| a.to_standard(tm.n)).collect::<Vec<_>>();
+            writeln!(f, "  {} │ {}", state_name(Some(i as u8), tm.n), actions.join("  "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Enumerates all TMs with `n` states and `symbols` symbols. Halting
+/// transitions are only generated moving right, as the direction doesn't
+/// change the result. TMs are numbered by their index, with the transition
+/// of the last state reading the last symbol being the most significant
+/// digit.
+#[derive(Debug, Clone, Copy)]
+pub struct MultiGenerator {
+    n: u8,
+    symbols: u8,
+    num_tms: u64,
+}
+
+impl MultiGenerator {
+    /// Returns an error if the number of states or symbols is invalid or if
+    /// there are more than 2^64 TMs.
+    pub fn new(n: u8, symbols: u8) -> Result<Self, String> {
+        if !(2..=MAX_SYMBOLS).contains(&symbols) {
+            return Err(format!("invalid number of symbols {} (2 to {})", symbols, MAX_SYMBOLS));
+        }
+        if !(1..=MAX_STATES).contains(&n) {
+            return Err(format!("invalid number of states {} (1 to {})", n, MAX_STATES));
+        }
+
+        let per_transition = Self::actions_per_transition(n, symbols);
+        let num_tms = per_transition.checked_pow(n as u32 * symbols as u32)
+            .ok_or_else(|| format!("too many TMs with {} states and {} symbols", n, symbols))?;
+
+        Ok(Self { n, symbols, num_tms })
+    }
+
+    /// Each transition either writes, moves and goes to a state, or halts
+    /// writing any symbol.
+    fn actions_per_transition(n: u8, symbols: u8) -> u64 {
+        symbols as u64 * (2 * n as u64 + 1)
+    }
+
+    pub fn num_tms(&self) -> u64 {
+        self.num_tms
+    }
+
+    /// Returns the TM with the given index. Panics if `index >= num_tms()`.
+    pub fn tm_at(&self, mut index: u64) -> MultiTm {
+        assert!(index < self.num_tms, "TM index {} out of bounds", index);
+
+        let (n, symbols) = (self.n as u64, self.symbols as u64);
+        let per_transition = Self::actions_per_transition(self.n, self.symbols);
+        let actions = (0..n * symbols)
+            .map(|_| {
+                let k = index % per_transition;
+                index /= per_transition;
+                if k < 2 * n * symbols {
+                    MultiAction {
+                        write: (k / (2 * n)) as u8,
+                        right: (k / n) % 2 == 1,
+                        next: Some((k % n) as u8),
+                    }
+                } else {
+                    MultiAction {
+                        write: (k - 2 * n * symbols) as u8,
+                        right: true,
+                        next: None,
+                    }
+                }
+            })
+            .collect();
+
+        MultiTm { n: self.n, symbols: self.symbols, actions }
+    }
+}
+
+/// The result of `analyze`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiOutcome {
+    NoHaltTransition,
+    Halted(MultiHalted),
+
+    /// The TM repeated a previous configuration.
+    Cycle,
+    AbortedAfterMaxSteps,
+}
+
+/// The result of a halted `MultiTm`, according to the halt convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiHalted {
+    pub steps: u64,
+
+    /// The number of cells that are not blank.
+    pub non_blank: u64,
+
+    /// The sum of all symbols on the tape.
+    pub symbol_sum: u64,
+}
+
+impl MultiHalted {
+    pub fn score(&self, score_def: ScoreDef) -> u64 {
+        match score_def {
+            ScoreDef::NonZero => self.non_blank,
+            ScoreDef::Sum => self.symbol_sum,
+        }
+    }
+}
+
+/// How the score of a halted TM is defined. With two symbols, both are the
+/// number of 1s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreDef {
+    /// The number of cells that are not blank, the usual definition of Σ.
+    NonZero,
+
+    /// The sum of all symbols on the tape, e.g. a 2 counts twice.
+    Sum,
+}
+
+impl FromStr for ScoreDef {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nonzero" => Ok(Self::NonZero),
+            "sum" => Ok(Self::Sum),
+            _ => Err("invalid value for score definition"),
+        }
+    }
+}
+
+impl fmt::Display for ScoreDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonZero => f.write_str("nonzero"),
+            Self::Sum => f.write_str("sum"),
+        }
+    }
+}
+
+/// Analyzes the TM: checks if it has a halting transition at all and then
+/// runs it for up to `max_steps` steps, with a cycle detection like the one of
+/// `Analyzer`: the configuration is saved whenever the number of steps is a
+/// power of two and compared to all following ones.
+pub fn analyze(tm: &MultiTm, max_steps: u64, halt_convention: HaltConvention) -> MultiOutcome {
+    if !tm.has_halt_transition() {
+        return MultiOutcome::NoHaltTransition;
+    }
+
+    let mut machine = MultiMachine::new(tm);
+    let mut saved = machine.snapshot();
+    while machine.steps() < max_steps && machine.step() {
+        if let Some(halted) = machine.halted(halt_convention) {
+            return MultiOutcome::Halted(halted);
+        }
+        if machine.matches(&saved) {
+            return MultiOutcome::Cycle;
+        }
+        if machine.steps().is_power_of_two() {
+            saved = machine.snapshot();
+        }
+    }
+
+    MultiOutcome::AbortedAfterMaxSteps
+}
+
+/// A configuration of a `MultiMachine`, see `MultiMachine::snapshot`.
+pub struct MultiSnapshot {
+    state: Option<u8>,
+    head: i64,
+    tape: Vec<u8>,
+    origin: usize,
+}
+
+/// A running `MultiTm`, starting on a blank tape in state A. The tape grows
+/// in both directions as needed.
+pub struct MultiMachine<'a> {
+    tm: &'a MultiTm,
+
+    /// `None` once the TM halted.
+    state: Option<u8>,
+    head: i64,
+    steps: u64,
+
+    /// The cells from `-origin` on.
+    tape: Vec<u8>,
+    origin: usize,
+
+    /// The symbol read and the one written by the halting transition.
+    halt: Option<(u8, u8)>,
+}
+
+impl<'a> MultiMachine<'a> {
+    pub fn new(tm: &'a MultiTm) -> Self {
+        Self {
+            tm,
+            state: Some(0),
+            head: 0,
+            steps: 0,
+            tape: vec![0; 64],
+            origin: 32,
+            halt: None,
+        }
+    }
+
+    /// Executes one step. Returns `false` (and does nothing) if the TM
+    /// already halted.
+    pub fn step(&mut self) -> bool {
+        let state = match self.state {
+            Some(state) => state,
+            None => return false,
+        };
+
+        let index = self.index(self.head);
+        let read = self.tape[index];
+        let action = self.tm.state(state).action_for(read);
+        self.tape[index] = action.write;
+        self.head += if action.right { 1 } else { -1 };
+        self.state = action.next;
+        self.steps += 1;
+        if action.next.is_none() {
+            self.halt = Some((read, action.write));
+        }
+        true
+    }
+
+    /// Returns the index of `cell` in `tape`, growing it if necessary.
+    fn index(&mut self, cell: i64) -> usize {
+        let index = cell + self.origin as i64;
+        if index < 0 {
+            let grow = self.tape.len();
+            self.tape.splice(0..0, std::iter::repeat_n(0, grow));
+            self.origin += grow;
+        } else if index as usize >= self.tape.len() {
+            self.tape.resize(2 * self.tape.len(), 0);
+        }
+        (cell + self.origin as i64) as usize
+    }
+
+    /// The state the TM is in, or `None` if it halted.
+    pub fn state(&self) -> Option<u8> {
+        self.state
+    }
+
+    pub fn head(&self) -> i64 {
+        self.head
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The symbol read and the one written by the halting transition, if the
+    /// TM halted.
+    pub fn halt(&self) -> Option<(u8, u8)> {
+        self.halt
+    }
+
+    /// The result if the TM halted. Like for binary TMs, the halting
+    /// transition is undone if it should not be counted.
+    pub fn halted(&self, halt_convention: HaltConvention) -> Option<MultiHalted> {
+        let (read, written) = self.halt?;
+        let mut result = MultiHalted {
+            steps: self.steps,
+            non_blank: self.non_blank(),
+            symbol_sum: self.symbol_sum(),
+        };
+        if halt_convention == HaltConvention::Exclude {
+            result.steps -= 1;
+            result.non_blank = result.non_blank + (read != 0) as u64 - (written != 0) as u64;
+            result.symbol_sum = result.symbol_sum + read as u64 - written as u64;
+        }
+
+        Some(result)
+    }
+
+    /// Returns the current configuration, to check if it repeats later.
+    pub fn snapshot(&self) -> MultiSnapshot {
+        MultiSnapshot {
+            state: self.state,
+            head: self.head,
+            tape: self.tape.clone(),
+            origin: self.origin,
+        }
+    }
+
+    /// Whether the current configuration is the one of `snapshot`. Cheap
+    /// unless state and head match.
+    pub fn matches(&self, snapshot: &MultiSnapshot) -> bool {
+        if self.state != snapshot.state || self.head != snapshot.head {
+            return false;
+        }
+
+        // The tape only grows, so it covers all cells of the snapshot.
+        let offset = self.origin - snapshot.origin;
+        let (before, rest) = self.tape.split_at(offset);
+        let (same, after) = rest.split_at(snapshot.tape.len());
+        same == &snapshot.tape[..]
+            && before.iter().all(|&v| v == 0)
+            && after.iter().all(|&v| v == 0)
+    }
+
+    pub fn get(&self, cell: i64) -> u8 {
+        usize::try_from(cell + self.origin as i64).ok()
+            .and_then(|index| self.tape.get(index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The number of cells that are not blank.
+    pub fn non_blank(&self) -> u64 {
+        self.tape.iter().filter(|&&v| v != 0).count() as u64
+    }
+
+    /// The sum of all symbols on the tape.
+    pub fn symbol_sum(&self) -> u64 {
+        self.tape.iter().map(|&v| v as u64).sum()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(tm: &str, symbols: u8, max_steps: u64) -> (u64, u64, bool) {
+        let tm = MultiTm::from_standard(tm, symbols).unwrap();
+        let mut machine = MultiMachine::new(&tm);
+        while machine.steps() < max_steps && machine.step() {}
+        (machine.steps(), machine.non_blank(), machine.state().is_none())
+    }
+
+    #[test]
+    fn champions() {
+        // BB(2,3): 38 steps, 9 non-blank cells.
+        assert_eq!(run("1RB2LB1RZ_2LA2RB1LB", 3, 100), (38, 9, true));
+        assert_eq!(run("1RB2LB1RZ_2LA2RB1LB", 3, 37), (37, 9, false));
+
+        // The binary BB(2) champion.
+        assert_eq!(run("1RB1LB_1LA1RZ", 2, 100), (6, 4, true));
+    }
+
+    #[test]
+    fn generator() {
+        // 2 * (2 * 2 + 1) = 10 actions per transition.
+        let gen = MultiGenerator::new(2, 2).unwrap();
+        assert_eq!(gen.num_tms(), 10u64.pow(4));
+        assert_eq!(gen.tm_at(0).to_string(), "0LA0LA_0LA0LA");
+        assert_eq!(gen.tm_at(9).to_string(), "1RH0LA_0LA0LA");
+        assert_eq!(gen.tm_at(gen.num_tms() - 1).to_string(), "1RH1RH_1RH1RH");
+
+        let all = (0..gen.num_tms()).map(|i| gen.tm_at(i).to_string()).collect::<Vec<_>>();
+        assert!(all.contains(&"1RB1LB_1LA1RH".to_string()));
+        let mut deduped = all.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), all.len());
+
+        assert!(MultiGenerator::new(2, 11).is_err());
+        assert!(MultiGenerator::new(5, 4).is_err());
+    }
+
+    #[test]
+    fn analyze_outcomes() {
+        let run = |tm, symbols, max_steps| {
+            let tm = MultiTm::from_standard(tm, symbols).unwrap();
+            analyze(&tm, max_steps, HaltConvention::Include)
+        };
+        let halted = |steps, non_blank, symbol_sum| {
+            MultiOutcome::Halted(MultiHalted { steps, non_blank, symbol_sum })
+        };
+
+        assert_eq!(run("1RB2LB1RZ_2LA2RB1LB", 3, 100), halted(38, 9, 17));
+        assert_eq!(run("1RB2LB1RZ_2LA2RB1LB", 3, 37), MultiOutcome::AbortedAfterMaxSteps);
+        assert_eq!(run("1RB2LB1RB_2LA2RB1LB", 3, 100), MultiOutcome::NoHaltTransition);
+
+        // Moves back and forth between two cells, writing 2s.
+        assert_eq!(run("2RB1RZ2RB_2LA1RZ2LA", 3, 100), MultiOutcome::Cycle);
+
+        // Writes a 2 and halts writing a 1, which is not counted if the
+        // halting transition is excluded.
+        let tm = MultiTm::from_standard("2RB0LA0LA_1RZ0LA0LA", 3).unwrap();
+        assert_eq!(analyze(&tm, 100, HaltConvention::Include), halted(2, 2, 3));
+        assert_eq!(analyze(&tm, 100, HaltConvention::Exclude), halted(1, 1, 2));
+        assert_eq!(MultiHalted { steps: 2, non_blank: 2, symbol_sum: 3 }.score(ScoreDef::Sum), 3);
+        assert_eq!("nonzero".parse(), Ok(ScoreDef::NonZero));
+        assert_eq!("sum".parse::<ScoreDef>().unwrap().to_string(), "sum");
+    }
+
+    #[test]
+    fn notation() {
+        let tm = MultiTm::from_standard("1RB2LA1RA1RA_1LB1LA3RB1RZ", 4).unwrap();
+        assert_eq!(tm.to_string(), "1RB2LA1RA1RA_1LB1LA3RB1RH");
+        let action = tm.state(1).action_for(2);
+        assert_eq!(action, MultiAction { write: 3, right: true, next: Some(1) });
+        assert!(MultiTm::from_standard("1RB2LB1RZ_2LA2RB1LB", 2).is_err());
+        assert!(MultiTm::from_standard("1RB3LB1RZ_2LA2RB1LB", 3).is_err());
+        assert!(MultiTm::from_standard("1RB2LB1RC_2LA2RB1LB", 3).is_err());
+
+        // With 8 states, `H` is a regular state.
+        let tm = "1RB1RZ_1RC1RZ_1RD1RZ_1RE1RZ_1RF1RZ_1RG1RZ_1RH1RZ_1LA1RZ";
+        let parsed = MultiTm::from_standard(tm, 2).unwrap();
+        assert_eq!(parsed.to_string(), tm);
+        assert_eq!(parsed.state(6).action_for(0).next, Some(7));
+    }
+}
@@ -1,29 +1,447 @@
+use crate::{tape::{CellId, Tape, TapeLike}, tm::Tm};
+
+
 /// A sink that accumulates results from analyzing TMs. One method per different
-/// analysis result.
-pub trait OutcomeSink {
-    /// The TM ran and halted.
-    fn report_halted(&mut self, num_steps: u32, num_ones: u32);
+/// analysis result. Each method also gets the TM that was analyzed, which most
+/// sinks can simply ignore.
+pub trait OutcomeSink<const N: usize> {
+    /// The TM ran and halted. `tape` is the final tape (without the halting
+    /// transition if it's not counted, see `HaltConvention`).
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, tape: &impl TapeLike);
 
     /// The start state of the TM for the cell value 0 has the halt state as
     /// next state. This means the TM terminates in one step. It might write a
-    /// single one, though.
-    fn report_immediate_halt(&mut self, wrote_one: bool);
+    /// single one, though. Unless the halting transition is not counted (see
+    /// `HaltConvention`), `num_steps` is 1 and `num_ones` is 0 or 1.
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32);
 
     /// The TM does not even have a transition to the halt state at all.
-    fn report_no_halt_transition(&mut self);
+    fn report_no_halt_transition(&mut self, tm: Tm<N>);
 
     /// The TM does immediately go into one direction without ever stopping.
     /// This happens if the start action has `next_state == 0`.
-    fn report_simple_elope(&mut self);
+    fn report_simple_elope(&mut self, tm: Tm<N>);
 
     /// If the turing machine has a state graph where the halt state cannot be
     /// reached from the start state.
-    fn report_halt_state_not_reachable(&mut self);
+    fn report_halt_state_not_reachable(&mut self, tm: Tm<N>);
 
     /// While executing the TM a run-away was detected, meaning that the TM
     /// was caught in a loop only visiting new cells, thus never terminating.
-    fn report_run_away(&mut self);
+    fn report_run_away(&mut self, tm: Tm<N>);
+
+    /// While executing the TM, it got into a configuration (state, head
+    /// position and tape) it was in before, so it repeats forever.
+    fn report_cycle(&mut self, tm: Tm<N>);
+
+    /// While executing the TM, it was found to repeat the same behavior
+    /// shifted along the tape forever (a translated cycler).
+    fn report_translated_cycler(&mut self, tm: Tm<N>);
+
+    /// The TM reached the maximum number of steps, but reasoning backwards
+    /// from its halting transitions showed that none of them can be reached.
+    fn report_backward_reasoning(&mut self, tm: Tm<N>);
+
+    /// The TM reached the maximum number of steps, but a closed tape
+    /// language without halting configurations was found (see `ctl`).
+    fn report_ctl(&mut self, tm: Tm<N>);
+
+    /// The TM reached the maximum number of steps, but running it further
+    /// showed that it repeats the same behavior forever, possibly shifted
+    /// along the tape (see `Analyzer::lin_recurrence`).
+    fn report_lin_recurrence(&mut self, tm: Tm<N>);
+
+    /// The TM ran but was aborted after the maximum number of steps (or when it
+    /// exceeded its run budget). `config` is the configuration the TM was in
+    /// when it was stopped.
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    );
+
+    /// The TM was stopped after `num_steps` steps because it could not reach
+    /// the high score anymore, even if it wrote a new 1 in every remaining
+    /// step (see `AnalyzerBuilder::prune`). It might still halt.
+    fn report_pruned(&mut self, tm: Tm<N>, num_steps: u64);
+
+    /// The TM was not analyzed at all, as it was already decided elsewhere,
+    /// e.g. by a previous run (see `SweepBuilder::known`).
+    fn report_known(&mut self, tm: Tm<N>);
+}
+
+/// The complete configuration of a running TM at one point in time. This only
+/// borrows the tape, so creating it is free. Sinks that want to keep it around
+/// have to copy the relevant parts.
+pub struct Configuration<'a, T = Tape> {
+    /// The state the TM is in.
+    pub state: u8,
+
+    /// The cell the head is currently on.
+    pub head: CellId,
+
+    /// The number of steps that were already executed.
+    pub steps: u64,
+
+    /// The tape.
+    pub tape: &'a T,
+}
+
+// Not derived, as that would require `T: Copy`.
+impl<T> Clone for Configuration<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Configuration<'_, T> {}
+
+/// The result of analyzing one TM as a value. This is the owned counterpart
+/// to the methods of `OutcomeSink` (see there for details), e.g. for passing
+/// results to code that is not generic over `N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisResult {
+    Halted { num_steps: u64, num_ones: u32 },
+    ImmediateHalt { num_steps: u64, num_ones: u32 },
+    NoHaltTransition,
+    SimpleElope,
+    HaltStateNotReachable,
+    RunAway,
+    CycleDetected,
+    TranslatedCycler,
+    BackwardReasoning,
+    ClosedTapeLanguage,
+    LinRecurrence,
+
+    /// The tape of the final configuration is not included, as that would
+    /// require an allocation for each aborted TM.
+    AbortedAfterMaxSteps { state: u8, head: CellId, num_steps: u64 },
+    Pruned { num_steps: u64 },
+    Known,
+}
+
+/// How certain an outcome is. Exports and summaries carry this so that
+/// undecided TMs are never mistaken for ones that provably never halt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// The TM halted, or it was shown to never halt by a static check or by
+    /// one of the deciders (or elsewhere, for known TMs).
+    Proven,
+
+    /// The TM was stopped without a decision (aborted or pruned). It might
+    /// still halt.
+    Undecided,
+}
+
+impl Confidence {
+    /// The name used in exports: `proven` or `undecided`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Proven => "proven",
+            Self::Undecided => "undecided",
+        }
+    }
+}
+
+/// All values `AnalysisResult::name` returns.
+pub const OUTCOME_NAMES: [&str; 14] = [
+    "halted",
+    "immediate-halt",
+    "no-halt-transition",
+    "simple-elope",
+    "halt-unreachable",
+    "run-away",
+    "cycle",
+    "translated-cycler",
+    "backward-reasoning",
+    "ctl",
+    "lin-recurrence",
+    "aborted",
+    "pruned",
+    "known",
+];
+
+impl AnalysisResult {
+    /// A short name of the kind of result, e.g. for CSV output. See also
+    /// `OUTCOME_NAMES`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Halted { .. } => "halted",
+            Self::ImmediateHalt { .. } => "immediate-halt",
+            Self::NoHaltTransition => "no-halt-transition",
+            Self::SimpleElope => "simple-elope",
+            Self::HaltStateNotReachable => "halt-unreachable",
+            Self::RunAway => "run-away",
+            Self::CycleDetected => "cycle",
+            Self::TranslatedCycler => "translated-cycler",
+            Self::BackwardReasoning => "backward-reasoning",
+            Self::ClosedTapeLanguage => "ctl",
+            Self::LinRecurrence => "lin-recurrence",
+            Self::AbortedAfterMaxSteps { .. } => "aborted",
+            Self::Pruned { .. } => "pruned",
+            Self::Known => "known",
+        }
+    }
+
+    /// Whether this outcome is a proof or just the point where we gave up.
+    pub fn confidence(&self) -> Confidence {
+        match self {
+            Self::AbortedAfterMaxSteps { .. } | Self::Pruned { .. } => Confidence::Undecided,
+            _ => Confidence::Proven,
+        }
+    }
+
+    /// The number of steps after which the TM halted, or `None` if it did
+    /// not halt.
+    pub fn halt_steps(&self) -> Option<u64> {
+        match *self {
+            Self::Halted { num_steps, .. } | Self::ImmediateHalt { num_steps, .. } => {
+                Some(num_steps)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A sink that converts each outcome to an `AnalysisResult` and passes it,
+/// together with the TM, to the wrapped function.
+pub struct FnSink<F>(pub F);
+
+impl<F: FnMut(Tm<N>, AnalysisResult), const N: usize> OutcomeSink<N> for FnSink<F> {
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, _: &impl TapeLike) {
+        (self.0)(tm, AnalysisResult::Halted { num_steps, num_ones });
+    }
+
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32) {
+        (self.0)(tm, AnalysisResult::ImmediateHalt { num_steps, num_ones });
+    }
+
+    fn report_no_halt_transition(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::NoHaltTransition);
+    }
+
+    fn report_simple_elope(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::SimpleElope);
+    }
+
+    fn report_halt_state_not_reachable(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::HaltStateNotReachable);
+    }
+
+    fn report_run_away(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::RunAway);
+    }
+
+    fn report_cycle(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::CycleDetected);
+    }
+
+    fn report_translated_cycler(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::TranslatedCycler);
+    }
+
+    fn report_backward_reasoning(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::BackwardReasoning);
+    }
+
+    fn report_ctl(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::ClosedTapeLanguage);
+    }
+
+    fn report_lin_recurrence(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::LinRecurrence);
+    }
+
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    ) {
+        let result = AnalysisResult::AbortedAfterMaxSteps {
+            state: config.state,
+            head: config.head,
+            num_steps: config.steps,
+        };
+        (self.0)(tm, result);
+    }
+
+    fn report_pruned(&mut self, tm: Tm<N>, num_steps: u64) {
+        (self.0)(tm, AnalysisResult::Pruned { num_steps });
+    }
+
+    fn report_known(&mut self, tm: Tm<N>) {
+        (self.0)(tm, AnalysisResult::Known);
+    }
+}
+
+/// Forwards all results to both sinks.
+impl<A, B, const N: usize> OutcomeSink<N> for (A, B)
+where
+    A: OutcomeSink<N>,
+    B: OutcomeSink<N>,
+{
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, tape: &impl TapeLike) {
+        self.0.report_halted(tm, num_steps, num_ones, tape);
+        self.1.report_halted(tm, num_steps, num_ones, tape);
+    }
+
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32) {
+        self.0.report_immediate_halt(tm, num_steps, num_ones);
+        self.1.report_immediate_halt(tm, num_steps, num_ones);
+    }
+
+    fn report_no_halt_transition(&mut self, tm: Tm<N>) {
+        self.0.report_no_halt_transition(tm);
+        self.1.report_no_halt_transition(tm);
+    }
+
+    fn report_simple_elope(&mut self, tm: Tm<N>) {
+        self.0.report_simple_elope(tm);
+        self.1.report_simple_elope(tm);
+    }
+
+    fn report_halt_state_not_reachable(&mut self, tm: Tm<N>) {
+        self.0.report_halt_state_not_reachable(tm);
+        self.1.report_halt_state_not_reachable(tm);
+    }
+
+    fn report_run_away(&mut self, tm: Tm<N>) {
+        self.0.report_run_away(tm);
+        self.1.report_run_away(tm);
+    }
+
+    fn report_cycle(&mut self, tm: Tm<N>) {
+        self.0.report_cycle(tm);
+        self.1.report_cycle(tm);
+    }
+
+    fn report_translated_cycler(&mut self, tm: Tm<N>) {
+        self.0.report_translated_cycler(tm);
+        self.1.report_translated_cycler(tm);
+    }
+
+    fn report_backward_reasoning(&mut self, tm: Tm<N>) {
+        self.0.report_backward_reasoning(tm);
+        self.1.report_backward_reasoning(tm);
+    }
+
+    fn report_ctl(&mut self, tm: Tm<N>) {
+        self.0.report_ctl(tm);
+        self.1.report_ctl(tm);
+    }
+
+    fn report_lin_recurrence(&mut self, tm: Tm<N>) {
+        self.0.report_lin_recurrence(tm);
+        self.1.report_lin_recurrence(tm);
+    }
+
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    ) {
+        self.0.report_aborted_after_max_steps(tm, config);
+        self.1.report_aborted_after_max_steps(tm, config);
+    }
+
+    fn report_pruned(&mut self, tm: Tm<N>, num_steps: u64) {
+        self.0.report_pruned(tm, num_steps);
+        self.1.report_pruned(tm, num_steps);
+    }
+
+    fn report_known(&mut self, tm: Tm<N>) {
+        self.0.report_known(tm);
+        self.1.report_known(tm);
+    }
+}
+
+/// Forwards all results to the inner sink, if there is one.
+impl<S: OutcomeSink<N>, const N: usize> OutcomeSink<N> for Option<S> {
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, tape: &impl TapeLike) {
+        if let Some(s) = self {
+            s.report_halted(tm, num_steps, num_ones, tape);
+        }
+    }
+
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32) {
+        if let Some(s) = self {
+            s.report_immediate_halt(tm, num_steps, num_ones);
+        }
+    }
+
+    fn report_no_halt_transition(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_no_halt_transition(tm);
+        }
+    }
+
+    fn report_simple_elope(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_simple_elope(tm);
+        }
+    }
+
+    fn report_halt_state_not_reachable(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_halt_state_not_reachable(tm);
+        }
+    }
+
+    fn report_run_away(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_run_away(tm);
+        }
+    }
+
+    fn report_cycle(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_cycle(tm);
+        }
+    }
+
+    fn report_translated_cycler(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_translated_cycler(tm);
+        }
+    }
+
+    fn report_backward_reasoning(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_backward_reasoning(tm);
+        }
+    }
+
+    fn report_ctl(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_ctl(tm);
+        }
+    }
+
+    fn report_lin_recurrence(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_lin_recurrence(tm);
+        }
+    }
+
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    ) {
+        if let Some(s) = self {
+            s.report_aborted_after_max_steps(tm, config);
+        }
+    }
+
+    fn report_pruned(&mut self, tm: Tm<N>, num_steps: u64) {
+        if let Some(s) = self {
+            s.report_pruned(tm, num_steps);
+        }
+    }
 
-    /// The TM ran but was aborted after the maximum number of steps.
-    fn report_aborted_after_max_steps(&mut self);
+    fn report_known(&mut self, tm: Tm<N>) {
+        if let Some(s) = self {
+            s.report_known(tm);
+        }
+    }
 }
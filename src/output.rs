@@ -0,0 +1,424 @@
+//! Files receiving the result of each TM of a sweep (e.g. `full --raw-out`).
+//!
+//! Each output is written by its own thread: the workers only pass batches
+//! of results to it. That way, several outputs can be used in one run and a
+//! slow output (e.g. on a network drive) does not stall the analysis. It just
+//! buffers more results in memory.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+use anyhow::{Context, Result};
+use crossbeam_channel::Sender;
+
+use crate::{
+    manifest::Manifest,
+    outcome::{AnalysisResult, Confidence, OUTCOME_NAMES},
+    profile::{self, Phase},
+};
+
+
+/// The result of one TM: `(index, id, result)`, like the arguments of
+/// `SweepBuilder::on_result`.
+pub type Record = (u64, u64, AnalysisResult);
+
+/// Where a batch of results was computed: the index of the chunk of TMs (see
+/// `sweep::chunk_size`) and the worker thread that analyzed it. Stored with
+/// the results, so that anomalies found later can be traced back and the
+/// affected chunk can be recomputed.
+#[derive(Debug, Clone, Copy)]
+pub struct Source {
+    pub chunk: u64,
+    pub worker: u32,
+}
+
+/// An output receiving the results of all TMs of a sweep. The results arrive
+/// in batches of consecutive indices, but the batches are in no particular
+/// order.
+pub trait ResultWriter: Send {
+    /// Writes one result that was computed as part of the chunk `source`.
+    fn write(&mut self, record: Record, source: Source) -> Result<()>;
+
+    /// Called after the last result was written.
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// A shared handle to an output, as stored in `SweepBuilder`.
+pub type SharedWriter = Arc<Mutex<dyn ResultWriter>>;
+
+/// Distributes batches of results to several outputs, each of which is
+/// written by its own thread with its own (unbounded) queue.
+pub struct Tee {
+    sender: TeeSender,
+    handles: Vec<JoinHandle<Result<()>>>,
+}
+
+impl Tee {
+    /// Starts one writer thread for each output.
+    pub fn start(outputs: &[SharedWriter]) -> Self {
+        let (senders, handles) = outputs.iter()
+            .map(|output| {
+                let (s, r) = crossbeam_channel::unbounded::<Arc<Batch>>();
+                let output = output.clone();
+                let handle = thread::spawn(move || -> Result<()> {
+                    let mut output = output.lock().expect("poisened lock");
+                    for batch in r.iter() {
+                        profile::enter(Phase::Output);
+                        for &record in &batch.records {
+                            output.write(record, batch.source)?;
+                        }
+                        profile::enter(Phase::Idle);
+                    }
+                    profile::enter(Phase::Output);
+                    output.finish()
+                });
+                (s, handle)
+            })
+            .unzip();
+
+        Self { sender: TeeSender(senders), handles }
+    }
+
+    /// Returns a handle for passing results to the outputs from the workers.
+    pub fn sender(&self) -> TeeSender {
+        self.sender.clone()
+    }
+
+    /// Waits until all outputs have written all results and returns the first
+    /// error of any of them. All senders have to be dropped before.
+    pub fn finish(self) -> Result<()> {
+        drop(self.sender);
+        let mut out = Ok(());
+        for handle in self.handles {
+            let res = handle.join().expect("panic in output thread");
+            if out.is_ok() {
+                out = res;
+            }
+        }
+        out
+    }
+}
+
+/// The results of (a part of) one chunk.
+struct Batch {
+    source: Source,
+    records: Vec<Record>,
+}
+
+/// Passes results to all outputs of a `Tee`.
+#[derive(Clone)]
+pub struct TeeSender(Vec<Sender<Arc<Batch>>>);
+
+impl TeeSender {
+    /// Returns `true` if there are no outputs, i.e. results don't need to be
+    /// collected at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Passes a batch of results to all outputs without waiting for them.
+    pub fn send(&self, source: Source, records: Vec<Record>) {
+        if records.is_empty() {
+            return;
+        }
+
+        // If an output failed, its thread stopped receiving. The error is
+        // returned by `finish`.
+        let batch = Arc::new(Batch { source, records });
+        for s in &self.0 {
+            let _ = s.send(batch.clone());
+        }
+    }
+}
+
+/// Writes the outcome of every TM as CSV row
+/// `index,id,outcome,confidence,steps,ones,chunk,worker`. `confidence` is
+/// `proven` or `undecided` (see `Confidence`). `steps` is empty for TMs that
+/// were not run, `ones` is empty for TMs that did not halt. `chunk` and
+/// `worker` are the `Source` of the result. TMs that halted after fewer
+/// than `ignore_below_steps` steps are skipped.
+pub struct RawOut {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    ignore_below_steps: u64,
+}
+
+impl RawOut {
+    /// Creates the file and writes the manifest (as comments) and the CSV
+    /// header.
+    pub fn create(path: &Path, manifest: &Manifest, ignore_below_steps: u64) -> Result<Self> {
+        let mut writer = create(path, manifest)?;
+        writeln!(writer, "index,id,outcome,confidence,steps,ones,chunk,worker")?;
+        Ok(Self { writer, path: path.to_owned(), ignore_below_steps })
+    }
+}
+
+impl ResultWriter for RawOut {
+    fn write(&mut self, (index, id, result): Record, source: Source) -> Result<()> {
+        if result.halt_steps().is_some_and(|steps| steps < self.ignore_below_steps) {
+            return Ok(());
+        }
+
+        let (steps, ones) = raw_steps_and_ones(&result);
+
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{}",
+            index,
+            id,
+            result.name(),
+            result.confidence().name(),
+            steps,
+            ones,
+            source.chunk,
+            source.worker,
+        ).with_context(|| format!("failed to write '{}'", self.path.display()))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush().with_context(|| format!("failed to write '{}'", self.path.display()))
+    }
+}
+
+/// The `steps` and `ones` columns of a raw-out file (see `RawOut`) for
+/// `result`. Empty if the outcome has no such value.
+pub fn raw_steps_and_ones(result: &AnalysisResult) -> (String, String) {
+    match *result {
+        AnalysisResult::Halted { num_steps, num_ones }
+            | AnalysisResult::ImmediateHalt { num_steps, num_ones }
+            => (num_steps.to_string(), num_ones.to_string()),
+        AnalysisResult::AbortedAfterMaxSteps { num_steps, .. }
+            | AnalysisResult::Pruned { num_steps }
+            => (num_steps.to_string(), String::new()),
+        _ => (String::new(), String::new()),
+    }
+}
+
+/// Writes the IDs of all TMs with one outcome (see `AnalysisResult::name`),
+/// e.g. all that were aborted. One ID per line, so that the file can be
+/// passed to `sheet`.
+pub struct OutcomeOut {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    outcome: String,
+}
+
+impl OutcomeOut {
+    /// Creates the file and writes the manifest as comments.
+    pub fn create(path: &Path, manifest: &Manifest, outcome: &str) -> Result<Self> {
+        let writer = create(path, manifest)?;
+        Ok(Self { writer, path: path.to_owned(), outcome: outcome.to_owned() })
+    }
+}
+
+impl ResultWriter for OutcomeOut {
+    fn write(&mut self, (_, id, result): Record, _: Source) -> Result<()> {
+        if result.name() == self.outcome {
+            writeln!(self.writer, "{}", id)
+                .with_context(|| format!("failed to write '{}'", self.path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush().with_context(|| format!("failed to write '{}'", self.path.display()))
+    }
+}
+
+/// Writes up to `k` example TMs per outcome as CSV file with the columns
+/// `outcome,confidence,index,id`. The examples are drawn uniformly from all TMs of the
+/// sweep with that outcome by reservoir sampling, so unlike taking the first
+/// `k`, they are not biased towards the chunks that happened to be analyzed
+/// first. The file is only written by `finish`.
+pub struct ExamplesOut {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    k: usize,
+    reservoirs: Vec<Reservoir>,
+    rng: SplitMix64,
+}
+
+/// The examples of one outcome and the number of TMs they were drawn from.
+#[derive(Default)]
+struct Reservoir {
+    seen: u64,
+    confidence: Option<Confidence>,
+    examples: Vec<(u64, u64)>,
+}
+
+impl ExamplesOut {
+    /// Creates the file and writes the manifest as comments.
+    pub fn create(path: &Path, manifest: &Manifest, k: usize) -> Result<Self> {
+        let writer = create(path, manifest)?;
+        Ok(Self {
+            writer,
+            path: path.to_owned(),
+            k,
+            reservoirs: OUTCOME_NAMES.iter().map(|_| Reservoir::default()).collect(),
+            rng: SplitMix64(0x6265_6176_6572),
+        })
+    }
+}
+
+impl ResultWriter for ExamplesOut {
+    fn write(&mut self, (index, id, result): Record, _: Source) -> Result<()> {
+        let outcome = OUTCOME_NAMES.iter()
+            .position(|&name| name == result.name())
+            .expect("outcome missing in OUTCOME_NAMES");
+        let reservoir = &mut self.reservoirs[outcome];
+
+        // Algorithm R: the n-th TM replaces a random example with
+        // probability k/n.
+        reservoir.seen += 1;
+        reservoir.confidence = Some(result.confidence());
+        if reservoir.examples.len() < self.k {
+            reservoir.examples.push((index, id));
+        } else {
+            let slot = self.rng.below(reservoir.seen);
+            if slot < self.k as u64 {
+                reservoir.examples[slot as usize] = (index, id);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let mut write = || -> std::io::Result<()> {
+            writeln!(self.writer, "outcome,confidence,index,id")?;
+            for (name, reservoir) in OUTCOME_NAMES.iter().zip(&mut self.reservoirs) {
+                // Without examples, there is no row that would need it.
+                let confidence = match reservoir.confidence {
+                    Some(confidence) => confidence.name(),
+                    None => continue,
+                };
+                reservoir.examples.sort_unstable();
+                for (index, id) in &reservoir.examples {
+                    writeln!(self.writer, "{},{},{},{}", name, confidence, index, id)?;
+                }
+            }
+            self.writer.flush()
+        };
+        write().with_context(|| format!("failed to write '{}'", self.path.display()))
+    }
+}
+
+/// A small, fast PRNG. The quality is more than enough for sampling.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, with negligible bias.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// Creates the file (truncating it if it exists) and writes the manifest as
+/// comments.
+fn create(path: &Path, manifest: &Manifest) -> Result<BufWriter<File>> {
+    let file = File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    manifest.write_comments(&mut writer)?;
+    Ok(writer)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use anyhow::{Result, anyhow};
+    use crate::{gen::Generator, outcome::AnalysisResult, sweep::SweepBuilder};
+    use super::{Record, ResultWriter, Source, Tee};
+
+    type Records = Arc<Mutex<Vec<Record>>>;
+
+    /// Collects all records, and fails on the first one if `fail` is set.
+    struct Collect {
+        records: Records,
+        fail: bool,
+        finished: Arc<Mutex<bool>>,
+    }
+
+    impl Collect {
+        fn new(fail: bool) -> (Self, Records, Arc<Mutex<bool>>) {
+            let records = Arc::new(Mutex::new(Vec::new()));
+            let finished = Arc::new(Mutex::new(false));
+            (Self { records: records.clone(), fail, finished: finished.clone() }, records, finished)
+        }
+    }
+
+    impl ResultWriter for Collect {
+        fn write(&mut self, record: Record, _: Source) -> Result<()> {
+            if self.fail {
+                return Err(anyhow!("broken output"));
+            }
+            self.records.lock().unwrap().push(record);
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<()> {
+            *self.finished.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tee() {
+        let (a, a_records, a_finished) = Collect::new(false);
+        let (b, b_records, b_finished) = Collect::new(true);
+        let tee = Tee::start(&[Arc::new(Mutex::new(a)), Arc::new(Mutex::new(b))]);
+
+        let sender = tee.sender();
+        for chunk in 0..3 {
+            let records = (0..10)
+                .map(|i| (chunk * 10 + i, i, AnalysisResult::CycleDetected))
+                .collect();
+            sender.send(Source { chunk, worker: 0 }, records);
+        }
+        drop(sender);
+
+        // The broken output does not stop the other one.
+        let err = tee.finish().unwrap_err();
+        assert_eq!(err.to_string(), "broken output");
+        assert_eq!(a_records.lock().unwrap().len(), 30);
+        assert!(*a_finished.lock().unwrap());
+        assert!(b_records.lock().unwrap().is_empty());
+        assert!(!*b_finished.lock().unwrap());
+    }
+
+    #[test]
+    fn sweep_with_several_outputs() {
+        let (a, a_records, _) = Collect::new(false);
+        let (b, b_records, _) = Collect::new(false);
+        let sweep = SweepBuilder::new(2)
+            .generator(Generator::All)
+            .max_steps(100)
+            .num_threads(Some(3))
+            .output(a)
+            .output(b);
+        let num_tms = sweep.num_tms();
+        sweep.run().unwrap();
+
+        let sorted = |records: &Records| {
+            let mut v = records.lock().unwrap().clone();
+            v.sort_by_key(|&(index, _, _)| index);
+            v
+        };
+        let a_records = sorted(&a_records);
+        assert_eq!(a_records.len() as u64, num_tms);
+        assert!(a_records.iter().enumerate().all(|(i, &(index, _, _))| index == i as u64));
+        assert_eq!(a_records, sorted(&b_records));
+    }
+}
@@ -0,0 +1,126 @@
+//! A pool of buffers shared by all worker threads, so that simulations that
+//! are parked and resumed over and over (e.g. the time slices of
+//! `continue --time-slice`) don't allocate new memory every time.
+//!
+//! Buffers are sorted into size classes by their capacity (powers of two), so
+//! a request for a small buffer never takes a huge one and the other way
+//! around.
+
+use std::sync::Mutex;
+
+
+/// The smallest size class: smaller requests get a buffer of this capacity.
+const MIN_CLASS: u32 = 6;
+
+/// Buffers larger than `2^MAX_CLASS` elements are not kept, but freed.
+const MAX_CLASS: u32 = 32;
+
+/// Free buffers of type `Vec<T>`, by size class.
+pub struct BufferPool<T> {
+    /// `classes[i]` holds buffers with a capacity of at least
+    /// `2^(MIN_CLASS + i)` elements.
+    classes: Mutex<Vec<Vec<Vec<T>>>>,
+
+    /// The maximum number of free buffers kept per size class. Returning more
+    /// frees them, so that the pool does not hold on to the memory of a peak
+    /// forever.
+    max_per_class: usize,
+}
+
+impl<T> BufferPool<T> {
+    pub fn new(max_per_class: usize) -> Self {
+        let classes = (MIN_CLASS..=MAX_CLASS).map(|_| Vec::new()).collect();
+        Self {
+            classes: Mutex::new(classes),
+            max_per_class,
+        }
+    }
+
+    /// Returns an empty buffer with a capacity of at least `capacity`, from
+    /// the pool if possible.
+    pub fn take(&self, capacity: usize) -> Vec<T> {
+        let class = class_for(capacity);
+        if let Some(i) = class.filter(|&c| c <= MAX_CLASS).map(|c| (c - MIN_CLASS) as usize) {
+            let buffer = self.classes.lock().expect("poisened lock")[i].pop();
+            if let Some(buffer) = buffer {
+                return buffer;
+            }
+        }
+
+        // Allocate the full size class, so that the buffer can be reused for
+        // all requests of that class.
+        let capacity = class.map_or(capacity, |c| capacity.max(1 << c));
+        Vec::with_capacity(capacity)
+    }
+
+    /// Returns a buffer to the pool. Its contents are dropped.
+    pub fn put(&self, mut buffer: Vec<T>) {
+        let capacity = buffer.capacity();
+        if capacity < 1 << MIN_CLASS {
+            return;
+        }
+
+        // Round down, as the buffer has to be large enough for every request
+        // of its class.
+        let class = usize::BITS - 1 - capacity.leading_zeros();
+        if class > MAX_CLASS {
+            return;
+        }
+
+        buffer.clear();
+        let mut classes = self.classes.lock().expect("poisened lock");
+        let free = &mut classes[(class - MIN_CLASS) as usize];
+        if free.len() < self.max_per_class {
+            free.push(buffer);
+        }
+    }
+
+    /// The number of free buffers in the pool.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.classes.lock().unwrap().iter().map(Vec::len).sum()
+    }
+}
+
+/// The size class of buffers with at least `capacity` elements, i.e. the
+/// exponent of the next power of two. `None` if that does not fit into
+/// `usize`.
+fn class_for(capacity: usize) -> Option<u32> {
+    capacity.checked_next_power_of_two().map(|c| c.trailing_zeros().max(MIN_CLASS))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse() {
+        let pool = BufferPool::<u32>::new(2);
+        let a = pool.take(10);
+        assert_eq!(a.capacity(), 64);
+        let b = pool.take(100);
+        assert_eq!(b.capacity(), 128);
+
+        let ptr = b.as_ptr();
+        pool.put(b);
+        assert_eq!(pool.len(), 1);
+
+        // A smaller request does not take the larger buffer.
+        assert_eq!(pool.take(64).capacity(), 64);
+        let b = pool.take(65);
+        assert_eq!(b.as_ptr(), ptr);
+        assert!(b.is_empty());
+        assert_eq!(pool.len(), 0);
+
+        // Only `max_per_class` buffers are kept.
+        pool.put(a);
+        pool.put(Vec::with_capacity(64));
+        pool.put(Vec::with_capacity(100));
+        assert_eq!(pool.len(), 2);
+
+        // Tiny buffers are not worth keeping.
+        pool.put(Vec::with_capacity(3));
+        assert_eq!(pool.len(), 2);
+    }
+}
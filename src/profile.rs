@@ -0,0 +1,170 @@
+//! A lightweight sampling profiler, enabled with the `profile` feature.
+//!
+//! Each thread doing interesting work publishes the phase it is currently in
+//! via `enter`, which is just a store to an atomic. A separate thread (see
+//! `Sampler`) periodically looks at the phases of all threads and counts
+//! them. The number of samples per phase is then proportional to the core
+//! time spent in it. Without the feature, `enter` does nothing.
+
+#[cfg(feature = "profile")]
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+
+/// What a thread is doing. Phases of the analysis are in the order of
+/// `Analyzer::analyze`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Waiting for work, e.g. for the next chunk of TMs.
+    Idle,
+    Generation,
+    ImmediateHalt,
+    SimpleElope,
+    HaltExists,
+    HaltReachable,
+
+    /// Running the TM, including the run-away and cycle checks.
+    Simulation,
+
+    /// Passing results to outputs, like `--raw-out`, or writing them.
+    Output,
+}
+
+#[cfg(feature = "profile")]
+const NUM_PHASES: usize = 8;
+
+#[cfg(feature = "profile")]
+const PHASE_NAMES: [&str; NUM_PHASES] = [
+    "idle",
+    "generation",
+    "decider: immediate-halt",
+    "decider: simple-elope",
+    "decider: halt-exists",
+    "decider: halt-reachable",
+    "simulation",
+    "output",
+];
+
+/// Marks that the current thread is now in the given phase.
+#[inline(always)]
+pub fn enter(phase: Phase) {
+    #[cfg(feature = "profile")]
+    MARKER.with(|marker| marker.0.store(phase as u8, Ordering::Relaxed));
+    #[cfg(not(feature = "profile"))]
+    let _ = phase;
+}
+
+/// Value of a marker of a thread that exited.
+#[cfg(feature = "profile")]
+const EXITED: u8 = u8::MAX;
+
+/// The phase of all threads that ever called `enter`.
+#[cfg(feature = "profile")]
+static MARKERS: Mutex<Vec<Arc<AtomicU8>>> = Mutex::new(Vec::new());
+
+/// The phase of one thread. It is registered in `MARKERS` on first use and
+/// marked as exited when the thread ends.
+#[cfg(feature = "profile")]
+struct Marker(Arc<AtomicU8>);
+
+#[cfg(feature = "profile")]
+impl Drop for Marker {
+    fn drop(&mut self) {
+        self.0.store(EXITED, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "profile")]
+thread_local! {
+    static MARKER: Marker = {
+        let marker = Arc::new(AtomicU8::new(Phase::Idle as u8));
+        MARKERS.lock().expect("poisened lock").push(marker.clone());
+        Marker(marker)
+    };
+}
+
+/// A thread sampling the phases of all threads until it is stopped.
+#[cfg(feature = "profile")]
+pub struct Sampler {
+    interval: Duration,
+    start: Instant,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<([u64; NUM_PHASES], u32)>,
+}
+
+#[cfg(feature = "profile")]
+impl Sampler {
+    /// Starts sampling every `interval`.
+    pub fn start(interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let (mut samples, mut ticks) = ([0; NUM_PHASES], 0);
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(interval);
+                    ticks += 1;
+                    for marker in MARKERS.lock().expect("poisened lock").iter() {
+                        let phase = marker.load(Ordering::Relaxed);
+                        if phase != EXITED {
+                            samples[phase as usize] += 1;
+                        }
+                    }
+                }
+                (samples, ticks)
+            })
+        };
+
+        Self { interval, start: Instant::now(), stop, handle }
+    }
+
+    /// Stops sampling and returns the result.
+    pub fn stop(self) -> Profile {
+        self.stop.store(true, Ordering::Relaxed);
+        let (samples, ticks) = self.handle.join().expect("panic in sampler thread");
+        let elapsed = self.start.elapsed();
+
+        // Sleeping usually takes a bit longer than requested.
+        let period = elapsed.checked_div(ticks).unwrap_or(self.interval);
+        Profile { samples, period, elapsed }
+    }
+}
+
+/// The samples collected by a `Sampler`.
+#[cfg(feature = "profile")]
+pub struct Profile {
+    samples: [u64; NUM_PHASES],
+
+    /// The actual average time between two samples.
+    period: Duration,
+    elapsed: Duration,
+}
+
+#[cfg(feature = "profile")]
+impl Profile {
+    pub fn print(&self) {
+        let total = self.samples.iter().sum::<u64>();
+        bunt::println!(
+            "{$blue+bold}▸ Profile{/$} ({[intense]} samples every {:.2?} over {:.2?}):",
+            total,
+            self.period,
+            self.elapsed,
+        );
+        println!("    {: <26}{: >10}{: >10}{: >14}", "phase", "samples", "share", "≈ core time");
+        for (name, &samples) in PHASE_NAMES.iter().zip(&self.samples) {
+            println!(
+                "    {: <26}{: >10}{: >9.1}%{: >14.2?}",
+                name,
+                samples,
+                100.0 * samples as f64 / total.max(1) as f64,
+                self.period * samples as u32,
+            );
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Rendering of TM behavior into images.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+use anyhow::{Context, Result, anyhow};
+
+use crate::{heatmap::HeadHeatmap, manifest::Manifest, space_time::SpaceTime};
+
+
+/// Gray value for cells containing a 1.
+pub const ONE: u8 = 0x20;
+
+/// Gray value for cells containing a 0.
+pub const ZERO: u8 = 0xff;
+
+/// Gray value for everything that is not a cell (e.g. the gaps in a contact
+/// sheet).
+pub const BACKGROUND: u8 = 0xa0;
+
+
+/// A simple 8-bit grayscale image.
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Creates an image filled with the given gray value.
+    pub fn new(width: usize, height: usize, fill: u8) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    /// Copies `other` into this image with its top left corner at `(x, y)`.
+    /// Pixels outside this image are ignored.
+    pub fn blit(&mut self, other: &Image, x: usize, y: usize) {
+        for oy in 0..other.height {
+            if y + oy >= self.height {
+                break;
+            }
+
+            let len = other.width.min(self.width.saturating_sub(x));
+            let src = &other.pixels[oy * other.width..][..len];
+            let start = (y + oy) * self.width + x;
+            self.pixels[start..start + len].copy_from_slice(src);
+        }
+    }
+
+    /// Returns a copy of this image with each pixel enlarged to a square of
+    /// `factor` x `factor` pixels.
+    pub fn scaled(&self, factor: usize) -> Self {
+        let mut out = Self::new(self.width * factor, self.height * factor, 0);
+        for y in 0..out.height {
+            let src = &self.pixels[(y / factor) * self.width..][..self.width];
+            for x in 0..out.width {
+                out.pixels[y * out.width + x] = src[x / factor];
+            }
+        }
+        out
+    }
+
+    /// Writes this image as PNG or SVG file, depending on the extension of
+    /// `path`. Each pixel becomes a square of `scale` x `scale` pixels (for
+    /// SVG, that's only the default display size).
+    pub fn write(&self, path: &Path, scale: usize, manifest: &Manifest) -> Result<()> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.scaled(scale).write_png(path, manifest),
+            Some("svg") => self.write_svg(path, scale, manifest),
+            _ => Err(anyhow!(
+                "unknown image format of '{}' (expected '.png' or '.svg')",
+                path.display(),
+            )),
+        }
+    }
+
+    /// Writes this image as SVG file, with one rectangle per run of equal
+    /// pixels in a row and the manifest embedded as metadata.
+    pub fn write_svg(&self, path: &Path, scale: usize, manifest: &Manifest) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut w = BufWriter::new(file);
+
+        let manifest = manifest.to_string()
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        writeln!(
+            w,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">",
+            self.width * scale,
+            self.height * scale,
+            self.width,
+            self.height,
+        )?;
+        writeln!(w, "<metadata>\n{}</metadata>", manifest)?;
+        for y in 0..self.height {
+            let row = &self.pixels[y * self.width..][..self.width];
+            let mut x = 0;
+            while x < self.width {
+                let len = row[x..].iter().take_while(|&&v| v == row[x]).count();
+                writeln!(
+                    w,
+                    r##"<rect x="{}" y="{}" width="{}" height="1" fill="#{:02x}{:02x}{:02x}"/>"##,
+                    x,
+                    y,
+                    len,
+                    row[x],
+                    row[x],
+                    row[x],
+                )?;
+                x += len;
+            }
+        }
+        writeln!(w, "</svg>")?;
+
+        w.flush().with_context(|| format!("failed to write '{}'", path.display()))
+    }
+
+    /// Writes this image as PNG file, with the manifest embedded as text
+    /// chunk.
+    pub fn write_png(&self, path: &Path, manifest: &Manifest) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(file),
+            self.width as u32,
+            self.height as u32,
+        );
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_itxt_chunk("beaver-manifest".into(), manifest.to_string())?;
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+
+        Ok(())
+    }
+}
+
+/// Renders a head heatmap with one pixel per cell and time slice: the more
+/// steps the head spent there, the darker. Cells never visited are white.
+pub fn heatmap(heatmap: &HeadHeatmap) -> Image {
+    let cells = heatmap.cells();
+    let width = (cells.end - cells.start) as usize;
+    let max = heatmap.max().max(1) as f64;
+    let mut image = Image::new(width, heatmap.rows(), ZERO);
+
+    for row in 0..heatmap.rows() {
+        for (x, cell) in cells.clone().enumerate() {
+            let heat = heatmap.get(row, cell) as f64 / max;
+            let value = ZERO as f64 - heat * (ZERO - ONE) as f64;
+            image.set(x, row, value.round() as u8);
+        }
+    }
+
+    image
+}
+
+/// Renders a captured space-time diagram with one pixel per cell: row `i`
+/// shows the cells after step `i` (row 0 is the empty start tape).
+pub fn space_time(capture: &SpaceTime) -> Image {
+    let window = capture.window();
+    let width = (window.end - window.start) as usize;
+    let mut image = Image::new(width, capture.rows(), BACKGROUND);
+
+    for row in 0..capture.rows() {
+        for (x, cell) in window.clone().enumerate() {
+            let value = if capture.get(row, cell) { ONE } else { ZERO };
+            image.set(x, row, value);
+        }
+    }
+
+    image
+}
@@ -0,0 +1,245 @@
+//! Replay files of whole sweeps (see `full --record` and `full --replay`).
+//!
+//! Which worker analyzes which job and the order in which the results reach
+//! the outputs depend on timing. So two runs with the same settings can
+//! write their result files in different orders and draw different examples
+//! (see `ExamplesOut`). A replay file records that order, and a run replaying
+//! it delivers the results in exactly the same order again, so its outputs
+//! are identical to the ones of the recorded run.
+//!
+//! The file starts with the header `beaver-replay 1`, followed by the
+//! manifest as comments and `<key> <value>` lines with the versions, the
+//! settings, the number of threads and the range of TMs. Then there is one
+//! line `job <start> <end> <worker>` per job in the order its results were
+//! passed to the outputs. The last line is `end`, so that files of runs that
+//! were interrupted can be detected.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use anyhow::{Context, Result, anyhow};
+
+use crate::{
+    checkpoint::{Settings, check_versions, write_versions},
+    manifest::Manifest,
+};
+
+
+const HEADER: &str = "beaver-replay 1";
+
+/// A job of a recorded sweep: the indices of its TMs and the worker thread
+/// that analyzed them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedJob {
+    pub range: Range<u64>,
+    pub worker: u32,
+}
+
+/// Everything that determines the order of results of a sweep.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub settings: Settings,
+    pub num_threads: u32,
+    pub range: Option<Range<u64>>,
+
+    /// All jobs, in the order their results were passed to the outputs.
+    pub jobs: Vec<RecordedJob>,
+}
+
+/// A replay file that is written while the sweep is running.
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    path: PathBuf,
+
+    /// The first error while writing, returned by `finish`.
+    error: Option<anyhow::Error>,
+}
+
+impl ReplayRecorder {
+    /// Creates the file and writes the header, manifest, versions and
+    /// settings.
+    pub fn create(
+        path: &Path,
+        settings: &Settings,
+        num_threads: u32,
+        range: Option<&Range<u64>>,
+        manifest: &Manifest,
+    ) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", HEADER)?;
+        manifest.write_comments(&mut writer)?;
+        write_versions(&mut writer)?;
+        settings.write(&mut writer)?;
+        writeln!(writer, "num-threads {}", num_threads)?;
+        match range {
+            None => writeln!(writer, "range none")?,
+            Some(range) => writeln!(writer, "range {}..{}", range.start, range.end)?,
+        }
+
+        Ok(Self { writer, path: path.to_owned(), error: None })
+    }
+
+    /// Appends a job whose results were just passed to the outputs. Errors
+    /// are returned by `finish`, as this is called from the worker threads.
+    pub fn record(&mut self, range: &Range<u64>, worker: u32) {
+        if let Err(e) = writeln!(self.writer, "job {} {} {}", range.start, range.end, worker) {
+            let e = self.write_error(e);
+            self.error.get_or_insert(e);
+        }
+    }
+
+    /// Marks the recording as complete and flushes the file. Returns the
+    /// first error that occured while writing, if any.
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        writeln!(self.writer, "end")
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| self.write_error(e))
+    }
+
+    fn write_error(&self, e: std::io::Error) -> anyhow::Error {
+        anyhow::Error::from(e).context(format!("failed to write '{}'", self.path.display()))
+    }
+}
+
+/// Reads a file written by `ReplayRecorder`.
+pub fn read(path: &Path) -> Result<Replay> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("failed to read '{}'", path.display()))?;
+    parse(&content).with_context(|| format!("failed to parse '{}'", path.display()))
+}
+
+fn parse(s: &str) -> Result<Replay> {
+    let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+    if lines.next() != Some(HEADER) {
+        return Err(anyhow!("missing header '{}'", HEADER));
+    }
+
+    let mut values = Vec::new();
+    let mut jobs = Vec::new();
+    let mut complete = false;
+    for line in lines {
+        if complete {
+            return Err(anyhow!("unexpected line '{}' after 'end'", line));
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "end" => complete = true,
+            "job" => {
+                let parts = value.split(' ').collect::<Vec<_>>();
+                let (start, end, worker) = match parts[..] {
+                    [start, end, worker] => (start, end, worker),
+                    _ => return Err(anyhow!("invalid line '{}'", line)),
+                };
+                jobs.push(RecordedJob {
+                    range: start.parse()?..end.parse()?,
+                    worker: worker.parse()?,
+                });
+            }
+            _ => values.push((key, value)),
+        }
+    }
+    if !complete {
+        return Err(anyhow!("the file is incomplete, the recorded run did not finish"));
+    }
+
+    let get = |key: &str| {
+        values.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| anyhow!("missing '{}'", key))
+    };
+    check_versions(get)?;
+    let range = match get("range")? {
+        "none" => None,
+        range => {
+            let (start, end) = range.split_once("..")
+                .ok_or_else(|| anyhow!("invalid range '{}'", range))?;
+            Some(start.parse()?..end.parse()?)
+        }
+    };
+
+    Ok(Replay {
+        settings: Settings::read(get)?,
+        num_threads: get("num-threads")?.parse()?,
+        range,
+        jobs,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs,
+        process,
+        sync::{Arc, Mutex},
+    };
+    use anyhow::Result;
+    use crate::{
+        gen::Generator,
+        manifest::Manifest,
+        output::{Record, ResultWriter, Source},
+        sweep::SweepBuilder,
+    };
+    use super::{ReplayRecorder, parse, read};
+
+    /// Collects the records with their worker, in the order they arrive.
+    struct Collect(Arc<Mutex<Vec<(Record, u32)>>>);
+
+    impl ResultWriter for Collect {
+        fn write(&mut self, record: Record, source: Source) -> Result<()> {
+            self.0.lock().unwrap().push((record, source.worker));
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let path = env::temp_dir().join(format!("beaver-replay-file-test-{}", process::id()));
+        let sweep = || {
+            SweepBuilder::new(3)
+                .generator(Generator::Optimized)
+                .max_steps(100)
+                .num_threads(Some(3))
+        };
+
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let settings = sweep().settings();
+        let recorder = ReplayRecorder::create(&path, &settings, 3, None, &Manifest::new(&[]))
+            .unwrap();
+        let recorder = Arc::new(Mutex::new(recorder));
+        sweep()
+            .record(Some(recorder.clone()))
+            .output(Collect(recorded.clone()))
+            .run()
+            .unwrap();
+        Arc::try_unwrap(recorder).ok().unwrap().into_inner().unwrap().finish().unwrap();
+
+        let replay = read(&path);
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let replay = replay.unwrap();
+        assert_eq!(replay.settings, settings);
+        assert_eq!((replay.num_threads, replay.range.clone()), (3, None));
+
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        sweep().replay(Some(replay)).output(Collect(replayed.clone())).run().unwrap();
+        assert_eq!(*replayed.lock().unwrap(), *recorded.lock().unwrap());
+
+        assert!(parse(&content.replace("end\n", "")).is_err());
+        assert!(parse(&content.replace("range none", "range 5")).is_err());
+    }
+}
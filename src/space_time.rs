@@ -0,0 +1,106 @@
+//! Capturing the tape of a TM over time (its space-time diagram) into a
+//! compact bitmap, which is then rendered to the terminal or into images.
+
+use std::ops::Range;
+
+use crate::{machine::Machine, tm::Tm};
+
+
+/// The cells in a fixed window of the tape after each step: row `i` is the
+/// tape after step `i` (row 0 is the empty start tape). Each row is stored as
+/// bits, so even long runs of wide windows fit into memory.
+pub struct SpaceTime {
+    window: Range<i64>,
+    words_per_row: usize,
+    bits: Vec<u64>,
+
+    /// The head position of each row.
+    heads: Vec<i64>,
+}
+
+impl SpaceTime {
+    /// Runs the TM for at most `max_steps` steps and captures the cells in
+    /// `window`. Stops when the TM halts, so the number of rows is the number
+    /// of executed steps plus one.
+    pub fn capture<const N: usize>(tm: Tm<N>, max_steps: u64, window: Range<i64>) -> Self {
+        let width = (window.end - window.start).max(0) as usize;
+        let mut out = Self {
+            window,
+            words_per_row: width.div_ceil(64),
+            bits: Vec::new(),
+            heads: Vec::new(),
+        };
+
+        let mut machine = Machine::new(tm);
+        loop {
+            out.push_row(&machine);
+            if machine.steps() >= max_steps || machine.step().is_none() {
+                break;
+            }
+        }
+
+        out
+    }
+
+    /// Appends the current tape of `machine` as new row.
+    fn push_row<const N: usize>(&mut self, machine: &Machine<N>) {
+        let start = self.bits.len();
+        self.bits.resize(start + self.words_per_row, 0);
+        let window = self.window.clone();
+        let ones = machine.tape().runs()
+            .filter(|(value, _)| value.0)
+            .flat_map(|(_, cells)| cells.start.0..cells.end.0)
+            .filter(|cell| window.contains(cell));
+        for cell in ones {
+            let x = (cell - window.start) as usize;
+            self.bits[start + x / 64] |= 1 << (x % 64);
+        }
+        self.heads.push(machine.head().0);
+    }
+
+    /// The cells of each row.
+    pub fn window(&self) -> Range<i64> {
+        self.window.clone()
+    }
+
+    /// The number of rows, i.e. the number of captured steps plus one.
+    pub fn rows(&self) -> usize {
+        self.heads.len()
+    }
+
+    /// The value of `cell` in row `row`. Cells outside the window are 0.
+    pub fn get(&self, row: usize, cell: i64) -> bool {
+        if !self.window.contains(&cell) {
+            return false;
+        }
+
+        let x = (cell - self.window.start) as usize;
+        (self.bits[row * self.words_per_row + x / 64] >> (x % 64)) & 1 == 1
+    }
+
+    /// The head position in row `row`.
+    pub fn head(&self, row: usize) -> i64 {
+        self.heads[row]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_champion() {
+        let tm = <Tm<2>>::new_unchecked(327814);
+        for window in &[-3..4, -100..100] {
+            let capture = SpaceTime::capture(tm, 100, window.clone());
+            assert_eq!(capture.rows(), 7);
+            assert_eq!(window.clone().filter(|&cell| capture.get(0, cell)).count(), 0);
+            assert_eq!(window.clone().filter(|&cell| capture.get(6, cell)).count(), 4);
+            assert_eq!(capture.head(0), 0);
+        }
+
+        // Capturing stops at the step limit.
+        assert_eq!(SpaceTime::capture(tm, 3, -3..4).rows(), 4);
+    }
+}
@@ -0,0 +1,211 @@
+//! Deltas between a summary and an earlier snapshot of it, so that progress
+//! can be reported without sending the full summary each time.
+
+use std::io::{self, Write};
+use ahash::AHashMap;
+use anyhow::Result;
+
+use super::{Champion, Summary, TieBreak};
+
+
+/// What changed in a summary since a snapshot of it (see
+/// `Summary::delta_since`): the counters and histogram buckets that
+/// changed, and the new champions. Applying it to the snapshot (see
+/// `Summary::apply_delta`) results in the summary again.
+#[derive(Clone)]
+pub struct SummaryDelta {
+    /// Stored as a summary such that adding it to the snapshot with
+    /// `Summary::add` results in the newer summary.
+    changes: Summary,
+}
+
+impl SummaryDelta {
+    /// Writes the delta in the format of `Summary::write_state`, but leaves
+    /// out all counters that did not change.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        self.changes.write_lines(w, true)
+    }
+
+    /// Reads a delta written by `write`. `tie_break` and `max_champions`
+    /// have to be the ones of the summary it was created from.
+    pub fn read<'a>(
+        tie_break: TieBreak,
+        max_champions: usize,
+        lines: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self> {
+        Summary::read_state(tie_break, max_champions, lines).map(|changes| Self { changes })
+    }
+}
+
+impl Summary {
+    /// Returns what changed since `snapshot`, which has to be an earlier
+    /// state of this summary (e.g. a clone taken at the last report).
+    pub fn delta_since(&self, snapshot: &Summary) -> SummaryDelta {
+        let mut changes = Summary::new(self.tie_break, self.max_champions);
+
+        // A summary only takes the winner-related values of the summary added
+        // to it if its score is at least as high (see `Summary::add`).
+        changes.high_score = self.high_score;
+        changes.fewest_winner_steps = self.fewest_winner_steps;
+        if self.high_score > snapshot.high_score {
+            changes.num_winners = self.num_winners;
+            changes.winners = self.winners.clone();
+            changes.winner_step_histogram = self.winner_step_histogram.clone();
+        } else {
+            changes.num_winners = self.num_winners - snapshot.num_winners;
+            changes.winners = new_champions(&self.winners, &snapshot.winners);
+            changes.winner_step_histogram = histogram_delta(
+                &self.winner_step_histogram,
+                &snapshot.winner_step_histogram,
+            );
+        }
+        changes.most_steps = self.most_steps;
+        if self.most_steps > snapshot.most_steps {
+            changes.num_step_winners = self.num_step_winners;
+            changes.step_winners = self.step_winners.clone();
+        } else {
+            changes.num_step_winners = self.num_step_winners - snapshot.num_step_winners;
+            changes.step_winners = new_champions(&self.step_winners, &snapshot.step_winners);
+        }
+
+        changes.step_histogram = histogram_delta(&self.step_histogram, &snapshot.step_histogram);
+        changes.simulated_step_histogram = histogram_delta(
+            &self.simulated_step_histogram,
+            &snapshot.simulated_step_histogram,
+        );
+
+        changes.num_halted = self.num_halted - snapshot.num_halted;
+        changes.num_aborted_after_max_steps =
+            self.num_aborted_after_max_steps - snapshot.num_aborted_after_max_steps;
+        changes.num_immediate_halt = self.num_immediate_halt - snapshot.num_immediate_halt;
+        changes.num_simple_elope = self.num_simple_elope - snapshot.num_simple_elope;
+        changes.num_no_halt_transition =
+            self.num_no_halt_transition - snapshot.num_no_halt_transition;
+        changes.num_halt_unreachable = self.num_halt_unreachable - snapshot.num_halt_unreachable;
+        changes.num_runaway = self.num_runaway - snapshot.num_runaway;
+        changes.num_cycle = self.num_cycle - snapshot.num_cycle;
+        changes.num_translated_cycler =
+            self.num_translated_cycler - snapshot.num_translated_cycler;
+        changes.num_backward_reasoning =
+            self.num_backward_reasoning - snapshot.num_backward_reasoning;
+        changes.num_ctl = self.num_ctl - snapshot.num_ctl;
+        changes.num_lin_recurrence = self.num_lin_recurrence - snapshot.num_lin_recurrence;
+        changes.num_pruned = self.num_pruned - snapshot.num_pruned;
+        changes.num_known = self.num_known - snapshot.num_known;
+        changes.num_halted_zero_ones = self.num_halted_zero_ones - snapshot.num_halted_zero_ones;
+        changes.num_aborted_growing = self.num_aborted_growing - snapshot.num_aborted_growing;
+
+        let first_action = self.first_action.counts.iter().zip(&snapshot.first_action.counts);
+        for (out, (row, old_row)) in changes.first_action.counts.iter_mut().zip(first_action) {
+            for (out, (count, old_count)) in out.iter_mut().zip(row.iter().zip(old_row)) {
+                *out = count - old_count;
+            }
+        }
+        let tape_patterns = self.tape_patterns.counts.iter().zip(&snapshot.tape_patterns.counts);
+        let out = changes.tape_patterns.counts.iter_mut();
+        for (out, (count, old_count)) in out.zip(tape_patterns) {
+            *out = count - old_count;
+        }
+
+        #[cfg(feature = "tape-stats")]
+        {
+            let (s, old) = (&self.tape_stats, &snapshot.tape_stats);
+            changes.tape_stats = crate::tape::TapeStats {
+                runs: s.runs - old.runs,
+                grows: s.grows - old.grows,
+                bytes_copied: s.bytes_copied - old.bytes_copied,
+                bucket_accesses: s.bucket_accesses - old.bucket_accesses,
+            };
+        }
+
+        SummaryDelta { changes }
+    }
+
+    /// Applies a delta created by `delta_since` on a summary equal to this
+    /// one, making this summary equal to the one the delta was created from.
+    pub fn apply_delta(&mut self, delta: SummaryDelta) {
+        self.add(delta.changes);
+    }
+}
+
+/// The champions in `champions` that are not in `old`.
+fn new_champions(champions: &[Champion], old: &[Champion]) -> Vec<Champion> {
+    champions.iter()
+        .filter(|c| !old.iter().any(|o| o.id == c.id && o.index == c.index))
+        .copied()
+        .collect()
+}
+
+/// The buckets of `histogram` that changed compared to `old`, with the
+/// difference as count.
+fn histogram_delta(histogram: &AHashMap<u64, u64>, old: &AHashMap<u64, u64>) -> AHashMap<u64, u64> {
+    histogram.iter()
+        .map(|(steps, count)| (*steps, count - old.get(steps).copied().unwrap_or(0)))
+        .filter(|(_, count)| *count != 0)
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analyze::Analyzer,
+        gen::{All, TmGenerator},
+        summary::{Summary, TieBreak},
+    };
+    use super::SummaryDelta;
+
+    fn sorted_state(summary: &Summary) -> Vec<String> {
+        let mut state = Vec::new();
+        summary.write_state(&mut state).unwrap();
+        let mut lines = String::from_utf8(state).unwrap()
+            .lines()
+            .map(|l| l.to_owned())
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn apply_delta() {
+        for max_champions in [0, 3] {
+            // Snapshots are taken at several points, the first ones before the
+            // final high score and step record are reached.
+            let mut summary = Summary::new(TieBreak::FewestSteps, max_champions);
+            let mut analyzer = <Analyzer<2>>::builder().build();
+            let mut snapshots = Vec::new();
+            let mut i = 0;
+            <All<2>>::default().for_all(|tm| {
+                if [0, 10, 100, 1000, 5000].contains(&i) {
+                    snapshots.push(summary.clone());
+                }
+                summary.set_index(i);
+                analyzer.analyze(tm, &mut summary);
+                i += 1;
+            });
+
+            let expected = sorted_state(&summary);
+            for snapshot in snapshots {
+                let delta = summary.delta_since(&snapshot);
+                let mut applied = snapshot.clone();
+                applied.apply_delta(delta.clone());
+                assert_eq!(sorted_state(&applied), expected);
+
+                // The same after sending the delta as text.
+                let mut text = Vec::new();
+                delta.write(&mut text).unwrap();
+                let text = String::from_utf8(text).unwrap();
+                let read = SummaryDelta::read(TieBreak::FewestSteps, max_champions, text.lines())
+                    .unwrap();
+                let mut applied = snapshot;
+                applied.apply_delta(read);
+                assert_eq!(sorted_state(&applied), expected);
+            }
+
+            // Nothing but the scores is sent if nothing changed.
+            let mut text = Vec::new();
+            summary.delta_since(&summary).write(&mut text).unwrap();
+            assert!(!String::from_utf8(text).unwrap().contains("count"));
+        }
+    }
+}
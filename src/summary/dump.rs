@@ -0,0 +1,65 @@
+//! Canonical plain-text representation of a summary (see the `dump`
+//! command).
+
+use std::io::{self, Write};
+use ahash::AHashMap;
+
+use super::{Champion, Summary};
+
+
+impl Summary {
+    /// Writes the key results (counts, confidence, Σ and S champions, histograms) as
+    /// plain text, one `key value...` pair per line. Everything is sorted and
+    /// nothing depends on the number of threads, the tie break or the time,
+    /// so the output of two runs with the same settings is identical.
+    pub fn write_dump(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "total {}", self.num_total_tms())?;
+        for (name, count) in &self.counts() {
+            writeln!(w, "count {} {}", name, count)?;
+        }
+        for (confidence, count) in &self.confidence_counts() {
+            writeln!(w, "confidence {} {}", confidence.name(), count)?;
+        }
+
+        writeln!(w, "sigma {}", self.high_score)?;
+        write_champions(w, "sigma-champion", &self.winners)?;
+        writeln!(w, "s {}", self.most_steps)?;
+        write_champions(w, "s-champion", &self.step_winners)?;
+
+        let histograms = [
+            ("halted", &self.step_histogram),
+            ("simulated", &self.simulated_step_histogram),
+            ("high-score", &self.winner_step_histogram),
+        ];
+        for (name, histogram) in &histograms {
+            write_histogram(w, name, histogram)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes one `<key> <id> <steps> <ones>` line per champion, sorted by ID.
+fn write_champions(w: &mut impl Write, key: &str, champions: &[Champion]) -> io::Result<()> {
+    let mut champions = champions.to_vec();
+    champions.sort_by_key(|c| c.id);
+    for c in &champions {
+        writeln!(w, "{} {} {} {}", key, c.id, c.steps, c.ones)?;
+    }
+    Ok(())
+}
+
+/// Writes one `histogram <name> <steps> <count>` line per bucket, sorted by
+/// steps.
+fn write_histogram(
+    w: &mut impl Write,
+    name: &str,
+    histogram: &AHashMap<u64, u64>,
+) -> io::Result<()> {
+    let mut buckets = histogram.iter().collect::<Vec<_>>();
+    buckets.sort_unstable();
+    for (steps, count) in buckets {
+        writeln!(w, "histogram {} {} {}", name, steps, count)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Outcome statistics split by the structure of the start action (the `on_0`
+//! action of state A).
+
+use crate::tm::{Move, NextState, Tm};
+
+
+/// The different outcome categories counted per start action class.
+#[derive(Debug, Clone, Copy)]
+pub enum Category {
+    Halted,
+    ImmediateHalt,
+    SimpleElope,
+    NoHaltTransition,
+    HaltUnreachable,
+    RunAway,
+    Cycle,
+    TranslatedCycler,
+    BackwardReasoning,
+    Ctl,
+    LinRecurrence,
+    Aborted,
+    Pruned,
+    Known,
+}
+
+const NUM_CATEGORIES: usize = 14;
+#[cfg(feature = "cli")]
+const CATEGORY_NAMES: [&str; NUM_CATEGORIES] = [
+    "halted", "imm.halt", "elope", "no-halt", "unreach.", "run-away", "cycle", "transl.",
+    "backward", "ctl", "lin-rec.", "aborted", "pruned", "known",
+];
+
+/// Start actions are classified by the value they write (2), the direction
+/// they move (2) and their next state (4: A, B, any other state or H).
+const NUM_CLASSES: usize = 16;
+
+/// Index of the class of actions equal to `1RB`, the only start action that
+/// remains with Brady's normalization.
+#[cfg(feature = "cli")]
+const BRADY_CLASS: usize = 0b0111;
+
+#[derive(Clone)]
+pub struct FirstActionStats {
+    pub(super) counts: [[u64; NUM_CATEGORIES]; NUM_CLASSES],
+}
+
+impl FirstActionStats {
+    pub fn new() -> Self {
+        Self {
+            counts: [[0; NUM_CATEGORIES]; NUM_CLASSES],
+        }
+    }
+
+    pub fn record<const N: usize>(&mut self, tm: Tm<N>, category: Category) {
+        self.counts[class_of(tm)][category as usize] += 1;
+    }
+
+    /// Undoes a previous `record` of the same TM and category.
+    pub fn forget<const N: usize>(&mut self, tm: Tm<N>, category: Category) {
+        self.counts[class_of(tm)][category as usize] -= 1;
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        for (row, other_row) in self.counts.iter_mut().zip(&other.counts) {
+            for (count, other_count) in row.iter_mut().zip(other_row) {
+                *count += other_count;
+            }
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn print(&self) {
+        let total: u64 = self.counts.iter().flatten().sum();
+
+        bunt::println!("{$blue+bold}▸ Outcomes by start action:{/$}");
+        print!("   start ");
+        for name in &CATEGORY_NAMES {
+            print!("{: >11}", name);
+        }
+        println!("{: >11}", "share");
+
+        for (class, row) in self.counts.iter().enumerate() {
+            let row_total: u64 = row.iter().sum();
+            if row_total == 0 {
+                continue;
+            }
+
+            print!("    {: <4}", class_label(class));
+            for count in row {
+                print!("{: >11}", count);
+            }
+            println!("{: >10.2}%", 100.0 * row_total as f64 / total as f64);
+        }
+
+        let brady: u64 = self.counts[BRADY_CLASS].iter().sum();
+        println!();
+        println!(
+            "Brady's normalization only keeps TMs starting with '1RB': {} of {} TMs ({:.2}%)",
+            brady,
+            total,
+            100.0 * brady as f64 / total as f64,
+        );
+    }
+}
+
+fn class_of<const N: usize>(tm: Tm<N>) -> usize {
+    let action = tm.start_action();
+    let write = action.write_value().0 as usize;
+    let right = (action.movement() == Move::Right) as usize;
+    let target = match action.next_state() {
+        NextState::State(0) => 0,
+        NextState::State(1) => 1,
+        NextState::State(_) => 2,
+        NextState::HaltState => 3,
+    };
+
+    (target << 2) | (right << 1) | write
+}
+
+#[cfg(feature = "cli")]
+fn class_label(class: usize) -> String {
+    let write = class & 1;
+    let direction = if class & 0b10 == 0 { 'L' } else { 'R' };
+    let target = ["A", "B", "C+", "H"][class >> 2];
+    format!("{}{}{}", write, direction, target)
+}
@@ -0,0 +1,115 @@
+//! JSON representation of the report (see `full --json-out`), for
+//! post-processing the results with other tools.
+
+use std::io::{self, Write};
+use ahash::AHashMap;
+
+use crate::{manifest::Manifest, tm};
+use super::{Champion, Summary};
+
+
+impl Summary {
+    /// Writes the results as a JSON object: the manifest, the counts per
+    /// outcome and per confidence, the Σ and S scores with all their champions (sorted by tie
+    /// break), the step histograms and the census of the final tapes.
+    pub fn write_json(&self, w: &mut impl Write, n: u8, manifest: &Manifest) -> io::Result<()> {
+        writeln!(w, "{{")?;
+
+        let entries = manifest.entries().iter()
+            .map(|(key, value)| format!("{}: {}", string(key), string(value)))
+            .collect::<Vec<_>>();
+        writeln!(w, "  \"manifest\": {{{}}},", entries.join(", "))?;
+
+        writeln!(w, "  \"total\": {},", self.num_total_tms())?;
+        let counts = self.counts().iter()
+            .map(|(name, count)| format!("{}: {}", string(name), count))
+            .collect::<Vec<_>>();
+        writeln!(w, "  \"counts\": {{{}}},", counts.join(", "))?;
+        let confidence = self.confidence_counts().iter()
+            .map(|(confidence, count)| format!("{}: {}", string(confidence.name()), count))
+            .collect::<Vec<_>>();
+        writeln!(w, "  \"confidence\": {{{}}},", confidence.join(", "))?;
+
+        writeln!(w, "  \"sigma\": {{")?;
+        writeln!(w, "    \"value\": {},", self.high_score)?;
+        writeln!(w, "    \"num-tms\": {},", self.num_winners)?;
+        writeln!(w, "    \"fewest-steps\": {},", self.fewest_winner_steps)?;
+        write_champions(w, n, &self.ranked(&self.winners))?;
+        writeln!(w, "  }},")?;
+        writeln!(w, "  \"s\": {{")?;
+        writeln!(w, "    \"value\": {},", self.most_steps)?;
+        writeln!(w, "    \"num-tms\": {},", self.num_step_winners)?;
+        write_champions(w, n, &self.ranked(&self.step_winners))?;
+        writeln!(w, "  }},")?;
+
+        writeln!(w, "  \"histograms\": {{")?;
+        writeln!(w, "    \"halted\": {},", histogram(&self.step_histogram))?;
+        writeln!(w, "    \"simulated\": {},", histogram(&self.simulated_step_histogram))?;
+        writeln!(w, "    \"high-score\": {}", histogram(&self.winner_step_histogram))?;
+        writeln!(w, "  }},")?;
+
+        let patterns = self.tape_patterns.named_counts()
+            .map(|(name, count)| format!("{}: {}", string(name), count))
+            .collect::<Vec<_>>();
+        writeln!(w, "  \"tape-patterns\": {{{}}}", patterns.join(", "))?;
+
+        writeln!(w, "}}")
+    }
+}
+
+/// Writes the `champions` field: one object per champion.
+fn write_champions(w: &mut impl Write, n: u8, champions: &[Champion]) -> io::Result<()> {
+    writeln!(w, "    \"champions\": [")?;
+    for (i, c) in champions.iter().enumerate() {
+        let separator = if i + 1 < champions.len() { "," } else { "" };
+        writeln!(
+            w,
+            "      {{\"id\": {}, \"steps\": {}, \"ones\": {}, \"standard\": {}}}{}",
+            c.id,
+            c.steps,
+            c.ones,
+            string(&tm::standard_notation(c.id, n)),
+            separator,
+        )?;
+    }
+    writeln!(w, "    ]")
+}
+
+/// Formats the histogram as an array of `[steps, count]` pairs, sorted by
+/// steps.
+fn histogram(histogram: &AHashMap<u64, u64>) -> String {
+    let mut buckets = histogram.iter().collect::<Vec<_>>();
+    buckets.sort_unstable();
+    let buckets = buckets.iter()
+        .map(|(steps, count)| format!("[{}, {}]", steps, count))
+        .collect::<Vec<_>>();
+    format!("[{}]", buckets.join(", "))
+}
+
+/// Formats `s` as JSON string literal.
+fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::string;
+
+    #[test]
+    fn escape() {
+        assert_eq!(string("full -n 2"), r#""full -n 2""#);
+        assert_eq!(string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(string("Σ\n"), "\"Σ\\u000a\"");
+    }
+}
@@ -0,0 +1,1473 @@
+//! Aggregated results of analyzing many TMs.
+
+use std::{cmp::min, fmt, str::FromStr};
+use ahash::AHashMap;
+
+use crate::{
+    budget::Budget,
+    outcome::{Configuration, OutcomeSink},
+    tape::TapeLike,
+    tm::Tm,
+};
+#[cfg(feature = "cli")]
+use crate::tm;
+#[cfg(feature = "tape-stats")]
+use crate::tape::TapeStats;
+use self::{
+    first_action::{Category, FirstActionStats},
+    tape_patterns::TapePatterns,
+};
+pub use self::{
+    delta::SummaryDelta,
+    reference::{ReferenceCheck, Verdict},
+};
+#[cfg(feature = "cli")]
+use self::stages::Stage;
+
+mod delta;
+mod dump;
+mod first_action;
+mod json;
+mod reference;
+#[cfg(feature = "cli")]
+mod stages;
+mod state;
+mod tape_patterns;
+
+
+/// If more than this fraction of the simulated TMs are aborted, the step limit
+/// is probably too low (see `Summary::warn_max_steps`).
+#[cfg(feature = "cli")]
+const ABORTED_WARNING_FRACTION: f64 = 0.01;
+
+/// Options controlling what `Summary::print_report` prints.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// The step limit used for the run. Only used for printing.
+    pub max_steps: u64,
+
+    /// The run budget used for the run. Only used for printing.
+    pub run_budget: Option<Budget>,
+
+    /// The number of states of the TMs. Only used for printing.
+    pub n: u8,
+
+    /// Also print the champions in the standard notation (see
+    /// `Tm::to_standard_notation`).
+    pub standard_notation: bool,
+
+    /// The max number of champions listed per score, or 0 to list all.
+    pub listed_champions: usize,
+
+    /// Height of the histogram in lines.
+    pub histogram_height: u32,
+
+    /// The max number of steps included in the histogram.
+    pub histogram_cutoff: HistogramCutoff,
+
+    pub hide_histogram: bool,
+
+    /// Which TMs are included in the histogram.
+    pub histogram_filter: HistogramFilter,
+
+    /// Print the outcomes split by the structure of the start action.
+    pub first_action_stats: bool,
+
+    /// Print the census of the final tapes of halted TMs.
+    pub tape_patterns: bool,
+
+    /// Print how many TMs were decided at which pipeline stage.
+    pub stage_stats: bool,
+
+    /// Like `stage_stats`, but also print a bar chart.
+    pub stage_chart: bool,
+
+    /// Additionally print the outcomes of simulated TMs with percentages
+    /// relative to those only, i.e. excluding statically decided TMs.
+    pub simulated_only: bool,
+}
+
+/// Selects the TMs whose number of steps is shown in the histogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramFilter {
+    /// All halted TMs, including those halting immediately.
+    Halted,
+
+    /// Only TMs that reached the high score.
+    HighScore,
+
+    /// Only TMs that halted while being simulated, i.e. excluding the ones
+    /// decided by the static checks.
+    Simulated,
+}
+
+impl FromStr for HistogramFilter {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halted" => Ok(Self::Halted),
+            "high-score" => Ok(Self::HighScore),
+            "simulated" => Ok(Self::Simulated),
+            _ => Err("invalid value for histogram filter"),
+        }
+    }
+}
+
+impl fmt::Display for HistogramFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Halted => f.write_str("halted"),
+            Self::HighScore => f.write_str("high-score"),
+            Self::Simulated => f.write_str("simulated"),
+        }
+    }
+}
+
+/// Where the histogram ends. TMs halting later are summarized in one
+/// additional bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramCutoff {
+    /// One bar per number of steps, up to (excluding) this number of steps.
+    Fixed(u32),
+
+    /// Includes 99.9% of the TMs in the histogram, but at least the steps
+    /// below `DEFAULT_HISTOGRAM_CUTOFF`. If that needs more than
+    /// `MAX_HISTOGRAM_BARS` bars, each bar covers several numbers of steps.
+    Auto,
+}
+
+/// The cutoff `HistogramCutoff::Auto` uses at least.
+#[cfg(feature = "cli")]
+const DEFAULT_HISTOGRAM_CUTOFF: u64 = 30;
+
+/// The max number of bars of `HistogramCutoff::Auto` (without the one of the
+/// TMs beyond the cutoff).
+#[cfg(feature = "cli")]
+const MAX_HISTOGRAM_BARS: u64 = 40;
+
+impl FromStr for HistogramCutoff {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            _ => s.parse().map(Self::Fixed)
+                .map_err(|_| "invalid value for histogram cutoff (a number or 'auto')"),
+        }
+    }
+}
+
+impl fmt::Display for HistogramCutoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(steps) => steps.fmt(f),
+            Self::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+/// The steps covered by the bars of the histogram: bar `i` covers the steps
+/// `1 + i * width` up to (excluding) `1 + (i + 1) * width`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HistogramBars {
+    width: u64,
+    count: u64,
+}
+
+#[cfg(feature = "cli")]
+impl HistogramBars {
+    fn new(histogram: &AHashMap<u64, u64>, cutoff: HistogramCutoff) -> Self {
+        let steps = match cutoff {
+            HistogramCutoff::Fixed(cutoff) => {
+                return Self { width: 1, count: u64::from(cutoff.max(1)) - 1 };
+            }
+            HistogramCutoff::Auto => {
+                // The smallest number of steps after which 99.9% of the TMs
+                // halted.
+                let mut entries = histogram.iter().map(|(&s, &c)| (s, c)).collect::<Vec<_>>();
+                entries.sort_unstable();
+                let total = entries.iter().map(|(_, c)| c).sum::<u64>();
+                let needed = total - total / 1000;
+                let mut sum = 0;
+                let p999 = entries.iter()
+                    .find(|(_, c)| {
+                        sum += c;
+                        sum >= needed
+                    })
+                    .map_or(0, |(s, _)| *s);
+                p999.max(DEFAULT_HISTOGRAM_CUTOFF - 1)
+            }
+        };
+
+        if steps <= MAX_HISTOGRAM_BARS {
+            return Self { width: 1, count: steps };
+        }
+
+        // Round the width up to 1, 2 or 5 times a power of 10, so that the
+        // bars start at round numbers.
+        let min_width = steps.div_ceil(MAX_HISTOGRAM_BARS);
+        let width = (0..)
+            .map(|e| 10u64.saturating_pow(e))
+            .flat_map(|p| [p, p.saturating_mul(2), p.saturating_mul(5)])
+            .find(|&w| w >= min_width)
+            .expect("no width found");
+        Self { width, count: steps.div_ceil(width) }
+    }
+
+    /// The first number of steps that is not covered by the bars.
+    fn cutoff(&self) -> u64 {
+        1 + self.count * self.width
+    }
+
+    /// The number of TMs per bar and the number of TMs beyond the cutoff.
+    fn counts(&self, histogram: &AHashMap<u64, u64>) -> (Vec<u64>, u64) {
+        let mut counts = vec![0; self.count as usize];
+        let mut beyond = 0;
+        for (&steps, &count) in histogram {
+            match steps {
+                0 => {}
+                _ if steps >= self.cutoff() => beyond += count,
+                _ => counts[((steps - 1) / self.width) as usize] += count,
+            }
+        }
+        (counts, beyond)
+    }
+}
+
+/// How the champion is chosen among all TMs reaching the high score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The TM needing the fewest steps; the smallest ID among those.
+    FewestSteps,
+
+    /// The TM with the smallest ID.
+    SmallestId,
+
+    /// The TM that comes first in the generator's order. This does not depend
+    /// on the order in which threads finish, so it's stable across runs.
+    Earliest,
+}
+
+impl FromStr for TieBreak {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fewest-steps" => Ok(Self::FewestSteps),
+            "smallest-id" => Ok(Self::SmallestId),
+            "earliest" => Ok(Self::Earliest),
+            _ => Err("invalid value for tie break"),
+        }
+    }
+}
+
+impl fmt::Display for TieBreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FewestSteps => f.write_str("fewest-steps"),
+            Self::SmallestId => f.write_str("smallest-id"),
+            Self::Earliest => f.write_str("earliest"),
+        }
+    }
+}
+
+/// A halted TM that reached a high score (either Σ or S).
+#[derive(Debug, Clone, Copy)]
+pub struct Champion {
+    /// The encoded TM (see `Tm::encoded`).
+    pub id: u64,
+    pub steps: u64,
+    pub ones: u32,
+
+    /// The index of the TM in the generator's order.
+    pub index: u64,
+}
+
+impl Champion {
+    /// Champions are ranked by this key (smallest first).
+    fn rank_key(&self, tie_break: TieBreak) -> (u64, u64) {
+        match tie_break {
+            TieBreak::FewestSteps => (self.steps, self.id),
+            TieBreak::SmallestId => (self.id, 0),
+            TieBreak::Earliest => (self.index, 0),
+        }
+    }
+}
+
+/// Which champions a summary keeps: the first `max` ones according to the
+/// tie break, or all of them if `max` is 0.
+#[derive(Debug, Clone, Copy)]
+struct Keep {
+    max: usize,
+    tie_break: TieBreak,
+}
+
+/// Adds `candidate` to `champions` if `score` is at least `best`, replacing
+/// all previous champions if it's larger. If there are `keep.max` champions
+/// already, it replaces the last ranked one if it is ranked before that.
+fn record_champion<T: Ord>(
+    champions: &mut Vec<Champion>,
+    best: &mut T,
+    score: T,
+    candidate: Champion,
+    keep: Keep,
+) {
+    if score > *best {
+        *best = score;
+        champions.clear();
+        champions.push(candidate);
+    } else if score == *best {
+        if keep.max == 0 || champions.len() < keep.max {
+            champions.push(candidate);
+            return;
+        }
+
+        let key = |c: &Champion| c.rank_key(keep.tie_break);
+        let last = champions.iter_mut().max_by_key(|c| key(c)).expect("no champions");
+        if key(&candidate) < key(last) {
+            *last = candidate;
+        }
+    }
+}
+
+/// Merges the champions of two summaries (see `record_champion`).
+fn merge_champions<T: Ord>(
+    champions: &mut Vec<Champion>,
+    best: &mut T,
+    other: Vec<Champion>,
+    other_best: T,
+    keep: Keep,
+) {
+    if other_best > *best {
+        *best = other_best;
+        *champions = other;
+    } else if other_best == *best {
+        champions.extend(other);
+    }
+    if keep.max != 0 && champions.len() > keep.max {
+        champions.sort_by_key(|c| c.rank_key(keep.tie_break));
+        champions.truncate(keep.max);
+    }
+}
+
+#[derive(Clone)]
+pub struct Summary {
+    /// How `champion` is chosen.
+    tie_break: TieBreak,
+
+    /// The max number of Σ and S champions that are kept (each), or 0 to
+    /// keep all. Only the first ones according to `tie_break` are kept.
+    max_champions: usize,
+
+    /// The generator index of the TM that is currently analyzed. Set via
+    /// `set_index`.
+    index: u64,
+
+    /// The most number 1s written.
+    high_score: u32,
+
+    /// The TMs reaching `high_score` (the Σ champions), see
+    /// `max_champions`.
+    winners: Vec<Champion>,
+
+    /// The most number of steps a halting TM ran for (the S score).
+    most_steps: u64,
+
+    /// The halted TMs that ran for `most_steps` steps (the S champions), see
+    /// `max_champions`.
+    step_winners: Vec<Champion>,
+
+    /// The number of TMs that have written `high_score` many 1s.
+    num_winners: u64,
+
+    /// The number of halted TMs that ran for `most_steps` steps.
+    num_step_winners: u64,
+
+    /// The fewest number of steps a winner required to write `high_score` many
+    /// 1s.
+    fewest_winner_steps: u64,
+
+    /// Records how many TMs finished after how many steps.
+    step_histogram: AHashMap<u64, u64>,
+
+    /// Like `step_histogram`, but only for TMs that reached `high_score`.
+    winner_step_histogram: AHashMap<u64, u64>,
+
+    /// Like `step_histogram`, but without immediately halting TMs.
+    simulated_step_histogram: AHashMap<u64, u64>,
+
+    /// `Outcome::Halted`
+    num_halted: u64,
+
+    /// `Outcome::AbortedAfterMaxSteps`
+    num_aborted_after_max_steps: u64,
+
+    /// `Outcome::ImmediateHalt`
+    num_immediate_halt: u64,
+
+    /// `Outcome::SimpleElope`
+    num_simple_elope: u64,
+
+    /// `Outcome::NoHaltState`
+    num_no_halt_transition: u64,
+
+    /// `Outcome::HaltStateNotReachable`
+    num_halt_unreachable: u64,
+
+    /// `Outcome::RunAwayDetected` (dynamic analysis)
+    num_runaway: u64,
+
+    /// `Outcome::CycleDetected` (dynamic analysis)
+    num_cycle: u64,
+
+    /// `Outcome::TranslatedCycler` (dynamic analysis)
+    num_translated_cycler: u64,
+
+    /// `Outcome::BackwardReasoning` (dynamic analysis)
+    num_backward_reasoning: u64,
+
+    /// `Outcome::ClosedTapeLanguage` (dynamic analysis, opt-in)
+    num_ctl: u64,
+
+    /// `Outcome::LinRecurrence` (dynamic analysis, opt-in)
+    num_lin_recurrence: u64,
+
+    /// `Outcome::Pruned` (dynamic analysis)
+    num_pruned: u64,
+
+    /// `Outcome::Known` (not analyzed)
+    num_known: u64,
+
+    /// Number of TMs that halted (immediately or not) without a single 1 on
+    /// the tape.
+    num_halted_zero_ones: u64,
+
+    /// Number of aborted TMs whose head was at the edge of the written part
+    /// of the tape, i.e. the tape was still growing. These hint at a step
+    /// limit that is too low.
+    num_aborted_growing: u64,
+
+    /// All outcomes split by the structure of the start action.
+    first_action: FirstActionStats,
+
+    /// The patterns of the final tapes of all halted TMs.
+    tape_patterns: TapePatterns,
+
+    /// How the tape was used by all runs.
+    #[cfg(feature = "tape-stats")]
+    tape_stats: TapeStats,
+}
+
+impl<const N: usize> OutcomeSink<N> for Summary {
+    fn report_halted(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32, tape: &impl TapeLike) {
+        self.num_halted += 1;
+        self.first_action.record(tm, Category::Halted);
+        self.tape_patterns.record(tape);
+        self.handle_high_score(tm, num_ones, num_steps);
+        self.handle_step_record(tm, num_ones, num_steps);
+        if num_ones == 0 {
+            self.num_halted_zero_ones += 1;
+        }
+
+        *self.step_histogram.entry(num_steps).or_insert(0) += 1;
+        *self.simulated_step_histogram.entry(num_steps).or_insert(0) += 1;
+    }
+
+    fn report_immediate_halt(&mut self, tm: Tm<N>, num_steps: u64, num_ones: u32) {
+        self.num_immediate_halt += 1;
+        self.first_action.record(tm, Category::ImmediateHalt);
+        self.tape_patterns.record_immediate_halt(num_ones);
+        if num_ones > 0 {
+            self.handle_high_score(tm, num_ones, num_steps);
+        } else {
+            self.num_halted_zero_ones += 1;
+        }
+        self.handle_step_record(tm, num_ones, num_steps);
+        *self.step_histogram.entry(num_steps).or_insert(0) += 1;
+    }
+
+    fn report_no_halt_transition(&mut self, tm: Tm<N>) {
+        self.num_no_halt_transition += 1;
+        self.first_action.record(tm, Category::NoHaltTransition);
+    }
+
+    fn report_simple_elope(&mut self, tm: Tm<N>) {
+        self.num_simple_elope += 1;
+        self.first_action.record(tm, Category::SimpleElope);
+    }
+
+    fn report_halt_state_not_reachable(&mut self, tm: Tm<N>) {
+        self.num_halt_unreachable += 1;
+        self.first_action.record(tm, Category::HaltUnreachable);
+    }
+
+    fn report_run_away(&mut self, tm: Tm<N>) {
+        self.num_runaway += 1;
+        self.first_action.record(tm, Category::RunAway);
+    }
+
+    fn report_cycle(&mut self, tm: Tm<N>) {
+        self.num_cycle += 1;
+        self.first_action.record(tm, Category::Cycle);
+    }
+
+    fn report_translated_cycler(&mut self, tm: Tm<N>) {
+        self.num_translated_cycler += 1;
+        self.first_action.record(tm, Category::TranslatedCycler);
+    }
+
+    fn report_backward_reasoning(&mut self, tm: Tm<N>) {
+        self.num_backward_reasoning += 1;
+        self.first_action.record(tm, Category::BackwardReasoning);
+    }
+
+    fn report_ctl(&mut self, tm: Tm<N>) {
+        self.num_ctl += 1;
+        self.first_action.record(tm, Category::Ctl);
+    }
+
+    fn report_lin_recurrence(&mut self, tm: Tm<N>) {
+        self.num_lin_recurrence += 1;
+        self.first_action.record(tm, Category::LinRecurrence);
+    }
+
+    fn report_aborted_after_max_steps(
+        &mut self,
+        tm: Tm<N>,
+        config: Configuration<'_, impl TapeLike>,
+    ) {
+        self.num_aborted_after_max_steps += 1;
+        let written = config.tape.written_range();
+        if config.head.0 <= written.start.0 || config.head.0 >= written.end.0 - 1 {
+            self.num_aborted_growing += 1;
+        }
+        self.first_action.record(tm, Category::Aborted);
+    }
+
+    fn report_pruned(&mut self, tm: Tm<N>, _: u64) {
+        self.num_pruned += 1;
+        self.first_action.record(tm, Category::Pruned);
+    }
+
+    fn report_known(&mut self, tm: Tm<N>) {
+        self.num_known += 1;
+        self.first_action.record(tm, Category::Known);
+    }
+}
+
+impl Summary {
+    /// Creates an empty summary that keeps the first `max_champions` Σ and S
+    /// champions according to `tie_break` (each), or all if that is 0.
+    pub fn new(tie_break: TieBreak, max_champions: usize) -> Self {
+        Self {
+            tie_break,
+            max_champions,
+            index: 0,
+            high_score: 0,
+            winners: Vec::new(),
+            most_steps: 0,
+            step_winners: Vec::new(),
+            num_winners: 0,
+            num_step_winners: 0,
+            fewest_winner_steps: 0,
+            step_histogram: AHashMap::new(),
+            winner_step_histogram: AHashMap::new(),
+            simulated_step_histogram: AHashMap::new(),
+            num_halted: 0,
+            num_aborted_after_max_steps: 0,
+            num_immediate_halt: 0,
+            num_simple_elope: 0,
+            num_no_halt_transition: 0,
+            num_halt_unreachable: 0,
+            num_runaway: 0,
+            num_cycle: 0,
+            num_translated_cycler: 0,
+            num_backward_reasoning: 0,
+            num_ctl: 0,
+            num_lin_recurrence: 0,
+            num_pruned: 0,
+            num_known: 0,
+            num_halted_zero_ones: 0,
+            num_aborted_growing: 0,
+            first_action: FirstActionStats::new(),
+            tape_patterns: TapePatterns::new(),
+            #[cfg(feature = "tape-stats")]
+            tape_stats: TapeStats::default(),
+        }
+    }
+
+    /// Sets the generator index of the TM that is analyzed next. Only used
+    /// for `TieBreak::Earliest`.
+    pub fn set_index(&mut self, index: u64) {
+        self.index = index;
+    }
+
+    /// Removes all TMs that were reported as aborted after the maximum
+    /// number of steps, e.g. because they are analyzed again with a larger
+    /// limit. `tms` have to be exactly those TMs.
+    pub fn forget_aborted<const N: usize>(&mut self, tms: impl IntoIterator<Item = Tm<N>>) {
+        for tm in tms {
+            self.num_aborted_after_max_steps -= 1;
+            self.first_action.forget(tm, Category::Aborted);
+        }
+        self.num_aborted_growing = 0;
+    }
+
+    /// The number of TMs that were aborted after the maximum number of steps.
+    pub fn num_aborted(&self) -> u64 {
+        self.num_aborted_after_max_steps
+    }
+
+    /// The number of aborted TMs whose tape was still growing (see
+    /// `num_aborted`).
+    pub fn num_aborted_growing(&self) -> u64 {
+        self.num_aborted_growing
+    }
+
+    fn handle_high_score<const N: usize>(&mut self, tm: Tm<N>, ones: u32, steps: u64) {
+        if ones > self.high_score {
+            self.num_winners = 1;
+            self.fewest_winner_steps = steps;
+            self.winner_step_histogram.clear();
+            self.winner_step_histogram.insert(steps, 1);
+        } else if ones == self.high_score {
+            self.num_winners += 1;
+            self.fewest_winner_steps = min(self.fewest_winner_steps, steps);
+            *self.winner_step_histogram.entry(steps).or_insert(0) += 1;
+        }
+
+        let candidate = Champion { id: tm.encoded, steps, ones, index: self.index };
+        let keep = self.keep();
+        record_champion(&mut self.winners, &mut self.high_score, ones, candidate, keep);
+    }
+
+    fn handle_step_record<const N: usize>(&mut self, tm: Tm<N>, ones: u32, steps: u64) {
+        if steps > self.most_steps {
+            self.num_step_winners = 1;
+        } else if steps == self.most_steps {
+            self.num_step_winners += 1;
+        }
+
+        let candidate = Champion { id: tm.encoded, steps, ones, index: self.index };
+        let keep = self.keep();
+        record_champion(&mut self.step_winners, &mut self.most_steps, steps, candidate, keep);
+    }
+
+    fn keep(&self) -> Keep {
+        Keep { max: self.max_champions, tie_break: self.tie_break }
+    }
+
+    /// The Σ champion that is ranked first according to the tie break.
+    pub fn champion(&self) -> Option<&Champion> {
+        self.winners.iter().min_by_key(|c| c.rank_key(self.tie_break))
+    }
+
+    /// The S champion that is ranked first according to the tie break.
+    pub fn step_champion(&self) -> Option<&Champion> {
+        self.step_winners.iter().min_by_key(|c| c.rank_key(self.tie_break))
+    }
+
+    /// Returns the champions sorted according to the tie break.
+    fn ranked(&self, champions: &[Champion]) -> Vec<Champion> {
+        let mut v = champions.to_vec();
+        v.sort_by_key(|c| c.rank_key(self.tie_break));
+        v
+    }
+
+    #[cfg(feature = "cli")]
+    /// Prints a table of the Σ champions (most ones) and the S champions (most
+    /// steps), each sorted according to the tie break, and which TMs are
+    /// champions in both.
+    fn print_champions(&self, options: &ReportOptions) {
+        let winners = self.ranked(&self.winners);
+        let step_winners = self.ranked(&self.step_winners);
+
+        let max_listed = match options.listed_champions {
+            0 => usize::MAX,
+            n => n,
+        };
+
+        bunt::println!(
+            "{$blue+bold}▸ Champions{/$} (Σ: most ones, S: most steps, \
+                sorted by tie break '{}'):",
+            self.tie_break,
+        );
+        println!("    {: <8}{: >8}{: >8}   champions", "score", "value", "TMs");
+        let rows = [
+            ("Σ", u64::from(self.high_score), self.num_winners, &winners),
+            ("S", self.most_steps, self.num_step_winners, &step_winners),
+        ];
+        for &(name, value, count, champions) in &rows {
+            let mut lines = champions.iter()
+                .take(max_listed)
+                .map(|c| {
+                    let mut line = format!("TM {} ({} steps, {} ones)", c.id, c.steps, c.ones);
+                    if options.standard_notation {
+                        line += &format!("  {}", tm::standard_notation(c.id, options.n));
+                    }
+                    line
+                })
+                .collect::<Vec<_>>();
+            let listed = min(champions.len(), max_listed) as u64;
+            if count > listed {
+                lines.push(format!(
+                    "... and {} more (see '--list-winners')",
+                    count - listed,
+                ));
+            }
+
+            let first = lines.first().map(|s| &**s).unwrap_or("-");
+            println!("    {: <8}{: >8}{: >8}   {}", name, value, count, first);
+            for line in lines.iter().skip(1) {
+                println!("    {: <27}{}", "", line);
+            }
+        }
+
+        let both = winners.iter()
+            .filter(|w| step_winners.iter().any(|s| s.id == w.id))
+            .map(|c| c.id.to_string())
+            .collect::<Vec<_>>();
+        // Only the recorded champions can be compared.
+        let all_recorded = winners.len() as u64 == self.num_winners
+            && step_winners.len() as u64 == self.num_step_winners;
+        let among = if all_recorded { "" } else { " (among the recorded champions)" };
+        if both.is_empty() {
+            println!("- No TM is champion of both Σ and S{}", among);
+        } else {
+            bunt::println!(
+                "- {[green+bold]} TMs are champions of both Σ and S{}: {}",
+                both.len(),
+                among,
+                both.join(", "),
+            );
+        }
+    }
+
+    /// Adds the results of `other`. If it keeps fewer champions, this one
+    /// does so from now on, too.
+    pub fn add(&mut self, other: Summary) {
+        if other.max_champions != 0
+            && (self.max_champions == 0 || other.max_champions < self.max_champions)
+        {
+            self.max_champions = other.max_champions;
+        }
+        if self.most_steps < other.most_steps {
+            self.num_step_winners = other.num_step_winners;
+        } else if self.most_steps == other.most_steps {
+            self.num_step_winners += other.num_step_winners;
+        }
+        if self.high_score < other.high_score {
+            self.num_winners = other.num_winners;
+            self.fewest_winner_steps = other.fewest_winner_steps;
+            self.winner_step_histogram = other.winner_step_histogram;
+        } else if self.high_score == other.high_score {
+            self.num_winners += other.num_winners;
+            self.fewest_winner_steps = min(self.fewest_winner_steps, other.fewest_winner_steps);
+            add_histogram(&mut self.winner_step_histogram, &other.winner_step_histogram);
+        }
+        let keep = self.keep();
+        merge_champions(
+            &mut self.winners,
+            &mut self.high_score,
+            other.winners,
+            other.high_score,
+            keep,
+        );
+        merge_champions(
+            &mut self.step_winners,
+            &mut self.most_steps,
+            other.step_winners,
+            other.most_steps,
+            keep,
+        );
+
+        self.num_halted += other.num_halted;
+        self.num_aborted_after_max_steps += other.num_aborted_after_max_steps;
+        self.num_immediate_halt += other.num_immediate_halt;
+        self.num_simple_elope += other.num_simple_elope;
+        self.num_no_halt_transition += other.num_no_halt_transition;
+        self.num_halt_unreachable += other.num_halt_unreachable;
+        self.num_runaway += other.num_runaway;
+        self.num_cycle += other.num_cycle;
+        self.num_translated_cycler += other.num_translated_cycler;
+        self.num_backward_reasoning += other.num_backward_reasoning;
+        self.num_ctl += other.num_ctl;
+        self.num_lin_recurrence += other.num_lin_recurrence;
+        self.num_pruned += other.num_pruned;
+        self.num_known += other.num_known;
+        self.num_halted_zero_ones += other.num_halted_zero_ones;
+        self.num_aborted_growing += other.num_aborted_growing;
+        self.first_action.add(&other.first_action);
+        self.tape_patterns.add(&other.tape_patterns);
+        #[cfg(feature = "tape-stats")]
+        self.tape_stats.add(&other.tape_stats);
+
+        add_histogram(&mut self.step_histogram, &other.step_histogram);
+        add_histogram(&mut self.simulated_step_histogram, &other.simulated_step_histogram);
+    }
+
+    #[cfg(feature = "tape-stats")]
+    pub fn add_tape_stats(&mut self, stats: &TapeStats) {
+        self.tape_stats.add(stats);
+    }
+
+    /// The most number of 1s written by a halting TM.
+    pub fn high_score(&self) -> u32 {
+        self.high_score
+    }
+
+    /// The most number of steps of a halting TM.
+    pub fn most_steps(&self) -> u64 {
+        self.most_steps
+    }
+
+    /// The number of TMs that halted (immediately or not).
+    pub fn num_halted(&self) -> u64 {
+        self.num_halted + self.num_immediate_halt
+    }
+
+    /// The number of TMs that were analyzed.
+    pub fn num_total_tms(&self) -> u64 {
+        self.num_halted
+            + self.num_aborted_after_max_steps
+            + self.num_immediate_halt
+            + self.num_simple_elope
+            + self.num_no_halt_transition
+            + self.num_halt_unreachable
+            + self.num_runaway
+            + self.num_cycle
+            + self.num_translated_cycler
+            + self.num_backward_reasoning
+            + self.num_ctl
+            + self.num_lin_recurrence
+            + self.num_pruned
+            + self.num_known
+    }
+
+    /// The number of TMs that were actually run, i.e. not decided by the
+    /// static checks (or skipped as known).
+    pub fn num_simulated(&self) -> u64 {
+        self.num_halted
+            + self.num_runaway
+            + self.num_cycle
+            + self.num_translated_cycler
+            + self.num_backward_reasoning
+            + self.num_ctl
+            + self.num_lin_recurrence
+            + self.num_aborted_after_max_steps
+            + self.num_pruned
+    }
+
+    /// The number of TMs that were stopped without a decision (aborted or
+    /// pruned), so they might still halt. All others are proven, see
+    /// `Confidence`.
+    pub fn num_undecided(&self) -> u64 {
+        self.num_aborted_after_max_steps + self.num_pruned
+    }
+
+    #[cfg(feature = "cli")]
+    fn percent(&self, v: u64) -> String {
+        let percent = 100.0 * (v as f64) / (self.num_total_tms() as f64);
+        format!("{:.2}%", percent)
+    }
+
+    #[cfg(feature = "cli")]
+    /// Warns if the step limit `max_steps` was clearly too low: if more than
+    /// `ABORTED_WARNING_FRACTION` of the simulated TMs were aborted or if any
+    /// aborted TM's tape was still growing. Returns whether it warned, so
+    /// that the caller can suggest a larger limit.
+    pub fn warn_max_steps(&self, max_steps: u64) -> bool {
+        let (aborted, growing) = (self.num_aborted_after_max_steps, self.num_aborted_growing);
+        let fraction = aborted as f64 / self.num_simulated().max(1) as f64;
+        if fraction <= ABORTED_WARNING_FRACTION && growing == 0 {
+            return false;
+        }
+
+        bunt::println!(
+            "{$yellow+bold}Warning:{/$} {} TMs ({:.2}% of the simulated ones) were aborted after \
+                the maximum number of steps ({}), {} of them while their tape was still growing. \
+                Their outcome is unknown, so the statistics above might be distorted.",
+            aborted,
+            100.0 * fraction,
+            max_steps,
+            growing,
+        );
+        true
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn print_report(&self, options: &ReportOptions) {
+        let halted_non_high_score = (self.num_halted + self.num_immediate_halt) - self.num_winners;
+        let num_never_halt = self.num_simple_elope
+            + self.num_no_halt_transition
+            + self.num_halt_unreachable
+            + self.num_runaway
+            + self.num_cycle
+            + self.num_translated_cycler
+            + self.num_backward_reasoning
+            + self.num_ctl
+            + self.num_lin_recurrence;
+
+        bunt::println!("{$blue+bold}▸ Results:{/$}");
+
+        // ----- High scores
+        bunt::println!(
+            "- The high score (Σ, number of 1s after halting) is: {[green+bold]}",
+            self.high_score,
+        );
+        bunt::println!("  - {[green+bold]} TMs reached that high score", self.num_winners);
+        bunt::println!(
+            "  - The quickest of which reached the high score in {[bold+green]} steps",
+            self.fewest_winner_steps,
+        );
+        if let Some(champion) = self.champion() {
+            bunt::println!(
+                "  - The champion (by tie break '{}') is TM {[green+bold]} ({} steps)",
+                self.tie_break,
+                champion.id,
+                champion.steps,
+            );
+        }
+        bunt::println!(
+            "- The most steps of a halting TM (S) are: {[green+bold]}",
+            self.most_steps,
+        );
+        bunt::println!("  - {[green+bold]} TMs ran for that many steps", self.num_step_winners);
+        if let Some(champion) = self.step_champion() {
+            bunt::println!(
+                "  - The champion (by tie break '{}') is TM {[green+bold]} ({} ones)",
+                self.tie_break,
+                champion.id,
+                champion.ones,
+            );
+        }
+
+        // ----- Other halted TMs
+        bunt::println!(
+            "- {[yellow+bold]} ({[yellow+bold]}) TMs halted but did not get a high score",
+            halted_non_high_score,
+            self.percent(halted_non_high_score),
+        );
+        bunt::println!(
+            "  - {[yellow+bold]} ({[yellow+bold]}) TMs halted after 1 step \
+                (their first transition was to the halt state)",
+            self.num_immediate_halt,
+            self.percent(self.num_immediate_halt),
+        );
+        bunt::println!(
+            "- {[yellow+bold]} ({[yellow+bold]}) TMs halted without writing a single 1",
+            self.num_halted_zero_ones,
+            self.percent(self.num_halted_zero_ones),
+        );
+
+        // ----- Proven to never halt
+        bunt::println!(
+            "- {[magenta+bold]} ({[magenta+bold]}) provably never halt:",
+            num_never_halt,
+            self.percent(num_never_halt),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) immediately ran away in one direction \
+                and remained in the start state",
+            self.num_simple_elope,
+            self.percent(self.num_simple_elope),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) did not contain a transition \
+                to the halt state",
+            self.num_no_halt_transition,
+            self.percent(self.num_no_halt_transition),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) statically could not reach the halt state",
+            self.num_halt_unreachable,
+            self.percent(self.num_halt_unreachable),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) were caught in a run-away loop",
+            self.num_runaway,
+            self.percent(self.num_runaway),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) repeated a previous configuration",
+            self.num_cycle,
+            self.percent(self.num_cycle),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) repeated their behavior shifted along \
+                the tape",
+            self.num_translated_cycler,
+            self.percent(self.num_translated_cycler),
+        );
+        bunt::println!(
+            "  - {[magenta+bold]} ({[magenta+bold]}) can't reach a halting transition \
+                (backward reasoning)",
+            self.num_backward_reasoning,
+            self.percent(self.num_backward_reasoning),
+        );
+        if self.num_ctl > 0 {
+            bunt::println!(
+                "  - {[magenta+bold]} ({[magenta+bold]}) have a closed tape language without \
+                    halting (CTL)",
+                self.num_ctl,
+                self.percent(self.num_ctl),
+            );
+        }
+        if self.num_lin_recurrence > 0 {
+            bunt::println!(
+                "  - {[magenta+bold]} ({[magenta+bold]}) repeated their behavior after the step \
+                    limit (Lin recurrence)",
+                self.num_lin_recurrence,
+                self.percent(self.num_lin_recurrence),
+            );
+        }
+
+        // ----- Undecided
+        bunt::println!(
+            "- {[red+bold]} ({[red+bold]}) are undecided (they might still halt):",
+            self.num_undecided(),
+            self.percent(self.num_undecided()),
+        );
+        bunt::println!(
+            "  - {[red+bold]} ({[red+bold]}) were aborted after the maximum number of steps ({})",
+            self.num_aborted_after_max_steps,
+            self.percent(self.num_aborted_after_max_steps),
+            options.max_steps,
+        );
+        if let Some(budget) = options.run_budget {
+            println!("    (or after exceeding the run budget of {})", budget);
+        }
+        if self.num_pruned > 0 {
+            bunt::println!(
+                "  - {[cyan+bold]} ({[cyan+bold]}) were pruned as they could not reach \
+                    the high score anymore",
+                self.num_pruned,
+                self.percent(self.num_pruned),
+            );
+        }
+
+        // ----- Skipped
+        if self.num_known > 0 {
+            bunt::println!(
+                "- {[blue+bold]} ({[blue+bold]}) were skipped as they were already decided \
+                    (see '--known')",
+                self.num_known,
+                self.percent(self.num_known),
+            );
+        }
+
+        println!();
+        let gcd = gcd(&[
+            self.num_winners,
+            halted_non_high_score,
+            self.num_immediate_halt,
+            self.num_simple_elope,
+            self.num_no_halt_transition,
+            self.num_halt_unreachable,
+            self.num_runaway,
+            self.num_cycle,
+            self.num_translated_cycler,
+            self.num_backward_reasoning,
+            self.num_ctl,
+            self.num_lin_recurrence,
+            self.num_aborted_after_max_steps,
+            self.num_pruned,
+            self.num_known,
+        ]);
+        println!("Hint: the greatest common denominator of all these numbers is {}.", gcd);
+        if gcd == 1 {
+            println!("This means the chosen generator does not generate duplicate/equivalent \
+                TMs. That's good!");
+        } else {
+            println!("This means the chosen generator generates {}-tuples of TMs that are \
+                equivalent to one another.", gcd);
+            println!("A better generator could speed this up by eleminating duplicate TMs.");
+        };
+        println!();
+
+        println!();
+        self.print_champions(options);
+        println!();
+
+        if options.simulated_only {
+            println!();
+            self.print_simulated_only();
+            println!();
+        }
+
+        if options.first_action_stats {
+            println!();
+            self.first_action.print();
+            println!();
+        }
+
+        if options.tape_patterns {
+            println!();
+            self.tape_patterns.print();
+            println!();
+        }
+
+        if options.stage_stats || options.stage_chart {
+            println!();
+            self.print_stages(options.stage_chart);
+            println!();
+        }
+
+        #[cfg(feature = "tape-stats")]
+        {
+            println!();
+            self.print_tape_stats();
+            println!();
+        }
+
+        if !options.hide_histogram {
+            println!();
+            println!();
+            self.print_histogram(options);
+        }
+
+        println!();
+    }
+
+    #[cfg(feature = "cli")]
+    /// Prints the outcomes of all TMs that were actually run, with
+    /// percentages relative to the number of those. Most TMs are decided by
+    /// the static checks, which otherwise dominate all percentages.
+    fn print_simulated_only(&self) {
+        let num_simulated = self.num_simulated();
+        let percent = |v: u64| format!("{:.2}%", 100.0 * v as f64 / num_simulated as f64);
+
+        bunt::println!(
+            "{$blue+bold}▸ Results of simulated TMs only{/$} \
+                ({[intense]} TMs, {:.2}% of all, the rest was decided statically):",
+            num_simulated,
+            100.0 * num_simulated as f64 / self.num_total_tms() as f64,
+        );
+        bunt::println!(
+            "- {[yellow+bold]} ({[yellow+bold]}) halted",
+            self.num_halted,
+            percent(self.num_halted),
+        );
+        bunt::println!(
+            "- {[magenta+bold]} ({[magenta+bold]}) were caught in a run-away loop",
+            self.num_runaway,
+            percent(self.num_runaway),
+        );
+        bunt::println!(
+            "- {[magenta+bold]} ({[magenta+bold]}) repeated a previous configuration",
+            self.num_cycle,
+            percent(self.num_cycle),
+        );
+        bunt::println!(
+            "- {[magenta+bold]} ({[magenta+bold]}) repeated their behavior shifted along \
+                the tape",
+            self.num_translated_cycler,
+            percent(self.num_translated_cycler),
+        );
+        bunt::println!(
+            "- {[magenta+bold]} ({[magenta+bold]}) can't reach a halting transition \
+                (backward reasoning)",
+            self.num_backward_reasoning,
+            percent(self.num_backward_reasoning),
+        );
+        if self.num_ctl > 0 {
+            bunt::println!(
+                "- {[magenta+bold]} ({[magenta+bold]}) have a closed tape language without \
+                    halting (CTL)",
+                self.num_ctl,
+                percent(self.num_ctl),
+            );
+        }
+        if self.num_lin_recurrence > 0 {
+            bunt::println!(
+                "- {[magenta+bold]} ({[magenta+bold]}) repeated their behavior after the step \
+                    limit (Lin recurrence)",
+                self.num_lin_recurrence,
+                percent(self.num_lin_recurrence),
+            );
+        }
+        bunt::println!(
+            "- {[red+bold]} ({[red+bold]}) were aborted",
+            self.num_aborted_after_max_steps,
+            percent(self.num_aborted_after_max_steps),
+        );
+        if self.num_pruned > 0 {
+            bunt::println!(
+                "- {[cyan+bold]} ({[cyan+bold]}) were pruned",
+                self.num_pruned,
+                percent(self.num_pruned),
+            );
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    fn print_stages(&self, chart: bool) {
+        // This has to match the order of checks in `Analyzer::analyze`. Known
+        // TMs are skipped before that.
+        let stages = [
+            Stage { name: "skipped: known", decided: self.num_known },
+            Stage { name: "static: immediate halt", decided: self.num_immediate_halt },
+            Stage { name: "static: simple elope", decided: self.num_simple_elope },
+            Stage { name: "static: no halt transition", decided: self.num_no_halt_transition },
+            Stage { name: "static: halt unreachable", decided: self.num_halt_unreachable },
+            Stage { name: "run: halted", decided: self.num_halted },
+            Stage { name: "run: run-away", decided: self.num_runaway },
+            Stage { name: "run: cycle", decided: self.num_cycle },
+            Stage { name: "run: translated cycler", decided: self.num_translated_cycler },
+            Stage { name: "run: backward reasoning", decided: self.num_backward_reasoning },
+            Stage { name: "run: ctl", decided: self.num_ctl },
+            Stage { name: "run: lin recurrence", decided: self.num_lin_recurrence },
+            Stage { name: "run: pruned", decided: self.num_pruned },
+        ];
+
+        stages::print(&stages, self.num_aborted_after_max_steps, chart);
+    }
+
+    #[cfg(all(feature = "cli", feature = "tape-stats"))]
+    fn print_tape_stats(&self) {
+        let stats = &self.tape_stats;
+        let per_run = |v: u64| v as f64 / stats.runs.max(1) as f64;
+
+        bunt::println!("{$blue+bold}▸ Tape stats:{/$}");
+        bunt::println!("- {[intense]} runs", stats.runs);
+        bunt::println!(
+            "- {[intense]} grow events ({:.4} per run)",
+            stats.grows,
+            per_run(stats.grows),
+        );
+        bunt::println!(
+            "- {[intense]} bytes copied while growing ({:.2} per run)",
+            stats.bytes_copied,
+            per_run(stats.bytes_copied),
+        );
+        bunt::println!(
+            "- {[intense]} bucket accesses ({:.2} per run)",
+            stats.bucket_accesses,
+            per_run(stats.bucket_accesses),
+        );
+    }
+
+    #[cfg(feature = "cli")]
+    fn print_histogram(&self, options: &ReportOptions) {
+        let histogram_height = options.histogram_height as usize;
+        let (histogram, title) = match options.histogram_filter {
+            HistogramFilter::Halted => (&self.step_histogram, "TMs halted"),
+            HistogramFilter::HighScore => {
+                (&self.winner_step_histogram, "TMs reaching the high score halted")
+            }
+            HistogramFilter::Simulated => {
+                (&self.simulated_step_histogram, "simulated TMs halted")
+            }
+        };
+
+        if histogram.is_empty() {
+            println!("   (histogram not shown as TMs ran for at most 1 step)");
+            return;
+        } else {
+            bunt::println!("{$blue+bold}▸ Histogram (how many {} after x steps):{/$}", title);
+            println!("note: the y-axis is logarithmic");
+            println!();
+        }
+
+        let bars = HistogramBars::new(histogram, options.histogram_cutoff);
+        let (mut counts, beyond) = bars.counts(histogram);
+        if beyond > 0 {
+            counts.push(beyond);
+        }
+
+        let max = counts.iter().max().copied().unwrap_or(0);
+        let max_log = (max as f64).log10();
+
+        let mut lines = vec![String::new(); histogram_height];
+        for (row, line) in lines.iter_mut().enumerate() {
+            let inv_row = histogram_height - row - 1;
+            if inv_row == 0 {
+                line.push_str("        0 ▕");
+            } else if inv_row == histogram_height - 1 {
+                line.push_str(&format!("{: >9} ▕", max));
+            } else if inv_row % 2 == 0 && inv_row < histogram_height - 2 {
+                let ratio = inv_row as f64 / histogram_height as f64;
+                let v = 10.0f64.powf(max_log * ratio).round() as u32;
+                line.push_str(&format!("{: >9} ▕", v));
+            } else {
+                line.push_str("          ▕");
+            }
+        }
+
+        for &count in &counts {
+            lines[..histogram_height - 1].iter_mut().for_each(|l| l.push(' '));
+            lines[histogram_height - 1].push('▁');
+
+            let count_log = if count == 0 {
+                0.0
+            } else {
+                (count as f64).log10()
+            };
+
+            // This is the height of the bar in eigths steps. We have to offset
+            // it all by 1 since we need the lowest eigth for the bottom line.
+            // To make logic inside the loop easier this height we are
+            // calculating is at least 1. In other words: now the bottom line
+            // is part of the bar.
+            let bar_height_eights = (
+                (8.0 * (histogram_height as f64) - 1.0) * (count_log / max_log)
+            ).round() as usize + 1;
+
+            for row in 0..histogram_height {
+                let inv_row = histogram_height - row - 1;
+                let eights_in_this_line = std::cmp::min(
+                    bar_height_eights.saturating_sub(inv_row * 8),
+                    8,
+                );
+                let symbols = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+                let symbol = symbols[eights_in_this_line];
+                lines[row].push(symbol);
+                lines[row].push(symbol);
+            }
+        }
+
+        lines.iter().for_each(|l| println!("{}", l));
+
+        // Each bar is three characters wide. If the labels don't fit, only
+        // every fifth bar is labeled (with the first number of steps it
+        // covers).
+        let mut labels = String::new();
+        for i in 0..bars.count {
+            let steps = 1 + i * bars.width;
+            if bars.cutoff() <= 1000 {
+                labels.push_str(&format!("{: >3}", steps));
+            } else if i % 5 == 0 {
+                labels = format!("{: <w$}  {}", labels, steps, w = 3 * i as usize);
+            }
+        }
+        if beyond > 0 {
+            labels = format!("{: <w$}  +", labels, w = 3 * bars.count as usize);
+        }
+        println!("    steps: {}", labels);
+
+        print!("    count: ");
+        for &count in &counts {
+            if count < 100 {
+                print!(" {: >2}", count);
+            } else {
+                print!("   ");
+            }
+        }
+        println!();
+
+        if bars.width > 1 || beyond > 0 {
+            println!();
+        }
+        if bars.width > 1 {
+            println!("note: each bar covers {} steps", bars.width);
+        }
+        if beyond > 0 {
+            println!(
+                "note: the bar '+' shows the {} TMs that halted after {} or more steps",
+                beyond,
+                bars.cutoff(),
+            );
+        }
+    }
+}
+
+fn add_histogram(dst: &mut AHashMap<u64, u64>, src: &AHashMap<u64, u64>) {
+    for (steps, count) in src {
+        *dst.entry(*steps).or_insert(0) += count;
+    }
+}
+
+/// Returns the greatest common denominator of all given numbers.
+#[cfg(feature = "cli")]
+fn gcd(nums: &[u64]) -> u64 {
+    let mut gdc = nums[0];
+    for mut n in nums[1..].iter().copied() {
+        while n > 0 {
+            let tmp = n;
+            n = gdc % n;
+            gdc = tmp;
+        }
+    }
+
+    gdc
+}
+
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use ahash::AHashMap;
+    use crate::{
+        analyze::Analyzer,
+        gen::{All, TmGenerator},
+    };
+    use super::{HistogramBars, HistogramCutoff, Summary, TieBreak};
+
+    #[test]
+    fn histogram_bars() {
+        let histogram = |entries: &[(u64, u64)]| {
+            entries.iter().copied().collect::<AHashMap<_, _>>()
+        };
+        let bars = |h: &AHashMap<u64, u64>, cutoff| {
+            let bars = HistogramBars::new(h, cutoff);
+            (bars.width, bars.count)
+        };
+
+        let small = histogram(&[(1, 10), (3, 5), (6, 1)]);
+        assert_eq!(bars(&small, HistogramCutoff::Fixed(30)), (1, 29));
+        assert_eq!(bars(&small, HistogramCutoff::Fixed(4)), (1, 3));
+        assert_eq!(bars(&small, HistogramCutoff::Auto), (1, 29));
+        assert_eq!(HistogramBars { width: 1, count: 3 }.counts(&small), (vec![10, 0, 5], 1));
+
+        // 99.9% halt after at most 35 steps, the rest is beyond the cutoff.
+        let medium = histogram(&[(1, 998), (35, 1), (1000, 1)]);
+        assert_eq!(bars(&medium, HistogramCutoff::Auto), (1, 35));
+
+        // 99.9% halt after at most 1000 steps: 20 bars of 50 steps.
+        let large = histogram(&[(2, 500), (1000, 499), (10_000, 1)]);
+        let auto = HistogramBars::new(&large, HistogramCutoff::Auto);
+        assert_eq!((auto.width, auto.count), (50, 20));
+        assert_eq!(auto.cutoff(), 1001);
+        let (counts, beyond) = auto.counts(&large);
+        assert_eq!((counts[0], counts[19], beyond), (500, 499, 1));
+    }
+
+    #[test]
+    fn max_champions() {
+        // With a limit of 3 steps, there are lots of champions.
+        let tms = {
+            let mut tms = Vec::new();
+            <All<2>>::default().for_all(|tm| tms.push(tm));
+            tms
+        };
+        let mut analyzer = <Analyzer<2>>::builder().max_steps(3).build();
+        let mut summarize = |summary: &mut Summary, tms: &[_]| {
+            tms.iter().for_each(|&tm| analyzer.analyze(tm, summary));
+        };
+
+        let mut all = Summary::new(TieBreak::FewestSteps, 0);
+        summarize(&mut all, &tms);
+        let mut capped = Summary::new(TieBreak::FewestSteps, 3);
+        summarize(&mut capped, &tms);
+        assert!(all.step_winners.len() > 3);
+
+        // The same when merging parts, each with its own first 3 champions.
+        let mut merged = Summary::new(TieBreak::FewestSteps, 3);
+        for part in tms.chunks(1000) {
+            let mut summary = Summary::new(TieBreak::FewestSteps, 3);
+            summarize(&mut summary, part);
+            merged.add(summary);
+        }
+
+        for summary in [&capped, &merged] {
+            assert_eq!(summary.num_winners, all.num_winners);
+            assert_eq!(summary.num_step_winners, all.step_winners.len() as u64);
+            let ids = |champions: &[_]| {
+                summary.ranked(champions).iter().map(|c| c.id).collect::<Vec<_>>()
+            };
+            assert_eq!(ids(&summary.winners), ids(&all.winners)[..3]);
+            assert_eq!(ids(&summary.step_winners), ids(&all.step_winners)[..3]);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Comparison of the results with the published values of Σ(n) and S(n) and
+//! with verified counts of this program's generators (see `full
+//! --check-reference`).
+//!
+//! Published counts of TMs per category (e.g. the holdouts of the
+//! bbchallenge) are based on other enumerations and other definitions of the
+//! categories, so they cannot be compared to the counts of any generator of
+//! this program. Instead, the counts of halted TMs are embedded per
+//! generator, as found by full sweeps.
+
+use crate::{analyze::HaltConvention, gen::Generator};
+use super::Summary;
+
+
+/// Σ(n) and S(n) for n = 1 to 5, counting the halting transition (i.e. with
+/// `HaltConvention::Include`). S(5) was proven by the bbchallenge in 2024.
+const PUBLISHED: [(u32, u64); 5] = [
+    (1, 1),
+    (4, 6),
+    (6, 21),
+    (13, 107),
+    (4098, 47_176_870),
+];
+
+/// The number of TMs that halted after being simulated (`halted`) and that
+/// halted immediately (`immediate-halt`) for n = 1 to 4, per generator in the
+/// order `all`, `no-symmetries`, `optimized`. These were found by full sweeps
+/// with a step limit above S(n); for n ≤ 2, the tests also check them with a
+/// plain simulation of all TMs.
+const VERIFIED_COUNTS: [[(u64, u64); 3]; 4] = [
+    [(0, 32), (0, 6), (0, 2)],
+    [(2_872, 6_912), (522, 1_000), (203, 364)],
+    [(3_377_536, 4_194_304), (535_768, 537_824), (204_278, 185_646)],
+    [
+        (6_020_566_368, 5_120_000_000),
+        (880_472_208, 612_220_032),
+        (326_902_284, 205_169_336),
+    ],
+];
+
+/// The number of TMs that halt immediately for n = 5, in the same order as
+/// `VERIFIED_COUNTS`. Unlike the number of halted TMs, these follow from the
+/// enumeration (the first transition is one of the halting ones) and are
+/// checked against the counts for smaller n by the tests.
+const IMMEDIATE_HALTS_5: [u64; 3] = [10_567_230_160_896, 1_207_269_217_792, 397_140_023_290];
+
+/// How a result compares to the published value, see
+/// `Summary::check_reference`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Match,
+
+    /// The result is larger than the published value, which is always a bug.
+    Larger,
+
+    /// The result is smaller, although all TMs were run long enough.
+    Missed,
+
+    /// The result is smaller, which is expected as not all TMs were run for
+    /// S(n) steps.
+    Incomplete,
+}
+
+/// The comparison of one value with the published or verified one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceCheck {
+    /// "Σ", "S" or the name of a count ("halted" or "immediate-halt").
+    pub name: &'static str,
+    pub published: u64,
+    pub found: u64,
+    pub verdict: Verdict,
+}
+
+impl ReferenceCheck {
+    /// E.g. "S(4) = 107" or "halted = 203".
+    fn label(&self, n: u8) -> String {
+        match self.name {
+            "Σ" | "S" => format!("{}({}) = {}", self.name, n, self.published),
+            name => format!("{} = {}", name, self.published),
+        }
+    }
+}
+
+impl Summary {
+    /// Compares the high score and the most steps with the published values
+    /// for `n` states, and the number of halted and immediately halting TMs
+    /// with the verified counts of `generator`, as far as they are known. A
+    /// result above the reference value is always a bug. A result below is
+    /// only a bug if all TMs of the generator were analyzed, which callers
+    /// indicate with `complete` (no range, fixed transitions or early stop),
+    /// none was pruned or known, and (except for the immediate halts) all
+    /// were run with a step limit of at least S(n). Returns `None` if no
+    /// values are known for `n` or if the halting transition is not counted,
+    /// unlike in the reference values.
+    pub fn check_reference(
+        &self,
+        n: u8,
+        generator: Generator,
+        max_steps: u64,
+        halt_convention: HaltConvention,
+        complete: bool,
+    ) -> Option<Vec<ReferenceCheck>> {
+        let &(sigma, s) = PUBLISHED.get(usize::from(n).checked_sub(1)?)?;
+        if halt_convention != HaltConvention::Include {
+            return None;
+        }
+
+        let generator = match generator {
+            Generator::All => 0,
+            Generator::NoSymmetries => 1,
+            Generator::Optimized => 2,
+        };
+        let all_analyzed = complete && self.num_pruned == 0 && self.num_known == 0;
+        let run_long_enough = all_analyzed && max_steps >= s;
+        let check = |name, published, found, complete| {
+            let verdict = if found == published {
+                Verdict::Match
+            } else if found > published {
+                Verdict::Larger
+            } else if complete {
+                Verdict::Missed
+            } else {
+                Verdict::Incomplete
+            };
+            ReferenceCheck { name, published, found, verdict }
+        };
+
+        let mut checks = vec![
+            check("Σ", u64::from(sigma), u64::from(self.high_score), run_long_enough),
+            check("S", s, self.most_steps, run_long_enough),
+        ];
+        match VERIFIED_COUNTS.get(usize::from(n) - 1) {
+            Some(counts) => {
+                let (halted, immediate_halts) = counts[generator];
+                checks.push(check("halted", halted, self.num_halted, run_long_enough));
+                checks.push(check(
+                    "immediate-halt",
+                    immediate_halts,
+                    self.num_immediate_halt,
+                    all_analyzed,
+                ));
+            }
+            None => checks.push(check(
+                "immediate-halt",
+                IMMEDIATE_HALTS_5[generator],
+                self.num_immediate_halt,
+                all_analyzed,
+            )),
+        }
+
+        Some(checks)
+    }
+
+    /// Prints the result of `check_reference` and a warning if the results
+    /// contradict the published values.
+    #[cfg(feature = "cli")]
+    pub fn print_reference_comparison(
+        &self,
+        n: u8,
+        generator: Generator,
+        max_steps: u64,
+        halt_convention: HaltConvention,
+        complete: bool,
+    ) {
+        bunt::println!("{$blue+bold}▸ Comparison with reference values:{/$}");
+        if PUBLISHED.get(usize::from(n) - 1).is_none() {
+            println!("    No values are known for n = {}.", n);
+            return;
+        }
+        let checks = self.check_reference(n, generator, max_steps, halt_convention, complete);
+        let checks = match checks {
+            Some(checks) => checks,
+            None => {
+                println!(
+                    "    The reference values count the halting transition, so they can \
+                        only be compared with '--halt-convention include'.",
+                );
+                return;
+            }
+        };
+
+        let s = checks[1].published;
+        for check in &checks {
+            let label = check.label(n);
+            match check.verdict {
+                Verdict::Match => {
+                    bunt::println!("    {: <32}found {[green+bold]}", label, check.found);
+                }
+                Verdict::Larger => bunt::println!(
+                    "    {: <32}found {[red+bold]}  {$red}(larger than possible!){/$}",
+                    label,
+                    check.found,
+                ),
+                Verdict::Missed => bunt::println!(
+                    "    {: <32}found {[red+bold]}  {$red}(missed, although all TMs were \
+                        run long enough){/$}",
+                    label,
+                    check.found,
+                ),
+                Verdict::Incomplete if max_steps < s && check.name != "immediate-halt" => {
+                    bunt::println!(
+                        "    {: <32}found {[yellow+bold]}  (expected, as not all TMs were \
+                            run for {} steps)",
+                        label,
+                        check.found,
+                        s,
+                    );
+                }
+                Verdict::Incomplete => bunt::println!(
+                    "    {: <32}found {[yellow+bold]}  (expected, as not all TMs were \
+                        analyzed)",
+                    label,
+                    check.found,
+                ),
+            }
+        }
+
+        let contradicts = checks.iter()
+            .any(|c| matches!(c.verdict, Verdict::Larger | Verdict::Missed));
+        if contradicts {
+            println!();
+            bunt::println!(
+                "{$red+bold}Warning:{/$} the results contradict the reference values. This \
+                    indicates a bug in the generator or in one of the deciders!",
+            );
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analyze::HaltConvention,
+        gen::{All, Generator, NoSymmetries, Optimized, TmGenerator},
+        machine::Machine,
+        summary::{Summary, TieBreak},
+        sweep::SweepBuilder,
+    };
+    use super::{IMMEDIATE_HALTS_5, PUBLISHED, VERIFIED_COUNTS, Verdict};
+
+    fn verdicts(summary: &Summary, max_steps: u64, complete: bool) -> Vec<Verdict> {
+        let checks = summary.check_reference(
+            3,
+            Generator::Optimized,
+            max_steps,
+            HaltConvention::Include,
+            complete,
+        );
+        checks.unwrap().iter().map(|c| c.verdict).collect()
+    }
+
+    #[test]
+    fn check_reference() {
+        use Verdict::*;
+
+        let summary = SweepBuilder::new(3)
+            .generator(Generator::Optimized)
+            .max_steps(100)
+            .run()
+            .unwrap();
+        assert_eq!(verdicts(&summary, 100, true), [Match; 4]);
+        let check = |n, halt_convention| {
+            summary.check_reference(n, Generator::Optimized, 100, halt_convention, true)
+        };
+        assert!(check(3, HaltConvention::Exclude).is_none());
+        assert!(check(6, HaltConvention::Include).is_none());
+
+        // The counts of other generators differ.
+        let checks = summary
+            .check_reference(3, Generator::All, 100, HaltConvention::Include, true)
+            .unwrap();
+        assert_eq!(checks.iter().map(|c| c.verdict).collect::<Vec<_>>(), [
+            Match,
+            Match,
+            Missed,
+            Missed,
+        ]);
+
+        // For n = 5, only the immediate halts are compared besides Σ and S.
+        let checks = check(5, HaltConvention::Include).unwrap();
+        assert_eq!(checks.iter().map(|c| c.name).collect::<Vec<_>>(), [
+            "Σ",
+            "S",
+            "immediate-halt",
+        ]);
+
+        let mut summary = Summary::new(TieBreak::FewestSteps, 0);
+        summary.high_score = 7;
+        summary.most_steps = 20;
+        summary.num_halted = 300_000;
+        summary.num_immediate_halt = 185_645;
+        assert_eq!(verdicts(&summary, 100, true), [Larger, Missed, Larger, Missed]);
+        assert_eq!(verdicts(&summary, 100, false), [Larger, Incomplete, Larger, Incomplete]);
+        assert_eq!(verdicts(&summary, 20, true), [Larger, Incomplete, Larger, Missed]);
+
+        // Pruned TMs might have halted.
+        summary.num_pruned = 1;
+        assert_eq!(verdicts(&summary, 100, true), [Larger, Incomplete, Larger, Incomplete]);
+    }
+
+    /// Counts the TMs that halt within S(n) steps with a plain simulation, as
+    /// `(halted, immediate-halt)`.
+    fn simulate_all<G: TmGenerator<N>, const N: usize>(generator: G) -> (u64, u64) {
+        let s = PUBLISHED[N - 1].1;
+        let (mut halted, mut immediate_halts) = (0, 0);
+        generator.for_all(|tm| {
+            let mut machine = Machine::new(tm);
+            while machine.steps() < s && machine.step().is_some() {}
+            if machine.state().is_none() {
+                match machine.steps() {
+                    1 => immediate_halts += 1,
+                    _ => halted += 1,
+                }
+            }
+        });
+        (halted, immediate_halts)
+    }
+
+    #[test]
+    fn verified_counts() {
+        assert_eq!(VERIFIED_COUNTS[0], [
+            simulate_all(<All<1>>::default()),
+            simulate_all(<NoSymmetries<1>>::default()),
+            simulate_all(<Optimized<1>>::default()),
+        ]);
+        assert_eq!(VERIFIED_COUNTS[1], [
+            simulate_all(<All<2>>::default()),
+            simulate_all(<NoSymmetries<2>>::default()),
+            simulate_all(<Optimized<2>>::default()),
+        ]);
+    }
+
+    #[test]
+    fn immediate_halts() {
+        // Of the actions of each transition, 4, 2 or 1 go to the halt state
+        // (see `Opt`), and all but `all` skip half of the TMs as symmetric.
+        let count = |n: u32, generator| {
+            let (skipped_actions, halt_actions, halves) = match generator {
+                0 => (0, 4, 1),
+                1 => (2, 2, 2),
+                _ => (3, 1, 2),
+            };
+            let actions = 4 * (n as u64 + 1) - skipped_actions;
+            halt_actions * actions.pow(2 * n - 1) / halves
+        };
+
+        for (n, counts) in (1..).zip(&VERIFIED_COUNTS) {
+            for (generator, &(_, immediate_halts)) in counts.iter().enumerate() {
+                assert_eq!(count(n, generator), immediate_halts, "n = {}", n);
+            }
+        }
+        for (generator, &immediate_halts) in IMMEDIATE_HALTS_5.iter().enumerate() {
+            assert_eq!(count(5, generator), immediate_halts);
+        }
+    }
+}
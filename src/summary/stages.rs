@@ -0,0 +1,75 @@
+//! Report of how many TMs were decided at which stage of the analysis
+//! pipeline.
+
+
+/// Width of the longest bar in the bar chart in characters.
+const CHART_WIDTH: usize = 40;
+
+/// One stage of the pipeline, in the order they are executed.
+pub struct Stage {
+    pub name: &'static str,
+
+    /// Number of TMs that were decided at this stage.
+    pub decided: u64,
+}
+
+/// Prints a table with one row per stage and, if `chart` is `true`, a bar
+/// chart of the decided TMs per stage. `undecided` is the number of TMs that
+/// passed all stages without being decided.
+pub fn print(stages: &[Stage], undecided: u64, chart: bool) {
+    let total = stages.iter().map(|s| s.decided).sum::<u64>() + undecided;
+    let percent = |v: u64| 100.0 * v as f64 / total as f64;
+
+    bunt::println!("{$blue+bold}▸ TMs decided per pipeline stage:{/$}");
+    println!(
+        "    {: <32}{: >14}{: >10}{: >12}{: >14}",
+        "stage",
+        "decided",
+        "share",
+        "cumulative",
+        "remaining",
+    );
+
+    let mut remaining = total;
+    for (i, stage) in stages.iter().enumerate() {
+        remaining -= stage.decided;
+        println!(
+            "    {: <32}{: >14}{: >9.2}%{: >11.2}%{: >14}",
+            format!("{}. {}", i + 1, stage.name),
+            stage.decided,
+            percent(stage.decided),
+            percent(total - remaining),
+            remaining,
+        );
+    }
+    println!("    {: <32}{: >14}{: >9.2}%", "undecided", undecided, percent(undecided));
+
+    if chart {
+        let max = stages.iter().map(|s| s.decided).chain(Some(undecided)).max().unwrap_or(0);
+
+        println!();
+        for (i, stage) in stages.iter().enumerate() {
+            let label = format!("{}. {}", i + 1, stage.name);
+            println!("    {: <32}▕{}", label, bar(stage.decided, max));
+        }
+        bunt::println!("    {: <32}▕{[red]}", "undecided", bar(undecided, max));
+    }
+}
+
+/// Returns a horizontal bar with a length proportional to `value / max`, with
+/// a resolution of eighths of a character.
+fn bar(value: u64, max: u64) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+    if max == 0 {
+        return String::new();
+    }
+
+    let eighths = ((8 * CHART_WIDTH) as f64 * value as f64 / max as f64).round() as usize;
+    let mut out = "█".repeat(eighths / 8);
+    match eighths % 8 {
+        0 => {}
+        rest => out.push(PARTIAL[rest]),
+    }
+    out
+}
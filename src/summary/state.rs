@@ -0,0 +1,273 @@
+//! Writing the complete state of a summary to a text format and reading it
+//! back, e.g. for checkpoints. Unlike the dump (see `dump.rs`), nothing is
+//! left out, so that a read summary can be merged with others.
+
+use std::io::{self, Write};
+use ahash::AHashMap;
+use anyhow::{Context, Result, anyhow};
+
+use crate::outcome::Confidence;
+#[cfg(feature = "tape-stats")]
+use crate::tape::TapeStats;
+use super::{Champion, Summary, TieBreak};
+
+
+impl Summary {
+    /// Writes the state as lines of the form `<key> <values...>`.
+    pub fn write_state(&self, w: &mut impl Write) -> io::Result<()> {
+        self.write_lines(w, false)
+    }
+
+    /// Like `write_state`, but leaves out counts that are 0 if `skip_zeros`
+    /// is set (see `SummaryDelta::write`).
+    pub(super) fn write_lines(&self, w: &mut impl Write, skip_zeros: bool) -> io::Result<()> {
+        writeln!(w, "high-score {}", self.high_score)?;
+        writeln!(w, "most-steps {}", self.most_steps)?;
+        writeln!(w, "num-winners {}", self.num_winners)?;
+        writeln!(w, "num-step-winners {}", self.num_step_winners)?;
+        writeln!(w, "fewest-winner-steps {}", self.fewest_winner_steps)?;
+        let champions = [("winner", &self.winners), ("step-winner", &self.step_winners)];
+        for (key, champions) in &champions {
+            for c in champions.iter() {
+                writeln!(w, "{} {} {} {} {}", key, c.id, c.steps, c.ones, c.index)?;
+            }
+        }
+
+        for (key, count) in &self.counts() {
+            if skip_zeros && *count == 0 {
+                continue;
+            }
+            writeln!(w, "count {} {}", key, count)?;
+        }
+
+        let histograms = [
+            ("halted", &self.step_histogram),
+            ("high-score", &self.winner_step_histogram),
+            ("simulated", &self.simulated_step_histogram),
+        ];
+        for (name, histogram) in &histograms {
+            for (steps, count) in histogram.iter() {
+                writeln!(w, "histogram {} {} {}", name, steps, count)?;
+            }
+        }
+
+        for (class, row) in self.first_action.counts.iter().enumerate() {
+            if skip_zeros && row.iter().all(|c| *c == 0) {
+                continue;
+            }
+            let row = row.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+            writeln!(w, "first-action {} {}", class, row.join(" "))?;
+        }
+
+        if !skip_zeros || self.tape_patterns.counts.iter().any(|c| *c != 0) {
+            let patterns = self.tape_patterns.counts.iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>();
+            writeln!(w, "tape-patterns {}", patterns.join(" "))?;
+        }
+
+        #[cfg(feature = "tape-stats")]
+        {
+            let s = &self.tape_stats;
+            writeln!(
+                w,
+                "tape-stats {} {} {} {}",
+                s.runs,
+                s.grows,
+                s.bytes_copied,
+                s.bucket_accesses,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a summary written by `write_state`. `max_champions` has to be
+    /// the one it was created with (see `Summary::new`).
+    pub fn read_state<'a>(
+        tie_break: TieBreak,
+        max_champions: usize,
+        lines: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self> {
+        let mut out = Self::new(tie_break, max_champions);
+        for line in lines {
+            out.read_state_line(line).with_context(|| format!("invalid line '{}'", line))?;
+        }
+        Ok(out)
+    }
+
+    fn read_state_line(&mut self, line: &str) -> Result<()> {
+        let mut parts = line.split(' ');
+        let key = parts.next().unwrap_or("");
+        let mut next = || -> Result<&str> {
+            parts.next().ok_or_else(|| anyhow!("too few values"))
+        };
+
+        match key {
+            "high-score" => self.high_score = next()?.parse()?,
+            "most-steps" => self.most_steps = next()?.parse()?,
+            "num-winners" => self.num_winners = next()?.parse()?,
+            "num-step-winners" => self.num_step_winners = next()?.parse()?,
+            "fewest-winner-steps" => self.fewest_winner_steps = next()?.parse()?,
+            "winner" | "step-winner" => {
+                let champion = Champion {
+                    id: next()?.parse()?,
+                    steps: next()?.parse()?,
+                    ones: next()?.parse()?,
+                    index: next()?.parse()?,
+                };
+                if key == "winner" {
+                    self.winners.push(champion);
+                } else {
+                    self.step_winners.push(champion);
+                }
+            }
+            "count" => {
+                let name = next()?;
+                let value = next()?.parse()?;
+                let count = match name {
+                    "halted" => &mut self.num_halted,
+                    "immediate-halt" => &mut self.num_immediate_halt,
+                    "no-halt-transition" => &mut self.num_no_halt_transition,
+                    "simple-elope" => &mut self.num_simple_elope,
+                    "halt-unreachable" => &mut self.num_halt_unreachable,
+                    "run-away" => &mut self.num_runaway,
+                    "cycle" => &mut self.num_cycle,
+                    "translated-cycler" => &mut self.num_translated_cycler,
+                    "backward-reasoning" => &mut self.num_backward_reasoning,
+                    "ctl" => &mut self.num_ctl,
+                    "lin-recurrence" => &mut self.num_lin_recurrence,
+                    "aborted" => &mut self.num_aborted_after_max_steps,
+                    "pruned" => &mut self.num_pruned,
+                    "known" => &mut self.num_known,
+                    "halted-zero-ones" => &mut self.num_halted_zero_ones,
+                    "aborted-growing" => &mut self.num_aborted_growing,
+                    _ => return Err(anyhow!("unknown count '{}'", name)),
+                };
+                *count = value;
+            }
+            "histogram" => {
+                let name = next()?;
+                let histogram: &mut AHashMap<u64, u64> = match name {
+                    "halted" => &mut self.step_histogram,
+                    "high-score" => &mut self.winner_step_histogram,
+                    "simulated" => &mut self.simulated_step_histogram,
+                    _ => return Err(anyhow!("unknown histogram '{}'", name)),
+                };
+                histogram.insert(next()?.parse()?, next()?.parse()?);
+            }
+            "first-action" => {
+                let class: usize = next()?.parse()?;
+                let row = self.first_action.counts.get_mut(class)
+                    .ok_or_else(|| anyhow!("invalid class {}", class))?;
+                for count in row.iter_mut() {
+                    *count = next()?.parse()?;
+                }
+            }
+            "tape-patterns" => {
+                for count in self.tape_patterns.counts.iter_mut() {
+                    *count = next()?.parse()?;
+                }
+            }
+
+            // Tape stats are silently ignored if the feature is disabled, as
+            // they don't affect any results.
+            #[cfg(feature = "tape-stats")]
+            "tape-stats" => {
+                self.tape_stats = TapeStats {
+                    runs: next()?.parse()?,
+                    grows: next()?.parse()?,
+                    bytes_copied: next()?.parse()?,
+                    bucket_accesses: next()?.parse()?,
+                };
+            }
+            #[cfg(not(feature = "tape-stats"))]
+            "tape-stats" => {}
+
+            _ => return Err(anyhow!("unknown key '{}'", key)),
+        }
+
+        Ok(())
+    }
+
+    /// The number of TMs per confidence of their outcome.
+    pub(super) fn confidence_counts(&self) -> [(Confidence, u64); 2] {
+        let undecided = self.num_undecided();
+        [
+            (Confidence::Proven, self.num_total_tms() - undecided),
+            (Confidence::Undecided, undecided),
+        ]
+    }
+
+    /// All outcome counts with their names.
+    pub(super) fn counts(&self) -> [(&'static str, u64); 16] {
+        [
+            ("halted", self.num_halted),
+            ("immediate-halt", self.num_immediate_halt),
+            ("no-halt-transition", self.num_no_halt_transition),
+            ("simple-elope", self.num_simple_elope),
+            ("halt-unreachable", self.num_halt_unreachable),
+            ("run-away", self.num_runaway),
+            ("cycle", self.num_cycle),
+            ("translated-cycler", self.num_translated_cycler),
+            ("backward-reasoning", self.num_backward_reasoning),
+            ("ctl", self.num_ctl),
+            ("lin-recurrence", self.num_lin_recurrence),
+            ("aborted", self.num_aborted_after_max_steps),
+            ("pruned", self.num_pruned),
+            ("known", self.num_known),
+            ("halted-zero-ones", self.num_halted_zero_ones),
+            ("aborted-growing", self.num_aborted_growing),
+        ]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analyze::Analyzer,
+        gen::{All, TmGenerator},
+        outcome::OutcomeSink,
+        summary::{Summary, TieBreak},
+        tape::Tape,
+        tm::Tm,
+    };
+
+    #[test]
+    fn roundtrip() {
+        let mut summary = Summary::new(TieBreak::FewestSteps, 0);
+        let mut analyzer = <Analyzer<2>>::builder().build();
+        <All<2>>::default().for_all(|tm| analyzer.analyze(tm, &mut summary));
+
+        let mut state = Vec::new();
+        summary.write_state(&mut state).unwrap();
+        let state = String::from_utf8(state).unwrap();
+        let read = Summary::read_state(TieBreak::FewestSteps, 0, state.lines()).unwrap();
+
+        let mut dump = Vec::new();
+        summary.write_dump(&mut dump).unwrap();
+        let mut read_dump = Vec::new();
+        read.write_dump(&mut read_dump).unwrap();
+        assert_eq!(dump, read_dump);
+    }
+
+    #[test]
+    fn steps_beyond_u32() {
+        let steps = 5 * u32::MAX as u64;
+        let tm = <Tm<2>>::from_standard("1RB1LB_1LA1RZ").unwrap();
+        let mut summary = Summary::new(TieBreak::FewestSteps, 0);
+        summary.report_halted(tm, steps, 4, &Tape::new());
+        summary.report_halted(tm, steps + 1, 3, &Tape::new());
+
+        let mut state = Vec::new();
+        summary.write_state(&mut state).unwrap();
+        let state = String::from_utf8(state).unwrap();
+        let read = Summary::read_state(TieBreak::FewestSteps, 0, state.lines()).unwrap();
+
+        assert_eq!(read.most_steps(), steps + 1);
+        assert_eq!(read.champion().unwrap().steps, steps);
+        assert_eq!(read.step_histogram.get(&steps), Some(&1));
+        assert_eq!(read.step_histogram.get(&(steps + 1)), Some(&1));
+    }
+}
@@ -0,0 +1,121 @@
+//! Census of the final tapes of halting TMs, classified into coarse patterns
+//! by the blocks of 1s on them.
+
+use crate::tape::TapeLike;
+
+
+const NUM_PATTERNS: usize = 7;
+const PATTERN_NAMES: [&str; NUM_PATTERNS] = [
+    "blank",
+    "single block",
+    "alternating (1010...1)",
+    "2 blocks",
+    "3 blocks",
+    "4 blocks",
+    "5 or more blocks",
+];
+
+const BLANK: usize = 0;
+const SINGLE_BLOCK: usize = 1;
+const ALTERNATING: usize = 2;
+
+/// Index of the pattern for two blocks of 1s. The patterns for more blocks
+/// follow.
+const TWO_BLOCKS: usize = 3;
+
+#[derive(Clone)]
+pub struct TapePatterns {
+    pub(super) counts: [u64; NUM_PATTERNS],
+}
+
+impl TapePatterns {
+    pub fn new() -> Self {
+        Self { counts: [0; NUM_PATTERNS] }
+    }
+
+    /// Records the final tape of a halted TM.
+    pub fn record(&mut self, tape: &impl TapeLike) {
+        self.counts[pattern_of(tape)] += 1;
+    }
+
+    /// Records a TM that halted immediately, i.e. wrote at most one 1.
+    pub fn record_immediate_halt(&mut self, num_ones: u32) {
+        self.counts[if num_ones == 0 { BLANK } else { SINGLE_BLOCK }] += 1;
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+    }
+
+    /// Returns the name and count of each pattern.
+    pub(super) fn named_counts(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        PATTERN_NAMES.iter().copied().zip(self.counts.iter().copied())
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn print(&self) {
+        let total: u64 = self.counts.iter().sum();
+
+        bunt::println!("{$blue+bold}▸ Final tapes of halted TMs:{/$}");
+        for (name, count) in self.named_counts() {
+            let percent = if total == 0 { 0.0 } else { 100.0 * count as f64 / total as f64 };
+            println!("    {: <26}{: >14}{: >9.2}%", name, count, percent);
+        }
+    }
+}
+
+/// Classifies the tape by its blocks of 1s. Tapes with at least two blocks
+/// where all blocks and the gaps between them have length 1 are alternating.
+fn pattern_of(tape: &impl TapeLike) -> usize {
+    let mut blocks = 0;
+    let mut alternating = true;
+    let mut last_gap = None;
+    for (value, cells) in tape.runs() {
+        let len = cells.end.0 - cells.start.0;
+        if value.0 {
+            blocks += 1;
+            if len != 1 || last_gap.is_some_and(|gap| gap != 1) {
+                alternating = false;
+            }
+        } else if blocks > 0 {
+            last_gap = Some(len);
+        }
+    }
+
+    match blocks {
+        0 => BLANK,
+        1 => SINGLE_BLOCK,
+        _ if alternating => ALTERNATING,
+        k => (TWO_BLOCKS + k - 2).min(NUM_PATTERNS - 1),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tape::{CellId, CellValue, Tape};
+    use super::*;
+
+    fn tape(cells: &str) -> Tape {
+        let mut tape = Tape::new();
+        for (i, c) in cells.chars().enumerate() {
+            tape.write(CellId(i as i64 - 3), CellValue(c == '1'));
+        }
+        tape
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(pattern_of(&tape("")), BLANK);
+        assert_eq!(pattern_of(&tape("000")), BLANK);
+        assert_eq!(pattern_of(&tape("0011100")), SINGLE_BLOCK);
+        assert_eq!(pattern_of(&tape("10101")), ALTERNATING);
+        assert_eq!(pattern_of(&tape("0101")), ALTERNATING);
+        assert_eq!(pattern_of(&tape("1001")), TWO_BLOCKS);
+        assert_eq!(pattern_of(&tape("11011")), TWO_BLOCKS);
+        assert_eq!(pattern_of(&tape("1011101")), TWO_BLOCKS + 1);
+        assert_eq!(pattern_of(&tape("1010101011")), NUM_PATTERNS - 1);
+    }
+}
@@ -0,0 +1,95 @@
+//! Files with the summary of a (partial) run (see `full --summary-out`), so
+//! that the results of runs over different ranges of TMs (see `range`) can be
+//! combined with `merge`.
+//!
+//! The file starts with the header `beaver-summary 9`, followed by the
+//! manifest as comments and `<key> <value>` lines with the versions, the
+//! settings and the range of TM indices. After a line `summary`, the state of
+//! the summary follows (see `Summary::write_state`).
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+use anyhow::{Context, Result, anyhow};
+
+use crate::{
+    checkpoint::{Settings, check_versions, write_versions},
+    manifest::Manifest,
+    summary::Summary,
+};
+
+
+const HEADER: &str = "beaver-summary 9";
+
+/// The summary of all TMs with the indices `range` analyzed with `settings`.
+pub struct SummaryFile {
+    pub settings: Settings,
+    pub range: Range<u64>,
+
+    /// Whether all TMs in `range` are included. This is `false` if the run
+    /// was stopped early or TMs were skipped after a panic.
+    pub complete: bool,
+    pub summary: Summary,
+}
+
+impl SummaryFile {
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("failed to parse summary file '{}'", path.display()))
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("missing header '{}'", HEADER));
+        }
+
+        let mut values = Vec::new();
+        for line in &mut lines {
+            if line == "summary" {
+                break;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            values.push((key, value));
+        }
+        let get = |key: &str| {
+            values.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| anyhow!("missing '{}'", key))
+        };
+
+        check_versions(get)?;
+        let settings = Settings::read(get)?;
+        let range = get("range")?;
+        let (start, end) = range.split_once("..")
+            .ok_or_else(|| anyhow!("invalid range '{}'", range))?;
+
+        Ok(Self {
+            range: start.parse()?..end.parse()?,
+            complete: get("complete")?.parse()?,
+            summary: Summary::read_state(settings.tie_break, settings.max_champions, lines)?,
+            settings,
+        })
+    }
+
+    pub fn write(&self, path: &Path, manifest: &Manifest) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        let mut w = BufWriter::new(file);
+        writeln!(w, "{}", HEADER)?;
+        manifest.write_comments(&mut w)?;
+        write_versions(&mut w)?;
+        self.settings.write(&mut w)?;
+        writeln!(w, "range {}..{}", self.range.start, self.range.end)?;
+        writeln!(w, "complete {}", self.complete)?;
+        writeln!(w, "summary")?;
+        self.summary.write_state(&mut w)?;
+        w.flush().with_context(|| format!("failed to write '{}'", path.display()))
+    }
+}
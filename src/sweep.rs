@@ -0,0 +1,1349 @@
+//! Analyzing all TMs of a generator in parallel ("full sweep").
+
+use std::{
+    any::Any,
+    borrow::BorrowMut,
+    cell::{Cell, RefCell},
+    cmp::{max, min},
+    collections::BTreeMap,
+    mem,
+    ops::Range,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}},
+    thread,
+};
+use ahash::{AHashMap, AHashSet};
+use anyhow::{Result, anyhow};
+
+use crate::{
+    analyze::{Analyzer, HaltConvention, OptionalDeciders, Scratch},
+    batch::{self, Halted},
+    best::{Best, BestSoFar, NewBestCallback},
+    budget::Budget,
+    checkpoint::{Checkpoint, CheckpointTarget, CheckpointWriter, ChunkSet, Settings},
+    continuation::{ContinuationFile, ContinuationSink},
+    dispatch,
+    gen::{Cursor, Generator, PartialTable, PerGenerator, TmGenerator, with_generator},
+    outcome::{AnalysisResult, FnSink, OutcomeSink},
+    output::{Record, ResultWriter, SharedWriter, Source, Tee, TeeSender},
+    profile::{self, Phase},
+    replay_file::{RecordedJob, Replay, ReplayRecorder},
+    summary::{Summary, TieBreak},
+    tape::Tape,
+    tm::Tm,
+};
+#[cfg(feature = "gpu")]
+use crate::gpu::GpuSimulator;
+
+
+/// Upper limit for the number of cells preallocated for each side of the tape
+/// of each worker.
+const MAX_PREALLOCATED_CELLS: usize = 1 << 16;
+
+/// A function receiving the result of each TM: `(index, id, result)`. See
+/// `SweepBuilder::on_result`.
+type ResultCallback = Arc<dyn Fn(u64, u64, AnalysisResult) + Send + Sync>;
+
+/// A function receiving each TM skipped after a panic: `(index, id, message)`.
+/// See `SweepBuilder::on_skip`.
+type SkipCallback = Arc<dyn Fn(u64, u64, &str) + Send + Sync>;
+
+/// What happens if analyzing a TM panics, e.g. because of a bug in a decider
+/// that only a few TMs trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// All workers are stopped and `run` returns an error naming the TM.
+    Fail,
+
+    /// The TM is skipped (see `SweepBuilder::on_skip`) and the sweep
+    /// continues with the next one. Skipped TMs are not part of the summary:
+    /// it sees each result last, so this also holds if an `on_result`
+    /// callback panics. Callbacks that ran before the panic and the
+    /// continuations (see `SweepBuilder::continuations`) may still contain
+    /// the skipped TM.
+    Skip,
+}
+
+impl FromStr for PanicPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "skip" => Ok(Self::Skip),
+            _ => Err("invalid value for 'on-panic'"),
+        }
+    }
+}
+
+impl fmt::Display for PanicPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fail => f.write_str("fail"),
+            Self::Skip => f.write_str("skip"),
+        }
+    }
+}
+
+/// Passed to the progress callback (see `SweepBuilder::on_progress`) after a
+/// worker thread finished a job.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    /// The number of TMs in the finished job.
+    pub num_tms: u64,
+
+    /// How many of those were simulated, i.e. not decided by the static
+    /// checks (see `Summary::num_simulated`).
+    pub num_simulated: u64,
+
+    /// The worker thread that finished the job, from 0 to the number of
+    /// threads.
+    pub worker: u32,
+
+    /// The best TM any worker thread has found so far.
+    pub best: Option<Best>,
+}
+
+/// Configures and runs a full sweep: analyzing all TMs with `n` states that
+/// a generator generates, distributed over multiple threads.
+///
+/// ```no_run
+/// # use beaver_core::{gen::Generator, sweep::SweepBuilder};
+/// let summary = SweepBuilder::new(4)
+///     .generator(Generator::Optimized)
+///     .max_steps(500)
+///     .run()?;
+/// # Ok::<_, anyhow::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct SweepBuilder {
+    n: u8,
+    generator: Generator,
+    fixed: Option<PartialTable>,
+    max_steps: u64,
+    halt_convention: HaltConvention,
+    lin_recurrence: bool,
+    optional_deciders: OptionalDeciders,
+    accelerated: bool,
+    run_budget: Option<Budget>,
+    cycle_budget: Option<Budget>,
+    ctl_budget: Option<Budget>,
+    num_threads: Option<u32>,
+    tie_break: TieBreak,
+    max_champions: usize,
+    prune: bool,
+    baseline_score: Option<u32>,
+    panic_policy: PanicPolicy,
+    on_skip: Option<SkipCallback>,
+    known: Option<Arc<AHashSet<u64>>>,
+    continuations: Option<ContinuationFile>,
+    on_progress: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+    on_new_best: Option<NewBestCallback>,
+    on_result: Vec<ResultCallback>,
+    stop_if: Option<Arc<dyn Fn(u64, AnalysisResult) -> bool + Send + Sync>>,
+    outputs: Vec<SharedWriter>,
+    checkpoint: Option<CheckpointTarget>,
+    resume: Option<Arc<Checkpoint>>,
+    range: Option<Range<u64>>,
+    record: Option<Arc<Mutex<ReplayRecorder>>>,
+    replay: Option<Arc<Replay>>,
+    simd: Option<u32>,
+    #[cfg(feature = "gpu")]
+    gpu: Option<Arc<GpuSimulator>>,
+    #[cfg(feature = "tape-stats")]
+    cold_tape: bool,
+}
+
+impl SweepBuilder {
+    /// Creates a new builder for a sweep over TMs with `n` states. All other
+    /// settings have sensible defaults. Panics if `n` is not between 1 and 6.
+    pub fn new(n: u8) -> Self {
+        assert!(dispatch::SUPPORTED.contains(&n), "invalid value for n: {}", n);
+
+        Self {
+            n,
+            generator: Generator::Optimized,
+            fixed: None,
+            max_steps: 200,
+            halt_convention: HaltConvention::Include,
+            lin_recurrence: false,
+            optional_deciders: OptionalDeciders::default(),
+            accelerated: false,
+            run_budget: None,
+            cycle_budget: None,
+            ctl_budget: None,
+            num_threads: None,
+            tie_break: TieBreak::FewestSteps,
+            max_champions: 0,
+            prune: false,
+            baseline_score: None,
+            panic_policy: PanicPolicy::Fail,
+            on_skip: None,
+            known: None,
+            continuations: None,
+            on_progress: None,
+            on_new_best: None,
+            on_result: Vec::new(),
+            stop_if: None,
+            outputs: Vec::new(),
+            checkpoint: None,
+            resume: None,
+            range: None,
+            record: None,
+            replay: None,
+            simd: None,
+            #[cfg(feature = "gpu")]
+            gpu: None,
+            #[cfg(feature = "tape-stats")]
+            cold_tape: false,
+        }
+    }
+
+    /// Sets the TM generator. Default: `Generator::Optimized`.
+    pub fn generator(mut self, generator: Generator) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    /// Only analyzes the TMs of the generator that match the given partial
+    /// transition table (see `gen::Fixed`). Default: `None`.
+    pub fn fixed(mut self, fixed: Option<PartialTable>) -> Self {
+        self.fixed = fixed;
+        self
+    }
+
+    /// Sets the number of steps after which TMs are stopped. Default: 200.
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets whether the halting transition is counted. Default:
+    /// `HaltConvention::Include`.
+    pub fn halt_convention(mut self, halt_convention: HaltConvention) -> Self {
+        self.halt_convention = halt_convention;
+        self
+    }
+
+    /// Sets whether TMs reaching the maximum number of steps are checked for
+    /// a Lin recurrence (see `AnalyzerBuilder::lin_recurrence`). Default:
+    /// `false`.
+    pub fn lin_recurrence(mut self, lin_recurrence: bool) -> Self {
+        self.lin_recurrence = lin_recurrence;
+        self
+    }
+
+    /// Sets the optional deciders that are applied to TMs reaching the
+    /// maximum number of steps (see `OptionalDeciders`). Default: none.
+    pub fn optional_deciders(mut self, optional_deciders: OptionalDeciders) -> Self {
+        self.optional_deciders = optional_deciders;
+        self
+    }
+
+    /// Sets whether long runs are accelerated with rules (see
+    /// `AnalyzerBuilder::accelerated`). As this does not change any outcome,
+    /// it is not part of the `settings`. Default: `false`.
+    pub fn accelerated(mut self, accelerated: bool) -> Self {
+        self.accelerated = accelerated;
+        self
+    }
+
+    /// Sets the budget for running a single TM (see
+    /// `AnalyzerBuilder::run_budget`). Default: `None`.
+    pub fn run_budget(mut self, run_budget: Option<Budget>) -> Self {
+        self.run_budget = run_budget;
+        self
+    }
+
+    /// Sets the budget of the cycle detection for a single TM (see
+    /// `AnalyzerBuilder::cycle_budget`). Default: `None`.
+    pub fn cycle_budget(mut self, cycle_budget: Option<Budget>) -> Self {
+        self.cycle_budget = cycle_budget;
+        self
+    }
+
+    /// Sets the budget of the CTL decider for a single TM (see
+    /// `AnalyzerBuilder::ctl_budget`). Default: `None`.
+    pub fn ctl_budget(mut self, ctl_budget: Option<Budget>) -> Self {
+        self.ctl_budget = ctl_budget;
+        self
+    }
+
+    /// Sets the number of worker threads. Default: the number of virtual
+    /// CPUs.
+    pub fn num_threads(mut self, num_threads: Option<u32>) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Sets how the champion is chosen among all TMs reaching the high
+    /// score. Default: `TieBreak::FewestSteps`.
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Sets how many Σ and S champions the summary keeps (each): the first
+    /// ones according to the tie break. The numbers of champions are always
+    /// counted completely. 0 keeps all of them, which can be a lot for a
+    /// small `max_steps`. Default: 0.
+    pub fn max_champions(mut self, max_champions: usize) -> Self {
+        self.max_champions = max_champions;
+        self
+    }
+
+    /// Sets whether running TMs are stopped as soon as they cannot reach the
+    /// high score found so far by any worker anymore (see
+    /// `AnalyzerBuilder::prune`). This does not change the high score, but
+    /// all other statistics of TMs that would halt late. Default: `false`.
+    pub fn prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+
+    /// Sets a high score that is known before the sweep starts, e.g. from a
+    /// previous run. Pruning (see `prune`) and the best TM reported in
+    /// `Progress` start from it instead of from zero. If it's higher than
+    /// the actual high score, pruning will wrongly prune the champions. The
+    /// `Summary` only counts the TMs of this sweep. Default: `None`.
+    pub fn baseline_score(mut self, baseline_score: Option<u32>) -> Self {
+        self.baseline_score = baseline_score;
+        self
+    }
+
+    /// Sets what happens if analyzing a TM panics. Default:
+    /// `PanicPolicy::Fail`.
+    pub fn panic_policy(mut self, panic_policy: PanicPolicy) -> Self {
+        self.panic_policy = panic_policy;
+        self
+    }
+
+    /// Sets a function that is called with the index, ID and panic message of
+    /// each TM that was skipped because analyzing it panicked (see
+    /// `PanicPolicy::Skip`). Like `on_result` functions, it is called from the
+    /// worker threads.
+    pub fn on_skip(mut self, f: impl Fn(u64, u64, &str) + Send + Sync + 'static) -> Self {
+        self.on_skip = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the IDs of TMs that were already decided elsewhere, e.g. by a
+    /// previous run. They are not analyzed, but only counted as known (see
+    /// `OutcomeSink::report_known`). Default: `None`.
+    pub fn known(mut self, known: Option<AHashSet<u64>>) -> Self {
+        self.known = known.map(Arc::new);
+        self
+    }
+
+    /// Sets a file to which the configurations of all aborted TMs are
+    /// written. Default: `None`.
+    pub fn continuations(mut self, continuations: Option<ContinuationFile>) -> Self {
+        self.continuations = continuations;
+        self
+    }
+
+    /// Sets whether the tape memory is deallocated before each TM instead of
+    /// being reused, to measure the tape in a "cold" state. Default: `false`.
+    #[cfg(feature = "tape-stats")]
+    pub fn cold_tape(mut self, cold_tape: bool) -> Self {
+        self.cold_tape = cold_tape;
+        self
+    }
+
+    /// Sets a function that is called whenever a worker thread finished a
+    /// job (a part of a chunk of TMs, see `chunk_size`). It is called from the
+    /// worker threads.
+    pub fn on_progress(mut self, f: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets a function that is called as soon as any worker thread finds a
+    /// halting TM with more 1s than all TMs before (and than the baseline,
+    /// see `baseline_score`). It is called from the worker threads, but
+    /// never concurrently and in increasing order of score. Ties are not
+    /// reported, so the TM is not necessarily the final champion.
+    pub fn on_new_best(mut self, f: impl Fn(Best) + Send + Sync + 'static) -> Self {
+        self.on_new_best = Some(Arc::new(f));
+        self
+    }
+
+    /// Adds a function that is called with the result of each analyzed TM as
+    /// `f(index, id, result)`, where `index` is the index of the TM in the
+    /// generator's order. Can be called multiple times to add several
+    /// functions, which are then called in the order they were added.
+    ///
+    /// Threading contract: the functions are called synchronously from the
+    /// worker threads, i.e. concurrently, so they must be `Send + Sync` and
+    /// should be fast, as they directly slow down the sweep. TMs are analyzed
+    /// in jobs of consecutive indices; within one job, the calls happen on
+    /// one thread in increasing index order. There is no ordering between
+    /// different jobs, even of the same chunk. All calls for a job happen
+    /// before the progress callback for that job and all calls have finished
+    /// when `run` returns.
+    pub fn on_result(
+        mut self,
+        f: impl Fn(u64, u64, AnalysisResult) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_result.push(Arc::new(f));
+        self
+    }
+
+    /// Sets a function that is called with the ID and result of each
+    /// analyzed TM. If it returns `true`, the sweep is stopped: all workers
+    /// stop after their current TM and `run` returns the summary of all TMs
+    /// analyzed so far. Like `on_result` functions, it is called from the
+    /// worker threads. Several workers might find a TM at the same time, so
+    /// it can return `true` more than once.
+    pub fn stop_if(
+        mut self,
+        f: impl Fn(u64, AnalysisResult) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.stop_if = Some(Arc::new(f));
+        self
+    }
+
+    /// Adds an output that receives the result of each analyzed TM. Can be
+    /// called multiple times to add several outputs.
+    ///
+    /// Unlike `on_result` functions, outputs are not called from the worker
+    /// threads: each output is written by its own thread, to which the
+    /// workers pass the results after each job. So a slow output does not
+    /// slow down the sweep. All results have been written when `run` returns.
+    pub fn output(mut self, output: impl ResultWriter + 'static) -> Self {
+        self.outputs.push(Arc::new(Mutex::new(output)));
+        self
+    }
+
+    /// Sets where and how often checkpoints are written. A checkpoint
+    /// contains the settings, all finished chunks of TMs and their combined
+    /// summary, so that the sweep can be resumed (see `resume`). Continuation
+    /// records and outputs are not part of it. Default: `None`.
+    pub fn checkpoint(mut self, target: Option<CheckpointTarget>) -> Self {
+        self.checkpoint = target;
+        self
+    }
+
+    /// Resumes the sweep from the given checkpoint: TMs of finished chunks are
+    /// skipped and the summary of the checkpoint is included in the result.
+    /// Fails in `run` if the settings of this builder don't match the ones of
+    /// the checkpoint.
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(Arc::new(checkpoint));
+        self
+    }
+
+    /// Restricts the sweep to the TMs with the given indices in the
+    /// generator's order, e.g. to split a large sweep over several machines.
+    /// Can't be combined with checkpoints, as those only store whole chunks.
+    /// Default: `None` (all TMs).
+    pub fn range(mut self, range: Option<Range<u64>>) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Records the order in which the results of all jobs are passed to the
+    /// outputs and which worker analyzed them (see `replay_file`). The
+    /// recording has to be finished by the caller after `run`. Default:
+    /// `None`.
+    pub fn record(mut self, recorder: Option<Arc<Mutex<ReplayRecorder>>>) -> Self {
+        self.record = recorder;
+        self
+    }
+
+    /// Passes the results of all jobs to the outputs in the order of a
+    /// recorded run (see `record`), so that the outputs are identical to the
+    /// ones of that run. Fails in `run` if the settings, the number of
+    /// threads or the range don't match the recorded ones, or if the jobs
+    /// differ. Default: `None`.
+    pub fn replay(mut self, replay: Option<Replay>) -> Self {
+        self.replay = replay.map(Arc::new);
+        self
+    }
+
+    /// Simulates the TMs that pass the static checks in batches of lanes
+    /// running in lockstep first, for at most the given number of steps (see
+    /// `batch`). Only the ones that don't halt within it are analyzed. This
+    /// doesn't change any outcome, but it currently makes sweeps slower, as
+    /// those TMs are analyzed from scratch. Can't be combined with pruning or
+    /// a run budget. Default: `None`.
+    pub fn simd(mut self, step_budget: Option<u32>) -> Self {
+        self.simd = step_budget;
+        self
+    }
+
+    /// Like `simd`, but simulates the TMs on the GPU (see `gpu`). Takes
+    /// precedence over `simd`. Default: `None`.
+    #[cfg(feature = "gpu")]
+    pub fn gpu(mut self, gpu: Option<GpuSimulator>) -> Self {
+        self.gpu = gpu.map(Arc::new);
+        self
+    }
+
+    /// Whether TMs are simulated in batches before analyzing them (see `simd`
+    /// and `gpu`).
+    fn presimulates(&self) -> bool {
+        #[cfg(feature = "gpu")]
+        if self.gpu.is_some() {
+            return true;
+        }
+
+        self.simd.is_some()
+    }
+
+    /// An empty summary with the settings of this sweep.
+    fn new_summary(&self) -> Summary {
+        Summary::new(self.tie_break, self.max_champions)
+    }
+
+    /// The settings that are stored in checkpoints.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            n: self.n,
+            generator: self.generator,
+            fixed: self.fixed.clone(),
+            max_steps: self.max_steps,
+            halt_convention: self.halt_convention,
+            lin_recurrence: self.lin_recurrence,
+            optional_deciders: self.optional_deciders,
+            run_budget: self.run_budget,
+            cycle_budget: self.cycle_budget,
+            ctl_budget: self.ctl_budget,
+            tie_break: self.tie_break,
+            max_champions: self.max_champions,
+            prune: self.prune,
+            baseline_score: self.baseline_score,
+        }
+    }
+
+    /// The number of TMs that will be analyzed.
+    pub fn num_tms(&self) -> u64 {
+        match &self.range {
+            Some(range) => range.end.saturating_sub(range.start),
+            None => self.num_generated_tms(),
+        }
+    }
+
+    /// Calls `f` with the generator and `N` matching the settings. The fixed
+    /// transitions have to be valid.
+    fn dispatch<F: PerGenerator>(&self, f: F) -> F::Output {
+        with_generator(self.n, self.generator, self.fixed.as_ref(), f)
+            .unwrap_or_else(|| unreachable!("invalid N = {}", self.n))
+    }
+
+    /// The number of TMs the generator generates, including the ones outside
+    /// of `range`.
+    pub fn num_generated_tms(&self) -> u64 {
+        self.dispatch(NumTms)
+    }
+
+    /// A description of the selected generator.
+    pub fn generator_description(&self) -> &'static str {
+        self.dispatch(Description)
+    }
+
+    /// The number of worker threads that will be used.
+    pub fn effective_num_threads(&self) -> u32 {
+        self.num_threads.unwrap_or_else(|| num_cpus::get() as u32)
+    }
+
+    /// The number of TMs that were already analyzed according to the
+    /// checkpoint passed to `resume`.
+    pub fn num_resumed_tms(&self) -> u64 {
+        let checkpoint = match &self.resume {
+            Some(checkpoint) => checkpoint,
+            None => return 0,
+        };
+
+        let num_tms = self.num_tms();
+        let num_chunks = num_tms.div_ceil(checkpoint.chunk_size);
+        let missing = checkpoint.done.missing(num_chunks).iter()
+            .map(|r| min(r.end * checkpoint.chunk_size, num_tms) - r.start * checkpoint.chunk_size)
+            .sum::<u64>();
+        num_tms - missing
+    }
+
+    /// Runs the sweep and returns the combined summary of all TMs.
+    pub fn run(self) -> Result<Summary> {
+        if let Some(table) = &self.fixed {
+            table.check(self.n, &self.generator.opt()).map_err(|e| anyhow!(e))?;
+        }
+        if let Some(range) = &self.range {
+            let num_tms = self.num_generated_tms();
+            if range.start > range.end || range.end > num_tms {
+                return Err(anyhow!(
+                    "invalid range {}..{}: the generator only generates {} TMs",
+                    range.start,
+                    range.end,
+                    num_tms,
+                ));
+            }
+            if self.checkpoint.is_some() || self.resume.is_some() {
+                return Err(anyhow!("checkpoints can't be used for a range of TMs"));
+            }
+        }
+        if let Some(checkpoint) = &self.resume {
+            let (ours, theirs) = (self.settings(), &checkpoint.settings);
+            if ours != *theirs || checkpoint.chunk_size != chunk_size(self.n) {
+                return Err(anyhow!(
+                    "the settings of the checkpoint ({:?}, chunk size {}) don't match \
+                        the ones of this run ({:?}, chunk size {})",
+                    theirs,
+                    checkpoint.chunk_size,
+                    ours,
+                    chunk_size(self.n),
+                ));
+            }
+        }
+
+        if self.record.is_some() || self.replay.is_some() {
+            // Which TMs are pruned and where the sweep stops depends on
+            // timing, so that can't be reproduced by replaying the order.
+            if self.prune || self.stop_if.is_some() {
+                return Err(anyhow!(
+                    "sweeps that prune TMs or stop early cannot be recorded or replayed",
+                ));
+            }
+        }
+        if self.presimulates() && (self.prune || self.run_budget.is_some()) {
+            // Both could decide a TM differently than its halting in a batch.
+            return Err(anyhow!(
+                "batch simulation (SIMD or GPU) can't be used with pruning or a run budget",
+            ));
+        }
+        if let Some(replay) = &self.replay {
+            let ours = (self.settings(), self.effective_num_threads(), self.range.clone());
+            let theirs = (replay.settings.clone(), replay.num_threads, replay.range.clone());
+            if ours != theirs {
+                return Err(anyhow!(
+                    "the settings, number of threads and range of the replay file \
+                        ({:?}) don't match the ones of this run ({:?})",
+                    theirs,
+                    ours,
+                ));
+            }
+        }
+
+        let tee = Tee::start(&self.outputs);
+        let summary = self.dispatch(RunSweep { config: &self, tee: &tee });
+        let finished = tee.finish();
+        let summary = summary?;
+        finished?;
+        if let Some(continuations) = &self.continuations {
+            continuations.flush()?;
+        }
+
+        Ok(summary)
+    }
+}
+
+struct NumTms;
+
+impl PerGenerator for NumTms {
+    type Output = u64;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> u64 {
+        generator.num_tms()
+    }
+}
+
+struct Description;
+
+impl PerGenerator for Description {
+    type Output = &'static str;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> &'static str {
+        generator.description()
+    }
+}
+
+struct RunSweep<'a> {
+    config: &'a SweepBuilder,
+    tee: &'a Tee,
+}
+
+impl PerGenerator for RunSweep<'_> {
+    type Output = Result<Summary>;
+
+    fn call<G: TmGenerator<N>, const N: usize>(self, generator: G) -> Result<Summary>
+    where
+        [bool; N]: Default,
+    {
+        run_sweep(generator, self.config, self.tee)
+    }
+}
+
+#[inline(never)] // Useful for inspecting assembly
+fn run_sweep<G: TmGenerator<N>, const N: usize>(
+    generator: G,
+    config: &SweepBuilder,
+    tee: &Tee,
+) -> Result<Summary>
+where
+    [bool; N]: Default,
+{
+    let num_tms = generator.num_tms();
+
+    // Create a channel to pass pass the work to the workers. We bound it to 32
+    // to have always have some work ready, but to not use too much memory.
+    let (s, r) = crossbeam_channel::bounded::<Job<G::Cursor>>(32);
+    let best = match config.baseline_score {
+        Some(score) => BestSoFar::with_baseline(score),
+        None => BestSoFar::default(),
+    };
+    let best = Arc::new(best.on_new_best(config.on_new_best.clone()));
+    let stop = Arc::new(AtomicBool::new(false));
+    let chunk_size = chunk_size(N as u8);
+    let delivery = Arc::new(Delivery::new(config));
+
+    // The summary of all TMs analyzed before resuming, plus the ones of all
+    // chunks finished in this run if checkpoints are written.
+    let (done, initial) = match &config.resume {
+        Some(checkpoint) => {
+            let mut summary = config.new_summary();
+            summary.add(checkpoint.summary.clone());
+            (checkpoint.done.clone(), summary)
+        }
+        None => (ChunkSet::default(), config.new_summary()),
+    };
+    let (checkpoint, mut summary) = match &config.checkpoint {
+        Some(target) => {
+            let state = Checkpoint {
+                settings: config.settings(),
+                report: target.report.clone(),
+                chunk_size,
+                done: done.clone(),
+                summary: initial,
+            };
+            let writer = CheckpointWriter::new(target.clone(), state);
+            (Some(Arc::new(Mutex::new(writer))), config.new_summary())
+        }
+        None => (None, initial),
+    };
+
+    // Create the worker threads
+    let join_handles = (0..config.effective_num_threads()).map(|worker| {
+        let new_jobs = r.clone();
+        let config = config.clone();
+        let tee = tee.sender();
+        let best = best.clone();
+        let stop = stop.clone();
+        let checkpoint = checkpoint.clone();
+        let delivery = delivery.clone();
+        thread::spawn(move || -> Result<Summary> {
+            // A TM can visit at most one new cell per step, so for small step
+            // limits, we can allocate a tape large enough to never grow.
+            let tape_cells = 2 * min(config.max_steps as usize, MAX_PREALLOCATED_CELLS);
+            let mut scratch = Scratch::with_tape_capacity(tape_cells + 2);
+            #[cfg(feature = "tape-stats")]
+            scratch.set_cold_tape(config.cold_tape);
+            let mut analyzer = Analyzer::builder()
+                .max_steps(config.max_steps)
+                .halt_convention(config.halt_convention)
+                .lin_recurrence(config.lin_recurrence)
+                .optional_deciders(config.optional_deciders)
+                .accelerated(config.accelerated)
+                .run_budget(config.run_budget)
+                .cycle_budget(config.cycle_budget)
+                .ctl_budget(config.ctl_budget)
+                .prune(if config.prune { Some(best.clone()) } else { None })
+                .scratch(&mut scratch)
+                .build();
+            let index = Cell::new(0);
+            let callbacks = &config.on_result;
+            let batch = RefCell::new(Vec::new());
+            let results = if callbacks.is_empty()
+                && tee.is_empty()
+                && config.stop_if.is_none()
+            {
+                None
+            } else {
+                Some(FnSink(|tm: Tm<N>, result| {
+                    profile::enter(Phase::Output);
+                    callbacks.iter().for_each(|f| f(index.get(), tm.encoded, result));
+                    if !tee.is_empty() {
+                        batch.borrow_mut().push((index.get(), tm.encoded, result));
+                    }
+                    if config.stop_if.as_ref().is_some_and(|f| f(tm.encoded, result)) {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }))
+            };
+            // The summary comes last, so that it does not count a TM for which
+            // one of the other sinks panics (see `PanicPolicy::Skip`).
+            let mut sink = (
+                (
+                    (config.continuations.clone().map(ContinuationSink::new), results),
+                    &*best,
+                ),
+                config.new_summary(),
+            );
+            let mut presimulated = config.presimulates().then(Presimulated::new);
+
+            for Job { range, mut cursor, chunk, chunk_len } in new_jobs.iter() {
+                let job_len = range.end - range.start;
+                let simulated_before = sink.1.num_simulated();
+                if let Some(presimulated) = &mut presimulated {
+                    presimulated.prepare(&config, &mut analyzer, &cursor, range.clone())?;
+                }
+
+                // Analyze each TM in this batch. If analyzing a TM panics,
+                // `index` and `current` still refer to that TM and the cursor
+                // already points to the next one. Depending on the policy, the
+                // batch is continued there.
+                let current = Cell::new(0);
+                index.set(range.start);
+                while index.get() < range.end {
+                    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                        while index.get() < range.end && !stop.load(Ordering::Relaxed) {
+                            profile::enter(Phase::Generation);
+                            let tm = cursor.next_tm();
+                            current.set(tm.encoded);
+                            sink.1.set_index(index.get());
+                            if config.known.as_ref().is_some_and(|k| k.contains(&tm.encoded)) {
+                                sink.report_known(tm);
+                            } else {
+                                match &mut presimulated {
+                                    Some(p) => p.analyze(index.get(), tm, &mut analyzer, &mut sink),
+                                    None => analyzer.analyze(tm, &mut sink),
+                                }
+                            }
+                            index.set(index.get() + 1);
+                        }
+                    }));
+                    let payload = match res {
+                        Ok(()) => break,
+                        Err(payload) => payload,
+                    };
+
+                    let (failed, id) = (index.get(), current.get());
+                    let msg = panic_message(&*payload);
+                    match config.panic_policy {
+                        PanicPolicy::Fail => {
+                            stop.store(true, Ordering::Relaxed);
+                            return Err(anyhow!(
+                                "analyzing TM {} (index {}) panicked: {}",
+                                id,
+                                failed,
+                                msg,
+                            ));
+                        }
+                        PanicPolicy::Skip => {
+                            if let Some(on_skip) = &config.on_skip {
+                                on_skip(failed, id, msg);
+                            }
+                            index.set(failed + 1);
+                        }
+                    }
+                }
+                profile::enter(Phase::Output);
+                let num_simulated = sink.1.num_simulated() - simulated_before;
+                if let Some(continuations) = &mut ((sink.0).0).0 {
+                    continuations.flush()?;
+                }
+                delivery.deliver(&tee, &range, Source { chunk, worker }, batch.take())?;
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // The job is only recorded as finished if it was not
+                // interrupted. Its summary is moved to the checkpoint.
+                if let Some(checkpoint) = &checkpoint {
+                    let job_summary = mem::replace(&mut sink.1, config.new_summary());
+                    checkpoint.lock().expect("poisened lock")
+                        .complete(chunk, job_len, chunk_len, job_summary)?;
+                }
+
+                if let Some(on_progress) = &config.on_progress {
+                    on_progress(Progress {
+                        num_tms: job_len,
+                        num_simulated,
+                        worker,
+                        best: best.get(),
+                    });
+                }
+                profile::enter(Phase::Idle);
+            }
+
+            #[cfg(feature = "tape-stats")]
+            sink.1.add_tape_stats(&scratch.tape_stats());
+
+            Ok(sink.1)
+        })
+    }).collect::<Vec<_>>();
+
+    // If all workers stopped early because of an error, sending fails. The
+    // error is then returned when joining the threads below.
+    drop(r);
+    let num_chunks = num_tms.div_ceil(chunk_size);
+    let selected = config.range.clone().unwrap_or(0..num_tms);
+    let missing = done.missing(num_chunks);
+
+    // Chunks keep their position even for a range of TMs, so that
+    // `Source::chunk` means the same in all runs.
+    let tms_of = |chunks: Range<u64>| {
+        let start = max(chunks.start * chunk_size, selected.start);
+        let end = min(min(chunks.end * chunk_size, num_tms), selected.end);
+        start..max(start, end)
+    };
+    let mut remaining = missing.iter()
+        .map(|chunks| {
+            let tms = tms_of(chunks.clone());
+            tms.end - tms.start
+        })
+        .sum::<u64>();
+
+    let mut cursor = generator.cursor_at(selected.start);
+    let mut pos = selected.start;
+    let num_threads = u64::from(config.effective_num_threads());
+    'outer: for chunks in missing {
+        for chunk in chunks {
+            let range = tms_of(chunk..chunk + 1);
+            if range.is_empty() {
+                continue;
+            }
+
+            // Chunks are split into jobs that get smaller towards the end of
+            // the sweep, so that all workers finish at roughly the same time
+            // even if some TMs take much longer than others. The split only
+            // depends on the TMs left, not on timing, so it's the same in
+            // every run.
+            let chunk_len = range.end - range.start;
+            let mut start = range.start;
+            while start < range.end {
+                let len = min(job_size(remaining, num_threads, chunk_size), range.end - start);
+                let job = start..start + len;
+                remaining -= len;
+                start += len;
+
+                // Jobs are mostly consecutive, so the cursor is advanced from
+                // the previous job instead of seeking from scratch.
+                cursor.skip(job.start - pos);
+                pos = job.start;
+                let job = Job { range: job, cursor: cursor.clone(), chunk, chunk_len };
+                if stop.load(Ordering::Relaxed) || s.send(job).is_err() {
+                    break 'outer;
+                }
+            }
+        }
+    }
+
+    // Join all threads. If checkpoints are written, the summaries of the
+    // workers only contain what's not part of any finished chunk.
+    drop(s);
+    let thread_summaries = join_handles.into_iter()
+        .map(|handle| handle.join().expect("panic in worker thread"))
+        .collect::<Result<Vec<_>>>()?;
+    delivery.check_complete()?;
+    if let Some(checkpoint) = checkpoint {
+        let writer = Arc::try_unwrap(checkpoint)
+            .ok()
+            .expect("checkpoint writer still shared after joining workers")
+            .into_inner()
+            .expect("poisened lock");
+        summary.add(writer.finish()?);
+    }
+    for thread_summary in thread_summaries {
+        summary.add(thread_summary);
+    }
+
+    Ok(summary)
+}
+
+/// Returns the message passed to `panic!`, if any.
+#[doc(hidden)]
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload.downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| &**s))
+        .unwrap_or("<no message>")
+}
+
+/// The TMs passed to a worker at once: a part of one chunk, with a cursor
+/// already positioned at the start of `range`.
+struct Job<C> {
+    range: Range<u64>,
+    cursor: C,
+    chunk: u64,
+
+    /// The number of TMs of the chunk that are part of this sweep.
+    chunk_len: u64,
+}
+
+/// Passes the results of finished jobs to the outputs, either directly or
+/// recording or replaying their order (see `SweepBuilder::record` and
+/// `SweepBuilder::replay`).
+enum Delivery {
+    Direct,
+    Record(Arc<Mutex<ReplayRecorder>>),
+    Replay(Mutex<Sequencer>),
+}
+
+/// Holds back the results of jobs that finished earlier than in the replayed
+/// run, until all jobs before them are delivered.
+struct Sequencer {
+    jobs: Vec<RecordedJob>,
+
+    /// The position of each job in `jobs`, by the start of its range.
+    positions: AHashMap<u64, usize>,
+
+    /// The position of the next job to deliver.
+    next: usize,
+    pending: BTreeMap<usize, (Source, Vec<Record>)>,
+}
+
+impl Delivery {
+    fn new(config: &SweepBuilder) -> Self {
+        match (&config.record, &config.replay) {
+            (_, Some(replay)) => {
+                let positions = replay.jobs.iter()
+                    .enumerate()
+                    .map(|(pos, job)| (job.range.start, pos))
+                    .collect();
+                Self::Replay(Mutex::new(Sequencer {
+                    jobs: replay.jobs.clone(),
+                    positions,
+                    next: 0,
+                    pending: BTreeMap::new(),
+                }))
+            }
+            (Some(recorder), None) => Self::Record(recorder.clone()),
+            (None, None) => Self::Direct,
+        }
+    }
+
+    fn deliver(
+        &self,
+        tee: &TeeSender,
+        range: &Range<u64>,
+        source: Source,
+        records: Vec<Record>,
+    ) -> Result<()> {
+        match self {
+            Self::Direct => tee.send(source, records),
+            Self::Record(recorder) => {
+                // Sending while holding the lock makes the recorded order the
+                // order in which the outputs receive the results.
+                let mut recorder = recorder.lock().expect("poisened lock");
+                tee.send(source, records);
+                recorder.record(range, source.worker);
+            }
+            Self::Replay(sequencer) => {
+                let mut sequencer = sequencer.lock().expect("poisened lock");
+                let pos = sequencer.positions.get(&range.start).copied()
+                    .filter(|&pos| sequencer.jobs[pos].range == *range)
+                    .ok_or_else(|| anyhow!(
+                        "the job {}..{} is not part of the replay file, so this run \
+                            differs from the recorded one",
+                        range.start,
+                        range.end,
+                    ))?;
+                let source = Source { worker: sequencer.jobs[pos].worker, ..source };
+                sequencer.pending.insert(pos, (source, records));
+                while let Some((source, records)) = {
+                    let next = sequencer.next;
+                    sequencer.pending.remove(&next)
+                } {
+                    tee.send(source, records);
+                    sequencer.next += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an error if not all jobs of the replayed run were delivered.
+    fn check_complete(&self) -> Result<()> {
+        if let Self::Replay(sequencer) = self {
+            let sequencer = sequencer.lock().expect("poisened lock");
+            if sequencer.next < sequencer.jobs.len() {
+                return Err(anyhow!(
+                    "only {} of the {} jobs of the replay file were run",
+                    sequencer.next,
+                    sequencer.jobs.len(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A job whose TMs are simulated in batches before it is analyzed (see
+/// `SweepBuilder::simd` and `SweepBuilder::gpu`). The static checks are done
+/// before as well, so that only the TMs passing them are simulated. Their
+/// outcomes are stored, so that the checks are not repeated when the TMs are
+/// reported.
+struct Presimulated {
+    /// The first index of the job.
+    start: u64,
+
+    /// What is known about each TM of the job, by index.
+    prepared: Vec<Prepared>,
+
+    /// The TMs that halted, with their indices, in ascending order.
+    halted: Vec<(u64, Halted)>,
+
+    /// The tape the final tapes of those TMs are restored to.
+    tape: Tape,
+}
+
+/// See `Presimulated::prepared`.
+#[derive(Debug, Clone, Copy)]
+enum Prepared {
+    /// Known TMs are skipped.
+    Known,
+    ImmediateHalt,
+    SimpleElope,
+    NoHaltTransition,
+    HaltStateNotReachable,
+    Halted,
+
+    /// The TM did not halt in the batch and has to be run by the analyzer.
+    Run,
+}
+
+impl Presimulated {
+    fn new() -> Self {
+        Self {
+            start: 0,
+            prepared: Vec::new(),
+            halted: Vec::new(),
+            tape: Tape::with_capacity(batch::MAX_CELLS),
+        }
+    }
+
+    /// Does the static checks for all TMs of the job that are not known and
+    /// simulates the ones passing them.
+    fn prepare<C: Cursor<N>, S: BorrowMut<Scratch>, const N: usize>(
+        &mut self,
+        config: &SweepBuilder,
+        analyzer: &mut Analyzer<N, S>,
+        cursor: &C,
+        range: Range<u64>,
+    ) -> Result<()>
+    where
+        [bool; N]: Default,
+    {
+        self.start = range.start;
+        self.prepared.clear();
+        self.halted.clear();
+        let mut cursor = cursor.clone();
+        let mut indices = Vec::new();
+        let mut candidates = Vec::new();
+        for index in range {
+            let tm = cursor.next_tm();
+            let prepared = if config.known.as_ref().is_some_and(|k| k.contains(&tm.encoded)) {
+                Prepared::Known
+            } else {
+                let mut decided = None;
+                analyzer.check_static(tm, &mut FnSink(|_, result| decided = Some(result)));
+                match decided {
+                    None => {
+                        indices.push(index);
+                        candidates.push(tm);
+                        Prepared::Run
+                    }
+                    Some(AnalysisResult::ImmediateHalt { .. }) => Prepared::ImmediateHalt,
+                    Some(AnalysisResult::SimpleElope) => Prepared::SimpleElope,
+                    Some(AnalysisResult::NoHaltTransition) => Prepared::NoHaltTransition,
+                    Some(AnalysisResult::HaltStateNotReachable) => {
+                        Prepared::HaltStateNotReachable
+                    }
+                    Some(other) => unreachable!("{:?} is not an outcome of a static check", other),
+                }
+            };
+            self.prepared.push(prepared);
+        }
+
+        profile::enter(Phase::Simulation);
+        let (start, prepared, halted) = (self.start, &mut self.prepared, &mut self.halted);
+        Self::simulate_batch(config, &candidates, |i, result| {
+            prepared[(indices[i] - start) as usize] = Prepared::Halted;
+            halted.push((indices[i], result));
+        })?;
+        halted.sort_unstable_by_key(|&(index, _)| index);
+        Ok(())
+    }
+
+    fn simulate_batch<const N: usize>(
+        config: &SweepBuilder,
+        tms: &[Tm<N>],
+        on_halted: impl FnMut(usize, Halted),
+    ) -> Result<()> {
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = &config.gpu {
+            return gpu.simulate(tms, config.max_steps, config.halt_convention, on_halted);
+        }
+
+        let step_budget = config.simd.expect("neither SIMD nor GPU simulation enabled");
+        batch::simulate(tms, config.max_steps, step_budget, config.halt_convention, on_halted);
+        Ok(())
+    }
+
+    /// Reports the TM with the given index to `sink`, like
+    /// `Analyzer::analyze` would. The TM must not be known.
+    fn analyze<S: BorrowMut<Scratch>, const N: usize>(
+        &mut self,
+        index: u64,
+        tm: Tm<N>,
+        analyzer: &mut Analyzer<N, S>,
+        sink: &mut impl OutcomeSink<N>,
+    ) where
+        [bool; N]: Default,
+    {
+        match self.prepared[(index - self.start) as usize] {
+            Prepared::Known => unreachable!("known TMs are not analyzed"),
+            Prepared::ImmediateHalt => {
+                analyzer.check_immediate_halt(tm, sink);
+            }
+            Prepared::SimpleElope => sink.report_simple_elope(tm),
+            Prepared::NoHaltTransition => sink.report_no_halt_transition(tm),
+            Prepared::HaltStateNotReachable => sink.report_halt_state_not_reachable(tm),
+            Prepared::Halted => {
+                let pos = self.halted.binary_search_by_key(&index, |&(i, _)| i)
+                    .expect("halted TM without result");
+                self.halted[pos].1.report(tm, &mut self.tape, sink);
+            }
+            Prepared::Run => {
+                profile::enter(Phase::Simulation);
+                analyzer.run_tm(tm, sink);
+            }
+        }
+    }
+}
+
+/// The number of TMs of the next job, if `remaining` TMs are not yet passed
+/// to any worker: a fraction of what each worker still has to do, so that
+/// the jobs at the end are small ("guided scheduling"). But at least 1/64 of
+/// a chunk, as each job has some overhead, and at most one chunk.
+fn job_size(remaining: u64, num_threads: u64, chunk_size: u64) -> u64 {
+    let min_size = max(chunk_size / 64, 1);
+    (remaining / (4 * num_threads)).clamp(min_size, chunk_size)
+}
+
+/// The number of TMs in a chunk. Chunks are the unit of checkpoints and of
+/// `output::Source`, while the workers get parts of them (see `job_size`).
+/// Chunk `i` consists of the TMs with indices `i * chunk_size(n)..(i + 1) *
+/// chunk_size(n)`.
+pub fn chunk_size(n: u8) -> u64 {
+    // So in theory, a very large number is best for performance. BUT the
+    // progress bar only changes when a whole job is done. So for super slow
+    // PCs, or debug builds, or runs with lots of debug output, or stuff like
+    // that -- we want the progress bar to still be useful.
+    match n {
+        1 => 1,
+        2 => 500,
+        3 => 50_000,
+        _ => 1_000_000,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use crate::{gen::Generator, outcome::AnalysisResult, summary::TieBreak};
+    use super::{PanicPolicy, SweepBuilder};
+
+    /// With this step limit, some TMs can be pruned, but the champion (11
+    /// steps) still halts.
+    fn sweep() -> SweepBuilder {
+        SweepBuilder::new(3)
+            .generator(Generator::Optimized)
+            .max_steps(20)
+            .tie_break(TieBreak::SmallestId)
+            .num_threads(Some(3))
+    }
+
+    #[test]
+    fn prune() {
+        let full = sweep().run().unwrap();
+        let pruned = sweep().prune(true).baseline_score(Some(6)).run().unwrap();
+
+        assert_eq!(pruned.num_total_tms(), full.num_total_tms());
+        assert_eq!(pruned.high_score(), 6);
+        assert_eq!(pruned.champion().unwrap().id, full.champion().unwrap().id);
+        assert!(pruned.num_undecided() > full.num_undecided());
+    }
+
+    #[test]
+    fn stop_if() {
+        let is_hit = |result| matches!(result, AnalysisResult::Halted { num_ones: 6, .. });
+        let first_hit = Arc::new(Mutex::new(None));
+        let summary = {
+            let first_hit = first_hit.clone();
+            sweep()
+                .num_threads(Some(1))
+                .on_result(move |index, _, result| {
+                    if is_hit(result) {
+                        first_hit.lock().unwrap().get_or_insert(index);
+                    }
+                })
+                .stop_if(move |_, result| is_hit(result))
+                .run()
+                .unwrap()
+        };
+
+        // With one thread, the sweep stops right after the first TM found.
+        let first_hit = first_hit.lock().unwrap().expect("no TM found");
+        assert_eq!(summary.num_total_tms(), first_hit + 1);
+        assert!(summary.num_total_tms() < sweep().num_tms());
+        assert_eq!(summary.high_score(), 6);
+        assert_eq!(summary.champion().unwrap().index, first_hit);
+
+        // Several threads might find more TMs before they stop.
+        let summary = sweep().stop_if(move |_, result| is_hit(result)).run().unwrap();
+        assert!(summary.num_total_tms() < sweep().num_tms());
+        assert_eq!(summary.high_score(), 6);
+    }
+
+    #[test]
+    fn panic_policy() {
+        // Panicking in a callback is like panicking in the analysis, as both
+        // happen while the TM is reported.
+        let failing = |policy| {
+            SweepBuilder::new(2)
+                .generator(Generator::All)
+                .num_threads(Some(3))
+                .panic_policy(policy)
+                .on_result(|index, _, _| if index == 7 || index == 300 {
+                    panic!("bad TM {}", index);
+                })
+        };
+
+        match failing(PanicPolicy::Fail).run() {
+            Ok(_) => panic!("panic was not reported"),
+            Err(e) => assert!(e.to_string().contains("panicked: bad TM"), "{}", e),
+        }
+
+        let skipped = Arc::new(Mutex::new(Vec::new()));
+        let analyzed = Arc::new(Mutex::new(Vec::new()));
+        let sweep = {
+            let skipped = skipped.clone();
+            let analyzed = analyzed.clone();
+            failing(PanicPolicy::Skip)
+                .on_skip(move |index, _, msg| {
+                    skipped.lock().unwrap().push((index, msg.to_owned()));
+                })
+                .on_result(move |index, _, _| analyzed.lock().unwrap().push(index))
+        };
+        let num_tms = sweep.num_tms();
+        let summary = sweep.run().unwrap();
+
+        let mut skipped = skipped.lock().unwrap().clone();
+        skipped.sort();
+        assert_eq!(skipped, [(7, "bad TM 7".to_owned()), (300, "bad TM 300".to_owned())]);
+
+        // All other TMs are analyzed.
+        let mut analyzed = analyzed.lock().unwrap().clone();
+        analyzed.sort();
+        let expected = (0..num_tms).filter(|&i| i != 7 && i != 300).collect::<Vec<_>>();
+        assert_eq!(analyzed, expected);
+
+        // The summary does not count the skipped TMs, even though they were
+        // analyzed before the callback panicked.
+        assert_eq!(summary.num_total_tms(), num_tms - 2);
+    }
+}
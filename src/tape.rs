@@ -1,6 +1,15 @@
 //! Defines the tape on which TMs are operating.
 
-use std::{cmp::max, convert::TryInto, mem, ops::Range};
+use std::{
+    cmp::max,
+    collections::{BTreeMap, hash_map::DefaultHasher},
+    convert::TryInto,
+    hash::Hasher,
+    mem,
+    ops::Range,
+};
+#[cfg(feature = "tape-stats")]
+use std::cell::Cell;
 
 
 
@@ -19,6 +28,35 @@ pub struct CellValue(pub bool);
 type BucketType = u64;
 const BITS_PER_BUCKET: u64 = mem::size_of::<BucketType>() as u64 * 8;
 
+/// Counters for how a tape was used, to guide optimizations of the tape with
+/// actual data. A "run" starts with each call to `Tape::clear`.
+#[cfg(feature = "tape-stats")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TapeStats {
+    pub runs: u64,
+
+    /// How often the tape had to grow (i.e. reallocate).
+    pub grows: u64,
+
+    /// The number of bytes copied from old to new storage when growing.
+    pub bytes_copied: u64,
+
+    /// The number of accesses to a bucket of the underlying bit vector by
+    /// `get` and `write`. Reads outside the written range don't touch a
+    /// bucket and are not counted.
+    pub bucket_accesses: u64,
+}
+
+#[cfg(feature = "tape-stats")]
+impl TapeStats {
+    pub fn add(&mut self, other: &TapeStats) {
+        self.runs += other.runs;
+        self.grows += other.grows;
+        self.bytes_copied += other.bytes_copied;
+        self.bucket_accesses += other.bucket_accesses;
+    }
+}
+
 /// The infinite tape of a TM. The cells are binary and can thus hold the
 /// values '0' or '1'. All cells are initialized to 0.
 pub struct Tape {
@@ -35,6 +73,26 @@ pub struct Tape {
     /// - `written.range.start + offset >= 0`
     /// - `written.range.end + offset < data.len() * BITS_PER_BUCKET`
     written_range: Range<CellId>,
+
+    /// If set, `clear` also deallocates, so that every run starts with a
+    /// minimal tape ("cold") instead of reusing the memory of the previous
+    /// run ("warm").
+    #[cfg(feature = "tape-stats")]
+    cold: bool,
+
+    #[cfg(feature = "tape-stats")]
+    stats: TapeStats,
+
+    /// `TapeStats::bucket_accesses`, separate as `get` only borrows `self`
+    /// immutably.
+    #[cfg(feature = "tape-stats")]
+    bucket_accesses: Cell<u64>,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Tape {
@@ -44,16 +102,63 @@ impl Tape {
             data: vec![0].into_boxed_slice(),
             offset: 32,
             written_range: CellId(0)..CellId(0),
+            #[cfg(feature = "tape-stats")]
+            cold: false,
+            #[cfg(feature = "tape-stats")]
+            stats: TapeStats::default(),
+            #[cfg(feature = "tape-stats")]
+            bucket_accesses: Cell::new(0),
+        }
+    }
+
+    /// Creates a new infinite tape that can store at least `cells` many cells
+    /// (half of them left, the other half right of cell 0) before it has to
+    /// grow.
+    pub fn with_capacity(cells: usize) -> Self {
+        let buckets = max(1, (cells as u64).div_ceil(BITS_PER_BUCKET));
+        Self {
+            data: vec![0; buckets as usize].into_boxed_slice(),
+            offset: (buckets * BITS_PER_BUCKET / 2) as i64,
+            written_range: CellId(0)..CellId(0),
+            #[cfg(feature = "tape-stats")]
+            cold: false,
+            #[cfg(feature = "tape-stats")]
+            stats: TapeStats::default(),
+            #[cfg(feature = "tape-stats")]
+            bucket_accesses: Cell::new(0),
         }
     }
 
     /// Clears the tape (sets all cells to 0) without deallocating memory.
     pub fn clear(&mut self) {
+        #[cfg(feature = "tape-stats")]
+        {
+            self.stats.runs += 1;
+            if self.cold {
+                self.data = vec![0].into_boxed_slice();
+            }
+        }
+
         self.data.iter_mut().for_each(|b| *b = 0);
         self.offset = (self.data.len() as u64 * BITS_PER_BUCKET / 2) as i64;
         self.written_range = CellId(0)..CellId(0);
     }
 
+    /// Sets whether `clear` deallocates the tape (see `TapeStats`).
+    #[cfg(feature = "tape-stats")]
+    pub fn set_cold(&mut self, cold: bool) {
+        self.cold = cold;
+    }
+
+    /// Returns the counters accumulated since this tape was created.
+    #[cfg(feature = "tape-stats")]
+    pub fn stats(&self) -> TapeStats {
+        TapeStats {
+            bucket_accesses: self.bucket_accesses.get(),
+            ..self.stats
+        }
+    }
+
     /// Returns the range in which cells have already been written. Not all
     /// cells are written within this range, but there are no cells outside
     /// this range that have not been written to yet.
@@ -72,10 +177,24 @@ impl Tape {
         // We can cast because the `written_range` invariant guarantees the
         // result is >= 0.
         let (bucket_idx, bit_in_bucket) = self.lookup_bucket(id);
+        #[cfg(feature = "tape-stats")]
+        self.bucket_accesses.set(self.bucket_accesses.get() + 1);
 
         CellValue((self.data[bucket_idx] & (1 << bit_in_bucket)) != 0)
     }
 
+    /// Iterates over all cells in `written_range`, from left to right.
+    pub fn iter_written(&self) -> impl Iterator<Item = (CellId, CellValue)> + '_ {
+        let r = self.written_range();
+        (r.start.0..r.end.0).map(move |id| (CellId(id), self.get(CellId(id))))
+    }
+
+    /// Iterates over the maximal runs of cells with the same value in
+    /// `written_range`, from left to right.
+    pub fn runs(&self) -> Runs<'_> {
+        Runs { tape: self, next: self.written_range.start }
+    }
+
     /// Write a new value into the given cell.
     pub fn write(&mut self, id: CellId, value: CellValue) {
         // This loop is another interesting hack. We know that the "grow check"
@@ -115,6 +234,8 @@ impl Tape {
         // by first clearing the specified bit from the target `u64`, then
         // adding it back IF `value` is set.
         let (bucket_idx, bit_in_bucket) = self.lookup_bucket(id);
+        #[cfg(feature = "tape-stats")]
+        self.bucket_accesses.set(self.bucket_accesses.get() + 1);
         self.data[bucket_idx] = (self.data[bucket_idx] & !(1 << bit_in_bucket))
             | ((value.0 as u64) << bit_in_bucket);
     }
@@ -142,6 +263,11 @@ impl Tape {
         let new_len =  self.data.len() + grow_by_buckets_usize;
 
         let mut new_data = vec![0; new_len].into_boxed_slice();
+        #[cfg(feature = "tape-stats")]
+        {
+            self.stats.grows += 1;
+            self.stats.bytes_copied += mem::size_of_val(&*self.data) as u64;
+        }
 
         if bit_idx < 0 {
             // We grew left
@@ -174,11 +300,248 @@ impl Tape {
     }
 }
 
+/// Iterator over the runs of a tape (see `Tape::runs`). Yields the value and
+/// the cells of each run.
+pub struct Runs<'a> {
+    tape: &'a Tape,
+    next: CellId,
+}
+
+impl Iterator for Runs<'_> {
+    type Item = (CellValue, Range<CellId>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.tape.written_range.end;
+        if self.next >= end {
+            return None;
+        }
+
+        let start = self.next;
+        let value = self.tape.get(start);
+        let mut id = start.0 + 1;
+        while id < end.0 && self.tape.get(CellId(id)) == value {
+            id += 1;
+        }
+        self.next = CellId(id);
+
+        Some((value, start..self.next))
+    }
+}
+
+/// The operations of `Tape` that the analyzer needs, so that it can also run
+/// TMs on an `RleTape`.
+pub trait TapeLike {
+    /// See `Tape::get`.
+    fn get(&self, id: CellId) -> CellValue;
+
+    /// See `Tape::write`.
+    fn write(&mut self, id: CellId, value: CellValue);
+
+    /// See `Tape::written_range`.
+    fn written_range(&self) -> Range<CellId>;
+
+    /// See `Tape::clear`.
+    fn clear(&mut self);
+
+    /// Writes `value` into all cells in `cells`.
+    fn write_range(&mut self, cells: Range<CellId>, value: CellValue) {
+        for id in cells.start.0..cells.end.0 {
+            self.write(CellId(id), value);
+        }
+    }
+
+    /// See `Tape::runs`.
+    fn runs(&self) -> impl Iterator<Item = (CellValue, Range<CellId>)> + '_;
+
+    /// See `Tape::iter_written`.
+    fn iter_written(&self) -> impl Iterator<Item = (CellId, CellValue)> + '_ {
+        self.runs().flat_map(|(value, cells)| (cells.start.0..cells.end.0).map(move |id| {
+            (CellId(id), value)
+        }))
+    }
+
+    /// Returns a hash of the cells in `written_range`, computed from the
+    /// runs. Tapes with the same written range and contents have the same
+    /// hash, regardless of their capacity or type.
+    fn content_hash(&self) -> u64 {
+        hash_runs(self.runs().map(|(value, cells)| (value.0, cells.end.0 - cells.start.0)))
+    }
+
+    /// The number of 1s on the tape.
+    fn ones(&self) -> u64 {
+        self.runs()
+            .filter(|(value, _)| value.0)
+            .map(|(_, cells)| (cells.end.0 - cells.start.0) as u64)
+            .sum()
+    }
+}
+
+/// Hashes runs given as value and length, see `TapeLike::content_hash`.
+pub(crate) fn hash_runs(runs: impl Iterator<Item = (bool, i64)>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (value, len) in runs {
+        hasher.write_u8(value as u8);
+        hasher.write_i64(len);
+    }
+    hasher.finish()
+}
+
+impl TapeLike for Tape {
+    #[inline(always)]
+    fn get(&self, id: CellId) -> CellValue {
+        Tape::get(self, id)
+    }
+
+    #[inline(always)]
+    fn write(&mut self, id: CellId, value: CellValue) {
+        Tape::write(self, id, value)
+    }
+
+    #[inline(always)]
+    fn written_range(&self) -> Range<CellId> {
+        Tape::written_range(self)
+    }
+
+    fn clear(&mut self) {
+        Tape::clear(self)
+    }
+
+    fn runs(&self) -> impl Iterator<Item = (CellValue, Range<CellId>)> + '_ {
+        Tape::runs(self)
+    }
+
+    fn iter_written(&self) -> impl Iterator<Item = (CellId, CellValue)> + '_ {
+        Tape::iter_written(self)
+    }
+}
+
+/// A tape that stores the runs of 1s instead of single cells, so that its
+/// memory only depends on the number of runs. Accessing a cell is
+/// logarithmic in that number, so it's slower than `Tape`, but long running
+/// TMs usually have long runs of equal cells on their tapes.
+pub struct RleTape {
+    /// The runs of 1s: the first cell of each run mapped to the cell after
+    /// its end. Runs are never adjacent to each other.
+    ones: BTreeMap<i64, i64>,
+
+    /// Like `Tape::written_range`.
+    written_range: Range<CellId>,
+}
+
+impl Default for RleTape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RleTape {
+    /// Creates a new infinite tape.
+    pub fn new() -> Self {
+        Self {
+            ones: BTreeMap::new(),
+            written_range: CellId(0)..CellId(0),
+        }
+    }
+
+    fn extend_written_range(&mut self, id: CellId) {
+        if self.written_range.start > id {
+            self.written_range.start = id;
+        } else if self.written_range.end <= id {
+            self.written_range.end = CellId(id.0 + 1);
+        }
+    }
+}
+
+impl TapeLike for RleTape {
+    fn get(&self, id: CellId) -> CellValue {
+        let run = self.ones.range(..=id.0).next_back();
+        CellValue(run.is_some_and(|(_, &end)| id.0 < end))
+    }
+
+    fn write(&mut self, id: CellId, value: CellValue) {
+        self.write_range(id..CellId(id.0 + 1), value);
+    }
+
+    fn written_range(&self) -> Range<CellId> {
+        self.written_range.clone()
+    }
+
+    fn clear(&mut self) {
+        self.ones.clear();
+        self.written_range = CellId(0)..CellId(0);
+    }
+
+    /// Takes time logarithmic in the number of runs (plus the number of
+    /// removed runs) instead of linear in the number of cells.
+    fn write_range(&mut self, cells: Range<CellId>, value: CellValue) {
+        let (mut start, mut end) = (cells.start.0, cells.end.0);
+        if start >= end {
+            return;
+        }
+        self.extend_written_range(CellId(start));
+        self.extend_written_range(CellId(end - 1));
+
+        // Cut all runs overlapping with the range (or touching it, when
+        // writing 1s, to merge them).
+        let touch = value.0 as i64;
+        let overlapping = self.ones.range(..end + touch)
+            .rev()
+            .take_while(|(_, &run_end)| run_end + touch > start)
+            .map(|(&run_start, &run_end)| (run_start, run_end))
+            .collect::<Vec<_>>();
+        for (run_start, run_end) in overlapping {
+            self.ones.remove(&run_start);
+            if value.0 {
+                start = start.min(run_start);
+                end = end.max(run_end);
+            } else {
+                if run_start < start {
+                    self.ones.insert(run_start, start);
+                }
+                if run_end > end {
+                    self.ones.insert(end, run_end);
+                }
+            }
+        }
+
+        if value.0 {
+            self.ones.insert(start, end);
+        }
+    }
+
+    fn runs(&self) -> impl Iterator<Item = (CellValue, Range<CellId>)> + '_ {
+        let end = self.written_range.end.0;
+        let mut next = self.written_range.start.0;
+        let mut ones = self.ones.iter().peekable();
+        std::iter::from_fn(move || {
+            if next >= end {
+                return None;
+            }
+
+            let start = next;
+            match ones.peek() {
+                Some((&run_start, &run_end)) if run_start <= start => {
+                    ones.next();
+                    next = run_end;
+                    Some((CellValue(true), CellId(start)..CellId(run_end)))
+                }
+                Some((&run_start, _)) => {
+                    next = run_start;
+                    Some((CellValue(false), CellId(start)..CellId(run_start)))
+                }
+                None => {
+                    next = end;
+                    Some((CellValue(false), CellId(start)..CellId(end)))
+                }
+            }
+        })
+    }
+}
 
 
 #[cfg(test)]
 mod tests {
-    use super::{CellId, CellValue, Tape};
+    use super::{CellId, CellValue, RleTape, Tape, TapeLike};
 
 
     #[test]
@@ -255,4 +618,103 @@ mod tests {
             assert_eq!(tape.get(CellId(i)), CellValue(false), "at cell {}", i);
         }
     }
+
+    #[test]
+    fn iterators() {
+        let mut tape = Tape::new();
+        assert_eq!(tape.iter_written().count(), 0);
+        assert_eq!(tape.runs().count(), 0);
+
+        for &id in &[-70, -69, -2, 3, 4, 5, 80] {
+            tape.write(CellId(id), CellValue(true));
+        }
+        tape.write(CellId(-71), CellValue(false));
+
+        let ones = tape.iter_written()
+            .filter(|(_, value)| value.0)
+            .map(|(id, _)| id.0)
+            .collect::<Vec<_>>();
+        assert_eq!(ones, [-70, -69, -2, 3, 4, 5, 80]);
+
+        let runs = tape.runs()
+            .map(|(value, cells)| (value.0, cells.start.0, cells.end.0))
+            .collect::<Vec<_>>();
+        assert_eq!(runs, [
+            (false, -71, -70),
+            (true, -70, -68),
+            (false, -68, -2),
+            (true, -2, -1),
+            (false, -1, 3),
+            (true, 3, 6),
+            (false, 6, 80),
+            (true, 80, 81),
+        ]);
+    }
+
+    #[test]
+    fn content_hash() {
+        let mut a = Tape::new();
+        let mut b = Tape::with_capacity(1000);
+        for &i in &[-70, -3, 5, 64] {
+            a.write(CellId(i), CellValue(true));
+            b.write(CellId(i), CellValue(true));
+        }
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        b.write(CellId(5), CellValue(false));
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn rle_tape_same_as_tape() {
+        let mut tape = Tape::new();
+        let mut rle = RleTape::new();
+        let mut x = 12345u64;
+        for _ in 0..3000 {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let id = CellId((x >> 33) as i64 % 40 - 20);
+            let value = CellValue(x >> 20 & 3 != 0);
+            if x >> 40 & 7 == 0 {
+                let end = CellId(id.0 + (x >> 45) as i64 % 10);
+                for i in id.0..end.0 {
+                    tape.write(CellId(i), value);
+                }
+                rle.write_range(id..end, value);
+            } else {
+                tape.write(id, value);
+                rle.write(id, value);
+            }
+
+            assert_eq!(TapeLike::written_range(&rle), tape.written_range());
+            assert!(TapeLike::runs(&rle).eq(tape.runs()));
+            assert_eq!(TapeLike::content_hash(&rle), tape.content_hash());
+        }
+        assert!((-25..25).all(|i| rle.get(CellId(i)) == tape.get(CellId(i))));
+        assert_eq!(rle.ones(), tape.iter_written().filter(|(_, v)| v.0).count() as u64);
+    }
+
+    #[cfg(feature = "tape-stats")]
+    #[test]
+    fn stats() {
+        let mut tape = Tape::with_capacity(128);
+        tape.clear();
+        tape.write(CellId(3), CellValue(true));
+        assert_eq!(tape.get(CellId(3)), CellValue(true));
+        assert_eq!(tape.get(CellId(-3)), CellValue(false));
+        tape.write(CellId(100), CellValue(true));
+
+        let stats = tape.stats();
+        assert_eq!(stats.runs, 1);
+        assert_eq!(stats.grows, 1);
+        assert_eq!(stats.bytes_copied, 16);
+        assert_eq!(stats.bucket_accesses, 3);
+
+        tape.set_cold(true);
+        tape.clear();
+        tape.write(CellId(100), CellValue(true));
+        let stats = tape.stats();
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.grows, 2);
+        assert_eq!(stats.bytes_copied, 24);
+    }
 }
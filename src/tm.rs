@@ -2,7 +2,10 @@
 
 use std::fmt;
 
-use crate::tape::CellValue;
+use crate::{
+    dispatch::{self, PerN},
+    tape::CellValue,
+};
 
 
 /// An N-state turing machine operating on a binary tape.
@@ -18,26 +21,61 @@ pub struct Tm<const N: usize> {
 }
 
 impl<const N: usize> Tm<N> {
+    /// Returns the TM with the given ID or `None` if the ID is invalid for
+    /// `N` states. Use `check_id` to find out why an ID is invalid.
     pub fn from_id(id: u64) -> Option<Self> {
-        // Check for too many states
-        if (id >> (N * 10)) != 0 {
-            return None;
+        check_id(id, N).ok().map(|_| Self::new_unchecked(id))
+    }
+
+    pub fn new_unchecked(encoded: u64) -> Self {
+        Self {
+            encoded,
+        }
+    }
+
+    /// Parses a TM in the standard notation used by the bbchallenge and in
+    /// most papers: the actions for reading 0 and 1 of each state (written
+    /// symbol, movement, next state), with the states separated by `_`, e.g.
+    /// `1RB1LB_1LA1RZ`. The halt state can be written as `H` or `Z`.
+    /// Undefined transitions (`---`) halt, and are treated as `1RH`.
+    pub fn from_standard(s: &str) -> Result<Self, String> {
+        let states = s.trim().split('_').collect::<Vec<_>>();
+        if states.len() != N {
+            return Err(format!(
+                "'{}' describes a TM with {} states, but N = {}",
+                s,
+                states.len(),
+                N,
+            ));
         }
 
-        // Make sure all actions transition to an actual state.
-        for action in (0..2 * N).map(|i| (id >> (5 * i)) & 0b11111) {
-            if (action >> 2) > N as u64 {
-                return None;
+        let mut encoded = 0;
+        for (i, state) in states.iter().enumerate() {
+            let chars = state.chars().collect::<Vec<_>>();
+            if chars.len() != 6 {
+                return Err(format!(
+                    "invalid state '{}' in '{}': expected two actions like '1RB'",
+                    state,
+                    s,
+                ));
+            }
+            for (read, action) in chars.chunks(3).enumerate() {
+                let action = Action::<N>::from_standard(action)
+                    .ok_or_else(|| format!("invalid action '{}' in '{}'", state, s))?;
+                encoded |= (action.encoded as u64) << (10 * i + 5 * read);
             }
         }
 
-        Some(Self::new_unchecked(id))
+        Ok(Self::new_unchecked(encoded))
     }
 
-    pub fn new_unchecked(encoded: u64) -> Self {
-        Self {
-            encoded,
-        }
+    /// Formats the TM in the standard notation (see `from_standard`), with
+    /// `H` as halt state.
+    pub fn to_standard_notation(self) -> String {
+        (0..N as u8)
+            .map(|i| format!("{}{}", self.state(i).on_0(), self.state(i).on_1()))
+            .collect::<Vec<_>>()
+            .join("_")
     }
 
     pub fn start_action(self) -> Action<N> {
@@ -52,6 +90,37 @@ impl<const N: usize> Tm<N> {
     }
 }
 
+impl<const N: usize> Tm<N> {
+    /// Returns a value that formats the TM as table (see `TransitionTable`).
+    pub fn table(self) -> TransitionTable<N> {
+        TransitionTable(self)
+    }
+}
+
+/// Formats the transition table of a TM as multi-line table with one row per
+/// state and one column per read symbol. Each cell shows the action in
+/// standard notation (written symbol, movement, next state):
+///
+/// ```text
+///     │  0    1
+/// ────┼──────────
+///   A │ 1RB  1LB
+///   B │ 1LA  1RH
+/// ```
+pub struct TransitionTable<const N: usize>(Tm<N>);
+
+impl<const N: usize> fmt::Display for TransitionTable<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "    │  0    1")?;
+        writeln!(f, "────┼──────────")?;
+        for i in 0..N as u8 {
+            let state = self.0.state(i);
+            writeln!(f, "  {} │ {}  {}", state_name::<N>(i), state.on_0(), state.on_1())?;
+        }
+        Ok(())
+    }
+}
+
 impl<const N: usize> fmt::Debug for Tm<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         struct Key(char);
@@ -117,7 +186,7 @@ pub enum NextState {
 }
 
 /// Everything that happens in one step of simulation.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Action<const N: usize> {
     /// This encodes the full action like follows:
     /// - Bit 0: the inverted value that is written to the tape, i.e. if this
@@ -130,6 +199,34 @@ pub struct Action<const N: usize> {
 }
 
 impl<const N: usize> Action<N> {
+    /// Parses an action like `1RB` or `---` (see `Tm::from_standard`).
+    fn from_standard(s: &[char]) -> Option<Self> {
+        let (write, movement, next) = match *s {
+            ['-', '-', '-'] => ('1', 'R', 'H'),
+            [write, movement, next] => (write, movement, next),
+            _ => return None,
+        };
+
+        let write = match write {
+            '0' => 1,
+            '1' => 0,
+            _ => return None,
+        };
+        let movement = match movement {
+            'L' => 0,
+            'R' => 0b10,
+            _ => return None,
+        };
+        let next = match next {
+            'H' | 'Z' => N as u8,
+            c => STATE_NAMES.iter()
+                .take(N)
+                .position(|&name| name == c)? as u8,
+        };
+
+        Some(Self { encoded: write | movement | (next << 2) })
+    }
+
     /// The next state value of this transition.
     pub fn next_state(&self) -> NextState {
         let v = self.encoded >> 2;
@@ -170,16 +267,168 @@ impl<const N: usize> fmt::Debug for Action<N> {
     }
 }
 
+/// Formats the action in the standard notation: written symbol, movement and
+/// next state, e.g. `1RB`.
+impl<const N: usize> fmt::Display for Action<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write = if self.write_value().0 { '1' } else { '0' };
+        let direction = if self.movement() == Move::Left { 'L' } else { 'R' };
+        let state = state_name::<N>(self.encoded >> 2);
+
+        write!(f, "{}{}{}", write, direction, state)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Move {
     Left,
     Right,
 }
 
-fn state_name<const N: usize>(id: u8) -> char {
+/// The reason why an ID does not describe a TM with a given number of states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidId {
+    /// Bits above the `10 * n` bits used by TMs with `n` states are set.
+    TooManyStates { n: usize },
+
+    /// The action of `state` for reading `read` transitions to `next_state`,
+    /// which is neither a state nor the halt state (which is `n`).
+    InvalidNextState { n: usize, state: u8, read: u8, next_state: u8 },
+}
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InvalidId::TooManyStates { n } => write!(
+                f,
+                "it has bits set above the {} bits used by TMs with {} states",
+                10 * n,
+                n,
+            ),
+            InvalidId::InvalidNextState { n, state, read, next_state } => write!(
+                f,
+                "the action of state {} for reading {} transitions to state #{}, but only \
+                    states #0 to #{} exist (#{} being the halt state)",
+                STATE_NAMES[state as usize],
+                read,
+                next_state,
+                n,
+                n,
+            ),
+        }
+    }
+}
+
+/// Checks whether `id` is a valid ID for TMs with `n` states.
+pub fn check_id(id: u64, n: usize) -> Result<(), InvalidId> {
+    // Make sure all actions transition to an actual state.
+    for i in 0..2 * n {
+        let next_state = ((id >> (5 * i)) & 0b11111) >> 2;
+        if next_state > n as u64 {
+            return Err(InvalidId::InvalidNextState {
+                n,
+                state: (i / 2) as u8,
+                read: (i % 2) as u8,
+                next_state: next_state as u8,
+            });
+        }
+    }
+
+    // Check for too many states
+    if (id >> (n * 10)) != 0 {
+        return Err(InvalidId::TooManyStates { n });
+    }
+
+    Ok(())
+}
+
+/// Returns the valid ID for `n` states that differs from `id` in the fewest
+/// actions: all bits above the used ones are cleared and all transitions to
+/// non-existing states are changed to transition to the halt state instead.
+pub fn nearest_valid_id(id: u64, n: usize) -> u64 {
+    let mut out = id & ((1 << (n * 10)) - 1);
+    for i in 0..2 * n {
+        let offset = 5 * i + 2;
+        if (out >> offset) & 0b111 > n as u64 {
+            out = (out & !(0b111 << offset)) | ((n as u64) << offset);
+        }
+    }
+
+    out
+}
+
+/// Returns the smallest number of states (up to 6) for which `id` is valid.
+pub fn smallest_valid_n(id: u64) -> Option<usize> {
+    dispatch::SUPPORTED.map(usize::from).find(|&n| check_id(id, n).is_ok())
+}
+
+/// Formats the TM with the given (valid) ID and `n` states in the standard
+/// notation (see `Tm::to_standard_notation`).
+pub fn standard_notation(id: u64, n: u8) -> String {
+    struct Notation(u64);
+
+    impl PerN for Notation {
+        type Output = String;
+
+        fn call<const N: usize>(self) -> String {
+            Tm::<N>::new_unchecked(self.0).to_standard_notation()
+        }
+    }
+
+    dispatch::with_n(n, Notation(id))
+        .unwrap_or_else(|| panic!("invalid number of states: {}", n))
+}
+
+pub(crate) const STATE_NAMES: [char; 6] = ['A', 'B', 'C', 'D', 'E', 'F'];
+
+pub fn state_name<const N: usize>(id: u8) -> char {
     if id == N as u8 {
         'H'
     } else {
-        ['A', 'B', 'C', 'D', 'E', 'F'][id as usize]
+        STATE_NAMES[id as usize]
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_ids() {
+        // The 2-state champion.
+        assert_eq!(check_id(327814, 2), Ok(()));
+
+        // B reading 1 transitions to state #5.
+        let id = (327814 & !(0b111 << 17)) | (5 << 17);
+        assert_eq!(
+            check_id(id, 2),
+            Err(InvalidId::InvalidNextState { n: 2, state: 1, read: 1, next_state: 5 }),
+        );
+        assert_eq!(nearest_valid_id(id, 2), (327814 & !(0b111 << 17)) | (2 << 17));
+        assert_eq!(smallest_valid_n(id), Some(5));
+
+        // Upper bits set.
+        let id = 327814 | (1 << 20);
+        assert_eq!(check_id(id, 2), Err(InvalidId::TooManyStates { n: 2 }));
+        assert_eq!(nearest_valid_id(id, 2), 327814);
+        assert_eq!(smallest_valid_n(id), Some(3));
+        assert_eq!(smallest_valid_n(u64::MAX), None);
+    }
+
+    #[test]
+    fn standard_notation() {
+        assert_eq!(<Tm<2>>::from_standard("1RB1LB_1LA1RZ").map(|tm| tm.encoded), Ok(327814));
+        assert_eq!(<Tm<2>>::from_standard("1RB1LB_1LA---").map(|tm| tm.encoded), Ok(327814));
+        assert_eq!(<Tm<2>>::new_unchecked(327814).to_standard_notation(), "1RB1LB_1LA1RH");
+
+        let bb5 = "1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA";
+        let tm = <Tm<5>>::from_standard(bb5).unwrap();
+        assert_eq!(tm.to_standard_notation(), bb5.replace('Z', "H"));
+
+        assert!(<Tm<2>>::from_standard(bb5).is_err());
+        assert!(<Tm<2>>::from_standard("1RB1LB_1LA1RC").is_err());
+        assert!(<Tm<2>>::from_standard("1RB1LB_1LA2RH").is_err());
+        assert!(<Tm<2>>::from_standard("1RB1L_1LA1RH").is_err());
     }
 }